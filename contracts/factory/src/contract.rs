@@ -1,15 +1,20 @@
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    attr, DepsMut, Empty, Env, MessageInfo, Reply, Response, StdError, SubMsgResponse, SubMsgResult,
+    attr, DepsMut, Empty, Env, MessageInfo, Order, Reply, Response, StdError, StdResult,
+    SubMsgResponse, SubMsgResult,
 };
 use cw2::{get_contract_version, set_contract_version};
 use cw_utils::parse_instantiate_response_data;
 use std::collections::HashSet;
 use ura::contracts::factory::{Config, InstantiateMsg, MigrateMsg};
+use ura::errors::DexError;
 use ura::utils::validation::addr_opt_validate;
 
 use crate::error::ContractError;
-use crate::state::{CONFIG, CREATED_PAIRS, PAIRS, PAIR_CONFIGS, TMP_PAIR_INFO};
+use crate::queries::query_pair_info;
+use crate::state::{
+    ADDR_TO_KEY, CONFIG, CREATED_PAIRS, LP_TO_PAIR, PAIRS, PAIR_CONFIGS, TMP_PAIR_INFOS,
+};
 
 const CONTRACT_NAME: &str = "pair-factory";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -35,6 +40,19 @@ pub fn instantiate(
         fee_address: fee_accumulation_address,
         coin_registry_address: deps.api.addr_validate(&msg.coin_registry_address)?,
         token_code_id: msg.token_code_id,
+        disallow_lp_assets: msg.disallow_lp_assets.unwrap_or(false),
+        controller_gas_limit: msg.controller_gas_limit,
+        trusted_denom_admins: msg
+            .trusted_denom_admins
+            .map(|admins| {
+                admins
+                    .iter()
+                    .map(|admin| deps.api.addr_validate(admin))
+                    .collect::<StdResult<Vec<_>>>()
+            })
+            .transpose()?,
+        asset_fee_addresses: vec![],
+        denom_creation_fee: msg.denom_creation_fee.unwrap_or_default(),
     };
 
     let config_set: HashSet<String> = msg
@@ -63,13 +81,14 @@ pub fn instantiate(
 pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
     match msg {
         Reply {
-            id: _,
+            id,
             result:
                 SubMsgResult::Ok(SubMsgResponse {
                     data: Some(data), ..
                 }),
         } => {
-            let tmp = TMP_PAIR_INFO.load(deps.storage)?;
+            let tmp = TMP_PAIR_INFOS.load(deps.storage, id)?;
+            TMP_PAIR_INFOS.remove(deps.storage, id);
             if PAIRS.has(deps.storage, &tmp.pair_key) {
                 return Err(ContractError::PairWasRegistered {});
             }
@@ -81,13 +100,22 @@ pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractE
             CREATED_PAIRS.save(deps.storage, &pair_contract, &Empty {})?;
 
             PAIRS.save(deps.storage, &tmp.pair_key, &pair_contract)?;
+            ADDR_TO_KEY.save(deps.storage, &pair_contract, &tmp.pair_key)?;
+
+            // The pair instantiation is complete, so its LP token is known by now
+            let pair_info = query_pair_info(deps.as_ref(), &pair_contract)?;
+            LP_TO_PAIR.save(
+                deps.storage,
+                pair_info.liquidity_token.as_bytes(),
+                &pair_contract,
+            )?;
 
             Ok(Response::new().add_attributes(vec![
                 attr("action", "register"),
                 attr("pair_contract_addr", pair_contract),
             ]))
         }
-        _ => Err(ContractError::FailedToParseReply {}),
+        _ => Err(ContractError::Dex(DexError::FailedToParseReply {})),
     }
 }
 
@@ -95,7 +123,27 @@ pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractE
 pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
     let contract_version = get_contract_version(deps.storage)?;
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    // Index the pairs registered before the address and LP token indexes existed. A pair that
+    // can't be queried is left out of the LP token index instead of failing the migration
+    let pairs = PAIRS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut unindexed = vec![];
+    for (pair_key, pair_contract) in pairs {
+        ADDR_TO_KEY.save(deps.storage, &pair_contract, &pair_key)?;
+        match query_pair_info(deps.as_ref(), &pair_contract) {
+            Ok(pair_info) => LP_TO_PAIR.save(
+                deps.storage,
+                pair_info.liquidity_token.as_bytes(),
+                &pair_contract,
+            )?,
+            Err(_) => unindexed.push(pair_contract.to_string()),
+        }
+    }
+
     Ok(Response::new()
+        .add_attribute("unindexed_pairs", unindexed.join(","))
         .add_attribute("previous_contract_name", &contract_version.contract)
         .add_attribute("previous_contract_version", &contract_version.version)
         .add_attribute("new_contract_name", CONTRACT_NAME)
@@ -0,0 +1,74 @@
+use cosmwasm_std::{
+    to_json_vec, Binary, ContractResult, Empty, QuerierWrapper, QueryRequest, StdError, StdResult,
+    SystemResult,
+};
+use prost::Message;
+
+// see https://github.com/osmosis-labs/osmosis/blob/main/proto/osmosis/tokenfactory/v1beta1/query.proto
+
+/// The stargate path of the tokenfactory denom admin query.
+pub const DENOM_AUTHORITY_METADATA_PATH: &str =
+    "/osmosis.tokenfactory.v1beta1.Query/DenomAuthorityMetadata";
+
+/// QueryDenomAuthorityMetadataRequest defines the request structure for the
+/// DenomAuthorityMetadata gRPC query.
+#[derive(Clone, PartialEq, Message)]
+pub struct QueryDenomAuthorityMetadataRequest {
+    #[prost(string, tag = "1")]
+    pub denom: String,
+}
+
+/// DenomAuthorityMetadata specifies metadata for addresses that have specific
+/// capabilities over a token factory denom.
+#[derive(Clone, PartialEq, Message)]
+pub struct DenomAuthorityMetadata {
+    /// The address allowed to mint and burn the denom, empty if the admin was renounced
+    #[prost(string, tag = "1")]
+    pub admin: String,
+}
+
+/// QueryDenomAuthorityMetadataResponse defines the response structure for the
+/// DenomAuthorityMetadata gRPC query.
+#[derive(Clone, PartialEq, Message)]
+pub struct QueryDenomAuthorityMetadataResponse {
+    #[prost(message, optional, tag = "1")]
+    pub authority_metadata: Option<DenomAuthorityMetadata>,
+}
+
+/// Returns the admin of a tokenfactory denom, an empty string if the admin was renounced.
+/// Stargate queries answer with the protobuf encoded response, so it is decoded here instead
+/// of being parsed as JSON by the querier.
+///
+/// * **denom** tokenfactory denom, e.g. factory/{creator}/{subdenom}.
+pub fn query_denom_admin(querier: &QuerierWrapper, denom: &str) -> StdResult<String> {
+    let request: QueryRequest<Empty> = QueryRequest::Stargate {
+        path: DENOM_AUTHORITY_METADATA_PATH.to_string(),
+        data: Binary(
+            QueryDenomAuthorityMetadataRequest {
+                denom: denom.to_string(),
+            }
+            .encode_to_vec(),
+        ),
+    };
+    let data = match querier.raw_query(&to_json_vec(&request)?) {
+        SystemResult::Err(system_err) => {
+            return Err(StdError::generic_err(format!(
+                "Querier system error: {system_err}"
+            )))
+        }
+        SystemResult::Ok(ContractResult::Err(contract_err)) => {
+            return Err(StdError::generic_err(format!(
+                "Querier contract error: {contract_err}"
+            )))
+        }
+        SystemResult::Ok(ContractResult::Ok(data)) => data,
+    };
+
+    let res = QueryDenomAuthorityMetadataResponse::decode(data.as_slice())
+        .map_err(|err| StdError::parse_err("QueryDenomAuthorityMetadataResponse", err))?;
+
+    Ok(res
+        .authority_metadata
+        .map(|metadata| metadata.admin)
+        .unwrap_or_default())
+}
@@ -1,13 +1,14 @@
 use cosmwasm_std::StdError;
 use thiserror::Error;
+use ura::errors::DexError;
 
 #[derive(Error, Debug, PartialEq)]
 pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
-    #[error("Unauthorized")]
-    Unauthorized {},
+    #[error("{0}")]
+    Dex(#[from] DexError),
 
     #[error("Pair was already created")]
     PairWasCreated {},
@@ -27,9 +28,15 @@ pub enum ContractError {
     #[error("Pair config disabled")]
     PairConfigDisabled {},
 
-    #[error("Doubling assets in asset infos")]
-    DoublingAssets {},
+    #[error("Asset {asset} looks like an LP token and cannot be used in a pair")]
+    LpAssetNotAllowed { asset: String },
+
+    #[error("Asset {asset} is minted by {admin}, which is not a trusted denom admin")]
+    UntrustedDenomAdmin { asset: String, admin: String },
+
+    #[error("At most {max} pairs can be created at once")]
+    TooManyPairs { max: usize },
 
-    #[error("Failed to parse or process reply message")]
-    FailedToParseReply {},
+    #[error("The attached funds must be exactly the denom creation fee: {fee}")]
+    DenomCreationFeeMismatch { fee: String },
 }
@@ -1,28 +1,37 @@
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    attr, to_json_binary, Binary, CosmosMsg, DepsMut, Env, MessageInfo, ReplyOn, Response,
-    StdError, SubMsg, WasmMsg,
+    attr, coin, to_json_binary, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response,
+    StdError, StdResult, SubMsg, Uint128, WasmMsg,
 };
 use itertools::Itertools;
-use ura::contracts::factory::{ExecuteMsg, PairConfig, PairType};
+use std::collections::{BTreeMap, HashSet};
+use ura::contracts::factory::{
+    Config, CreatePairRequest, ExecuteMsg, PairConfig, MAX_CREATE_PAIRS,
+};
 use ura::contracts::pair::InstantiateMsg as PairInstantiateMsg;
+use ura::errors::DexError;
 use ura::structs::asset_info::AssetInfo;
+use ura::utils::format::pair_key;
 use ura::utils::ownership::{claim_ownership, drop_ownership_proposal, propose_new_owner};
 
+use crate::denom::query_denom_admin;
 use crate::error::ContractError;
+use crate::queries::query_pair_info;
 use crate::state::{
-    check_asset_infos, pair_key, TmpPairInfo, CONFIG, OWNERSHIP_PROPOSAL, PAIRS, PAIR_CONFIGS,
-    TMP_PAIR_INFO,
+    check_asset_infos, save_tmp_pair_info, ADDR_TO_KEY, CONFIG, LP_TO_PAIR, OWNERSHIP_PROPOSAL,
+    PAIRS, PAIR_CONFIGS,
 };
 
 pub struct UpdateConfig {
     fee_address: Option<String>,
     controller_address: Option<String>,
     coin_registry_address: Option<String>,
+    disallow_lp_assets: Option<bool>,
+    controller_gas_limit: Option<u64>,
+    trusted_denom_admins: Option<Vec<String>>,
+    denom_creation_fee: Option<Vec<Coin>>,
 }
 
-const INSTANTIATE_PAIR_REPLY_ID: u64 = 1;
-
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
@@ -35,6 +44,10 @@ pub fn execute(
             fee_address,
             controller_address,
             coin_registry_address,
+            disallow_lp_assets,
+            controller_gas_limit,
+            trusted_denom_admins,
+            denom_creation_fee,
         } => execute_update_config(
             deps,
             info,
@@ -42,6 +55,10 @@ pub fn execute(
                 fee_address,
                 controller_address,
                 coin_registry_address,
+                disallow_lp_assets,
+                controller_gas_limit,
+                trusted_denom_admins,
+                denom_creation_fee,
             },
         ),
         ExecuteMsg::UpdatePairConfig { config } => execute_update_pair_config(deps, info, config),
@@ -50,16 +67,28 @@ pub fn execute(
             asset_infos,
             init_params,
             toggle_cw20_token,
+            immutable_lp,
         } => execute_create_pair(
             deps,
             env,
             info,
-            pair_type,
-            asset_infos,
-            init_params,
-            toggle_cw20_token,
+            CreatePairRequest {
+                pair_type,
+                asset_infos,
+                init_params,
+                toggle_cw20_token,
+                immutable_lp,
+            },
         ),
+        ExecuteMsg::CreatePairs { pairs } => execute_create_pairs(deps, env, info, pairs),
+        ExecuteMsg::SetAssetFeeAddress {
+            asset_info,
+            fee_address,
+        } => execute_set_asset_fee_address(deps, info, asset_info, fee_address),
         ExecuteMsg::Deregister { asset_infos } => deregister(deps, info, asset_infos),
+        ExecuteMsg::UpdatePairLpToken { previous_lp_token } => {
+            update_pair_lp_token(deps, info, previous_lp_token)
+        }
         ExecuteMsg::ProposeNewOwner { owner, expires_in } => {
             let config = CONFIG.load(deps.storage)?;
 
@@ -103,7 +132,7 @@ pub fn execute_update_config(
 
     // Permission check
     if info.sender != config.owner {
-        return Err(ContractError::Unauthorized {});
+        return Err(ContractError::Dex(DexError::Unauthorized {}));
     }
 
     if let Some(controller_address) = param.controller_address {
@@ -123,11 +152,70 @@ pub fn execute_update_config(
         config.fee_address = deps.api.addr_validate(&fee_address)?;
     }
 
+    if let Some(disallow_lp_assets) = param.disallow_lp_assets {
+        config.disallow_lp_assets = disallow_lp_assets;
+    }
+
+    if let Some(controller_gas_limit) = param.controller_gas_limit {
+        config.controller_gas_limit = Some(controller_gas_limit);
+    }
+
+    if let Some(trusted_denom_admins) = param.trusted_denom_admins {
+        config.trusted_denom_admins = if trusted_denom_admins.is_empty() {
+            None
+        } else {
+            Some(
+                trusted_denom_admins
+                    .iter()
+                    .map(|admin| deps.api.addr_validate(admin))
+                    .collect::<StdResult<Vec<_>>>()?,
+            )
+        };
+    }
+
+    if let Some(denom_creation_fee) = param.denom_creation_fee {
+        config.denom_creation_fee = denom_creation_fee;
+    }
+
     CONFIG.save(deps.storage, &config)?;
 
     Ok(Response::new().add_attribute("action", "update_config"))
 }
 
+/// Sets the address that accumulates the commission denominated in `asset_info`. Without a
+/// `fee_address` the commission in this asset goes to the global fee address again.
+pub fn execute_set_asset_fee_address(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_info: AssetInfo,
+    fee_address: Option<String>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+
+    // Permission check
+    if info.sender != config.owner {
+        return Err(ContractError::Dex(DexError::Unauthorized {}));
+    }
+
+    asset_info.check(deps.api)?;
+    config
+        .asset_fee_addresses
+        .retain(|(info, _)| !info.equal(&asset_info));
+    if let Some(fee_address) = &fee_address {
+        config
+            .asset_fee_addresses
+            .push((asset_info.clone(), deps.api.addr_validate(fee_address)?));
+    }
+
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_asset_fee_address"),
+        attr("asset", asset_info.to_string()),
+        attr("fee_address", fee_address.unwrap_or_default()),
+    ]))
+}
+
 pub fn execute_update_pair_config(
     deps: DepsMut,
     info: MessageInfo,
@@ -137,7 +225,7 @@ pub fn execute_update_pair_config(
 
     // Permission check
     if info.sender != config.owner {
-        return Err(ContractError::Unauthorized {});
+        return Err(ContractError::Dex(DexError::Unauthorized {}));
     }
 
     // Validate total  fee bps
@@ -158,19 +246,163 @@ pub fn execute_create_pair(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    pair_type: PairType,
-    asset_infos: Vec<AssetInfo>,
-    init_params: Option<Binary>,
-    toggle_cw20_token: Option<bool>,
+    request: CreatePairRequest,
 ) -> Result<Response, ContractError> {
-    check_asset_infos(deps.api, &asset_infos)?;
+    let config = CONFIG.load(deps.storage)?;
+
+    let pair = request.asset_infos.iter().join("-");
+    let pair_key = pair_key(&request.asset_infos);
+
+    // Only the chain fee for creating the native LP denom is forwarded to the pair
+    let funds = if request.toggle_cw20_token.unwrap_or(false) {
+        vec![]
+    } else {
+        config.denom_creation_fee.clone()
+    };
+    assert_denom_creation_fee(&info.funds, &funds)?;
+    let instantiate_msg = create_pair_msg(deps.as_ref(), &env, &config, request, funds)?;
+    let reply_id = save_tmp_pair_info(deps.storage, pair_key)?;
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(instantiate_msg, reply_id))
+        .add_attributes(vec![attr("action", "create_pair"), attr("pair", pair)]))
+}
+
+/// Creates up to [`MAX_CREATE_PAIRS`] pairs at once, each registered by its own reply.
+pub fn execute_create_pairs(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pairs: Vec<CreatePairRequest>,
+) -> Result<Response, ContractError> {
+    if pairs.len() > MAX_CREATE_PAIRS {
+        return Err(ContractError::TooManyPairs {
+            max: MAX_CREATE_PAIRS,
+        });
+    }
 
     let config = CONFIG.load(deps.storage)?;
 
+    // Every pair creating a native LP denom is forwarded the chain fee for it
+    let native_lp_pairs = pairs
+        .iter()
+        .filter(|request| !request.toggle_cw20_token.unwrap_or(false))
+        .count() as u128;
+    let total_fee = config
+        .denom_creation_fee
+        .iter()
+        .map(|fee| {
+            Ok(coin(
+                fee.amount.checked_mul(native_lp_pairs.into())?.u128(),
+                &fee.denom,
+            ))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    assert_denom_creation_fee(&info.funds, &total_fee)?;
+
+    let mut pair_keys = HashSet::new();
+    let mut instantiate_msgs = vec![];
+    let mut created = vec![];
+    for request in pairs {
+        let pair_key = pair_key(&request.asset_infos);
+        if !pair_keys.insert(pair_key.clone()) {
+            return Err(ContractError::PairWasCreated {});
+        }
+        created.push(request.asset_infos.iter().join("-"));
+
+        let funds = if request.toggle_cw20_token.unwrap_or(false) {
+            vec![]
+        } else {
+            config.denom_creation_fee.clone()
+        };
+        let instantiate_msg = create_pair_msg(deps.as_ref(), &env, &config, request, funds)?;
+        instantiate_msgs.push((pair_key, instantiate_msg));
+    }
+
+    // Reply ids are only handed out once the whole batch is valid
+    let sub_msgs = instantiate_msgs
+        .into_iter()
+        .map(|(pair_key, instantiate_msg)| {
+            let reply_id = save_tmp_pair_info(deps.storage, pair_key)?;
+            Ok(SubMsg::reply_on_success(instantiate_msg, reply_id))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(Response::new()
+        .add_submessages(sub_msgs)
+        .add_attributes(vec![
+            attr("action", "create_pairs"),
+            attr("pairs", created.join(", ")),
+        ]))
+}
+
+/// Checks that the attached `funds` are exactly the denom creation `fee`, anything else would be
+/// left in the pairs.
+fn assert_denom_creation_fee(funds: &[Coin], fee: &[Coin]) -> Result<(), ContractError> {
+    let total = |coins: &[Coin]| {
+        let mut total = BTreeMap::new();
+        for coin in coins.iter().filter(|coin| !coin.amount.is_zero()) {
+            *total.entry(coin.denom.clone()).or_insert(Uint128::zero()) += coin.amount;
+        }
+        total
+    };
+
+    if total(funds) != total(fee) {
+        return Err(ContractError::DenomCreationFeeMismatch {
+            fee: fee.iter().join(", "),
+        });
+    }
+
+    Ok(())
+}
+
+/// Validates a pair creation request and returns the message instantiating the pair.
+///
+/// * **funds** coins sent with the instantiation to pay for the native LP denom.
+fn create_pair_msg(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    request: CreatePairRequest,
+    funds: Vec<Coin>,
+) -> Result<WasmMsg, ContractError> {
+    let CreatePairRequest {
+        pair_type,
+        asset_infos,
+        init_params,
+        toggle_cw20_token,
+        immutable_lp,
+    } = request;
+
+    check_asset_infos(deps.api, &asset_infos)?;
+
     if PAIRS.has(deps.storage, &pair_key(&asset_infos)) {
         return Err(ContractError::PairWasCreated {});
     }
 
+    if config.disallow_lp_assets {
+        for asset_info in &asset_infos {
+            if asset_info.looks_like_lp(&deps.querier)? {
+                return Err(ContractError::LpAssetNotAllowed {
+                    asset: asset_info.to_string(),
+                });
+            }
+        }
+    }
+
+    if let Some(trusted_denom_admins) = &config.trusted_denom_admins {
+        for asset_info in asset_infos.iter().filter(|a| a.is_tokenfactory()) {
+            let admin = query_denom_admin(&deps.querier, &asset_info.to_string())?;
+            // Nobody can mint a denom whose admin was renounced
+            if !admin.is_empty() && !trusted_denom_admins.iter().any(|a| *a == admin) {
+                return Err(ContractError::UntrustedDenomAdmin {
+                    asset: asset_info.to_string(),
+                    admin,
+                });
+            }
+        }
+    }
+
     // Get pair type from config
     let pair_config = PAIR_CONFIGS
         .load(deps.storage, pair_type.to_string())
@@ -181,38 +413,61 @@ pub fn execute_create_pair(
         return Err(ContractError::PairConfigDisabled {});
     }
 
-    let pair_key = pair_key(&asset_infos);
-    TMP_PAIR_INFO.save(deps.storage, &TmpPairInfo { pair_key })?;
+    let use_cw20_token = toggle_cw20_token.unwrap_or(false);
+    let denom_creation_fee = if use_cw20_token {
+        vec![]
+    } else {
+        config.denom_creation_fee.clone()
+    };
 
-    let sub_msg: Vec<SubMsg> = vec![SubMsg {
-        id: INSTANTIATE_PAIR_REPLY_ID,
-        msg: WasmMsg::Instantiate {
-            admin: Some(config.owner.to_string()),
-            code_id: pair_config.code_id,
-            msg: to_json_binary(&PairInstantiateMsg {
-                asset_infos: asset_infos.clone(),
-                factory_addr: env.contract.address.to_string(),
-                init_params,
-                token_code_id: if toggle_cw20_token.unwrap_or(false) {
-                    Some(config.token_code_id)
-                } else {
-                    None
-                },
-            })?,
-            funds: info.funds,
-            label: "Ura Pair".to_string(),
-        }
-        .into(),
-        gas_limit: None,
-        reply_on: ReplyOn::Success,
-    }];
+    Ok(WasmMsg::Instantiate {
+        admin: Some(config.owner.to_string()),
+        code_id: pair_config.code_id,
+        msg: to_json_binary(&PairInstantiateMsg {
+            asset_infos,
+            factory_addr: env.contract.address.to_string(),
+            init_params,
+            token_code_id: if use_cw20_token {
+                Some(config.token_code_id)
+            } else {
+                None
+            },
+            denom_creation_fee,
+            immutable_lp: immutable_lp.unwrap_or(false),
+        })?,
+        funds,
+        label: "Ura Pair".to_string(),
+    })
+}
 
-    Ok(Response::new()
-        .add_submessages(sub_msg)
-        .add_attributes(vec![
-            attr("action", "create_pair"),
-            attr("pair", asset_infos.iter().join("-")),
-        ]))
+/// Indexes the calling pair under its current LP token instead of `previous_lp_token`, after
+/// the pair migrated its LP token.
+pub fn update_pair_lp_token(
+    deps: DepsMut,
+    info: MessageInfo,
+    previous_lp_token: AssetInfo,
+) -> Result<Response, ContractError> {
+    let pair_info = query_pair_info(deps.as_ref(), &info.sender)?;
+    if PAIRS.may_load(deps.storage, &pair_key(&pair_info.asset_infos))? != Some(info.sender.clone())
+    {
+        return Err(ContractError::Dex(DexError::Unauthorized {}));
+    }
+
+    if LP_TO_PAIR.may_load(deps.storage, previous_lp_token.as_bytes())? == Some(info.sender.clone())
+    {
+        LP_TO_PAIR.remove(deps.storage, previous_lp_token.as_bytes());
+    }
+    LP_TO_PAIR.save(
+        deps.storage,
+        pair_info.liquidity_token.as_bytes(),
+        &info.sender,
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "update_pair_lp_token"),
+        attr("pair_contract_addr", info.sender),
+        attr("liquidity_token", pair_info.liquidity_token.to_string()),
+    ]))
 }
 
 pub fn deregister(
@@ -225,11 +480,14 @@ pub fn deregister(
     let config = CONFIG.load(deps.storage)?;
 
     if info.sender != config.owner {
-        return Err(ContractError::Unauthorized {});
+        return Err(ContractError::Dex(DexError::Unauthorized {}));
     }
 
     let pair_addr = PAIRS.load(deps.storage, &pair_key(&asset_infos))?;
     PAIRS.remove(deps.storage, &pair_key(&asset_infos));
+    ADDR_TO_KEY.remove(deps.storage, &pair_addr);
+    let pair_info = query_pair_info(deps.as_ref(), &pair_addr)?;
+    LP_TO_PAIR.remove(deps.storage, pair_info.liquidity_token.as_bytes());
 
     let messages: Vec<CosmosMsg> = vec![];
 
@@ -1,4 +1,5 @@
 pub mod contract;
+pub mod denom;
 pub mod error;
 pub mod executes;
 pub mod queries;
@@ -1,10 +1,17 @@
 use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_json, to_json_binary, Coin, Empty, OwnedDeps, Querier, QuerierResult, QueryRequest,
-    SystemError, SystemResult, WasmQuery,
+    from_json, to_json_binary, Binary, Coin, ContractResult, Empty, OwnedDeps, Querier,
+    QuerierResult, QueryRequest, SystemError, SystemResult, WasmQuery,
 };
+use prost::Message;
 use std::collections::HashMap;
-use ura::contracts::pair::QueryMsg;
+
+use crate::denom::{
+    DenomAuthorityMetadata, QueryDenomAuthorityMetadataRequest,
+    QueryDenomAuthorityMetadataResponse, DENOM_AUTHORITY_METADATA_PATH,
+};
+use ura::contracts::pair::{PoolResponse, QueryMsg};
+use ura::structs::asset::Asset;
 use ura::structs::pair_info::PairInfo;
 
 /// mock_dependencies is a drop-in replacement for cosmwasm_std::testing::mock_dependencies.
@@ -26,17 +33,20 @@ pub fn mock_dependencies(
 pub struct WasmMockQuerier {
     base: MockQuerier<Empty>,
     ura_pair_querier: UraPairQuerier,
+    denom_admins: HashMap<String, String>,
 }
 
 #[derive(Clone, Default)]
 pub struct UraPairQuerier {
     pairs: HashMap<String, PairInfo>,
+    pools: HashMap<String, Vec<Asset>>,
 }
 
 impl UraPairQuerier {
     pub fn new(pairs: &[(&String, &PairInfo)]) -> Self {
         UraPairQuerier {
             pairs: pairs_to_map(pairs),
+            pools: HashMap::new(),
         }
     }
 }
@@ -83,8 +93,27 @@ impl WasmMockQuerier {
 
                     SystemResult::Ok(to_json_binary(&pair_info).into())
                     }
+                    QueryMsg::Pool {} => {
+                        let assets = self
+                            .ura_pair_querier
+                            .pools
+                            .get(contract_addr)
+                            .cloned()
+                            .unwrap_or_default();
+
+                        SystemResult::Ok(
+                            to_json_binary(&PoolResponse {
+                                assets,
+                                total_share: Default::default(),
+                            })
+                            .into(),
+                        )
+                    }
                     _ => panic!("DO NOT ENTER HERE")
             }
+            QueryRequest::Stargate { path, data } if path == DENOM_AUTHORITY_METADATA_PATH => {
+                self.handle_denom_admin_query(data)
+            }
             _ => self.base.handle_query(request),
         }
     }
@@ -95,6 +124,24 @@ impl WasmMockQuerier {
         WasmMockQuerier {
             base,
             ura_pair_querier: UraPairQuerier::default(),
+            denom_admins: HashMap::new(),
+        }
+    }
+
+    fn handle_denom_admin_query(&self, data: &Binary) -> QuerierResult {
+        let denom = QueryDenomAuthorityMetadataRequest::decode(data.as_slice())
+            .unwrap()
+            .denom;
+        match self.denom_admins.get(&denom) {
+            Some(admin) => SystemResult::Ok(ContractResult::Ok(Binary(
+                QueryDenomAuthorityMetadataResponse {
+                    authority_metadata: Some(DenomAuthorityMetadata {
+                        admin: admin.clone(),
+                    }),
+                }
+                .encode_to_vec(),
+            ))),
+            None => SystemResult::Ok(ContractResult::Err(format!("denom {denom} not found"))),
         }
     }
 
@@ -102,4 +149,21 @@ impl WasmMockQuerier {
     pub fn with_ura_pairs(&mut self, pairs: &[(&String, &PairInfo)]) {
         self.ura_pair_querier = UraPairQuerier::new(pairs);
     }
+
+    // Configure the pool assets returned by the Ura pairs
+    pub fn with_pool_assets(&mut self, pools: &[(&String, &[Asset])]) {
+        for (pair, assets) in pools {
+            self.ura_pair_querier
+                .pools
+                .insert(pair.to_string(), assets.to_vec());
+        }
+    }
+
+    // Configure the admins of tokenfactory denoms
+    pub fn with_denom_admins(&mut self, admins: &[(&str, &str)]) {
+        for (denom, admin) in admins {
+            self.denom_admins
+                .insert(denom.to_string(), admin.to_string());
+        }
+    }
 }
@@ -1,13 +1,19 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{to_json_binary, Binary, Deps, Env, Order, StdResult};
-use ura::contracts::factory::{ConfigResponse, FeeInfoResponse, PairType, PairsResponse, QueryMsg};
-use ura::contracts::pair::QueryMsg as PairQueryMsg;
+use cosmwasm_std::{to_json_binary, Binary, Deps, Env, Order, StdError, StdResult};
+use ura::contracts::factory::{
+    ConfigResponse, FeeInfoResponse, PairTvl, PairType, PairTypeCodeIdResponse, PairsResponse,
+    QueryMsg, TvlResponse,
+};
+use ura::contracts::pair::{PoolResponse, QueryMsg as PairQueryMsg};
 use ura::structs::asset_info::AssetInfo;
 use ura::structs::pair_info::PairInfo;
+use ura::utils::format::pair_key;
+use ura::utils::pagination::MAX_LIMIT;
 
+use crate::error::ContractError;
 use crate::state::CREATED_PAIRS;
-use crate::state::{pair_key, read_pairs, CONFIG, PAIRS, PAIR_CONFIGS};
+use crate::state::{read_pairs, ADDR_TO_KEY, CONFIG, LP_TO_PAIR, PAIRS, PAIR_CONFIGS};
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
@@ -19,6 +25,24 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
         }
         QueryMsg::FeeInfo { pair_type } => to_json_binary(&query_fee_info(deps, pair_type)?),
         QueryMsg::BlacklistedPairTypes {} => to_json_binary(&query_blacklisted_pair_types(deps)?),
+        QueryMsg::Tvl { start_after, limit } => {
+            to_json_binary(&query_tvl(deps, start_after, limit)?)
+        }
+        QueryMsg::PairsByAssetPairs { asset_info_pairs } => {
+            to_json_binary(&query_pairs_by_asset_pairs(deps, asset_info_pairs)?)
+        }
+        QueryMsg::PairTypeCodeId { pair_type } => {
+            to_json_binary(&query_pair_type_code_id(deps, pair_type)?)
+        }
+        QueryMsg::PairByAddress { contract_addr } => {
+            to_json_binary(&query_pair_by_address(deps, contract_addr)?)
+        }
+        QueryMsg::PairByLpToken { lp_token } => {
+            to_json_binary(&query_pair_by_lp_token(deps, lp_token)?)
+        }
+        QueryMsg::CanonicalAssetOrder { asset_infos } => {
+            to_json_binary(&query_canonical_asset_order(deps, asset_infos)?)
+        }
     }
 }
 
@@ -49,6 +73,11 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
         controller_address: config.controller_address,
         coin_registry_address: config.coin_registry_address,
         fee_address: config.fee_address,
+        disallow_lp_assets: config.disallow_lp_assets,
+        controller_gas_limit: config.controller_gas_limit,
+        trusted_denom_admins: config.trusted_denom_admins,
+        asset_fee_addresses: config.asset_fee_addresses,
+        denom_creation_fee: config.denom_creation_fee,
     };
 
     Ok(resp)
@@ -59,6 +88,62 @@ pub fn query_pair(deps: Deps, asset_infos: Vec<AssetInfo>) -> StdResult<PairInfo
     query_pair_info(deps, pair_addr)
 }
 
+/// Returns information about the registered pair at `contract_addr`. The pair must still be
+/// the one [`PAIRS`] holds under its key.
+pub fn query_pair_by_address(deps: Deps, contract_addr: String) -> StdResult<PairInfo> {
+    let pair_addr = deps.api.addr_validate(&contract_addr)?;
+    let registered_addr = match ADDR_TO_KEY.may_load(deps.storage, &pair_addr)? {
+        Some(pair_key) => PAIRS.may_load(deps.storage, &pair_key)?,
+        None => None,
+    };
+    if registered_addr.as_ref() != Some(&pair_addr) {
+        return Err(StdError::generic_err(format!(
+            "{contract_addr} is not a registered pair"
+        )));
+    }
+    query_pair_info(deps, pair_addr)
+}
+
+/// Returns information about the registered pair that issued `lp_token`.
+pub fn query_pair_by_lp_token(deps: Deps, lp_token: AssetInfo) -> StdResult<PairInfo> {
+    let pair_addr = LP_TO_PAIR.load(deps.storage, lp_token.as_bytes())?;
+    query_pair_info(deps, pair_addr)
+}
+
+/// Returns the assets of the pair registered for `asset_infos` in the order of its pool.
+///
+/// The [`pair_key`] the pair is stored under sorts the assets, but the pair keeps the order it
+/// was created with, so the order is read from the pair itself.
+pub fn query_canonical_asset_order(
+    deps: Deps,
+    asset_infos: Vec<AssetInfo>,
+) -> StdResult<Vec<AssetInfo>> {
+    Ok(query_pair(deps, asset_infos)?.asset_infos)
+}
+
+/// Returns information about the pairs for each of the given asset pairs in the same order.
+/// Asset pairs without a registered pair are returned as [`None`].
+pub fn query_pairs_by_asset_pairs(
+    deps: Deps,
+    asset_info_pairs: Vec<Vec<AssetInfo>>,
+) -> StdResult<Vec<Option<PairInfo>>> {
+    if asset_info_pairs.len() > MAX_LIMIT as usize {
+        return Err(StdError::generic_err(format!(
+            "Cannot query more than {MAX_LIMIT} asset pairs at once"
+        )));
+    }
+
+    asset_info_pairs
+        .iter()
+        .map(|asset_infos| {
+            PAIRS
+                .may_load(deps.storage, &pair_key(asset_infos))?
+                .map(|pair_addr| query_pair_info(deps, pair_addr))
+                .transpose()
+        })
+        .collect()
+}
+
 pub fn query_pairs(
     deps: Deps,
     start_after: Option<Vec<AssetInfo>>,
@@ -72,11 +157,53 @@ pub fn query_pairs(
     Ok(PairsResponse { pairs })
 }
 
+/// Returns the reserves of the pairs read according to `start_after` and `limit` (see [`read_pairs`]).
+pub fn query_tvl(
+    deps: Deps,
+    start_after: Option<Vec<AssetInfo>>,
+    limit: Option<u32>,
+) -> StdResult<TvlResponse> {
+    let pools = read_pairs(deps, start_after, limit)?
+        .iter()
+        .map(|pair_addr| {
+            let pair = query_pair_info(deps, pair_addr)?;
+            let pool: PoolResponse = deps
+                .querier
+                .query_wasm_smart(&pair.contract_addr, &PairQueryMsg::Pool {})?;
+
+            Ok(PairTvl {
+                pair,
+                assets: pool.assets,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(TvlResponse { pools })
+}
+
 pub fn query_fee_info(deps: Deps, pair_type: PairType) -> StdResult<FeeInfoResponse> {
     let pair_config = PAIR_CONFIGS.load(deps.storage, pair_type.to_string())?;
 
     Ok(FeeInfoResponse {
         total_fee_bps: pair_config.total_fee_bps,
+        is_min_commission_enabled: pair_config.is_min_commission_enabled,
+        min_swap_amount: pair_config.min_swap_amount,
+        max_price_impact: pair_config.max_price_impact,
+    })
+}
+
+/// Returns the code ID and disabled flag stored in the config of the given pair type.
+pub fn query_pair_type_code_id(
+    deps: Deps,
+    pair_type: PairType,
+) -> StdResult<PairTypeCodeIdResponse> {
+    let pair_config = PAIR_CONFIGS
+        .may_load(deps.storage, pair_type.to_string())?
+        .ok_or_else(|| StdError::generic_err(ContractError::PairConfigNotFound {}.to_string()))?;
+
+    Ok(PairTypeCodeIdResponse {
+        code_id: pair_config.code_id,
+        is_disabled: pair_config.is_disabled,
     })
 }
 
@@ -1,10 +1,13 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Api, Deps, Empty, Order, StdResult};
+use cosmwasm_std::{Addr, Api, Deps, Empty, Order, StdResult, Storage};
 use cw_storage_plus::{Bound, Item, Map};
 use itertools::Itertools;
 use ura::contracts::factory::{Config, PairConfig};
+use ura::errors::DexError;
 use ura::structs::asset_info::AssetInfo;
+use ura::utils::format::pair_key;
 use ura::utils::ownership::OwnershipProposal;
+use ura::utils::pagination::{calc_range_start, clamp_limit};
 
 use crate::error::ContractError;
 /// This is an intermediate structure for storing a pair's key. It is used in a submessage response.
@@ -13,8 +16,11 @@ pub struct TmpPairInfo {
     pub pair_key: Vec<u8>,
 }
 
-/// Saves a pair's key
-pub const TMP_PAIR_INFO: Item<TmpPairInfo> = Item::new("tmp_pair_info");
+/// Saves the keys of the pairs being created by the reply id of their instantiation
+pub const TMP_PAIR_INFOS: Map<u64, TmpPairInfo> = Map::new("tmp_pair_infos");
+
+/// Saves the reply id of the next pair instantiation
+pub const NEXT_PAIR_REPLY_ID: Item<u64> = Item::new("next_pair_reply_id");
 
 /// Saves factory settings
 pub const CONFIG: Item<Config> = Item::new("config");
@@ -22,31 +28,28 @@ pub const CONFIG: Item<Config> = Item::new("config");
 /// Saves created pairs (from olders to latest)
 pub const PAIRS: Map<&[u8], Addr> = Map::new("pair_info");
 
+/// Saves registered pairs by the bytes of their LP token [`AssetInfo`]
+pub const LP_TO_PAIR: Map<&[u8], Addr> = Map::new("lp_to_pair");
+
+/// Saves the [`PAIRS`] key of registered pairs by their contract address
+pub const ADDR_TO_KEY: Map<&Addr, Vec<u8>> = Map::new("addr_to_key");
+
 /// Saves pairs created by factory
 pub const CREATED_PAIRS: Map<&Addr, Empty> = Map::new("created_pairs");
 
-/// Calculates a pair key from the specified parameters in the `asset_infos` variable.
-///
-/// `asset_infos` is an array with multiple items of type [`AssetInfo`].
-pub fn pair_key(asset_infos: &[AssetInfo]) -> Vec<u8> {
-    asset_infos
-        .iter()
-        .map(AssetInfo::as_bytes)
-        .sorted()
-        .flatten()
-        .copied()
-        .collect()
+/// Stores the key of a pair being created under a fresh reply id and returns that id, so that
+/// pending creations never overwrite each other.
+pub fn save_tmp_pair_info(storage: &mut dyn Storage, pair_key: Vec<u8>) -> StdResult<u64> {
+    let reply_id = NEXT_PAIR_REPLY_ID.may_load(storage)?.unwrap_or(1);
+    NEXT_PAIR_REPLY_ID.save(storage, &(reply_id + 1))?;
+    TMP_PAIR_INFOS.save(storage, reply_id, &TmpPairInfo { pair_key })?;
+
+    Ok(reply_id)
 }
 
 /// Saves pair type configurations
 pub const PAIR_CONFIGS: Map<String, PairConfig> = Map::new("pair_configs");
 
-/// ## Pagination settings
-/// The maximum limit for reading pairs from [`PAIRS`]
-const MAX_LIMIT: u32 = 30;
-/// The default limit for reading pairs from [`PAIRS`]
-const DEFAULT_LIMIT: u32 = 10;
-
 /// Reads pairs from the [`PAIRS`] vector according to the `start_after` and `limit` variables.
 /// Otherwise, it returns the default number of pairs, starting from the oldest one.
 ///
@@ -58,52 +61,33 @@ pub fn read_pairs(
     start_after: Option<Vec<AssetInfo>>,
     limit: Option<u32>,
 ) -> StdResult<Vec<Addr>> {
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-
-    if let Some(start) = calc_range_start(start_after) {
-        PAIRS
-            .range(
-                deps.storage,
-                Some(Bound::exclusive(start.as_slice())),
-                None,
-                Order::Ascending,
-            )
-            .take(limit)
-            .map(|item| {
-                let (_, pair_addr) = item?;
-                Ok(pair_addr)
-            })
-            .collect()
-    } else {
-        PAIRS
-            .range(deps.storage, None, None, Order::Ascending)
-            .take(limit)
-            .map(|item| {
-                let (_, pair_addr) = item?;
-                Ok(pair_addr)
-            })
-            .collect()
-    }
-}
-
-/// Calculates the key of a pair from which to start reading data.
-///
-/// `start_after` is an [`Option`] type that accepts [`AssetInfo`] elements.
-/// It is the token pair which we use to determine the start index for a range when returning data for multiple pairs
-fn calc_range_start(start_after: Option<Vec<AssetInfo>>) -> Option<Vec<u8>> {
-    start_after.map(|ref asset| {
-        let mut key = pair_key(asset);
-        key.push(1);
-        key
-    })
+    let start = calc_range_start(start_after.map(|asset_infos| pair_key(&asset_infos)));
+
+    PAIRS
+        .range(
+            deps.storage,
+            start.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(clamp_limit(limit))
+        .map(|item| {
+            let (_, pair_addr) = item?;
+            Ok(pair_addr)
+        })
+        .collect()
 }
 
 pub(crate) fn check_asset_infos(
     api: &dyn Api,
     asset_infos: &[AssetInfo],
 ) -> Result<(), ContractError> {
-    if !asset_infos.iter().all_unique() {
-        return Err(ContractError::DoublingAssets {});
+    if asset_infos
+        .iter()
+        .tuple_combinations()
+        .any(|(a, b)| a.equal(b))
+    {
+        return Err(ContractError::Dex(DexError::DoublingAssets {}));
     }
 
     asset_infos
@@ -142,6 +126,20 @@ mod tests {
         ]
     }
 
+    #[test]
+    fn check_asset_infos_rejects_case_variants() {
+        let api = cosmwasm_std::testing::MockApi::default();
+        let err = check_asset_infos(
+            &api,
+            &[
+                native_asset_info("ibc/ABCDEF".to_string()),
+                native_asset_info("ibc/abcdef".to_string()),
+            ],
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::Dex(DexError::DoublingAssets {}));
+    }
+
     #[test]
     fn test_legacy_pair_key() {
         fn legacy_pair_key(asset_infos: &[AssetInfo; 2]) -> Vec<u8> {
@@ -175,7 +173,7 @@ mod tests {
         for asset_infos in get_test_case() {
             assert_eq!(
                 legacy_calc_range_start(Some(asset_infos.clone())),
-                calc_range_start(Some(asset_infos.to_vec()))
+                calc_range_start(Some(pair_key(&asset_infos)))
             );
         }
     }
@@ -1,21 +1,26 @@
 use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    attr, from_json, to_json_binary, Addr, Reply, ReplyOn, SubMsg, SubMsgResponse, SubMsgResult,
-    WasmMsg,
+    attr, coin, from_json, to_json_binary, Addr, Binary, CosmosMsg, Deps, DepsMut, Empty, Reply,
+    ReplyOn, StdError, StdResult, SubMsg, SubMsgResponse, SubMsgResult, Uint128, WasmMsg,
 };
 use prost::Message;
 use ura::contracts::factory::{
-    ConfigResponse, ExecuteMsg, InstantiateMsg, PairConfig, PairType, PairsResponse, QueryMsg,
+    ConfigResponse, CreatePairRequest, ExecuteMsg, InstantiateMsg, MigrateMsg, PairConfig, PairTvl,
+    PairType, PairTypeCodeIdResponse, PairsResponse, QueryMsg, TvlResponse, MAX_CREATE_PAIRS,
 };
 use ura::contracts::pair::InstantiateMsg as PairInstantiateMsg;
+use ura::errors::DexError;
+use ura::structs::asset::Asset;
 use ura::structs::asset_info::AssetInfo;
 use ura::structs::pair_info::PairInfo;
+use ura::utils::format::pair_key;
+use ura::utils::ownership::MAX_OWNERSHIP_PROPOSAL_TTL;
 
-use crate::contract::reply;
+use crate::contract::{migrate, reply};
 use crate::executes::execute;
-use crate::mock_querier::mock_dependencies;
+use crate::mock_querier::{mock_dependencies, WasmMockQuerier};
 use crate::queries::query;
-use crate::state::CONFIG;
+use crate::state::{CONFIG, CREATED_PAIRS, PAIRS, TMP_PAIR_INFOS};
 use crate::{contract::instantiate, error::ContractError};
 
 #[derive(Clone, PartialEq, Message)]
@@ -46,6 +51,9 @@ fn proper_initialization() {
                 total_fee_bps: 100,
                 is_disabled: false,
                 is_controller_disabled: false,
+                is_min_commission_enabled: false,
+                min_swap_amount: None,
+                max_price_impact: None,
             },
             PairConfig {
                 code_id: 325u64,
@@ -53,11 +61,18 @@ fn proper_initialization() {
                 total_fee_bps: 100,
                 is_disabled: false,
                 is_controller_disabled: false,
+                is_min_commission_enabled: false,
+                min_swap_amount: None,
+                max_price_impact: None,
             },
         ],
         controller_address: Some(String::from("controller")),
         owner: owner.clone(),
         coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
         fee_address: None,
         token_code_id: 123u64,
     };
@@ -75,10 +90,17 @@ fn proper_initialization() {
             total_fee_bps: 10_001,
             is_disabled: false,
             is_controller_disabled: false,
+            is_min_commission_enabled: false,
+            min_swap_amount: None,
+            max_price_impact: None,
         }],
         controller_address: Some(String::from("controller")),
         owner: owner.clone(),
         coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
         fee_address: None,
         token_code_id: 123u64,
     };
@@ -99,6 +121,9 @@ fn proper_initialization() {
                 total_fee_bps: 100,
                 is_disabled: false,
                 is_controller_disabled: false,
+                is_min_commission_enabled: false,
+                min_swap_amount: None,
+                max_price_impact: None,
             },
             PairConfig {
                 code_id: 123u64,
@@ -106,11 +131,18 @@ fn proper_initialization() {
                 total_fee_bps: 100,
                 is_disabled: false,
                 is_controller_disabled: false,
+                is_min_commission_enabled: false,
+                min_swap_amount: None,
+                max_price_impact: None,
             },
         ],
         controller_address: Some(String::from("controller")),
         owner: owner.clone(),
         coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
         fee_address: None,
         token_code_id: 123u64,
     };
@@ -137,6 +169,9 @@ fn update_config() {
         total_fee_bps: 3,
         is_disabled: false,
         is_controller_disabled: false,
+        is_min_commission_enabled: false,
+        min_swap_amount: None,
+        max_price_impact: None,
     }];
 
     let msg = InstantiateMsg {
@@ -144,6 +179,10 @@ fn update_config() {
         owner: owner.to_string(),
         controller_address: Some(String::from("controller")),
         coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
         fee_address: None,
         token_code_id: 123u64,
     };
@@ -161,6 +200,10 @@ fn update_config() {
         fee_address: None,
         controller_address: Some(String::from("new_controller_addr")),
         coin_registry_address: None,
+        disallow_lp_assets: None,
+        controller_gas_limit: Some(500_000),
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
     };
 
     let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
@@ -174,6 +217,7 @@ fn update_config() {
         String::from("new_controller_addr"),
         config_res.controller_address.unwrap()
     );
+    assert_eq!(config_res.controller_gas_limit, Some(500_000));
 
     // Unauthorized err
     let env = mock_env();
@@ -182,10 +226,83 @@ fn update_config() {
         fee_address: None,
         controller_address: None,
         coin_registry_address: None,
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
     };
 
     let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
-    assert_eq!(res, ContractError::Unauthorized {});
+    assert_eq!(res, ContractError::Dex(DexError::Unauthorized {}));
+}
+
+#[test]
+fn set_asset_fee_address() {
+    let mut deps = mock_dependencies(&[]);
+    let owner = "owner0000";
+
+    let msg = InstantiateMsg {
+        pair_configs: vec![],
+        owner: owner.to_string(),
+        controller_address: None,
+        coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
+        fee_address: Some("fee".to_string()),
+        token_code_id: 123u64,
+    };
+
+    let env = mock_env();
+    instantiate(deps.as_mut(), env.clone(), mock_info(owner, &[]), msg).unwrap();
+
+    let uusd = AssetInfo::native("uusd");
+    let token = AssetInfo::cw20_unchecked("asset0000");
+    for (asset_info, fee_address) in [(&uusd, "uusd_fee"), (&token, "token_fee")] {
+        let msg = ExecuteMsg::SetAssetFeeAddress {
+            asset_info: asset_info.clone(),
+            fee_address: Some(fee_address.to_string()),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(owner, &[]), msg).unwrap();
+    }
+
+    let config: ConfigResponse =
+        from_json(query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(config.fee_address, Addr::unchecked("fee"));
+    assert_eq!(
+        config.asset_fee_addresses,
+        vec![
+            (uusd.clone(), Addr::unchecked("uusd_fee")),
+            (token.clone(), Addr::unchecked("token_fee")),
+        ]
+    );
+
+    // Setting an asset again replaces its address, unsetting it falls back to the fee address
+    let msg = ExecuteMsg::SetAssetFeeAddress {
+        asset_info: uusd.clone(),
+        fee_address: Some("new_uusd_fee".to_string()),
+    };
+    execute(deps.as_mut(), env.clone(), mock_info(owner, &[]), msg).unwrap();
+    let msg = ExecuteMsg::SetAssetFeeAddress {
+        asset_info: token.clone(),
+        fee_address: None,
+    };
+    execute(deps.as_mut(), env.clone(), mock_info(owner, &[]), msg).unwrap();
+
+    let config: ConfigResponse =
+        from_json(query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(
+        config.asset_fee_addresses,
+        vec![(uusd.clone(), Addr::unchecked("new_uusd_fee"))]
+    );
+
+    let msg = ExecuteMsg::SetAssetFeeAddress {
+        asset_info: uusd,
+        fee_address: None,
+    };
+    let err = execute(deps.as_mut(), env, mock_info("addr0000", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::Unauthorized {}));
 }
 
 #[test]
@@ -198,6 +315,10 @@ fn update_owner() {
         owner: owner.to_string(),
         controller_address: Some(String::from("controller")),
         coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
         fee_address: None,
         token_code_id: 123u64,
     };
@@ -210,8 +331,24 @@ fn update_owner() {
 
     let new_owner = String::from("new_owner");
 
-    // New owner
+    // Over-long proposal window
     let env = mock_env();
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(owner, &[]),
+        ExecuteMsg::ProposeNewOwner {
+            owner: new_owner.clone(),
+            expires_in: MAX_OWNERSHIP_PROPOSAL_TTL + 1,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("Generic error: Parameter expires_in cannot be higher than {MAX_OWNERSHIP_PROPOSAL_TTL}")
+    );
+
+    // New owner
     let msg = ExecuteMsg::ProposeNewOwner {
         owner: new_owner.clone(),
         expires_in: 100, // seconds
@@ -276,6 +413,9 @@ fn update_pair_config() {
         total_fee_bps: 100,
         is_disabled: false,
         is_controller_disabled: false,
+        is_min_commission_enabled: false,
+        min_swap_amount: None,
+        max_price_impact: None,
     }];
 
     let msg = InstantiateMsg {
@@ -283,6 +423,10 @@ fn update_pair_config() {
         owner: owner.to_string(),
         controller_address: Some(String::from("controller")),
         coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
         fee_address: None,
         token_code_id: 123u64,
     };
@@ -305,6 +449,9 @@ fn update_pair_config() {
         total_fee_bps: 1,
         is_disabled: false,
         is_controller_disabled: false,
+        is_min_commission_enabled: false,
+        min_swap_amount: None,
+        max_price_impact: None,
     };
 
     // Unauthorized err
@@ -315,7 +462,7 @@ fn update_pair_config() {
     };
 
     let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
-    assert_eq!(res, ContractError::Unauthorized {});
+    assert_eq!(res, ContractError::Dex(DexError::Unauthorized {}));
 
     let info = mock_info(owner.clone(), &[]);
     let msg = ExecuteMsg::UpdatePairConfig {
@@ -330,6 +477,37 @@ fn update_pair_config() {
     let config_res: ConfigResponse = from_json(&query_res).unwrap();
     assert_eq!(vec![pair_config.clone()], config_res.pair_configs);
 
+    let query_res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::PairTypeCodeId {
+            pair_type: PairType::Xyk,
+        },
+    )
+    .unwrap();
+    let code_id_res: PairTypeCodeIdResponse = from_json(query_res).unwrap();
+    assert_eq!(
+        code_id_res,
+        PairTypeCodeIdResponse {
+            code_id: 800,
+            is_disabled: false,
+        }
+    );
+
+    // The stable pair type is not configured yet
+    let err = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::PairTypeCodeId {
+            pair_type: PairType::Stable,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("Generic error: {}", ContractError::PairConfigNotFound {})
+    );
+
     // Add second config
     let pair_config_2 = PairConfig {
         code_id: 100,
@@ -337,6 +515,9 @@ fn update_pair_config() {
         total_fee_bps: 10,
         is_disabled: false,
         is_controller_disabled: false,
+        is_min_commission_enabled: false,
+        min_swap_amount: None,
+        max_price_impact: None,
     };
 
     let info = mock_info(owner.clone(), &[]);
@@ -365,6 +546,9 @@ fn create_pair() {
         total_fee_bps: 100,
         is_disabled: false,
         is_controller_disabled: false,
+        is_min_commission_enabled: false,
+        min_swap_amount: None,
+        max_price_impact: None,
     };
 
     let msg = InstantiateMsg {
@@ -372,6 +556,10 @@ fn create_pair() {
         owner: "owner0000".to_string(),
         controller_address: Some(String::from("controller")),
         coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
         fee_address: None,
         token_code_id: 123u64,
     };
@@ -405,6 +593,7 @@ fn create_pair() {
             asset_infos: asset_infos.clone(),
             init_params: None,
             toggle_cw20_token: Some(true),
+            immutable_lp: None,
         },
     )
     .unwrap_err();
@@ -419,6 +608,7 @@ fn create_pair() {
             asset_infos: asset_infos.clone(),
             init_params: None,
             toggle_cw20_token: Some(true),
+            immutable_lp: Some(true),
         },
     )
     .unwrap();
@@ -439,6 +629,8 @@ fn create_pair() {
                     asset_infos: asset_infos.clone(),
                     init_params: None,
                     token_code_id: Some(123u64),
+                    denom_creation_fee: vec![],
+                    immutable_lp: true,
                 })
                 .unwrap(),
                 code_id: pair_config.code_id,
@@ -455,28 +647,32 @@ fn create_pair() {
 }
 
 #[test]
-fn register() {
+fn create_pair_forwards_denom_creation_fee() {
     let mut deps = mock_dependencies(&[]);
-    let owner = "owner0000";
 
+    let pair_config = PairConfig {
+        code_id: 321u64,
+        pair_type: PairType::Xyk,
+        total_fee_bps: 100,
+        is_disabled: false,
+        is_controller_disabled: false,
+        is_min_commission_enabled: false,
+        min_swap_amount: None,
+        max_price_impact: None,
+    };
     let msg = InstantiateMsg {
-        pair_configs: vec![PairConfig {
-            code_id: 123u64,
-            pair_type: PairType::Xyk,
-            total_fee_bps: 100,
-            is_disabled: false,
-            is_controller_disabled: false,
-        }],
-        controller_address: Some(String::from("controller")),
-        owner: owner.to_string(),
+        pair_configs: vec![pair_config.clone()],
+        owner: "owner0000".to_string(),
+        controller_address: None,
         coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: Some(vec![coin(10_000_000, "uosmo")]),
         fee_address: None,
         token_code_id: 123u64,
     };
-
-    let env = mock_env();
-    let info = mock_info("addr0000", &[]);
-    let _res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
 
     let asset_infos = vec![
         AssetInfo::Token {
@@ -486,127 +682,676 @@ fn register() {
             contract_addr: Addr::unchecked("asset0001"),
         },
     ];
-
-    let msg = ExecuteMsg::CreatePair {
+    let create_pair = |toggle_cw20_token: Option<bool>| ExecuteMsg::CreatePair {
         pair_type: PairType::Xyk,
         asset_infos: asset_infos.clone(),
         init_params: None,
-        toggle_cw20_token: None,
-    };
-
-    let env = mock_env();
-    let info = mock_info("addr0000", &[]);
-    let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
-
-    let pair0_addr = "pair0000".to_string();
-    let pair0_info = PairInfo {
-        asset_infos: asset_infos.clone(),
-        contract_addr: Addr::unchecked("pair0000"),
-        liquidity_token: AssetInfo::Token {
-            contract_addr: Addr::unchecked("liquidity0000".to_string()),
-        },
-        pair_type: PairType::Xyk,
-    };
-
-    let mut deployed_pairs = vec![(&pair0_addr, &pair0_info)];
-
-    // Register an URA pair querier
-    deps.querier.with_ura_pairs(&deployed_pairs);
-
-    let instantiate_reply = MsgInstantiateContractResponse {
-        contract_address: String::from("pair0000"),
-        data: vec![],
-    };
-
-    let mut encoded_instantiate_reply = Vec::<u8>::with_capacity(instantiate_reply.encoded_len());
-    instantiate_reply
-        .encode(&mut encoded_instantiate_reply)
-        .unwrap();
-
-    let reply_msg = Reply {
-        id: 2,
-        result: SubMsgResult::Ok(SubMsgResponse {
-            events: vec![],
-            data: Some(encoded_instantiate_reply.into()),
-        }),
+        toggle_cw20_token,
+        immutable_lp: None,
     };
 
-    let _res = reply(deps.as_mut(), mock_env(), reply_msg.clone()).unwrap();
+    // Funds other than the configured fee would be left in the pair
+    for (funds, toggle_cw20_token) in [
+        (vec![coin(9_000_000, "uosmo")], None),
+        (vec![coin(15_000_000, "uosmo")], None),
+        (vec![coin(10_000_000, "uosmo"), coin(1, "uusd")], None),
+        (vec![coin(10_000_000, "uosmo")], Some(true)),
+    ] {
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("addr0000", &funds),
+            create_pair(toggle_cw20_token),
+        )
+        .unwrap_err();
+        let fee = if toggle_cw20_token.is_some() {
+            ""
+        } else {
+            "10000000uosmo"
+        };
+        assert_eq!(
+            err,
+            ContractError::DenomCreationFeeMismatch {
+                fee: fee.to_string()
+            }
+        );
+    }
 
-    let query_res = query(
-        deps.as_ref(),
-        env.clone(),
-        QueryMsg::Pair {
-            asset_infos: asset_infos.clone(),
-        },
+    let funds = vec![coin(10_000_000, "uosmo")];
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &funds),
+        create_pair(None),
     )
     .unwrap();
 
-    let pair_res: PairInfo = from_json(&query_res).unwrap();
     assert_eq!(
-        pair_res,
-        PairInfo {
-            liquidity_token: AssetInfo::Token {
-                contract_addr: Addr::unchecked("liquidity0000".to_string()),
-            },
-            contract_addr: Addr::unchecked("pair0000"),
-            asset_infos: asset_infos.clone(),
-            pair_type: PairType::Xyk,
-        }
+        res.messages,
+        vec![SubMsg {
+            msg: WasmMsg::Instantiate {
+                msg: to_json_binary(&PairInstantiateMsg {
+                    factory_addr: String::from(MOCK_CONTRACT_ADDR),
+                    asset_infos,
+                    init_params: None,
+                    token_code_id: None,
+                    denom_creation_fee: vec![coin(10_000_000, "uosmo")],
+                    immutable_lp: false,
+                })
+                .unwrap(),
+                code_id: pair_config.code_id,
+                funds,
+                admin: Some("owner0000".to_string()),
+                label: String::from("Ura Pair"),
+            }
+            .into(),
+            id: 1,
+            gas_limit: None,
+            reply_on: ReplyOn::Success
+        }]
     );
+}
 
-    // Check pair was registered
-    let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap_err();
-    assert_eq!(res, ContractError::PairWasRegistered {});
+#[test]
+fn create_pair_rejects_untrusted_denom_admins() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_denom_admins(&[
+        ("factory/minter/uura", "minter"),
+        ("factory/stranger/umeme", "stranger"),
+        ("factory/stranger/ufixed", ""),
+    ]);
 
-    // Store one more item to test query pairs
-    let asset_infos_2 = vec![
-        AssetInfo::Token {
-            contract_addr: Addr::unchecked("asset0000"),
-        },
-        AssetInfo::Token {
-            contract_addr: Addr::unchecked("asset0002"),
-        },
-    ];
+    let msg = InstantiateMsg {
+        pair_configs: vec![PairConfig {
+            code_id: 321u64,
+            pair_type: PairType::Xyk,
+            total_fee_bps: 100,
+            is_disabled: false,
+            is_controller_disabled: false,
+            is_min_commission_enabled: false,
+            min_swap_amount: None,
+            max_price_impact: None,
+        }],
+        owner: "owner0000".to_string(),
+        controller_address: None,
+        coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: Some(vec!["minter".to_string()]),
+        denom_creation_fee: None,
+        fee_address: None,
+        token_code_id: 123u64,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
 
-    let msg = ExecuteMsg::CreatePair {
+    let create_pair = |denom: &str| ExecuteMsg::CreatePair {
         pair_type: PairType::Xyk,
-        asset_infos: asset_infos_2.clone(),
+        asset_infos: vec![AssetInfo::native("uusd"), AssetInfo::native(denom)],
         init_params: None,
         toggle_cw20_token: None,
+        immutable_lp: None,
     };
 
-    let env = mock_env();
-    let info = mock_info("addr0000", &[]);
-    let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        create_pair("factory/stranger/umeme"),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::UntrustedDenomAdmin {
+            asset: "factory/stranger/umeme".to_string(),
+            admin: "stranger".to_string(),
+        }
+    );
 
-    let pair1_addr = "pair0001".to_string();
-    let pair1_info = PairInfo {
-        asset_infos: asset_infos_2.clone(),
-        contract_addr: Addr::unchecked("pair0001"),
-        liquidity_token: AssetInfo::Token {
-            contract_addr: Addr::unchecked("liquidity0001".to_string()),
-        },
-        pair_type: PairType::Xyk,
+    // Denoms of a trusted admin or without any admin are fine
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        create_pair("factory/minter/uura"),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        create_pair("factory/stranger/ufixed"),
+    )
+    .unwrap();
+
+    // The owner can lift the restriction
+    let msg = ExecuteMsg::UpdateConfig {
+        fee_address: None,
+        controller_address: None,
+        coin_registry_address: None,
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: Some(vec![]),
+        denom_creation_fee: None,
     };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+    let config: ConfigResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(config.trusted_denom_admins, None);
 
-    deployed_pairs.push((&pair1_addr, &pair1_info));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        create_pair("factory/stranger/umeme"),
+    )
+    .unwrap();
+}
 
-    // Register URA pair querier
-    deps.querier.with_ura_pairs(&deployed_pairs);
+#[test]
+fn create_pair_rejects_lp_assets() {
+    let mut deps = mock_dependencies(&[]);
 
-    let instantiate_reply = MsgInstantiateContractResponse {
-        contract_address: String::from("pair0001"),
-        data: vec![],
+    let msg = InstantiateMsg {
+        pair_configs: vec![PairConfig {
+            code_id: 321u64,
+            pair_type: PairType::Xyk,
+            total_fee_bps: 100,
+            is_disabled: false,
+            is_controller_disabled: false,
+            is_min_commission_enabled: false,
+            min_swap_amount: None,
+            max_price_impact: None,
+        }],
+        owner: "owner0000".to_string(),
+        controller_address: None,
+        coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: Some(true),
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
+        fee_address: None,
+        token_code_id: 123u64,
     };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
 
-    let mut encoded_instantiate_reply = Vec::<u8>::with_capacity(instantiate_reply.encoded_len());
-    instantiate_reply
-        .encode(&mut encoded_instantiate_reply)
-        .unwrap();
+    let create_pair = |denom: &str| ExecuteMsg::CreatePair {
+        pair_type: PairType::Xyk,
+        asset_infos: vec![AssetInfo::native("uusd"), AssetInfo::native(denom)],
+        init_params: None,
+        toggle_cw20_token: None,
+        immutable_lp: None,
+    };
+    let lp_denom = "factory/pair0000/UUSD-ULUN-LP";
 
-    let reply_msg = Reply {
-        id: 2,
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        create_pair(lp_denom),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::LpAssetNotAllowed {
+            asset: lp_denom.to_string()
+        }
+    );
+
+    // Other tokenfactory denoms are fine
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        create_pair("factory/minter/uura"),
+    )
+    .unwrap();
+
+    // The owner can lift the restriction
+    let msg = ExecuteMsg::UpdateConfig {
+        fee_address: None,
+        controller_address: None,
+        coin_registry_address: None,
+        disallow_lp_assets: Some(false),
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner0000", &[]), msg).unwrap();
+    let config: ConfigResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert!(!config.disallow_lp_assets);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        create_pair(lp_denom),
+    )
+    .unwrap();
+}
+
+#[test]
+fn create_pairs_registers_every_pair() {
+    let mut deps = mock_dependencies(&[]);
+
+    let pair_config = PairConfig {
+        code_id: 321u64,
+        pair_type: PairType::Xyk,
+        total_fee_bps: 100,
+        is_disabled: false,
+        is_controller_disabled: false,
+        is_min_commission_enabled: false,
+        min_swap_amount: None,
+        max_price_impact: None,
+    };
+    let msg = InstantiateMsg {
+        pair_configs: vec![pair_config],
+        owner: "owner0000".to_string(),
+        controller_address: None,
+        coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: Some(vec![coin(10, "uosmo")]),
+        fee_address: None,
+        token_code_id: 123u64,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let token = |addr: &str| AssetInfo::Token {
+        contract_addr: Addr::unchecked(addr),
+    };
+    let pair_assets = [
+        vec![token("asset0000"), token("asset0001")],
+        vec![token("asset0000"), token("asset0002")],
+        vec![token("asset0001"), token("asset0002")],
+    ];
+    let request =
+        |asset_infos: &Vec<AssetInfo>, toggle_cw20_token: Option<bool>| CreatePairRequest {
+            pair_type: PairType::Xyk,
+            asset_infos: asset_infos.clone(),
+            init_params: None,
+            toggle_cw20_token,
+            immutable_lp: None,
+        };
+
+    // The batch size is capped
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::CreatePairs {
+            pairs: vec![request(&pair_assets[0], None); MAX_CREATE_PAIRS + 1],
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::TooManyPairs {
+            max: MAX_CREATE_PAIRS
+        }
+    );
+
+    // The same pair cannot be created twice in a batch
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[coin(20, "uosmo")]),
+        ExecuteMsg::CreatePairs {
+            pairs: vec![
+                request(&pair_assets[0], None),
+                request(&pair_assets[0], None),
+            ],
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::PairWasCreated {});
+
+    // The denom creation fee is due once for each native LP pair, nothing more
+    let pairs = vec![
+        request(&pair_assets[0], None),
+        request(&pair_assets[1], Some(true)),
+        request(&pair_assets[2], None),
+    ];
+    for funds in [coin(10, "uosmo"), coin(30, "uosmo")] {
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("addr0000", &[funds]),
+            ExecuteMsg::CreatePairs {
+                pairs: pairs.clone(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::DenomCreationFeeMismatch {
+                fee: "20uosmo".to_string()
+            }
+        );
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[coin(20, "uosmo")]),
+        ExecuteMsg::CreatePairs { pairs },
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "create_pairs"),
+            attr(
+                "pairs",
+                "asset0000-asset0001, asset0000-asset0002, asset0001-asset0002"
+            )
+        ]
+    );
+    let ids: Vec<_> = res.messages.iter().map(|sub_msg| sub_msg.id).collect();
+    assert_eq!(ids, vec![1, 2, 3]);
+    let funds: Vec<_> = res
+        .messages
+        .iter()
+        .map(|sub_msg| match &sub_msg.msg {
+            CosmosMsg::Wasm(WasmMsg::Instantiate { funds, .. }) => funds.clone(),
+            _ => panic!("unexpected message"),
+        })
+        .collect();
+    assert_eq!(
+        funds,
+        vec![vec![coin(10, "uosmo")], vec![], vec![coin(10, "uosmo")]]
+    );
+
+    // Replies can arrive in any order, each registers its own pair
+    with_mock_pairs(&mut deps.querier, &pair_assets);
+    for (id, pair_addr) in [(3, "pair0002"), (1, "pair0000"), (2, "pair0001")] {
+        let instantiate_reply = MsgInstantiateContractResponse {
+            contract_address: pair_addr.to_string(),
+            data: vec![],
+        };
+        let mut encoded_instantiate_reply =
+            Vec::<u8>::with_capacity(instantiate_reply.encoded_len());
+        instantiate_reply
+            .encode(&mut encoded_instantiate_reply)
+            .unwrap();
+        reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(encoded_instantiate_reply.into()),
+                }),
+            },
+        )
+        .unwrap();
+    }
+
+    for (i, asset_infos) in pair_assets.iter().enumerate() {
+        assert_eq!(
+            PAIRS.load(&deps.storage, &pair_key(asset_infos)).unwrap(),
+            Addr::unchecked(format!("pair000{i}"))
+        );
+    }
+    assert!(TMP_PAIR_INFOS.is_empty(&deps.storage));
+}
+
+#[test]
+fn pending_pair_creations_register_with_interleaved_replies() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        pair_configs: vec![PairConfig {
+            code_id: 321u64,
+            pair_type: PairType::Xyk,
+            total_fee_bps: 100,
+            is_disabled: false,
+            is_controller_disabled: false,
+            is_min_commission_enabled: false,
+            min_swap_amount: None,
+            max_price_impact: None,
+        }],
+        owner: "owner0000".to_string(),
+        controller_address: None,
+        coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
+        fee_address: None,
+        token_code_id: 123u64,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let token = |addr: &str| AssetInfo::Token {
+        contract_addr: Addr::unchecked(addr),
+    };
+    let pair_assets = [
+        vec![token("asset0000"), token("asset0001")],
+        vec![token("asset0000"), token("asset0002")],
+    ];
+
+    // Both creations are pending before either pair is instantiated
+    let reply_ids: Vec<_> = pair_assets
+        .iter()
+        .map(|asset_infos| {
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("addr0000", &[]),
+                ExecuteMsg::CreatePair {
+                    pair_type: PairType::Xyk,
+                    asset_infos: asset_infos.clone(),
+                    init_params: None,
+                    toggle_cw20_token: None,
+                    immutable_lp: None,
+                },
+            )
+            .unwrap();
+            res.messages[0].id
+        })
+        .collect();
+    assert_ne!(reply_ids[0], reply_ids[1]);
+
+    // The later creation is answered first
+    with_mock_pairs(&mut deps.querier, &pair_assets);
+    for (reply_id, pair_addr) in [(reply_ids[1], "pair0001"), (reply_ids[0], "pair0000")] {
+        let instantiate_reply = MsgInstantiateContractResponse {
+            contract_address: pair_addr.to_string(),
+            data: vec![],
+        };
+        let mut encoded_instantiate_reply =
+            Vec::<u8>::with_capacity(instantiate_reply.encoded_len());
+        instantiate_reply
+            .encode(&mut encoded_instantiate_reply)
+            .unwrap();
+        reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: reply_id,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(encoded_instantiate_reply.into()),
+                }),
+            },
+        )
+        .unwrap();
+    }
+
+    for (i, asset_infos) in pair_assets.iter().enumerate() {
+        assert_eq!(
+            PAIRS.load(&deps.storage, &pair_key(asset_infos)).unwrap(),
+            Addr::unchecked(format!("pair000{i}"))
+        );
+    }
+    assert!(TMP_PAIR_INFOS.is_empty(&deps.storage));
+}
+
+#[test]
+fn register() {
+    let mut deps = mock_dependencies(&[]);
+    let owner = "owner0000";
+
+    let msg = InstantiateMsg {
+        pair_configs: vec![PairConfig {
+            code_id: 123u64,
+            pair_type: PairType::Xyk,
+            total_fee_bps: 100,
+            is_disabled: false,
+            is_controller_disabled: false,
+            is_min_commission_enabled: false,
+            min_swap_amount: None,
+            max_price_impact: None,
+        }],
+        controller_address: Some(String::from("controller")),
+        owner: owner.to_string(),
+        coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
+        fee_address: None,
+        token_code_id: 123u64,
+    };
+
+    let env = mock_env();
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    let asset_infos = vec![
+        AssetInfo::Token {
+            contract_addr: Addr::unchecked("asset0000"),
+        },
+        AssetInfo::Token {
+            contract_addr: Addr::unchecked("asset0001"),
+        },
+    ];
+
+    let msg = ExecuteMsg::CreatePair {
+        pair_type: PairType::Xyk,
+        asset_infos: asset_infos.clone(),
+        init_params: None,
+        toggle_cw20_token: None,
+        immutable_lp: None,
+    };
+
+    let env = mock_env();
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let pair0_addr = "pair0000".to_string();
+    let pair0_info = PairInfo {
+        asset_infos: asset_infos.clone(),
+        contract_addr: Addr::unchecked("pair0000"),
+        liquidity_token: AssetInfo::Token {
+            contract_addr: Addr::unchecked("liquidity0000".to_string()),
+        },
+        pair_type: PairType::Xyk,
+    };
+
+    let mut deployed_pairs = vec![(&pair0_addr, &pair0_info)];
+
+    // Register an URA pair querier
+    deps.querier.with_ura_pairs(&deployed_pairs);
+
+    let instantiate_reply = MsgInstantiateContractResponse {
+        contract_address: String::from("pair0000"),
+        data: vec![],
+    };
+
+    let mut encoded_instantiate_reply = Vec::<u8>::with_capacity(instantiate_reply.encoded_len());
+    instantiate_reply
+        .encode(&mut encoded_instantiate_reply)
+        .unwrap();
+
+    let reply_msg = Reply {
+        id: res.messages[0].id,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: Some(encoded_instantiate_reply.into()),
+        }),
+    };
+
+    let _res = reply(deps.as_mut(), mock_env(), reply_msg.clone()).unwrap();
+
+    let query_res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::Pair {
+            asset_infos: asset_infos.clone(),
+        },
+    )
+    .unwrap();
+
+    let pair_res: PairInfo = from_json(&query_res).unwrap();
+    assert_eq!(
+        pair_res,
+        PairInfo {
+            liquidity_token: AssetInfo::Token {
+                contract_addr: Addr::unchecked("liquidity0000".to_string()),
+            },
+            contract_addr: Addr::unchecked("pair0000"),
+            asset_infos: asset_infos.clone(),
+            pair_type: PairType::Xyk,
+        }
+    );
+
+    // The reply id is consumed once the pair is registered
+    let res = reply(deps.as_mut(), mock_env(), reply_msg).unwrap_err();
+    assert!(matches!(res, ContractError::Std(StdError::NotFound { .. })));
+
+    // Store one more item to test query pairs
+    let asset_infos_2 = vec![
+        AssetInfo::Token {
+            contract_addr: Addr::unchecked("asset0000"),
+        },
+        AssetInfo::Token {
+            contract_addr: Addr::unchecked("asset0002"),
+        },
+    ];
+
+    let msg = ExecuteMsg::CreatePair {
+        pair_type: PairType::Xyk,
+        asset_infos: asset_infos_2.clone(),
+        init_params: None,
+        toggle_cw20_token: None,
+        immutable_lp: None,
+    };
+
+    let env = mock_env();
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let pair1_addr = "pair0001".to_string();
+    let pair1_info = PairInfo {
+        asset_infos: asset_infos_2.clone(),
+        contract_addr: Addr::unchecked("pair0001"),
+        liquidity_token: AssetInfo::Token {
+            contract_addr: Addr::unchecked("liquidity0001".to_string()),
+        },
+        pair_type: PairType::Xyk,
+    };
+
+    deployed_pairs.push((&pair1_addr, &pair1_info));
+
+    // Register URA pair querier
+    deps.querier.with_ura_pairs(&deployed_pairs);
+
+    let instantiate_reply = MsgInstantiateContractResponse {
+        contract_address: String::from("pair0001"),
+        data: vec![],
+    };
+
+    let mut encoded_instantiate_reply = Vec::<u8>::with_capacity(instantiate_reply.encoded_len());
+    instantiate_reply
+        .encode(&mut encoded_instantiate_reply)
+        .unwrap();
+
+    let reply_msg = Reply {
+        id: res.messages[0].id,
         result: SubMsgResult::Ok(SubMsgResponse {
             events: vec![],
             data: Some(encoded_instantiate_reply.into()),
@@ -695,7 +1440,7 @@ fn register() {
     )
     .unwrap_err();
 
-    assert_eq!(res, ContractError::Unauthorized {});
+    assert_eq!(res, ContractError::Dex(DexError::Unauthorized {}));
 
     // Proper deregister
     let env = mock_env();
@@ -731,3 +1476,599 @@ fn register() {
         },]
     );
 }
+
+/// Registers `pair000{i}` issuing the `liquidity000{i}` LP token for each of the given asset pairs.
+fn with_mock_pairs(querier: &mut WasmMockQuerier, pair_assets: &[Vec<AssetInfo>]) {
+    let pairs: Vec<_> = pair_assets
+        .iter()
+        .enumerate()
+        .map(|(i, asset_infos)| {
+            let pair_info = PairInfo {
+                asset_infos: asset_infos.clone(),
+                contract_addr: Addr::unchecked(format!("pair000{i}")),
+                liquidity_token: AssetInfo::Token {
+                    contract_addr: Addr::unchecked(format!("liquidity000{i}")),
+                },
+                pair_type: PairType::Xyk,
+            };
+            (format!("pair000{i}"), pair_info)
+        })
+        .collect();
+    querier.with_ura_pairs(
+        &pairs
+            .iter()
+            .map(|(pair_addr, pair_info)| (pair_addr, pair_info))
+            .collect::<Vec<_>>(),
+    );
+}
+
+fn create_and_register_pair(mut deps: DepsMut, asset_infos: &[AssetInfo], pair_addr: &str) {
+    let res = execute(
+        deps.branch(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::CreatePair {
+            pair_type: PairType::Xyk,
+            asset_infos: asset_infos.to_vec(),
+            init_params: None,
+            toggle_cw20_token: None,
+            immutable_lp: None,
+        },
+    )
+    .unwrap();
+
+    let instantiate_reply = MsgInstantiateContractResponse {
+        contract_address: pair_addr.to_string(),
+        data: vec![],
+    };
+    let mut encoded_instantiate_reply = Vec::<u8>::with_capacity(instantiate_reply.encoded_len());
+    instantiate_reply
+        .encode(&mut encoded_instantiate_reply)
+        .unwrap();
+
+    let reply_msg = Reply {
+        id: res.messages[0].id,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: Some(encoded_instantiate_reply.into()),
+        }),
+    };
+    reply(deps, mock_env(), reply_msg).unwrap();
+}
+
+#[test]
+fn pair_by_lp_token_resolves_native_and_cw20_lp_tokens() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        pair_configs: vec![PairConfig {
+            code_id: 321u64,
+            pair_type: PairType::Xyk,
+            total_fee_bps: 100,
+            is_disabled: false,
+            is_controller_disabled: false,
+            is_min_commission_enabled: false,
+            min_swap_amount: None,
+            max_price_impact: None,
+        }],
+        owner: "owner0000".to_string(),
+        controller_address: None,
+        coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
+        fee_address: None,
+        token_code_id: 123u64,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let token = |addr: &str| AssetInfo::Token {
+        contract_addr: Addr::unchecked(addr),
+    };
+    let cw20_lp_pair = PairInfo {
+        asset_infos: vec![token("asset0000"), token("asset0001")],
+        contract_addr: Addr::unchecked("pair0000"),
+        liquidity_token: token("liquidity0000"),
+        pair_type: PairType::Xyk,
+    };
+    let native_lp_pair = PairInfo {
+        asset_infos: vec![token("asset0000"), token("asset0002")],
+        contract_addr: Addr::unchecked("pair0001"),
+        liquidity_token: AssetInfo::NativeToken {
+            denom: "factory/pair0001/uLP".to_string(),
+        },
+        pair_type: PairType::Xyk,
+    };
+    let (pair0_addr, pair1_addr) = ("pair0000".to_string(), "pair0001".to_string());
+    deps.querier
+        .with_ura_pairs(&[(&pair0_addr, &cw20_lp_pair), (&pair1_addr, &native_lp_pair)]);
+    for pair_info in [&cw20_lp_pair, &native_lp_pair] {
+        create_and_register_pair(
+            deps.as_mut(),
+            &pair_info.asset_infos,
+            pair_info.contract_addr.as_str(),
+        );
+    }
+
+    let pair_by_lp_token = |deps: Deps, lp_token: &AssetInfo| -> StdResult<PairInfo> {
+        query(
+            deps,
+            mock_env(),
+            QueryMsg::PairByLpToken {
+                lp_token: lp_token.clone(),
+            },
+        )
+        .and_then(from_json)
+    };
+    for pair_info in [&cw20_lp_pair, &native_lp_pair] {
+        assert_eq!(
+            pair_by_lp_token(deps.as_ref(), &pair_info.liquidity_token).unwrap(),
+            *pair_info
+        );
+    }
+    pair_by_lp_token(deps.as_ref(), &token("liquidity0001")).unwrap_err();
+
+    // A pair that migrated its LP token is re-indexed under the new one
+    let migrated_pair = PairInfo {
+        liquidity_token: AssetInfo::NativeToken {
+            denom: "factory/pair0000/uLP".to_string(),
+        },
+        ..cw20_lp_pair.clone()
+    };
+    deps.querier.with_ura_pairs(&[
+        (&pair0_addr, &migrated_pair),
+        (&pair1_addr, &native_lp_pair),
+    ]);
+    let update = ExecuteMsg::UpdatePairLpToken {
+        previous_lp_token: cw20_lp_pair.liquidity_token.clone(),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner0000", &[]),
+        update.clone(),
+    )
+    .unwrap_err();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("pair0000", &[]),
+        update,
+    )
+    .unwrap();
+    pair_by_lp_token(deps.as_ref(), &cw20_lp_pair.liquidity_token).unwrap_err();
+    assert_eq!(
+        pair_by_lp_token(deps.as_ref(), &migrated_pair.liquidity_token).unwrap(),
+        migrated_pair
+    );
+
+    // A deregistered pair can't be found by its LP token anymore
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner0000", &[]),
+        ExecuteMsg::Deregister {
+            asset_infos: native_lp_pair.asset_infos.clone(),
+        },
+    )
+    .unwrap();
+    pair_by_lp_token(deps.as_ref(), &native_lp_pair.liquidity_token).unwrap_err();
+}
+
+#[test]
+fn pair_by_address_resolves_registered_pairs() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        pair_configs: vec![PairConfig {
+            code_id: 321u64,
+            pair_type: PairType::Xyk,
+            total_fee_bps: 100,
+            is_disabled: false,
+            is_controller_disabled: false,
+            is_min_commission_enabled: false,
+            min_swap_amount: None,
+            max_price_impact: None,
+        }],
+        owner: "owner0000".to_string(),
+        controller_address: None,
+        coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
+        fee_address: None,
+        token_code_id: 123u64,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let pair_info = PairInfo {
+        asset_infos: vec![AssetInfo::native("uusd"), AssetInfo::native("uluna")],
+        contract_addr: Addr::unchecked("pair0000"),
+        liquidity_token: AssetInfo::cw20_unchecked("liquidity0000"),
+        pair_type: PairType::Xyk,
+    };
+    deps.querier
+        .with_ura_pairs(&[(&"pair0000".to_string(), &pair_info)]);
+    create_and_register_pair(deps.as_mut(), &pair_info.asset_infos, "pair0000");
+
+    let pair_by_address = |deps: Deps, contract_addr: &str| -> StdResult<PairInfo> {
+        query(
+            deps,
+            mock_env(),
+            QueryMsg::PairByAddress {
+                contract_addr: contract_addr.to_string(),
+            },
+        )
+        .and_then(from_json)
+    };
+    assert_eq!(
+        pair_by_address(deps.as_ref(), "pair0000").unwrap(),
+        pair_info
+    );
+    assert_eq!(
+        pair_by_address(deps.as_ref(), "pair0001").unwrap_err(),
+        StdError::generic_err("pair0001 is not a registered pair")
+    );
+
+    // A deregistered pair can't be found by its address anymore
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner0000", &[]),
+        ExecuteMsg::Deregister {
+            asset_infos: pair_info.asset_infos.clone(),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        pair_by_address(deps.as_ref(), "pair0000").unwrap_err(),
+        StdError::generic_err("pair0000 is not a registered pair")
+    );
+}
+
+#[test]
+fn migrate_indexes_pairs_registered_before_the_indexes() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        pair_configs: vec![],
+        owner: "owner0000".to_string(),
+        controller_address: None,
+        coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
+        fee_address: None,
+        token_code_id: 123u64,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    // A pair registered by a factory without the address and LP token indexes
+    let pair_info = PairInfo {
+        asset_infos: vec![AssetInfo::native("uusd"), AssetInfo::native("uluna")],
+        contract_addr: Addr::unchecked("pair0000"),
+        liquidity_token: AssetInfo::cw20_unchecked("liquidity0000"),
+        pair_type: PairType::Xyk,
+    };
+    deps.querier
+        .with_ura_pairs(&[(&"pair0000".to_string(), &pair_info)]);
+    PAIRS
+        .save(
+            deps.as_mut().storage,
+            &pair_key(&pair_info.asset_infos),
+            &pair_info.contract_addr,
+        )
+        .unwrap();
+    CREATED_PAIRS
+        .save(deps.as_mut().storage, &pair_info.contract_addr, &Empty {})
+        .unwrap();
+    // A broken pair that can't be queried anymore
+    PAIRS
+        .save(
+            deps.as_mut().storage,
+            &pair_key(&[AssetInfo::native("uusd"), AssetInfo::native("uatom")]),
+            &Addr::unchecked("pair0001"),
+        )
+        .unwrap();
+
+    let pair_by_address = |deps: Deps| -> StdResult<PairInfo> {
+        query(
+            deps,
+            mock_env(),
+            QueryMsg::PairByAddress {
+                contract_addr: "pair0000".to_string(),
+            },
+        )
+        .and_then(from_json)
+    };
+    let pair_by_lp_token = |deps: Deps| -> StdResult<PairInfo> {
+        query(
+            deps,
+            mock_env(),
+            QueryMsg::PairByLpToken {
+                lp_token: pair_info.liquidity_token.clone(),
+            },
+        )
+        .and_then(from_json)
+    };
+    pair_by_address(deps.as_ref()).unwrap_err();
+    pair_by_lp_token(deps.as_ref()).unwrap_err();
+
+    let res = migrate(
+        deps.as_mut(),
+        mock_env(),
+        MigrateMsg {
+            params: Binary::default(),
+        },
+    )
+    .unwrap();
+    assert_eq!(res.attributes[0], attr("unindexed_pairs", "pair0001"));
+    assert_eq!(pair_by_address(deps.as_ref()).unwrap(), pair_info);
+    assert_eq!(pair_by_lp_token(deps.as_ref()).unwrap(), pair_info);
+}
+
+#[test]
+fn canonical_asset_order_follows_the_pair() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        pair_configs: vec![PairConfig {
+            code_id: 321u64,
+            pair_type: PairType::Xyk,
+            total_fee_bps: 100,
+            is_disabled: false,
+            is_controller_disabled: false,
+            is_min_commission_enabled: false,
+            min_swap_amount: None,
+            max_price_impact: None,
+        }],
+        owner: "owner0000".to_string(),
+        controller_address: None,
+        coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
+        fee_address: None,
+        token_code_id: 123u64,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    // The pair is created with its assets in reverse bytewise order
+    let pair_info = PairInfo {
+        asset_infos: vec![AssetInfo::native("uusd"), AssetInfo::native("uluna")],
+        contract_addr: Addr::unchecked("pair0000"),
+        liquidity_token: AssetInfo::cw20_unchecked("liquidity0000"),
+        pair_type: PairType::Xyk,
+    };
+    deps.querier
+        .with_ura_pairs(&[(&"pair0000".to_string(), &pair_info)]);
+    create_and_register_pair(deps.as_mut(), &pair_info.asset_infos, "pair0000");
+
+    let canonical_order = |asset_infos: Vec<AssetInfo>| -> StdResult<Vec<AssetInfo>> {
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::CanonicalAssetOrder { asset_infos },
+        )
+        .and_then(from_json)
+    };
+    let mut reversed = pair_info.asset_infos.clone();
+    reversed.reverse();
+    assert_eq!(
+        canonical_order(pair_info.asset_infos.clone()).unwrap(),
+        pair_info.asset_infos
+    );
+    assert_eq!(canonical_order(reversed).unwrap(), pair_info.asset_infos);
+
+    canonical_order(vec![AssetInfo::native("uusd"), AssetInfo::native("uatom")]).unwrap_err();
+}
+
+#[test]
+fn tvl() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        pair_configs: vec![PairConfig {
+            code_id: 123u64,
+            pair_type: PairType::Xyk,
+            total_fee_bps: 100,
+            is_disabled: false,
+            is_controller_disabled: false,
+            is_min_commission_enabled: false,
+            min_swap_amount: None,
+            max_price_impact: None,
+        }],
+        controller_address: Some(String::from("controller")),
+        owner: "owner0000".to_string(),
+        coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
+        fee_address: None,
+        token_code_id: 123u64,
+    };
+
+    let env = mock_env();
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let token = |addr: &str| AssetInfo::Token {
+        contract_addr: Addr::unchecked(addr),
+    };
+    let pair_assets = [
+        vec![token("asset0000"), token("asset0001")],
+        vec![token("asset0000"), token("asset0002")],
+        vec![token("asset0001"), token("asset0002")],
+    ];
+
+    let pair_addrs: Vec<String> = (0..pair_assets.len())
+        .map(|i| format!("pair000{}", i))
+        .collect();
+    let pair_infos: Vec<PairInfo> = pair_assets
+        .iter()
+        .enumerate()
+        .map(|(i, asset_infos)| PairInfo {
+            asset_infos: asset_infos.clone(),
+            contract_addr: Addr::unchecked(&pair_addrs[i]),
+            liquidity_token: AssetInfo::Token {
+                contract_addr: Addr::unchecked(format!("liquidity000{}", i)),
+            },
+            pair_type: PairType::Xyk,
+        })
+        .collect();
+    let pool_assets: Vec<Vec<Asset>> = pair_assets
+        .iter()
+        .enumerate()
+        .map(|(i, asset_infos)| {
+            asset_infos
+                .iter()
+                .enumerate()
+                .map(|(j, info)| Asset {
+                    info: info.clone(),
+                    amount: Uint128::new(1_000 * (i as u128 + 1) + j as u128),
+                })
+                .collect()
+        })
+        .collect();
+
+    let deployed_pairs: Vec<_> = pair_addrs.iter().zip(pair_infos.iter()).collect();
+    deps.querier.with_ura_pairs(&deployed_pairs);
+    let pools: Vec<_> = pair_addrs
+        .iter()
+        .zip(pool_assets.iter().map(Vec::as_slice))
+        .collect();
+    deps.querier.with_pool_assets(&pools);
+
+    for (asset_infos, pair_addr) in pair_assets.iter().zip(pair_addrs.iter()) {
+        create_and_register_pair(deps.as_mut(), asset_infos, pair_addr);
+    }
+
+    let expected: Vec<PairTvl> = pair_infos
+        .iter()
+        .zip(pool_assets.iter())
+        .map(|(pair, assets)| PairTvl {
+            pair: pair.clone(),
+            assets: assets.clone(),
+        })
+        .collect();
+
+    let query_tvl = |start_after: Option<Vec<AssetInfo>>, limit: Option<u32>| -> TvlResponse {
+        let res = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Tvl { start_after, limit },
+        )
+        .unwrap();
+        from_json(&res).unwrap()
+    };
+
+    // All pools fit into the default limit
+    assert_eq!(query_tvl(None, None).pools, expected);
+
+    // Paginate through the pools two at a time
+    let first_page = query_tvl(None, Some(2));
+    assert_eq!(first_page.pools, expected[..2]);
+
+    let start_after = first_page.pools.last().unwrap().pair.asset_infos.clone();
+    let second_page = query_tvl(Some(start_after), Some(2));
+    assert_eq!(second_page.pools, expected[2..]);
+
+    let start_after = second_page.pools.last().unwrap().pair.asset_infos.clone();
+    assert_eq!(query_tvl(Some(start_after), Some(2)).pools, vec![]);
+
+    // Total reserves of asset0000 across all pools
+    let total: Uint128 = query_tvl(None, None)
+        .pools
+        .iter()
+        .flat_map(|pool| pool.assets.iter())
+        .filter(|asset| asset.info == token("asset0000"))
+        .map(|asset| asset.amount)
+        .sum();
+    assert_eq!(total, Uint128::new(1_000 + 2_000));
+}
+
+#[test]
+fn pairs_by_asset_pairs() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        pair_configs: vec![PairConfig {
+            code_id: 123u64,
+            pair_type: PairType::Xyk,
+            total_fee_bps: 100,
+            is_disabled: false,
+            is_controller_disabled: false,
+            is_min_commission_enabled: false,
+            min_swap_amount: None,
+            max_price_impact: None,
+        }],
+        controller_address: Some(String::from("controller")),
+        owner: "owner0000".to_string(),
+        coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
+        fee_address: None,
+        token_code_id: 123u64,
+    };
+    let env = mock_env();
+    instantiate(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let token = |addr: &str| AssetInfo::Token {
+        contract_addr: Addr::unchecked(addr),
+    };
+    let pair_info = |asset_infos: Vec<AssetInfo>, i: usize| PairInfo {
+        asset_infos,
+        contract_addr: Addr::unchecked(format!("pair000{}", i)),
+        liquidity_token: AssetInfo::Token {
+            contract_addr: Addr::unchecked(format!("liquidity000{}", i)),
+        },
+        pair_type: PairType::Xyk,
+    };
+    let pair0 = pair_info(vec![token("asset0000"), token("asset0001")], 0);
+    let pair1 = pair_info(vec![token("asset0001"), token("asset0002")], 1);
+    let (pair0_addr, pair1_addr) = (
+        pair0.contract_addr.to_string(),
+        pair1.contract_addr.to_string(),
+    );
+    deps.querier
+        .with_ura_pairs(&[(&pair0_addr, &pair0), (&pair1_addr, &pair1)]);
+
+    create_and_register_pair(deps.as_mut(), &pair0.asset_infos, &pair0_addr);
+    create_and_register_pair(deps.as_mut(), &pair1.asset_infos, &pair1_addr);
+
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::PairsByAssetPairs {
+            asset_info_pairs: vec![
+                vec![token("asset0002"), token("asset0001")],
+                vec![token("asset0000"), token("asset0002")],
+                vec![token("asset0000"), token("asset0001")],
+            ],
+        },
+    )
+    .unwrap();
+    let pairs: Vec<Option<PairInfo>> = from_json(&res).unwrap();
+    assert_eq!(pairs, vec![Some(pair1), None, Some(pair0)]);
+
+    // The number of requested asset pairs is bounded
+    let err = query(
+        deps.as_ref(),
+        env,
+        QueryMsg::PairsByAssetPairs {
+            asset_info_pairs: vec![vec![token("asset0000"), token("asset0001")]; 31],
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Cannot query more than 30 asset pairs at once"
+    );
+}
@@ -75,10 +75,17 @@ impl FactoryHelper {
                 total_fee_bps: 100,
                 is_disabled: false,
                 is_controller_disabled: false,
+                is_min_commission_enabled: false,
+                min_swap_amount: None,
+                max_price_impact: None,
             }],
             controller_address: None,
             owner: owner.to_string(),
             coin_registry_address: "coin_registry".to_string(),
+            disallow_lp_assets: None,
+            controller_gas_limit: None,
+            trusted_denom_admins: None,
+            denom_creation_fee: None,
             fee_address: None,
             token_code_id: cw20_token_code_id,
         };
@@ -116,6 +123,10 @@ impl FactoryHelper {
             fee_address,
             controller_address,
             coin_registry_address,
+            disallow_lp_assets: None,
+            controller_gas_limit: None,
+            trusted_denom_admins: None,
+            denom_creation_fee: None,
         };
 
         router.execute_contract(sender.clone(), self.factory.clone(), &msg, &[])
@@ -143,6 +154,7 @@ impl FactoryHelper {
             asset_infos,
             init_params,
             toggle_cw20_token: None,
+            immutable_lp: None,
         };
 
         router.execute_contract(sender.clone(), self.factory.clone(), &msg, &[])
@@ -39,6 +39,9 @@ fn proper_initialization() {
         total_fee_bps: 100,
         is_disabled: false,
         is_controller_disabled: false,
+        is_min_commission_enabled: false,
+        min_swap_amount: None,
+        max_price_impact: None,
     }];
 
     let msg = InstantiateMsg {
@@ -46,6 +49,10 @@ fn proper_initialization() {
         owner: owner.to_string(),
         controller_address: Some(String::from("controller")),
         coin_registry_address: "coin_registry".to_string(),
+        disallow_lp_assets: None,
+        controller_gas_limit: None,
+        trusted_denom_admins: None,
+        denom_creation_fee: None,
         fee_address: None,
         token_code_id: 123u64,
     };
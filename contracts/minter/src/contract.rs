@@ -4,9 +4,13 @@ use cosmwasm_std::{
 };
 use cw2::set_contract_version;
 use std::ops::{Mul, Sub};
-use ura::contracts::controller::UpdateEmissionsRequest;
+use ura::contracts::controller::{
+    AllGaugesRequest, AllGaugesResponse, QueryMsg as ControllerQueryMsg, TotalGaugeVotesRequest,
+    TotalGaugeVotesResponse, UpdateEmissionsRequest,
+};
 use ura::contracts::minter::{
-    ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, UpdateConfigRequest,
+    EmissionSplitPreviewResponse, EpochEmissions, ExecuteMsg, GaugeEmissions, InstantiateMsg,
+    MigrateMsg, NextEpochInfoResponse, QueryMsg, UpdateConfigRequest,
 };
 use ura::contracts::ve_stake::{query_total_voting_power, RebaseRequest as VeRebaseRequest};
 use ura::utils::math::truncate;
@@ -14,10 +18,12 @@ use ura::utils::time::get_current_epoch;
 
 use crate::denom::{MsgCreateDenom, MsgMint};
 use crate::error::ContractError;
-use crate::state::{Config, CONFIG};
+use crate::state::{read_emissions_history, Config, CONFIG, EMISSIONS_HISTORY, TEAM_ESCROW};
 
 const CONTRACT_NAME: &str = "minter";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The number of gauges requested from the controller at once
+const GAUGES_PAGE_LIMIT: u64 = 30;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -69,6 +75,8 @@ pub fn instantiate(
         epoch_start_time: msg.epoch_start_time,
         controller: Addr::unchecked(""),
         ve_stake: Addr::unchecked(""),
+        sunset_epochs: None,
+        sunset_start_epoch: None,
     };
     CONFIG.save(deps.storage, &config)?;
     Ok(Response::default().add_messages(vec![base_token_msg, mint_msg, send_msg]))
@@ -86,6 +94,8 @@ pub fn execute(
         ExecuteMsg::SetVeStaking {} => set_ve_staking(deps, env, info),
         ExecuteMsg::SetGaugeController {} => set_gauge_controller(deps, env, info),
         ExecuteMsg::UpdateConfig(req) => update_config(deps, env, info, req),
+        ExecuteMsg::StartSunset { epochs } => start_sunset(deps, env, info, epochs),
+        ExecuteMsg::ClaimTeamEmissions {} => claim_team_emissions(deps, info),
     }
 }
 
@@ -213,6 +223,69 @@ fn update_config(
     ]))
 }
 
+fn start_sunset(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    epochs: u64,
+) -> Result<Response, ContractError> {
+    // ensure that sender is creator of the current contract
+    let current_contract_info = deps
+        .querier
+        .query_wasm_contract_info(env.contract.address.to_string())?;
+    if current_contract_info.creator.ne(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut config = CONFIG.load(deps.storage)?;
+    if !config.is_emitting {
+        return Err(ContractError::InvalidRequest(
+            "Config is current not set for emissions.".to_string(),
+        ));
+    }
+    if epochs == 0 {
+        return Err(ContractError::InvalidRequest(
+            "Sunset must last at least one epoch".to_string(),
+        ));
+    }
+
+    config.sunset_epochs = Some(epochs);
+    config.sunset_start_epoch = Some(config.current_epoch);
+    CONFIG.save(deps.storage, &config)?;
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "start_sunset"),
+        attr("sunset_epochs", epochs.to_string()),
+        attr("sunset_start_epoch", config.current_epoch.to_string()),
+    ]))
+}
+
+fn claim_team_emissions(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.team_wallet {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let amount = TEAM_ESCROW.may_load(deps.storage)?.unwrap_or_default();
+    if amount.is_zero() {
+        return Err(ContractError::NotEnoughTokens());
+    }
+    TEAM_ESCROW.save(deps.storage, &Uint128::zero())?;
+
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: config.team_wallet.to_string(),
+            amount: vec![Coin {
+                denom: config.base_token,
+                amount,
+            }],
+        })
+        .add_attributes(vec![
+            attr("action", "claim_team_emissions"),
+            attr("team_wallet", config.team_wallet.to_string()),
+            attr("amount", amount.to_string()),
+        ]))
+}
+
 fn end_epoch(deps: DepsMut, env: Env, _info: MessageInfo) -> Result<Response, ContractError> {
     let mut config = CONFIG.load(deps.storage)?;
 
@@ -232,32 +305,15 @@ fn end_epoch(deps: DepsMut, env: Env, _info: MessageInfo) -> Result<Response, Co
             (config.current_epoch + 1) * config.epoch_length,
         ));
     }
-    // Calculate total emissions
-    // emissions = initial_supply * inflation * (decay ^ epoch)
-    // inflation has a lower bound of config.min_inflation
-    let decay = (Decimal::one() - config.decay).pow(config.current_epoch as u32);
-    let actual_inflation = decay
-        .checked_mul(config.inflation)
-        .map_err(ContractError::OverflowError)?
-        .max(config.min_inflation);
-
-    let total_emissions = actual_inflation.mul(config.initial_supply);
-
-    // Calculate team emissions
-    let team_emissions = config.team_allocation.mul(total_emissions);
-
-    // Calculate rebase emissions
-
-    // query total voting power
-    let ve_supply = query_total_voting_power(deps.as_ref(), None, config.ve_stake.clone())?.weight;
-
-    let token_supply = config.initial_supply;
-
-    let rebase_emissions_dec = Decimal::from_ratio(ve_supply, token_supply)
-        .pow(3)
-        .mul(Decimal::from_ratio(total_emissions, Uint128::new(2)));
-
-    let rebase_emissions = truncate(rebase_emissions_dec)?;
+    let Some(emissions) = compute_epoch_emissions(deps.as_ref(), &config, actual_epoch)? else {
+        return finish_sunset(deps, config, actual_epoch);
+    };
+    let EpochEmissions {
+        team: team_emissions,
+        rebase: rebase_emissions,
+        lp: lp_emissions,
+        ..
+    } = emissions;
 
     // Create message to mint naked tokens
     let mut mint_msgs = mint_naked_tokens(
@@ -268,20 +324,26 @@ fn end_epoch(deps: DepsMut, env: Env, _info: MessageInfo) -> Result<Response, Co
     );
 
     // mint LP emissions
-    let lp_emissions = total_emissions.sub(team_emissions).sub(rebase_emissions);
     mint_msgs.extend(mint_naked_tokens(
         lp_emissions,
         &env.contract.address,
         &env.contract.address,
         config.base_token.clone(),
     ));
-    // mint esTokens for team
+    // mint team emissions into the escrow, the team wallet claims them later
     mint_msgs.extend(mint_naked_tokens(
         team_emissions,
         &env.contract.address,
-        &config.team_wallet,
+        &env.contract.address,
         config.base_token.clone(),
     ));
+    let team_escrow = TEAM_ESCROW.may_load(deps.storage)?.unwrap_or_default();
+    TEAM_ESCROW.save(
+        deps.storage,
+        &team_escrow
+            .checked_add(team_emissions)
+            .map_err(ContractError::OverflowError)?,
+    )?;
 
     // mint tokens and deposit into ve contract
     let mut msgs = vec![];
@@ -309,6 +371,7 @@ fn end_epoch(deps: DepsMut, env: Env, _info: MessageInfo) -> Result<Response, Co
 
     config.current_epoch = actual_epoch;
     CONFIG.save(deps.storage, &config)?;
+    EMISSIONS_HISTORY.save(deps.storage, actual_epoch, &emissions)?;
 
     Ok(Response::default()
         .add_attributes(vec![
@@ -322,6 +385,96 @@ fn end_epoch(deps: DepsMut, env: Env, _info: MessageInfo) -> Result<Response, Co
         .add_submessages(msgs))
 }
 
+// Computes the emissions `EndEpoch` mints when starting `actual_epoch`, or `None` when the epoch
+// ends a sunset and nothing is minted
+fn compute_epoch_emissions(
+    deps: Deps,
+    config: &Config,
+    actual_epoch: u64,
+) -> Result<Option<EpochEmissions>, ContractError> {
+    // Calculate total emissions
+    // emissions = initial_supply * inflation * (decay ^ epoch)
+    // inflation has a lower bound of config.min_inflation
+    let decay = (Decimal::one() - config.decay).pow(config.current_epoch as u32);
+    let actual_inflation = decay
+        .checked_mul(config.inflation)
+        .map_err(ContractError::OverflowError)?
+        .max(config.min_inflation);
+
+    // During a sunset the inflation ramps down linearly and reaches zero in the last epoch
+    let actual_inflation = match (config.sunset_epochs, config.sunset_start_epoch) {
+        (Some(sunset_epochs), Some(sunset_start_epoch)) => {
+            let remaining_epochs =
+                sunset_epochs.saturating_sub(actual_epoch.saturating_sub(sunset_start_epoch));
+            if remaining_epochs == 0 {
+                return Ok(None);
+            }
+            actual_inflation.mul(Decimal::from_ratio(remaining_epochs, sunset_epochs))
+        }
+        _ => actual_inflation,
+    };
+
+    let total_emissions = actual_inflation.mul(config.initial_supply);
+
+    // Calculate team emissions
+    let team_emissions = config.team_allocation.mul(total_emissions);
+
+    // Calculate rebase emissions
+
+    // query total voting power
+    let ve_supply = query_total_voting_power(deps, None, config.ve_stake.clone())?.weight;
+
+    let token_supply = config.initial_supply;
+
+    let rebase_emissions_dec = Decimal::from_ratio(ve_supply, token_supply)
+        .pow(3)
+        .mul(Decimal::from_ratio(total_emissions, Uint128::new(2)));
+
+    let rebase_emissions = truncate(rebase_emissions_dec)?;
+
+    let lp_emissions = total_emissions.sub(team_emissions).sub(rebase_emissions);
+
+    Ok(Some(EpochEmissions {
+        epoch: actual_epoch,
+        total: total_emissions,
+        team: team_emissions,
+        rebase: rebase_emissions,
+        lp: lp_emissions,
+        inflation: actual_inflation,
+    }))
+}
+
+// Ends the last epoch of a sunset without minting and stops the emissions
+fn finish_sunset(
+    deps: DepsMut,
+    mut config: Config,
+    actual_epoch: u64,
+) -> Result<Response, ContractError> {
+    config.current_epoch = actual_epoch;
+    config.is_emitting = false;
+    config.sunset_epochs = None;
+    config.sunset_start_epoch = None;
+    CONFIG.save(deps.storage, &config)?;
+    EMISSIONS_HISTORY.save(
+        deps.storage,
+        actual_epoch,
+        &EpochEmissions {
+            epoch: actual_epoch,
+            total: Uint128::zero(),
+            team: Uint128::zero(),
+            rebase: Uint128::zero(),
+            lp: Uint128::zero(),
+            inflation: Decimal::zero(),
+        },
+    )?;
+
+    Ok(Response::default().add_attributes(vec![
+        attr("action", "end_epoch"),
+        attr("new_epoch", format!("{}", config.current_epoch)),
+        attr("sunset", "complete"),
+    ]))
+}
+
 // Create message to mint naked tokens
 fn mint_naked_tokens(
     amount: Uint128,
@@ -351,11 +504,16 @@ fn mint_naked_tokens(
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
     match msg {
         QueryMsg::Config {} => query_config(deps),
         QueryMsg::TokenInfo { .. } => Ok(Binary::default()),
         QueryMsg::DownloadLogo { .. } => Ok(Binary::default()),
+        QueryMsg::EmissionsHistory { start_after, limit } => {
+            query_emissions_history(deps, start_after, limit)
+        }
+        QueryMsg::NextEpochInfo {} => query_next_epoch_info(deps, env),
+        QueryMsg::EmissionSplitPreview { epoch } => query_emission_split_preview(deps, epoch),
     }
 }
 
@@ -363,3 +521,89 @@ fn query_config(deps: Deps) -> Result<Binary, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     to_json_binary(&config).map_err(|e| ContractError::Std(e))
 }
+
+fn query_emissions_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> Result<Binary, ContractError> {
+    let history = read_emissions_history(deps.storage, start_after, limit)?;
+    Ok(to_json_binary(&history)?)
+}
+
+// Splits the LP emissions of an epoch across the controller gauges by their share of the votes
+fn query_emission_split_preview(deps: Deps, epoch: u64) -> Result<Binary, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let lp_emissions = match EMISSIONS_HISTORY.may_load(deps.storage, epoch)? {
+        Some(emissions) => emissions.lp,
+        None if config.is_emitting => compute_epoch_emissions(deps, &config, epoch)?
+            .map(|emissions| emissions.lp)
+            .unwrap_or_default(),
+        None => Uint128::zero(),
+    };
+
+    let mut gauges = vec![];
+    let mut last_pool = None;
+    loop {
+        let page: AllGaugesResponse = deps.querier.query_wasm_smart(
+            &config.controller,
+            &ControllerQueryMsg::AllGauges(AllGaugesRequest {
+                limit: GAUGES_PAGE_LIMIT,
+                last_pool,
+            }),
+        )?;
+        let page_len = page.gauges.len() as u64;
+        last_pool = page.gauges.last().map(|gauge| gauge.pool.clone());
+
+        for gauge in page.gauges {
+            let votes: TotalGaugeVotesResponse = deps.querier.query_wasm_smart(
+                &config.controller,
+                &ControllerQueryMsg::TotalGaugeVotes(TotalGaugeVotesRequest {
+                    gauge: gauge.gauge.to_string(),
+                    epoch: Some(epoch),
+                }),
+            )?;
+            let amount = if votes.total_votes.is_zero() {
+                Uint128::zero()
+            } else {
+                lp_emissions.multiply_ratio(votes.votes, votes.total_votes)
+            };
+            gauges.push(GaugeEmissions {
+                pool: gauge.pool,
+                gauge: gauge.gauge,
+                votes: votes.votes,
+                amount,
+            });
+        }
+
+        if page_len < GAUGES_PAGE_LIMIT {
+            break;
+        }
+    }
+
+    Ok(to_json_binary(&EmissionSplitPreviewResponse {
+        epoch,
+        lp_emissions,
+        gauges,
+    })?)
+}
+
+fn query_next_epoch_info(deps: Deps, env: Env) -> Result<Binary, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let now = env.block.time.seconds();
+
+    let next_epoch_start_time =
+        config.epoch_start_time + (config.current_epoch + 1) * config.epoch_length;
+    // No epoch can be ended before the emissions schedule has started
+    let can_end_now = config.is_emitting
+        && now >= config.epoch_start_time
+        && get_current_epoch(now, config.epoch_start_time, config.epoch_length)
+            > config.current_epoch;
+
+    Ok(to_json_binary(&NextEpochInfoResponse {
+        current_epoch: config.current_epoch,
+        next_epoch_start_time,
+        seconds_remaining: next_epoch_start_time.saturating_sub(now),
+        can_end_now,
+    })?)
+}
@@ -2,3 +2,6 @@ pub mod contract;
 mod denom;
 mod error;
 pub mod state;
+
+#[cfg(test)]
+mod testing;
@@ -2,8 +2,10 @@ use cosmwasm_schema::cw_serde;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Binary, Decimal, Uint128};
-use cw_storage_plus::Item;
+use cosmwasm_std::{Addr, Binary, Decimal, Order, StdResult, Storage, Uint128};
+use cw_storage_plus::{Bound, Item, Map};
+use ura::contracts::minter::EpochEmissions;
+use ura::utils::pagination::clamp_limit;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
@@ -21,6 +23,11 @@ pub struct Config {
     pub epoch_start_time: u64,
     pub controller: Addr,
     pub ve_stake: Addr,
+    // Set by StartSunset to ramp the inflation down to zero over a number of epochs
+    #[serde(default)]
+    pub sunset_epochs: Option<u64>,
+    #[serde(default)]
+    pub sunset_start_epoch: Option<u64>,
 }
 
 #[cw_serde]
@@ -31,3 +38,23 @@ pub struct Logo {
 
 pub const CONFIG: Item<Config> = Item::new("config");
 pub const LOGO: Item<Logo> = Item::new("logo");
+/// Team emissions held by the minter until the team wallet claims them
+pub const TEAM_ESCROW: Item<Uint128> = Item::new("team_escrow");
+/// Emissions minted by each `EndEpoch`, keyed by the new epoch
+pub const EMISSIONS_HISTORY: Map<u64, EpochEmissions> = Map::new("emissions_history");
+
+/// Reads the emissions history in ascending epoch order
+pub fn read_emissions_history(
+    storage: &dyn Storage,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<EpochEmissions>> {
+    let limit = clamp_limit(limit);
+    let start = start_after.map(Bound::exclusive);
+
+    EMISSIONS_HISTORY
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| Ok(item?.1))
+        .collect()
+}
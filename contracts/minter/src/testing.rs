@@ -0,0 +1,412 @@
+use cosmwasm_std::testing::{
+    mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR,
+};
+use cosmwasm_std::{
+    coins, from_json, to_json_binary, Addr, BankMsg, ContractInfoResponse, ContractResult,
+    CosmosMsg, Decimal, Deps, DepsMut, Env, OwnedDeps, Response, StdError, SubMsg, SystemError,
+    SystemResult, Uint128, WasmQuery,
+};
+use ura::contracts::controller::{
+    AllGaugesRequest, AllGaugesResponse, GaugeResponse, QueryMsg as ControllerQueryMsg,
+    TotalGaugeVotesRequest, TotalGaugeVotesResponse,
+};
+use ura::contracts::minter::{
+    BaseTokenParams, EmissionSplitPreviewResponse, EpochEmissions, ExecuteMsg, GaugeEmissions,
+    InstantiateMsg, NextEpochInfoResponse, QueryMsg, UpdateConfigRequest,
+};
+use ura::contracts::ve_stake::{QueryMsg as VeQueryMsg, TotalVeSupplyResponse};
+
+use crate::contract::{execute, instantiate, query};
+use crate::error::ContractError;
+use crate::state::{CONFIG, TEAM_ESCROW};
+
+const EPOCH_LENGTH: u64 = 604_800;
+
+fn setup(decay: Decimal) -> (OwnedDeps<MockStorage, MockApi, MockQuerier>, Env) {
+    let mut deps = mock_dependencies();
+    deps.querier.update_wasm(|request| match request {
+        WasmQuery::Smart { contract_addr, msg } if contract_addr == "ve_stake" => {
+            match from_json(msg).unwrap() {
+                VeQueryMsg::TotalVotingPower { .. } => SystemResult::Ok(ContractResult::Ok(
+                    to_json_binary(&TotalVeSupplyResponse {
+                        weight: Uint128::new(500_000_000),
+                    })
+                    .unwrap(),
+                )),
+                _ => panic!("DO NOT ENTER HERE"),
+            }
+        }
+        WasmQuery::ContractInfo { .. } => {
+            let mut info = ContractInfoResponse::default();
+            info.creator = "owner".to_string();
+            SystemResult::Ok(ContractResult::Ok(to_json_binary(&info).unwrap()))
+        }
+        _ => SystemResult::Err(SystemError::Unknown {}),
+    });
+
+    let env = mock_env();
+    let msg = InstantiateMsg {
+        epoch_start_time: env.block.time.seconds(),
+        epoch_duration: EPOCH_LENGTH,
+        initial_supply: Uint128::new(1_000_000_000),
+        inflation: Decimal::percent(2),
+        decay,
+        min_inflation: Decimal::permille(5),
+        team_allocation: Decimal::percent(10),
+        team_wallet: "team".to_string(),
+        base_token_params: BaseTokenParams {
+            marketing_info: None,
+            name: "Ura".to_string(),
+            symbol: "URA".to_string(),
+            decimals: 6,
+            initial_balances: vec![],
+        },
+    };
+    instantiate(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap();
+    CONFIG
+        .update::<_, StdError>(deps.as_mut().storage, |mut config| {
+            config.is_emitting = true;
+            config.controller = Addr::unchecked("controller");
+            config.ve_stake = Addr::unchecked("ve_stake");
+            Ok(config)
+        })
+        .unwrap();
+
+    (deps, env)
+}
+
+fn end_epoch(deps: DepsMut, env: &mut Env) -> Result<Response, ContractError> {
+    env.block.time = env.block.time.plus_seconds(EPOCH_LENGTH);
+    execute(
+        deps,
+        env.clone(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::EndEpoch {},
+    )
+}
+
+fn query_history(deps: Deps, start_after: Option<u64>, limit: Option<u32>) -> Vec<EpochEmissions> {
+    from_json(
+        query(
+            deps,
+            mock_env(),
+            QueryMsg::EmissionsHistory { start_after, limit },
+        )
+        .unwrap(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn emissions_history() {
+    let (mut deps, mut env) = setup(Decimal::percent(1));
+
+    for epoch in 1..=2 {
+        end_epoch(deps.as_mut(), &mut env).unwrap();
+        assert_eq!(
+            CONFIG.load(deps.as_ref().storage).unwrap().current_epoch,
+            epoch
+        );
+    }
+
+    let first = EpochEmissions {
+        epoch: 1,
+        total: Uint128::new(20_000_000),
+        team: Uint128::new(2_000_000),
+        rebase: Uint128::new(1_250_000),
+        lp: Uint128::new(16_750_000),
+        inflation: Decimal::percent(2),
+    };
+    // The inflation decays by 1% in the second epoch
+    let second = EpochEmissions {
+        epoch: 2,
+        total: Uint128::new(19_800_000),
+        team: Uint128::new(1_980_000),
+        rebase: Uint128::new(1_237_500),
+        lp: Uint128::new(16_582_500),
+        inflation: Decimal::from_ratio(198u128, 10_000u128),
+    };
+    assert_eq!(
+        query_history(deps.as_ref(), None, None),
+        vec![first.clone(), second.clone()]
+    );
+    assert_eq!(query_history(deps.as_ref(), None, Some(1)), vec![first]);
+    assert_eq!(query_history(deps.as_ref(), Some(1), None), vec![second]);
+    assert_eq!(query_history(deps.as_ref(), Some(2), None), vec![]);
+}
+
+#[test]
+fn sunset_ramps_emissions_down() {
+    let (mut deps, mut env) = setup(Decimal::zero());
+
+    // Only the contract creator can start a sunset
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::StartSunset { epochs: 3 },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    end_epoch(deps.as_mut(), &mut env).unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("owner", &[]),
+        ExecuteMsg::StartSunset { epochs: 3 },
+    )
+    .unwrap();
+
+    for _ in 0..3 {
+        end_epoch(deps.as_mut(), &mut env).unwrap();
+    }
+
+    let history = query_history(deps.as_ref(), None, None);
+    let totals: Vec<Uint128> = history.iter().map(|emissions| emissions.total).collect();
+    assert_eq!(
+        totals,
+        vec![
+            Uint128::new(20_000_000),
+            Uint128::new(13_333_333),
+            Uint128::new(6_666_666),
+            Uint128::zero()
+        ]
+    );
+    // The min inflation does not apply during a sunset
+    assert_eq!(history[3].inflation, Decimal::zero());
+
+    // Emissions stop once the sunset is over
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert!(!config.is_emitting);
+    assert_eq!(config.sunset_epochs, None);
+    assert_eq!(config.current_epoch, 4);
+    let err = end_epoch(deps.as_mut(), &mut env).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidRequest("Config is current not set for emissions.".to_string())
+    );
+}
+
+#[test]
+fn team_emissions_escrow() {
+    let (mut deps, mut env) = setup(Decimal::zero());
+
+    // Nothing accrued yet
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("team", &[]),
+        ExecuteMsg::ClaimTeamEmissions {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NotEnoughTokens());
+
+    // Team emissions accrue in the minter instead of being sent to the wallet
+    for _ in 0..2 {
+        let res = end_epoch(deps.as_mut(), &mut env).unwrap();
+        assert!(!res.messages.iter().any(|msg| matches!(
+            &msg.msg,
+            CosmosMsg::Bank(BankMsg::Send { to_address, .. }) if to_address == "team"
+        )));
+    }
+    assert_eq!(
+        TEAM_ESCROW.load(deps.as_ref().storage).unwrap(),
+        Uint128::new(4_000_000)
+    );
+
+    // The team wallet is updated before claiming, the escrow follows the new wallet
+    let msg = ExecuteMsg::UpdateConfig(UpdateConfigRequest {
+        epoch_length: None,
+        inflation: None,
+        decay: None,
+        min_inflation: None,
+        team_allocation: None,
+        team_wallet: Some("new_team".to_string()),
+        is_emitting: None,
+        epoch_start_time: None,
+    });
+    execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap();
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("team", &[]),
+        ExecuteMsg::ClaimTeamEmissions {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("new_team", &[]),
+        ExecuteMsg::ClaimTeamEmissions {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "new_team".to_string(),
+            amount: coins(4_000_000, format!("factory/{MOCK_CONTRACT_ADDR}/URA")),
+        })]
+    );
+    assert_eq!(
+        TEAM_ESCROW.load(deps.as_ref().storage).unwrap(),
+        Uint128::zero()
+    );
+}
+
+#[test]
+fn next_epoch_info_around_boundary() {
+    let (mut deps, mut env) = setup(Decimal::percent(1));
+    let epoch_start_time = env.block.time.seconds();
+    let query_next_epoch = |deps: Deps, env: &Env| -> NextEpochInfoResponse {
+        from_json(query(deps, env.clone(), QueryMsg::NextEpochInfo {}).unwrap()).unwrap()
+    };
+
+    // One second before the boundary the epoch cannot be ended yet
+    env.block.time = env.block.time.plus_seconds(EPOCH_LENGTH - 1);
+    assert_eq!(
+        query_next_epoch(deps.as_ref(), &env),
+        NextEpochInfoResponse {
+            current_epoch: 0,
+            next_epoch_start_time: epoch_start_time + EPOCH_LENGTH,
+            seconds_remaining: 1,
+            can_end_now: false,
+        }
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::EndEpoch {},
+    )
+    .unwrap_err();
+
+    // One second after it the epoch can be ended
+    env.block.time = env.block.time.plus_seconds(2);
+    assert_eq!(
+        query_next_epoch(deps.as_ref(), &env),
+        NextEpochInfoResponse {
+            current_epoch: 0,
+            next_epoch_start_time: epoch_start_time + EPOCH_LENGTH,
+            seconds_remaining: 0,
+            can_end_now: true,
+        }
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::EndEpoch {},
+    )
+    .unwrap();
+
+    // Ending the epoch moves the boundary forward
+    assert_eq!(
+        query_next_epoch(deps.as_ref(), &env),
+        NextEpochInfoResponse {
+            current_epoch: 1,
+            next_epoch_start_time: epoch_start_time + 2 * EPOCH_LENGTH,
+            seconds_remaining: EPOCH_LENGTH - 1,
+            can_end_now: false,
+        }
+    );
+
+    // Nothing can be ended while the emissions are paused
+    CONFIG
+        .update::<_, StdError>(deps.as_mut().storage, |mut config| {
+            config.is_emitting = false;
+            Ok(config)
+        })
+        .unwrap();
+    env.block.time = env.block.time.plus_seconds(EPOCH_LENGTH);
+    assert!(!query_next_epoch(deps.as_ref(), &env).can_end_now);
+}
+
+#[test]
+fn emission_split_preview_follows_gauge_votes() {
+    let (mut deps, mut env) = setup(Decimal::percent(1));
+    deps.querier.update_wasm(|request| match request {
+        WasmQuery::Smart { contract_addr, msg } if contract_addr == "ve_stake" => {
+            match from_json(msg).unwrap() {
+                VeQueryMsg::TotalVotingPower { .. } => SystemResult::Ok(ContractResult::Ok(
+                    to_json_binary(&TotalVeSupplyResponse {
+                        weight: Uint128::new(500_000_000),
+                    })
+                    .unwrap(),
+                )),
+                _ => panic!("DO NOT ENTER HERE"),
+            }
+        }
+        WasmQuery::Smart { contract_addr, msg } if contract_addr == "controller" => {
+            let response = match from_json(msg).unwrap() {
+                ControllerQueryMsg::AllGauges(AllGaugesRequest { last_pool, .. }) => {
+                    let gauges = match last_pool {
+                        Some(_) => vec![],
+                        None => ["a", "b"]
+                            .into_iter()
+                            .map(|name| GaugeResponse {
+                                pool: Addr::unchecked(format!("pool_{name}")),
+                                gauge: Addr::unchecked(format!("gauge_{name}")),
+                            })
+                            .collect(),
+                    };
+                    to_json_binary(&AllGaugesResponse { gauges })
+                }
+                ControllerQueryMsg::TotalGaugeVotes(TotalGaugeVotesRequest { gauge, epoch }) => {
+                    to_json_binary(&TotalGaugeVotesResponse {
+                        votes: Uint128::new(if gauge == "gauge_a" { 300 } else { 100 }),
+                        total_votes: Uint128::new(400),
+                        epoch: epoch.unwrap(),
+                    })
+                }
+                _ => panic!("DO NOT ENTER HERE"),
+            };
+            SystemResult::Ok(ContractResult::Ok(response.unwrap()))
+        }
+        _ => SystemResult::Err(SystemError::Unknown {}),
+    });
+    end_epoch(deps.as_mut(), &mut env).unwrap();
+
+    let preview = |deps: Deps, epoch: u64| -> EmissionSplitPreviewResponse {
+        from_json(query(deps, mock_env(), QueryMsg::EmissionSplitPreview { epoch }).unwrap())
+            .unwrap()
+    };
+    let split = |amount_a: u128, amount_b: u128| {
+        vec![
+            GaugeEmissions {
+                pool: Addr::unchecked("pool_a"),
+                gauge: Addr::unchecked("gauge_a"),
+                votes: Uint128::new(300),
+                amount: Uint128::new(amount_a),
+            },
+            GaugeEmissions {
+                pool: Addr::unchecked("pool_b"),
+                gauge: Addr::unchecked("gauge_b"),
+                votes: Uint128::new(100),
+                amount: Uint128::new(amount_b),
+            },
+        ]
+    };
+
+    // The minted epoch uses the recorded LP emissions
+    assert_eq!(
+        preview(deps.as_ref(), 1),
+        EmissionSplitPreviewResponse {
+            epoch: 1,
+            lp_emissions: Uint128::new(16_750_000),
+            gauges: split(12_562_500, 4_187_500),
+        }
+    );
+    // The next epoch is projected with the decayed inflation
+    assert_eq!(
+        preview(deps.as_ref(), 2),
+        EmissionSplitPreviewResponse {
+            epoch: 2,
+            lp_emissions: Uint128::new(16_582_500),
+            gauges: split(12_436_875, 4_145_625),
+        }
+    );
+}
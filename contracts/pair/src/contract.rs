@@ -3,38 +3,60 @@ use std::vec;
 
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    attr, from_json, to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal, Decimal256,
-    Deps, DepsMut, Env, Fraction, MessageInfo, QuerierWrapper, Reply, ReplyOn, Response, StdError,
-    StdResult, SubMsg, SubMsgResult, Uint128, Uint256, WasmMsg,
+    attr, from_json, has_coins, to_json_binary, Addr, BankMsg, Binary, BlockInfo, Coin,
+    ContractInfoResponse, CosmosMsg, Decimal, Decimal256, Deps, DepsMut, Empty, Env, MessageInfo,
+    QuerierWrapper, Reply, ReplyOn, Response, StdError, StdResult, Storage, SubMsg, SubMsgResult,
+    Uint128, Uint256, WasmMsg, WasmQuery,
 };
 use cw2::{get_contract_version, set_contract_version};
 
-use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, MinterResponse, TokenInfoResponse};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, MinterResponse};
 use cw_utils::parse_instantiate_response_data;
 use ura::contracts::pair::MINIMUM_LIQUIDITY_AMOUNT;
-use ura::utils::format::format_lp_token_name;
-use ura::utils::validation::{addr_opt_validate, check_swap_parameters};
+use ura::utils::fees::{accumulate_fees, read_cumulative_fees};
+use ura::utils::format::{format_lp_token_name, native_lp_denom};
+use ura::utils::validation::addr_opt_validate;
 
+use ura::contracts::controller::AccumEmissionsBatch;
+#[cfg(not(feature = "no-emissions"))]
 use ura::contracts::controller::{AccumEmissionsRequest, ExecuteMsg as ControllerExecuteMsg};
-use ura::contracts::factory::PairType;
+use ura::contracts::factory::{ExecuteMsg as FactoryExecuteMsg, PairType};
 use ura::contracts::gauge::{Cw20HookMsg as GaugeHookMsg, ExecuteMsg as GaugeExecuteMsg};
 use ura::contracts::pair::{
-    ConfigResponse, LpReceivedResponse, DEFAULT_SLIPPAGE, MAX_ALLOWED_SLIPPAGE,
+    CanExecuteResponse, ConfigResponse, LpReceivedResponse, PairAction, DEFAULT_SLIPPAGE,
+    MAX_ALLOWED_SLIPPAGE,
 };
 use ura::contracts::pair::{
-    Cw20HookMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, PoolResponse, QueryMsg,
-    ReverseSimulationResponse, SimulationResponse,
+    Cw20HookMsg, ExecuteMsg, InstantiateMsg, LiquidityProvision, MigrateMsg, PoolResponse,
+    QueryMsg, QuoteBothResponse, ReverseSimulationResponse, SimulateProvideResponse,
+    SimulationResponse, SwapRateResponse, SwapToPriceResponse, WithdrawPreviewResponse,
+    XykPoolParams, XykPoolUpdateParams,
 };
-use ura::structs::asset::Asset;
-use ura::structs::asset_info::AssetInfo;
+use ura::errors::DexError;
+use ura::structs::asset::{assets_into_msgs, Asset};
+use ura::structs::asset_info::{AssetInfo, AssetInfoExt};
 use ura::structs::coin::CoinsExt;
+use ura::structs::fee_info::FeeInfo;
 use ura::structs::pair_info::PairInfo;
-use ura::utils::querier::{query_factory_config, query_fee_info};
-use ura::{contracts::token::InstantiateMsg as TokenInstantiateMsg, U256};
+use ura::utils::querier::{
+    query_factory_config, query_fee_info, query_fee_info_basic, query_oracle_price,
+    query_total_supply,
+};
+use ura::{contracts::token::InstantiateMsg as TokenInstantiateMsg, to_decimal, to_uint128, U256};
 
-use crate::denom::{MsgBurn, MsgCreateDenom, MsgMint};
+use crate::denom::{DenomUnit, Metadata, MsgBurn, MsgCreateDenom, MsgMint, MsgSetDenomMetadata};
 use crate::error::ContractError;
-use crate::state::{Config, CONFIG, LP_PROVIDERS};
+use crate::state::{
+    read_lp_providers, settle_withdrawn_fees, untrack_lp_provider, Config, CONFIG, DELEGATED_LP,
+    LP_PROVIDERS, LP_TOKEN_READY, MIGRATION_RESERVES, PENDING_LP_MINTS, PROVIDE_OPERATORS,
+};
+#[cfg(not(feature = "no-emissions"))]
+use crate::state::{read_uncompounded_fees, record_compounded_fees};
+
+pub use crate::math::{
+    apply_min_commission, assert_max_spread, compute_offer_amount,
+    compute_offer_amount_with_min_commission, compute_swap, compute_swap_to_price,
+};
 
 /// Contract name that is used for migration.
 const CONTRACT_NAME: &str = "pair";
@@ -43,24 +65,53 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 /// A `reply` call code ID used for sub-messages.
 const INSTANTIATE_NATIVE_REPLY_ID: u64 = 1;
 const INSTANTIATE_CW20_REPLY_ID: u64 = 2;
+#[cfg(not(feature = "no-emissions"))]
+const ACCUM_EMISSIONS_REPLY_ID: u64 = 3;
+const MIGRATE_NATIVE_REPLY_ID: u64 = 4;
+const MIGRATE_CW20_REPLY_ID: u64 = 5;
+const MINT_NATIVE_LP_REPLY_ID: u64 = 6;
+const BURN_LP_REPLY_ID: u64 = 7;
+/// Number of decimals of the LP tokens created by the pair
+const LP_TOKEN_DECIMALS: u8 = 6;
 
 /// Creates a new contract with the specified parameters in the [`InstantiateMsg`].
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     if msg.asset_infos.len() != 2 {
         return Err(StdError::generic_err("asset_infos must contain exactly two elements").into());
     }
 
+    if msg.asset_infos[0].equal(&msg.asset_infos[1]) {
+        return Err(ContractError::Dex(DexError::DoublingAssets {}));
+    }
+
     msg.asset_infos[0].check(deps.api)?;
     msg.asset_infos[1].check(deps.api)?;
 
-    if msg.asset_infos[0] == msg.asset_infos[1] {
-        return Err(ContractError::DoublingAssets {});
+    let params: XykPoolParams = msg
+        .init_params
+        .as_ref()
+        .map(from_json)
+        .transpose()?
+        .unwrap_or_default();
+    if params
+        .max_belief_price_deviation
+        .is_some_and(|deviation| deviation < Decimal::one())
+    {
+        return Err(ContractError::InvalidBeliefPriceDeviation {});
+    }
+    let oracle_addr = addr_opt_validate(deps.api, &params.oracle_addr)?;
+    if (oracle_addr.is_some() && params.max_oracle_deviation.is_none())
+        || params
+            .max_oracle_deviation
+            .is_some_and(|deviation| deviation < Decimal::one())
+    {
+        return Err(ContractError::InvalidOracleDeviation {});
     }
 
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
@@ -68,13 +119,23 @@ pub fn instantiate(
     let token_name = format_lp_token_name(&msg.asset_infos, &deps.querier)?;
     let mut sub_msgs: Vec<SubMsg> = vec![];
     let liquidity_token = if let Some(token_code_id) = msg.token_code_id {
+        // The factory owner can migrate the LP token unless the pair opts out
+        let lp_admin = if msg.immutable_lp {
+            None
+        } else {
+            Some(
+                query_factory_config(&deps.querier, &msg.factory_addr)?
+                    .owner
+                    .to_string(),
+            )
+        };
         sub_msgs.push(SubMsg {
             msg: WasmMsg::Instantiate {
                 code_id: token_code_id,
                 msg: to_json_binary(&TokenInstantiateMsg {
                     name: token_name,
                     symbol: "uLP".to_string(),
-                    decimals: 6,
+                    decimals: LP_TOKEN_DECIMALS,
                     initial_balances: vec![],
                     mint: Some(MinterResponse {
                         minter: env.contract.address.to_string(),
@@ -83,7 +144,7 @@ pub fn instantiate(
                     marketing: None,
                 })?,
                 funds: vec![],
-                admin: None,
+                admin: lp_admin,
                 label: String::from("Ura LP Token"),
             }
             .into(),
@@ -96,6 +157,17 @@ pub fn instantiate(
             contract_addr: Addr::unchecked(""),
         }
     } else {
+        if !msg
+            .denom_creation_fee
+            .iter()
+            .all(|fee| has_coins(&info.funds, fee))
+        {
+            return Err(ContractError::Dex(
+                DexError::InsufficientDenomCreationFee {},
+            ));
+        }
+
+        // The denom creation fee is charged from the pair balance, which holds the sent funds
         sub_msgs.push(SubMsg {
             id: INSTANTIATE_NATIVE_REPLY_ID,
             msg: MsgCreateDenom {
@@ -107,12 +179,11 @@ pub fn instantiate(
             reply_on: ReplyOn::Success,
         });
 
+        // The denom can't be minted before its creation is confirmed in the reply
+        LP_TOKEN_READY.save(deps.storage, &false)?;
+
         AssetInfo::NativeToken {
-            denom: format!(
-                "factory/{}/{}",
-                env.contract.address.to_string(),
-                token_name
-            ),
+            denom: native_lp_denom(&env.contract.address, &token_name),
         }
     };
 
@@ -126,6 +197,13 @@ pub fn instantiate(
                 pair_type: PairType::Xyk,
             },
             factory_addr: deps.api.addr_validate(msg.factory_addr.as_str())?,
+            launch_end_time: params.launch_end_time,
+            max_lp_per_address: params.max_lp_per_address,
+            max_belief_price_deviation: params.max_belief_price_deviation,
+            oracle_addr,
+            max_oracle_deviation: params.max_oracle_deviation,
+            legacy_lp_token: None,
+            reserve_cap: params.reserve_cap,
         },
     )?;
 
@@ -134,8 +212,93 @@ pub fn instantiate(
 
 /// The entry point to the contract for processing replies from submessages.
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
     match msg {
+        // A failed emission accounting call must not revert the LP operation that triggered it
+        #[cfg(not(feature = "no-emissions"))]
+        Reply {
+            id: ACCUM_EMISSIONS_REPLY_ID,
+            result: SubMsgResult::Err(reason),
+        } => Ok(Response::new().add_attributes(vec![
+            attr("action", "accum_emissions_failed"),
+            attr("reason", reason),
+        ])),
+        Reply {
+            id: MINT_NATIVE_LP_REPLY_ID,
+            result: SubMsgResult::Ok(_),
+        } => {
+            let config = CONFIG.load(deps.storage)?;
+            let mut pending = PENDING_LP_MINTS.load(deps.storage)?;
+            let expected = pending.remove(0);
+            if pending.is_empty() {
+                PENDING_LP_MINTS.remove(deps.storage);
+            } else {
+                PENDING_LP_MINTS.save(deps.storage, &pending)?;
+            }
+
+            let actual = query_total_supply(&deps.querier, &config.pair_info.liquidity_token)?;
+            if actual != expected {
+                return Err(ContractError::LpMintMismatch { expected, actual });
+            }
+
+            Ok(Response::new())
+        }
+        Reply {
+            id: reply_id @ (MIGRATE_NATIVE_REPLY_ID | MIGRATE_CW20_REPLY_ID),
+            result: SubMsgResult::Ok(res),
+        } => {
+            let mut config: Config = CONFIG.load(deps.storage)?;
+            let mut messages = vec![];
+            let liquidity_token = if reply_id == MIGRATE_CW20_REPLY_ID {
+                let init_response =
+                    parse_instantiate_response_data(res.data.unwrap_or_default().as_slice())
+                        .map_err(|e| StdError::generic_err(format!("{e}")))?;
+                AssetInfo::Token {
+                    contract_addr: deps.api.addr_validate(&init_response.contract_address)?,
+                }
+            } else {
+                let reserves = MIGRATION_RESERVES.load(deps.storage)?;
+                MIGRATION_RESERVES.remove(deps.storage);
+                let pools = config
+                    .pair_info
+                    .query_pools(&deps.querier, &env.contract.address)?;
+                if pools
+                    .iter()
+                    .zip(reserves)
+                    .any(|(pool, reserve)| pool.amount < reserve)
+                {
+                    return Err(ContractError::Dex(
+                        DexError::InsufficientDenomCreationFee {},
+                    ));
+                }
+
+                let token_name =
+                    format_lp_token_name(&config.pair_info.asset_infos, &deps.querier)?;
+                let denom = native_lp_denom(&env.contract.address, &token_name);
+                messages.push(set_lp_denom_metadata_msg(&env, &denom, &token_name));
+                AssetInfo::NativeToken { denom }
+            };
+
+            // The previous LP token stays claimable until all of it is exchanged
+            let previous_lp_token = std::mem::replace(
+                &mut config.pair_info.liquidity_token,
+                liquidity_token.clone(),
+            );
+            config.legacy_lp_token = Some(previous_lp_token.clone());
+            CONFIG.save(deps.storage, &config)?;
+
+            // The factory looks the pair up by its LP token
+            messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: config.factory_addr.to_string(),
+                msg: to_json_binary(&FactoryExecuteMsg::UpdatePairLpToken { previous_lp_token })?,
+                funds: vec![],
+            }));
+
+            Ok(Response::new().add_messages(messages).add_attributes(vec![
+                attr("action", "migrate_lp_token"),
+                attr("liquidity_token_addr", liquidity_token.to_string()),
+            ]))
+        }
         Reply {
             id: reply_id,
             result: SubMsgResult::Ok(res),
@@ -147,11 +310,12 @@ pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractE
             };
 
             if !is_cw20_in_config && reply_id != INSTANTIATE_NATIVE_REPLY_ID {
-                return Err(ContractError::InvalidState {});
+                return Err(ContractError::Dex(DexError::InvalidState {}));
             } else if is_cw20_in_config && reply_id != INSTANTIATE_CW20_REPLY_ID {
-                return Err(ContractError::InvalidState {});
+                return Err(ContractError::Dex(DexError::InvalidState {}));
             };
 
+            let mut messages = vec![];
             let liquidity_token_addr = match config.pair_info.liquidity_token {
                 AssetInfo::Token { .. } => {
                     let init_response =
@@ -165,13 +329,61 @@ pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractE
                     CONFIG.save(deps.storage, &config)?;
                     contract_addr.to_string()
                 }
-                AssetInfo::NativeToken { denom } => denom,
+                AssetInfo::NativeToken { denom } => {
+                    let token_name =
+                        format_lp_token_name(&config.pair_info.asset_infos, &deps.querier)?;
+                    messages.push(set_lp_denom_metadata_msg(&env, &denom, &token_name));
+                    denom
+                }
             };
+            LP_TOKEN_READY.save(deps.storage, &true)?;
 
-            Ok(Response::new().add_attribute("liquidity_token_addr", liquidity_token_addr))
+            Ok(Response::new()
+                .add_messages(messages)
+                .add_attribute("liquidity_token_addr", liquidity_token_addr))
         }
-        _ => Err(ContractError::FailedToParseReply {}),
+        // Surface why the withdraw reverted, e.g. the pair is no longer the admin of its LP denom
+        Reply {
+            id: BURN_LP_REPLY_ID,
+            result: SubMsgResult::Err(_),
+        } => Err(ContractError::LpBurnFailed {}),
+        Reply {
+            result: SubMsgResult::Err(reason),
+            ..
+        } => Err(ContractError::Dex(
+            DexError::LiquidityTokenInstantiationFailed { reason },
+        )),
+    }
+}
+
+/// Returns the message setting the bank metadata of the native LP token `denom`, so that wallets
+/// display it as `token_name` with [`LP_TOKEN_DECIMALS`] decimals.
+fn set_lp_denom_metadata_msg(env: &Env, denom: &str, token_name: &str) -> CosmosMsg {
+    MsgSetDenomMetadata {
+        sender: env.contract.address.to_string(),
+        metadata: Some(Metadata {
+            description: format!("Ura {token_name} liquidity token"),
+            denom_units: vec![
+                DenomUnit {
+                    denom: denom.to_string(),
+                    exponent: 0,
+                    aliases: vec![],
+                },
+                DenomUnit {
+                    denom: token_name.to_string(),
+                    exponent: LP_TOKEN_DECIMALS.into(),
+                    aliases: vec![],
+                },
+            ],
+            base: denom.to_string(),
+            display: token_name.to_string(),
+            name: token_name.to_string(),
+            symbol: token_name.to_string(),
+            uri: String::new(),
+            uri_hash: String::new(),
+        }),
     }
+    .into()
 }
 
 /// Exposes all the execute functions available in the contract.
@@ -188,33 +400,92 @@ pub fn execute(
             assets,
             slippage_tolerance,
             receiver,
-        } => provide_liquidity(deps, env, info, assets, slippage_tolerance, receiver),
-        ExecuteMsg::WithdrawLiquidity {} => {
+            owner,
+            beneficiary,
+        } => provide_liquidity(
+            deps,
+            env,
+            info,
+            assets,
+            slippage_tolerance,
+            receiver,
+            owner,
+            beneficiary,
+        ),
+        ExecuteMsg::ProvideLiquidityBatch {
+            provisions,
+            slippage_tolerance,
+        } => provide_liquidity_batch(deps, env, info, provisions, slippage_tolerance),
+        ExecuteMsg::ProvideLiquidityAutoBalance {
+            assets,
+            max_spread,
+            receiver,
+        } => provide_liquidity_auto_balance(deps, env, info, assets, max_spread, receiver),
+        ExecuteMsg::WithdrawLiquidity { beneficiary } => {
             let config = CONFIG.load(deps.storage)?;
             let lp_denom = match config.pair_info.liquidity_token {
                 AssetInfo::NativeToken { denom } => Ok(denom),
-                AssetInfo::Token { .. } => Err(ContractError::NonSupported {}),
+                AssetInfo::Token { .. } => Err(ContractError::Dex(DexError::NonSupported {})),
             }?;
             if info.funds.len() != 1 || !info.funds[0].denom.eq(&lp_denom) {
-                return Err(ContractError::InvalidLiquidityToken {});
+                return Err(ContractError::Dex(DexError::InvalidLiquidityToken {}));
             }
             let share = info.funds[0].amount;
             let sender = info.sender.clone();
-            withdraw_liquidity(deps, env, info, sender, share)
+            let beneficiary = addr_opt_validate(deps.api, &beneficiary)?;
+            withdraw_liquidity(deps, env, info, sender, share, beneficiary)
+        }
+        ExecuteMsg::WithdrawLiquidityOneSided {
+            amount,
+            ask_asset_info,
+            max_spread,
+        } => {
+            let config = CONFIG.load(deps.storage)?;
+            let lp_denom = match config.pair_info.liquidity_token {
+                AssetInfo::NativeToken { denom } => Ok(denom),
+                AssetInfo::Token { .. } => Err(ContractError::Dex(DexError::NonSupported {})),
+            }?;
+            if info.funds.len() != 1
+                || !info.funds[0].denom.eq(&lp_denom)
+                || info.funds[0].amount != amount
+            {
+                return Err(ContractError::Dex(DexError::InvalidLiquidityToken {}));
+            }
+            withdraw_liquidity_one_sided(deps, env, info.sender, amount, ask_asset_info, max_spread)
+        }
+        ExecuteMsg::EmergencyWithdraw {} => {
+            let config = CONFIG.load(deps.storage)?;
+            let lp_denom = match config.pair_info.liquidity_token {
+                AssetInfo::NativeToken { denom } => Ok(denom),
+                AssetInfo::Token { .. } => Err(ContractError::Dex(DexError::NonSupported {})),
+            }?;
+            if info.funds.len() != 1 || !info.funds[0].denom.eq(&lp_denom) {
+                return Err(ContractError::Dex(DexError::InvalidLiquidityToken {}));
+            }
+            let share = info.funds[0].amount;
+            emergency_withdraw(deps, env, info.sender, share)
+        }
+        ExecuteMsg::SetProvideOperator { operator, approved } => {
+            set_provide_operator(deps, info, operator, approved)
         }
         ExecuteMsg::Swap {
             offer_asset,
+            ask_asset_info,
             belief_price,
             max_spread,
             to,
-            ..
+            to_hook,
         } => {
             offer_asset.info.check(deps.api)?;
             if !offer_asset.is_native_token() {
-                return Err(ContractError::Cw20DirectSwap {});
+                return Err(ContractError::Dex(DexError::Cw20DirectSwap {}));
             }
 
             let to_addr = addr_opt_validate(deps.api, &to)?;
+            if to_hook.is_some() {
+                let receiver = to_addr.as_ref().unwrap_or(&info.sender);
+                assert_hook_recipient(deps.as_ref(), &env, receiver)?;
+            }
 
             swap(
                 deps,
@@ -222,12 +493,40 @@ pub fn execute(
                 info.clone(),
                 info.sender,
                 offer_asset,
+                ask_asset_info,
                 belief_price,
                 max_spread,
                 to_addr,
+                to_hook,
             )
         }
         ExecuteMsg::UpdateConfig { params } => update_config(deps, env, info, params),
+        ExecuteMsg::RescueTokens {
+            asset_info,
+            amount,
+            recipient,
+        } => rescue_tokens(deps, info, asset_info, amount, recipient),
+        ExecuteMsg::MigrateLpToken {
+            to_cw20,
+            token_code_id,
+        } => migrate_lp_token(deps, env, info, to_cw20, token_code_id),
+        #[cfg(not(feature = "no-emissions"))]
+        ExecuteMsg::CompoundFees { max_spread } => compound_fees(deps, env, info, max_spread),
+        #[cfg(feature = "no-emissions")]
+        ExecuteMsg::CompoundFees { .. } => Err(ContractError::Dex(DexError::NonSupported {})),
+        ExecuteMsg::ClaimMigratedLp {} => {
+            let config = CONFIG.load(deps.storage)?;
+            let lp_denom = match config.legacy_lp_token {
+                Some(AssetInfo::NativeToken { denom }) => Ok(denom),
+                Some(AssetInfo::Token { .. }) => Err(ContractError::Dex(DexError::NonSupported {})),
+                None => Err(ContractError::NoLpMigration {}),
+            }?;
+            if info.funds.len() != 1 || !info.funds[0].denom.eq(&lp_denom) {
+                return Err(ContractError::Dex(DexError::InvalidLiquidityToken {}));
+            }
+            let share = info.funds[0].amount;
+            claim_migrated_lp(deps, env, info.sender, share)
+        }
     }
 }
 
@@ -242,10 +541,10 @@ pub fn receive_cw20(
 ) -> Result<Response, ContractError> {
     match from_json(&cw20_msg.msg)? {
         Cw20HookMsg::Swap {
+            ask_asset_info,
             belief_price,
             max_spread,
             to,
-            ..
         } => {
             // Only asset contract can execute this message
             let config = CONFIG.load(deps.storage)?;
@@ -260,7 +559,7 @@ pub fn receive_cw20(
             }
 
             if !authorized {
-                return Err(ContractError::Unauthorized {});
+                return Err(ContractError::Dex(DexError::Unauthorized {}));
             }
 
             let to_addr = addr_opt_validate(deps.api, &to)?;
@@ -275,30 +574,86 @@ pub fn receive_cw20(
                     info: AssetInfo::Token { contract_addr },
                     amount: cw20_msg.amount,
                 },
+                ask_asset_info,
                 belief_price,
                 max_spread,
                 to_addr,
+                None,
             )
         }
-        Cw20HookMsg::WithdrawLiquidity {} => {
+        Cw20HookMsg::WithdrawLiquidity { beneficiary } => {
             let config = CONFIG.load(deps.storage)?;
             match config.pair_info.liquidity_token {
-                AssetInfo::NativeToken { .. } => Err(ContractError::NonSupported {}),
+                AssetInfo::NativeToken { .. } => Err(ContractError::Dex(DexError::NonSupported {})),
                 AssetInfo::Token { contract_addr } => {
                     if info.sender != contract_addr {
-                        return Err(ContractError::Unauthorized {});
+                        return Err(ContractError::Dex(DexError::Unauthorized {}));
                     }
                     Ok(contract_addr)
                 }
             }?;
+            let beneficiary = addr_opt_validate(deps.api, &beneficiary)?;
             withdraw_liquidity(
                 deps,
                 env,
                 info,
                 Addr::unchecked(cw20_msg.sender),
                 cw20_msg.amount,
+                beneficiary,
+            )
+        }
+        Cw20HookMsg::WithdrawLiquidityOneSided {
+            ask_asset_info,
+            max_spread,
+        } => {
+            let config = CONFIG.load(deps.storage)?;
+            match config.pair_info.liquidity_token {
+                AssetInfo::NativeToken { .. } => Err(ContractError::Dex(DexError::NonSupported {})),
+                AssetInfo::Token { contract_addr } => {
+                    if info.sender != contract_addr {
+                        return Err(ContractError::Dex(DexError::Unauthorized {}));
+                    }
+                    Ok(contract_addr)
+                }
+            }?;
+            withdraw_liquidity_one_sided(
+                deps,
+                env,
+                Addr::unchecked(cw20_msg.sender),
+                cw20_msg.amount,
+                ask_asset_info,
+                max_spread,
             )
         }
+        Cw20HookMsg::EmergencyWithdraw {} => {
+            let config = CONFIG.load(deps.storage)?;
+            match config.pair_info.liquidity_token {
+                AssetInfo::NativeToken { .. } => Err(ContractError::Dex(DexError::NonSupported {})),
+                AssetInfo::Token { contract_addr } => {
+                    if info.sender != contract_addr {
+                        return Err(ContractError::Dex(DexError::Unauthorized {}));
+                    }
+                    Ok(contract_addr)
+                }
+            }?;
+            emergency_withdraw(deps, env, Addr::unchecked(cw20_msg.sender), cw20_msg.amount)
+        }
+        Cw20HookMsg::ClaimMigratedLp {} => {
+            let config = CONFIG.load(deps.storage)?;
+            match config.legacy_lp_token {
+                Some(AssetInfo::NativeToken { .. }) => {
+                    Err(ContractError::Dex(DexError::NonSupported {}))
+                }
+                Some(AssetInfo::Token { contract_addr }) => {
+                    if info.sender != contract_addr {
+                        return Err(ContractError::Dex(DexError::Unauthorized {}));
+                    }
+                    Ok(contract_addr)
+                }
+                None => Err(ContractError::NoLpMigration {}),
+            }?;
+            claim_migrated_lp(deps, env, Addr::unchecked(cw20_msg.sender), cw20_msg.amount)
+        }
     }
 }
 
@@ -307,12 +662,25 @@ pub fn receive_cw20(
 /// * **assets** is an array with assets available in the pool.
 ///
 /// * **slippage_tolerance** is an optional parameter which is used to specify how much
-/// the pool price can move until the provide liquidity transaction goes through.
+///   the pool price can move until the provide liquidity transaction goes through.
 ///
 /// * **receiver** is an optional parameter which defines the receiver of the LP tokens.
-/// If no custom receiver is specified, the pair will mint LP tokens for the function caller.
+///   If no custom receiver is specified, the pair will mint LP tokens for the owner.
+///
+/// * **owner** is an optional address the CW20 deposits are pulled from (defaults to the caller).
+///
+/// Providing on behalf of an owner lets e.g. a router spend a user's allowance. The owner must
+/// have approved the caller with [`set_provide_operator`], the LP tokens can only be minted to
+/// the owner and the caller must set a slippage tolerance, so that an allowance granted to the
+/// pair cannot be spent by anyone else or against a manipulated pool.
+///
+/// * **beneficiary** is an optional address the controller accrues the emissions of the receiver's LP tokens to (defaults to the receiver).
+///
+/// A beneficiary other than the receiver is only allowed if the LP tokens go to the caller.
+/// The LP tokens are then tracked for the beneficiary.
 ///
 /// NOTE - the address that wants to provide liquidity should approve the pair contract to pull its relevant tokens.
+#[allow(clippy::too_many_arguments)]
 pub fn provide_liquidity(
     deps: DepsMut,
     env: Env,
@@ -320,6 +688,8 @@ pub fn provide_liquidity(
     assets: Vec<Asset>,
     slippage_tolerance: Option<Decimal>,
     receiver: Option<String>,
+    owner: Option<String>,
+    beneficiary: Option<String>,
 ) -> Result<Response, ContractError> {
     if assets.len() != 2 {
         return Err(StdError::generic_err("asset_infos must contain exactly two elements").into());
@@ -328,9 +698,38 @@ pub fn provide_liquidity(
     assets[1].info.check(deps.api)?;
 
     let pool_address = env.clone().contract.address;
+    assert_pair_ready(deps.storage)?;
     let config = CONFIG.load(deps.storage)?;
+    // Both pool assets must be provided exactly once
+    if assets[0].info.equal(&assets[1].info)
+        || !assets
+            .iter()
+            .all(|a| config.pair_info.asset_infos.contains(&a.info))
+    {
+        return Err(ContractError::AssetMismatch {});
+    }
+    info.funds
+        .assert_no_extra_funds(&config.pair_info.asset_infos)?;
     info.funds
         .assert_coins_properly_sent(&assets, &config.pair_info.asset_infos)?;
+
+    let owner = addr_opt_validate(deps.api, &owner)?.unwrap_or_else(|| info.sender.clone());
+    let receiver = addr_opt_validate(deps.api, &receiver)?.unwrap_or_else(|| owner.clone());
+    if owner != info.sender {
+        if receiver != owner || !PROVIDE_OPERATORS.has(deps.storage, (&owner, &info.sender)) {
+            return Err(ContractError::Dex(DexError::Unauthorized {}));
+        }
+        if slippage_tolerance.is_none() {
+            return Err(ContractError::SlippageToleranceRequired {});
+        }
+    }
+    let beneficiary =
+        addr_opt_validate(deps.api, &beneficiary)?.unwrap_or_else(|| receiver.clone());
+    // Only LP tokens minted to the sender can accrue their emissions to another address
+    if beneficiary != receiver && receiver != info.sender {
+        return Err(ContractError::Dex(DexError::Unauthorized {}));
+    }
+
     let mut pools = config
         .pair_info
         .query_pools(&deps.querier, &config.pair_info.contract_addr)?;
@@ -339,16 +738,16 @@ pub fn provide_liquidity(
             .iter()
             .find(|a| a.info.equal(&pools[0].info))
             .map(|a| a.amount)
-            .expect("Wrong asset info is given"),
+            .ok_or(ContractError::AssetMismatch {})?,
         assets
             .iter()
             .find(|a| a.info.equal(&pools[1].info))
             .map(|a| a.amount)
-            .expect("Wrong asset info is given"),
+            .ok_or(ContractError::AssetMismatch {})?,
     ];
 
     if deposits[0].is_zero() || deposits[1].is_zero() {
-        return Err(ContractError::InvalidZeroAmount {});
+        return Err(ContractError::Dex(DexError::InvalidZeroAmount {}));
     }
 
     let mut messages = vec![];
@@ -356,10 +755,10 @@ pub fn provide_liquidity(
     for (i, pool) in pools.iter_mut().enumerate() {
         // If the asset is a token contract, then we need to execute a TransferFrom msg to receive assets
         if let AssetInfo::Token { contract_addr, .. } = &pool.info {
-            messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            messages.push(SubMsg::new(WasmMsg::Execute {
                 contract_addr: contract_addr.to_string(),
                 msg: to_json_binary(&Cw20ExecuteMsg::TransferFrom {
-                    owner: info.sender.to_string(),
+                    owner: owner.to_string(),
                     recipient: pool_address.to_string(),
                     amount: deposits[i],
                 })?,
@@ -372,58 +771,35 @@ pub fn provide_liquidity(
         }
     }
 
-    let total_share = match &config.pair_info.liquidity_token {
-        AssetInfo::NativeToken { denom } => deps.querier.query_supply(denom)?.amount,
-        AssetInfo::Token { contract_addr } => {
-            let res: TokenInfoResponse = deps
-                .querier
-                .query_wasm_smart(contract_addr, &Cw20QueryMsg::TokenInfo {})?;
-            res.total_supply
-        }
-    };
-
-    let share = if total_share.is_zero() {
-        // Initial share = collateral amount
-        let share = Uint128::new(
-            (U256::from(deposits[0].u128()) * U256::from(deposits[1].u128()))
-                .integer_sqrt()
-                .as_u128(),
-        )
-        .checked_sub(MINIMUM_LIQUIDITY_AMOUNT)
-        .map_err(|_| ContractError::MinimumLiquidityAmountError {})?;
+    let total_share = query_total_share(&deps.querier, &config)?;
 
+    if total_share.is_zero() {
         messages.extend(mint_liquidity_token_message(
+            deps.storage,
             deps.querier,
             &config,
             &pool_address,
             &pool_address,
             MINIMUM_LIQUIDITY_AMOUNT,
         )?);
-
-        // share cannot become zero after minimum liquidity subtraction
-        if share.is_zero() {
-            return Err(ContractError::MinimumLiquidityAmountError {});
-        }
-
-        share
     } else {
         // Assert slippage tolerance
         assert_slippage_tolerance(slippage_tolerance, &deposits, &pools)?;
+    }
+    let share = compute_provide_share(&deposits, &pools, total_share)?;
 
-        // min(1, 2)
-        // 1. sqrt(deposit_0 * exchange_rate_0_to_1 * deposit_0) * (total_share / sqrt(pool_0 * pool_0))
-        // == deposit_0 * total_share / pool_0
-        // 2. sqrt(deposit_1 * exchange_rate_1_to_0 * deposit_1) * (total_share / sqrt(pool_1 * pool_1))
-        // == deposit_1 * total_share / pool_1
-        std::cmp::min(
-            deposits[0].multiply_ratio(total_share, pools[0].amount),
-            deposits[1].multiply_ratio(total_share, pools[1].amount),
-        )
-    };
+    assert_launch_lp_cap(deps.storage, &env.block, &config, &receiver, share)?;
+    assert_reserve_cap(
+        &config,
+        [
+            pools[0].amount.checked_add(deposits[0])?,
+            pools[1].amount.checked_add(deposits[1])?,
+        ],
+    )?;
 
-    // Mint LP tokens for the sender or for the receiver (if set)
-    let receiver = addr_opt_validate(deps.api, &receiver)?.unwrap_or_else(|| info.sender.clone());
+    // Mint LP tokens for the owner or for the receiver (if set)
     messages.extend(mint_liquidity_token_message(
+        deps.storage,
         deps.querier,
         &config,
         &pool_address,
@@ -431,503 +807,2239 @@ pub fn provide_liquidity(
         share,
     )?);
 
-    // Stores the amount of lp tokens is sent to the lp_provider for emission calculations
-    // Calls gauge controller to accum the emission rewards first
-    let mut lp_amount_before_providing = Uint128::zero();
-    LP_PROVIDERS.update(deps.storage, &receiver, |current_amount| -> StdResult<_> {
-        if let Some(current_amount) = current_amount {
-            lp_amount_before_providing = current_amount;
-            Ok(current_amount.checked_add(share)?)
-        } else {
-            Ok(share)
-        }
-    })?;
-    let fee_info = query_fee_info(
-        &deps.querier,
-        &config.factory_addr,
-        config.pair_info.pair_type.clone(),
-        &pool_address,
-    )?;
-    let controller = fee_info.controller_address;
-    if lp_amount_before_providing.ne(&Uint128::zero()) && controller.is_some() {
-        let controller = controller.unwrap();
-        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: controller.to_string(),
-            msg: to_json_binary(&ControllerExecuteMsg::AccumUserEmissions(
-                AccumEmissionsRequest {
-                    address: receiver.to_string(),
-                    previous_amount: lp_amount_before_providing,
-                },
-            ))?,
-            funds: vec![],
-        }));
-    }
+    let accum_msgs =
+        track_lp_provider(deps, &config, &pool_address, &receiver, &beneficiary, share)?;
 
-    Ok(Response::new().add_messages(messages).add_attributes(vec![
-        attr("action", "provide_liquidity"),
-        attr("sender", info.sender),
-        attr("receiver", receiver),
-        attr("assets", format!("{}, {}", assets[0], assets[1])),
-        attr("share", share),
-    ]))
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_submessages(accum_msgs)
+        .add_attributes(vec![
+            attr("action", "provide_liquidity"),
+            attr("sender", info.sender),
+            attr("receiver", receiver),
+            attr("assets", format!("{}, {}", assets[0], assets[1])),
+            attr("share", share),
+        ]))
 }
 
-/// Mint LP tokens for a beneficiary and auto stake the tokens in the Controller contract (if auto staking is specified).
-///
-/// * **recipient** is the LP token recipient.
-///
-/// * **amount** is the amount of LP tokens that will be minted for the recipient.
-fn mint_liquidity_token_message(
-    _querier: QuerierWrapper,
-    config: &Config,
-    contract_address: &Addr,
-    recipient: &Addr,
-    amount: Uint128,
-) -> Result<Vec<CosmosMsg>, ContractError> {
-    match &config.pair_info.liquidity_token {
-        AssetInfo::NativeToken { denom } => {
-            if recipient == contract_address {
-                return Ok(vec![MsgMint {
-                    sender: contract_address.to_string(),
-                    amount: Some(crate::denom::Coin {
-                        denom: denom.clone(),
-                        amount: amount.to_string(),
-                    }),
-                }
-                .into()]);
-            }
+/// Approves or revokes `operator` to provide liquidity from the caller's CW20 allowance.
+pub fn set_provide_operator(
+    deps: DepsMut,
+    info: MessageInfo,
+    operator: String,
+    approved: bool,
+) -> Result<Response, ContractError> {
+    let operator = deps.api.addr_validate(&operator)?;
+    if approved {
+        PROVIDE_OPERATORS.save(deps.storage, (&info.sender, &operator), &Empty {})?;
+    } else {
+        PROVIDE_OPERATORS.remove(deps.storage, (&info.sender, &operator));
+    }
 
-            return Ok(vec![
-                MsgMint {
-                    sender: contract_address.to_string(),
-                    amount: Some(crate::denom::Coin {
-                        denom: denom.clone(),
-                        amount: amount.to_string(),
-                    }),
-                }
-                .into(),
-                CosmosMsg::Bank(BankMsg::Send {
-                    to_address: recipient.to_string(),
-                    amount: vec![Coin {
-                        denom: denom.clone(),
-                        amount: amount,
-                    }],
-                }),
-            ]);
-        }
-        AssetInfo::Token { contract_addr } => {
-            return Ok(vec![CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: contract_addr.to_string(),
-                msg: to_json_binary(&Cw20ExecuteMsg::Mint {
-                    recipient: recipient.to_string(),
-                    amount,
-                })?,
-                funds: vec![],
-            })]);
-        }
-    };
+    Ok(Response::new().add_attributes(vec![
+        attr("action", "set_provide_operator"),
+        attr("owner", info.sender),
+        attr("operator", operator),
+        attr("approved", approved.to_string()),
+    ]))
 }
 
-/// Withdraw liquidity from the pool.
-/// * **sender** is the address that will receive assets back from the pair contract.
+/// Provides liquidity for every entry of `provisions`, pulling all the deposits from the caller.
+/// The pool state is carried over from one deposit to the next and the controller accumulates
+/// the emissions of all the receivers in a single call.
 ///
-/// * **amount** is the amount of LP tokens to burn.
-pub fn withdraw_liquidity(
+/// * **provisions** deposits to make, each with an optional LP token receiver.
+///
+/// * **slippage_tolerance** slippage tolerance applied to every deposit.
+pub fn provide_liquidity_batch(
     deps: DepsMut,
     env: Env,
-    _info: MessageInfo,
-    sender: Addr,
-    amount: Uint128,
+    info: MessageInfo,
+    provisions: Vec<LiquidityProvision>,
+    slippage_tolerance: Option<Decimal>,
 ) -> Result<Response, ContractError> {
-    let config: Config = CONFIG.load(deps.storage).unwrap();
-    let pool_address = env.clone().contract.address;
+    if provisions.is_empty() {
+        return Err(StdError::generic_err("provisions must not be empty").into());
+    }
 
-    let (pools, total_share) = pool_info(deps.querier, &config)?;
+    let pool_address = env.contract.address.clone();
+    assert_pair_ready(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
+    let mut pools = config
+        .pair_info
+        .query_pools(&deps.querier, &config.pair_info.contract_addr)?;
 
-    let refund_assets = get_share_in_assets(&pools, amount, total_share);
+    let mut deposits = Vec::with_capacity(provisions.len());
+    let mut receivers = Vec::with_capacity(provisions.len());
+    let mut totals = [Uint128::zero(); 2];
+    for provision in &provisions {
+        let assets = &provision.assets;
+        if assets.len() != 2 || assets[0].info.equal(&assets[1].info) {
+            return Err(ContractError::AssetMismatch {});
+        }
+        let mut deposit = [Uint128::zero(); 2];
+        for (i, pool) in pools.iter().enumerate() {
+            deposit[i] = assets
+                .iter()
+                .find(|a| a.info.equal(&pool.info))
+                .map(|a| a.amount)
+                .ok_or(ContractError::AssetMismatch {})?;
+            if deposit[i].is_zero() {
+                return Err(ContractError::Dex(DexError::InvalidZeroAmount {}));
+            }
+            totals[i] = totals[i].checked_add(deposit[i])?;
+        }
+        deposits.push(deposit);
+        receivers.push(
+            addr_opt_validate(deps.api, &provision.receiver)?
+                .unwrap_or_else(|| info.sender.clone()),
+        );
+    }
 
-    // Update the pool info
-    let mut messages: Vec<CosmosMsg> = vec![];
+    let total_assets = [
+        pools[0].info.with_balance(totals[0]),
+        pools[1].info.with_balance(totals[1]),
+    ];
+    info.funds
+        .assert_no_extra_funds(&config.pair_info.asset_infos)?;
+    info.funds
+        .assert_coins_properly_sent(&total_assets, &config.pair_info.asset_infos)?;
 
-    let burn_msg: CosmosMsg = match config.pair_info.liquidity_token {
-        AssetInfo::NativeToken { denom } => MsgBurn {
-            sender: env.contract.address.to_string(),
-            amount: Some(crate::denom::Coin {
-                denom,
-                amount: amount.to_string(),
-            }),
+    let mut messages = vec![];
+    for (pool, total) in pools.iter_mut().zip(totals) {
+        if let AssetInfo::Token { contract_addr, .. } = &pool.info {
+            messages.push(SubMsg::new(WasmMsg::Execute {
+                contract_addr: contract_addr.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::TransferFrom {
+                    owner: info.sender.to_string(),
+                    recipient: pool_address.to_string(),
+                    amount: total,
+                })?,
+                funds: vec![],
+            }));
+        } else {
+            // The native deposits of the whole batch are already in the pool balance
+            pool.amount = pool.amount.checked_sub(total)?;
         }
-        .into(),
-        AssetInfo::Token { contract_addr } => CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: contract_addr.to_string(),
-            msg: to_json_binary(&Cw20ExecuteMsg::Burn { amount })?,
-            funds: vec![],
-        }),
-    };
-
-    messages.extend(vec![
-        refund_assets[0].clone().into_msg(sender.clone())?,
-        refund_assets[1].clone().into_msg(sender.clone())?,
-        burn_msg,
-    ]);
+    }
 
-    // Stores the amount of lp tokens is sent to the lp_provider for emission calculations
-    // Calls gauge controller to accum the emission rewards first
-    let mut lp_amount_before_withdrawing = Uint128::zero();
-    LP_PROVIDERS.update(deps.storage, &sender, |current_amount| -> StdResult<_> {
-        if let Some(current_amount) = current_amount {
-            lp_amount_before_withdrawing = current_amount;
-            Ok(current_amount.checked_sub(amount.clone())?)
+    let mut total_share = query_total_share(&deps.querier, &config)?;
+    let mut minted = Uint128::zero();
+    let mut accum_batch = AccumEmissionsBatch::default();
+    for (deposit, receiver) in deposits.iter().zip(&receivers) {
+        let share = compute_provide_share(deposit, &pools, total_share)?;
+        if total_share.is_zero() {
+            messages.extend(mint_liquidity_token_message(
+                deps.storage,
+                deps.querier,
+                &config,
+                &pool_address,
+                &pool_address,
+                MINIMUM_LIQUIDITY_AMOUNT,
+            )?);
+            total_share = MINIMUM_LIQUIDITY_AMOUNT;
         } else {
-            Ok(Uint128::zero())
+            assert_slippage_tolerance(slippage_tolerance, deposit, &pools)?;
         }
-    })?;
-    let fee_info = query_fee_info(
-        &deps.querier,
-        &config.factory_addr,
-        config.pair_info.pair_type.clone(),
-        &pool_address,
-    )?;
-    let controller = fee_info.controller_address;
-    if lp_amount_before_withdrawing.ne(&Uint128::zero()) && controller.is_some() {
-        let controller = controller.unwrap();
-        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: controller.to_string(),
-            msg: to_json_binary(&ControllerExecuteMsg::AccumUserEmissions(
-                AccumEmissionsRequest {
-                    address: sender.to_string(),
-                    previous_amount: lp_amount_before_withdrawing,
-                },
-            ))?,
-            funds: vec![],
-        }));
+
+        assert_launch_lp_cap(deps.storage, &env.block, &config, receiver, share)?;
+        messages.extend(mint_liquidity_token_message(
+            deps.storage,
+            deps.querier,
+            &config,
+            &pool_address,
+            receiver,
+            share,
+        )?);
+        accum_batch.push(receiver, record_lp_provider(deps.storage, receiver, share)?);
+
+        pools[0].amount = pools[0].amount.checked_add(deposit[0])?;
+        pools[1].amount = pools[1].amount.checked_add(deposit[1])?;
+        total_share = total_share.checked_add(share)?;
+        minted = minted.checked_add(share)?;
     }
 
-    Ok(Response::new().add_messages(messages).add_attributes(vec![
-        attr("action", "withdraw_liquidity"),
-        attr("sender", sender),
-        attr("withdrawn_share", amount),
-        attr(
-            "refund_assets",
-            format!("{}, {}", refund_assets[0], refund_assets[1]),
-        ),
-    ]))
+    assert_reserve_cap(&config, [pools[0].amount, pools[1].amount])?;
+
+    let accum_msgs =
+        accum_emissions_batch_msgs(&deps.querier, &config, &pool_address, accum_batch)?;
+
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_submessages(accum_msgs)
+        .add_attributes(vec![
+            attr("action", "provide_liquidity_batch"),
+            attr("sender", info.sender),
+            attr("provisions", provisions.len().to_string()),
+            attr("share", minted),
+        ]))
 }
 
-/// Returns the amount of pool assets that correspond to an amount of LP tokens.
-///
-/// * **pools** is the array with assets in the pool.
+/// Returns the amount of LP tokens minted for `deposits`, which are ordered like `pools`.
 ///
-/// * **amount** is amount of LP tokens to compute a corresponding amount of assets for.
-///
-/// * **total_share** is the total amount of LP tokens currently minted.
-pub fn get_share_in_assets(pools: &[Asset], amount: Uint128, total_share: Uint128) -> Vec<Asset> {
-    let mut share_ratio = Decimal::zero();
-    if !total_share.is_zero() {
-        share_ratio = Decimal::from_ratio(amount, total_share);
-    }
+/// The first deposit mints `sqrt(deposit_0 * deposit_1)` minus [`MINIMUM_LIQUIDITY_AMOUNT`],
+/// which is locked in the pool. Later deposits mint in proportion to the smaller of the two ratios.
+fn compute_provide_share(
+    deposits: &[Uint128; 2],
+    pools: &[Asset],
+    total_share: Uint128,
+) -> Result<Uint128, ContractError> {
+    if total_share.is_zero() {
+        // Initial share = collateral amount
+        let share = to_uint128(
+            (U256::from(deposits[0].u128()) * U256::from(deposits[1].u128())).integer_sqrt(),
+        )?
+        .checked_sub(MINIMUM_LIQUIDITY_AMOUNT)
+        .map_err(|_| ContractError::Dex(DexError::MinimumLiquidityAmountError {}))?;
 
-    pools
-        .iter()
-        .map(|a| Asset {
-            info: a.info.clone(),
-            amount: a.amount * share_ratio,
-        })
-        .collect()
+        // share cannot become zero after minimum liquidity subtraction
+        if share.is_zero() {
+            return Err(ContractError::Dex(DexError::MinimumLiquidityAmountError {}));
+        }
+
+        Ok(share)
+    } else {
+        // min(1, 2)
+        // 1. sqrt(deposit_0 * exchange_rate_0_to_1 * deposit_0) * (total_share / sqrt(pool_0 * pool_0))
+        // == deposit_0 * total_share / pool_0
+        // 2. sqrt(deposit_1 * exchange_rate_1_to_0 * deposit_1) * (total_share / sqrt(pool_1 * pool_1))
+        // == deposit_1 * total_share / pool_1
+        Ok(std::cmp::min(
+            deposits[0].multiply_ratio(total_share, pools[0].amount),
+            deposits[1].multiply_ratio(total_share, pools[1].amount),
+        ))
+    }
 }
 
-/// Performs an swap operation with the specified parameters. The trader must approve the
-/// pool contract to transfer offer assets from their wallet.
-///
-/// * **sender** is the sender of the swap operation.
-///
-/// * **offer_asset** proposed asset for swapping.
-///
-/// * **belief_price** is used to calculate the maximum swap spread.
+/// Provides liquidity from an imbalanced deposit. The excess side is first swapped inside the
+/// pool (paying the regular commission) so that the deposit matches the pool ratio, then the
+/// balanced amounts are provided and any rounding leftover is refunded.
 ///
-/// * **max_spread** sets the maximum spread of the swap operation.
+/// * **assets** assets to deposit. One of the pool assets may be omitted or have a zero amount.
 ///
-/// * **to** sets the recipient of the swap operation.
+/// * **max_spread** max spread allowed for the balancing swap.
 ///
-/// NOTE - the address that wants to swap should approve the pair contract to pull the offer token.
-#[allow(clippy::too_many_arguments)]
-pub fn swap(
+/// * **receiver** optional receiver of the LP tokens, defaults to the function caller.
+pub fn provide_liquidity_auto_balance(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    sender: Addr,
-    offer_asset: Asset,
-    belief_price: Option<Decimal>,
+    assets: Vec<Asset>,
     max_spread: Option<Decimal>,
-    to: Option<Addr>,
+    receiver: Option<String>,
 ) -> Result<Response, ContractError> {
-    offer_asset.assert_sent_native_token_balance(&info)?;
+    if assets.is_empty() || assets.len() > 2 {
+        return Err(StdError::generic_err("assets must contain one or two elements").into());
+    }
+    for asset in &assets {
+        asset.info.check(deps.api)?;
+    }
 
+    let pool_address = env.contract.address;
+    assert_pair_ready(deps.storage)?;
     let config = CONFIG.load(deps.storage)?;
-
-    // If the asset balance is already increased, we should subtract the user deposit from the pool amount
-    let pools = config
+    info.funds
+        .assert_no_extra_funds(&config.pair_info.asset_infos)?;
+    info.funds
+        .assert_coins_properly_sent(&assets, &config.pair_info.asset_infos)?;
+    let mut pools = config
         .pair_info
-        .query_pools(&deps.querier, &config.pair_info.contract_addr)?
-        .into_iter()
-        .map(|mut p| {
-            if p.info.equal(&offer_asset.info) {
-                p.amount = p.amount.checked_sub(offer_asset.amount)?;
-            }
-            Ok(p)
-        })
-        .collect::<StdResult<Vec<_>>>()?;
+        .query_pools(&deps.querier, &config.pair_info.contract_addr)?;
+    let mut deposits = [0, 1].map(|i| {
+        assets
+            .iter()
+            .find(|a| a.info.equal(&pools[i].info))
+            .map(|a| a.amount)
+            .unwrap_or_default()
+    });
 
-    let offer_pool: Asset;
-    let ask_pool: Asset;
+    if deposits[0].is_zero() && deposits[1].is_zero() {
+        return Err(ContractError::Dex(DexError::InvalidZeroAmount {}));
+    }
 
-    if offer_asset.info.equal(&pools[0].info) {
-        offer_pool = pools[0].clone();
-        ask_pool = pools[1].clone();
-    } else if offer_asset.info.equal(&pools[1].info) {
-        offer_pool = pools[1].clone();
-        ask_pool = pools[0].clone();
-    } else {
-        return Err(ContractError::AssetMismatch {});
+    let mut messages = vec![];
+    for (i, pool) in pools.iter_mut().enumerate() {
+        if let AssetInfo::Token { contract_addr, .. } = &pool.info {
+            if !deposits[i].is_zero() {
+                messages.push(SubMsg::new(WasmMsg::Execute {
+                    contract_addr: contract_addr.to_string(),
+                    msg: to_json_binary(&Cw20ExecuteMsg::TransferFrom {
+                        owner: info.sender.to_string(),
+                        recipient: pool_address.to_string(),
+                        amount: deposits[i],
+                    })?,
+                    funds: vec![],
+                }));
+            }
+        } else {
+            // The pool balance already includes native deposits
+            pool.amount = pool.amount.checked_sub(deposits[i])?;
+        }
+    }
+
+    let (_, total_share) = pool_info(deps.querier, &config)?;
+    if total_share.is_zero() {
+        return Err(StdError::generic_err(
+            "Liquidity must be provided to the pool before it can be auto balanced",
+        )
+        .into());
     }
 
-    // Get fee info from the factory
     let fee_info = query_fee_info(
         &deps.querier,
         &config.factory_addr,
         config.pair_info.pair_type.clone(),
-        &env.contract.address,
+        &pool_address,
     )?;
 
-    let offer_amount = offer_asset.amount;
-
-    let (return_amount, spread_amount, commission_amount) = compute_swap(
-        offer_pool.amount,
-        ask_pool.amount,
-        offer_amount,
+    let (offer_index, swap_amount) = compute_auto_balance_swap(
+        deposits,
+        [pools[0].amount, pools[1].amount],
         fee_info.total_fee_rate,
     )?;
+    let ask_index = 1 - offer_index;
+
+    if !swap_amount.is_zero() {
+        let (return_amount, spread_amount, commission_amount) = compute_swap(
+            pools[offer_index].amount,
+            pools[ask_index].amount,
+            swap_amount,
+            fee_info.total_fee_rate,
+        )?;
+        assert_max_spread(
+            None,
+            max_spread,
+            swap_amount,
+            return_amount + commission_amount,
+            spread_amount,
+        )?;
+        assert_swap_limits(
+            &deps.querier,
+            &config,
+            fee_info.max_price_impact,
+            [&pools[offer_index], &pools[ask_index]],
+            swap_amount,
+            return_amount,
+            return_amount + commission_amount,
+        )?;
+
+        accumulate_fees(deps.storage, &pools[ask_index].info, commission_amount)?;
+
+        // The balancing swap happens inside the pool: the commission stays in the ask pool
+        deposits[offer_index] -= swap_amount;
+        deposits[ask_index] += return_amount;
+        pools[offer_index].amount += swap_amount;
+        pools[ask_index].amount -= return_amount;
+    }
 
-    // Check the max spread limit (if it was specified)
-    assert_max_spread(
-        belief_price,
-        max_spread,
-        offer_amount,
-        return_amount + commission_amount,
-        spread_amount,
+    let share = std::cmp::min(
+        deposits[0].multiply_ratio(total_share, pools[0].amount),
+        deposits[1].multiply_ratio(total_share, pools[1].amount),
+    );
+    if share.is_zero() {
+        return Err(ContractError::Dex(DexError::InvalidZeroAmount {}));
+    }
+
+    // Refund whatever is not backed by the minted share
+    let used = [0, 1].map(|i| {
+        share
+            .multiply_ratio(pools[i].amount, total_share)
+            .min(deposits[i])
+    });
+    assert_reserve_cap(
+        &config,
+        [
+            pools[0].amount.checked_add(used[0])?,
+            pools[1].amount.checked_add(used[1])?,
+        ],
     )?;
+    let sender = info.sender.clone();
+    let refunds = pools.iter().enumerate().filter_map(|(i, pool)| {
+        let refund = deposits[i] - used[i];
+        (!refund.is_zero()).then(|| pool.info.with_balance(refund))
+    });
+    messages.extend(
+        assets_into_msgs(refunds, &sender)?
+            .into_iter()
+            .map(SubMsg::new),
+    );
+
+    let receiver = addr_opt_validate(deps.api, &receiver)?.unwrap_or_else(|| sender.clone());
+    assert_launch_lp_cap(deps.storage, &env.block, &config, &receiver, share)?;
+    messages.extend(mint_liquidity_token_message(
+        deps.storage,
+        deps.querier,
+        &config,
+        &pool_address,
+        &receiver,
+        share,
+    )?);
+    let accum_msgs = track_lp_provider(deps, &config, &pool_address, &receiver, &receiver, share)?;
 
-    let return_asset = Asset {
-        info: ask_pool.info.clone(),
-        amount: return_amount,
-    };
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_submessages(accum_msgs)
+        .add_attributes(vec![
+            attr("action", "provide_liquidity_auto_balance"),
+            attr("sender", sender),
+            attr("receiver", receiver),
+            attr("offer_asset", pools[offer_index].info.to_string()),
+            attr("swap_amount", swap_amount),
+            attr("share", share),
+        ]))
+}
 
-    let receiver = to.unwrap_or_else(|| sender.clone());
-    let mut messages = vec![];
-    if !return_amount.is_zero() {
-        messages.push(return_asset.into_msg(receiver.clone())?);
+/// Turns the swap commission collected since the last call into protocol-owned liquidity.
+///
+/// The commission stays in the pool, so it is provided against the reserves without it: the
+/// excess side is balanced with an internal swap like in [`provide_liquidity_auto_balance`]
+/// and the LP tokens backed by the balanced amounts are minted to the fee address. Whatever
+/// the rounding leaves over stays with the LPs. Only the factory owner can compound fees.
+///
+/// * **max_spread** max spread allowed for the balancing swap.
+#[cfg(not(feature = "no-emissions"))]
+pub fn compound_fees(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    max_spread: Option<Decimal>,
+) -> Result<Response, ContractError> {
+    assert_pair_ready(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
+    let factory_config = query_factory_config(&deps.querier, &config.factory_addr)?;
+    if info.sender != factory_config.owner {
+        return Err(ContractError::Dex(DexError::Unauthorized {}));
     }
 
-    // Compute the fee for gauge
-    let gauge_fee_amount = Uint128::zero();
+    let pool_address = env.contract.address;
+    let (mut pools, total_share) = pool_info(deps.querier, &config)?;
+    if total_share.is_zero() {
+        return Err(ContractError::Dex(DexError::InvalidZeroAmount {}));
+    }
+
+    // Withdrawals settle their share of the commission, the cap only guards against rounding
+    let fees = read_uncompounded_fees(deps.storage, &config.pair_info.asset_infos)?
+        .into_iter()
+        .zip(&pools)
+        .map(|(fees, pool)| fees.info.with_balance(fees.amount.min(pool.amount)))
+        .collect::<Vec<_>>();
+    let mut deposits = [fees[0].amount, fees[1].amount];
+    if deposits[0].is_zero() && deposits[1].is_zero() {
+        return Err(ContractError::Dex(DexError::InvalidZeroAmount {}));
+    }
+    for (pool, deposit) in pools.iter_mut().zip(deposits) {
+        pool.amount -= deposit;
+    }
+
+    let fee_info = query_fee_info(
+        &deps.querier,
+        &config.factory_addr,
+        config.pair_info.pair_type.clone(),
+        &pool_address,
+    )?;
+
+    let (offer_index, swap_amount) = compute_auto_balance_swap(
+        deposits,
+        [pools[0].amount, pools[1].amount],
+        fee_info.total_fee_rate,
+    )?;
+    let ask_index = 1 - offer_index;
+
+    if !swap_amount.is_zero() {
+        let (return_amount, spread_amount, commission_amount) = compute_swap(
+            pools[offer_index].amount,
+            pools[ask_index].amount,
+            swap_amount,
+            fee_info.total_fee_rate,
+        )?;
+        assert_max_spread(
+            None,
+            max_spread,
+            swap_amount,
+            return_amount + commission_amount,
+            spread_amount,
+        )?;
+        assert_swap_limits(
+            &deps.querier,
+            &config,
+            fee_info.max_price_impact,
+            [&pools[offer_index], &pools[ask_index]],
+            swap_amount,
+            return_amount,
+            return_amount + commission_amount,
+        )?;
+
+        deposits[offer_index] -= swap_amount;
+        deposits[ask_index] += return_amount;
+        pools[offer_index].amount += swap_amount;
+        pools[ask_index].amount -= return_amount;
+    }
+
+    let share = std::cmp::min(
+        deposits[0].multiply_ratio(total_share, pools[0].amount),
+        deposits[1].multiply_ratio(total_share, pools[1].amount),
+    );
+    if share.is_zero() {
+        return Err(ContractError::Dex(DexError::InvalidZeroAmount {}));
+    }
+    for fees in &fees {
+        record_compounded_fees(deps.storage, fees)?;
+    }
+
+    let messages = mint_liquidity_token_message(
+        deps.storage,
+        deps.querier,
+        &config,
+        &pool_address,
+        &fee_info.fee_address,
+        share,
+    )?;
+    let accum_msgs = track_lp_provider(
+        deps,
+        &config,
+        &pool_address,
+        &fee_info.fee_address,
+        &fee_info.fee_address,
+        share,
+    )?;
+
     Ok(Response::new()
-        .add_messages(
-            // 1. send collateral tokens from the contract to a user
-            // 2. send fees to the Gauge contract
-            messages,
-        )
+        .add_submessages(messages)
+        .add_submessages(accum_msgs)
         .add_attributes(vec![
-            attr("action", "swap"),
-            attr("sender", sender),
-            attr("receiver", receiver),
-            attr("offer_asset", offer_asset.info.to_string()),
-            attr("ask_asset", ask_pool.info.to_string()),
-            attr("offer_amount", offer_amount),
-            attr("return_amount", return_amount),
-            attr("spread_amount", spread_amount),
-            attr("commission_amount", commission_amount),
-            attr("gauge_fee_amount", gauge_fee_amount),
+            attr("action", "compound_fees"),
+            attr("receiver", fee_info.fee_address),
+            attr("fees", format!("{}, {}", fees[0], fees[1])),
+            attr("swap_amount", swap_amount),
+            attr("share", share),
         ]))
 }
 
-/// Updates the pool configuration with the specified parameters in the `params` variable.
-///
-/// * **params** new parameter values.
-pub fn update_config(
-    deps: DepsMut,
-    _env: Env,
-    info: MessageInfo,
-    _params: Binary,
-) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
-    let factory_config = query_factory_config(&deps.querier, &config.factory_addr)?;
-    if info.sender != factory_config.owner {
-        return Err(ContractError::Unauthorized {});
+/// Returns the index of the asset to swap and the amount to swap so that the remaining
+/// deposit matches the pool ratio after the swap.
+///
+/// With `x`/`y` the offer/ask pools, `A`/`B` the offer/ask deposits and `f` the commission rate,
+/// the pool ratio after swapping `s` is matched when
+/// `(B + y) * s^2 + (2 * B * x + (2 - f) * x * y - f * A * y) * s - x * (A * y - B * x) = 0`.
+/// The equation is solved on values normalized by `x` to stay within [`Decimal256`] bounds.
+///
+/// * **deposits** deposited amounts, in the same order as `pools`.
+///
+/// * **pools** pool balances excluding the deposits.
+///
+/// * **commission_rate** total amount of fees charged for the swap.
+pub fn compute_auto_balance_swap(
+    deposits: [Uint128; 2],
+    pools: [Uint128; 2],
+    commission_rate: Decimal,
+) -> StdResult<(usize, Uint128)> {
+    if pools[0].is_zero() || pools[1].is_zero() {
+        return Err(StdError::generic_err("One of the pools is empty"));
+    }
+
+    let left = deposits[0].full_mul(pools[1]);
+    let right = deposits[1].full_mul(pools[0]);
+    let (offer, ask) = match left.cmp(&right) {
+        std::cmp::Ordering::Equal => return Ok((0, Uint128::zero())),
+        std::cmp::Ordering::Greater => (0, 1),
+        std::cmp::Ordering::Less => (1, 0),
+    };
+
+    let x = pools[offer];
+    let u = Decimal256::from_ratio(deposits[offer], x);
+    let v = Decimal256::from_ratio(deposits[ask], x);
+    let p = Decimal256::from_ratio(pools[ask], x);
+    let f = Decimal256::from(commission_rate);
+    let two = Decimal256::from_ratio(2u8, 1u8);
+
+    let a = v.checked_add(p)?;
+    let b_pos = two.checked_mul(v)?.checked_add((two - f).checked_mul(p)?)?;
+    let b_neg = f.checked_mul(u)?.checked_mul(p)?;
+    let c = p.checked_mul(u)?.saturating_sub(v);
+
+    let b = b_pos.abs_diff(b_neg);
+    let sqrt_disc = b
+        .checked_pow(2)?
+        .checked_add(
+            Decimal256::from_ratio(4u8, 1u8)
+                .checked_mul(a)?
+                .checked_mul(c)?,
+        )?
+        .sqrt();
+
+    // Use the numerically stable root for either sign of `b`
+    let t = if b_pos >= b_neg {
+        two.checked_mul(c)?
+            .checked_div(b.checked_add(sqrt_disc)?)
+            .map_err(|e| StdError::generic_err(e.to_string()))?
+    } else {
+        sqrt_disc
+            .checked_add(b)?
+            .checked_div(two.checked_mul(a)?)
+            .map_err(|e| StdError::generic_err(e.to_string()))?
+    };
+
+    let swap_amount: Uint128 = (Uint256::from(x) * t).try_into()?;
+
+    Ok((offer, swap_amount.min(deposits[offer])))
+}
+
+/// Checks that `receiver` doesn't go over `max_lp_per_address` with the new `share` while the
+/// launch window is running. The running total is the amount tracked in [`LP_PROVIDERS`].
+fn assert_launch_lp_cap(
+    storage: &dyn Storage,
+    block: &BlockInfo,
+    config: &Config,
+    receiver: &Addr,
+    share: Uint128,
+) -> Result<(), ContractError> {
+    let (Some(launch_end_time), Some(cap)) = (config.launch_end_time, config.max_lp_per_address)
+    else {
+        return Ok(());
+    };
+    if block.time.seconds() >= launch_end_time {
+        return Ok(());
+    }
+
+    let provided = LP_PROVIDERS
+        .may_load(storage, receiver)?
+        .unwrap_or_default();
+    if provided.checked_add(share)? > cap {
+        return Err(ContractError::LaunchLpCapExceeded { cap });
+    }
+
+    Ok(())
+}
+
+/// Checks that the native LP token created at instantiation exists, see [`LP_TOKEN_READY`].
+fn assert_pair_ready(storage: &dyn Storage) -> Result<(), ContractError> {
+    if !LP_TOKEN_READY.may_load(storage)?.unwrap_or(true) {
+        return Err(ContractError::PairNotReady {});
+    }
+    Ok(())
+}
+
+/// Checks that the pool `reserves` after a provide, ordered like the pool assets, stay within
+/// the configured `reserve_cap`.
+fn assert_reserve_cap(config: &Config, reserves: [Uint128; 2]) -> Result<(), ContractError> {
+    match config.reserve_cap {
+        Some(cap) if reserves[0] > cap[0] || reserves[1] > cap[1] => {
+            Err(ContractError::ReserveCapExceeded {})
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Stores the amount of LP tokens minted for `receiver` for emission calculations under
+/// `beneficiary`. If the beneficiary already had LP tokens tracked, the controller is asked to
+/// accumulate their emissions.
+///
+/// * **receiver** LP token recipient.
+///
+/// * **beneficiary** the address the LP tokens are tracked and the emissions accrued for.
+///
+/// * **share** amount of LP tokens minted for the receiver.
+fn track_lp_provider(
+    deps: DepsMut,
+    config: &Config,
+    pool_address: &Addr,
+    receiver: &Addr,
+    beneficiary: &Addr,
+    share: Uint128,
+) -> Result<Vec<SubMsg>, ContractError> {
+    let lp_amount_before_providing = record_lp_provider(deps.storage, beneficiary, share)?;
+    if beneficiary != receiver {
+        DELEGATED_LP.update(
+            deps.storage,
+            (receiver, beneficiary),
+            |delegated| -> StdResult<_> { Ok(delegated.unwrap_or_default().checked_add(share)?) },
+        )?;
+    }
+
+    Ok(accum_emissions_msgs(
+        &deps.querier,
+        config,
+        pool_address,
+        beneficiary,
+        lp_amount_before_providing,
+    )?)
+}
+
+/// Adds `share` to the LP tokens tracked for `receiver` and returns the amount tracked before.
+fn record_lp_provider(
+    storage: &mut dyn Storage,
+    receiver: &Addr,
+    share: Uint128,
+) -> StdResult<Uint128> {
+    let previous_amount = LP_PROVIDERS
+        .may_load(storage, receiver)?
+        .unwrap_or_default();
+    LP_PROVIDERS.save(storage, receiver, &previous_amount.checked_add(share)?)?;
+
+    Ok(previous_amount)
+}
+
+/// Returns the controller call that accumulates the emissions of `address`, if the pool has a
+/// controller and the address held LP tokens before. The call is capped at the gas limit
+/// configured in the factory and its failure is caught in the reply.
+///
+/// * **previous_amount** LP tokens tracked for the address before the current operation.
+#[cfg(not(feature = "no-emissions"))]
+fn accum_emissions_msgs(
+    querier: &QuerierWrapper,
+    config: &Config,
+    pool_address: &Addr,
+    address: &Addr,
+    previous_amount: Uint128,
+) -> StdResult<Vec<SubMsg>> {
+    let fee_info = query_fee_info(
+        querier,
+        &config.factory_addr,
+        config.pair_info.pair_type.clone(),
+        pool_address,
+    )?;
+    let Some(controller) = fee_info.controller_address else {
+        return Ok(vec![]);
+    };
+    if previous_amount.is_zero() {
+        return Ok(vec![]);
+    }
+
+    Ok(vec![SubMsg {
+        id: ACCUM_EMISSIONS_REPLY_ID,
+        msg: WasmMsg::Execute {
+            contract_addr: controller.to_string(),
+            msg: to_json_binary(&ControllerExecuteMsg::AccumUserEmissions(
+                AccumEmissionsRequest {
+                    address: address.to_string(),
+                    previous_amount,
+                },
+            ))?,
+            funds: vec![],
+        }
+        .into(),
+        gas_limit: fee_info.controller_gas_limit,
+        reply_on: ReplyOn::Error,
+    }])
+}
+
+/// Pairs built without emissions have no controller to notify.
+#[cfg(feature = "no-emissions")]
+fn accum_emissions_msgs(
+    _querier: &QuerierWrapper,
+    _config: &Config,
+    _pool_address: &Addr,
+    _address: &Addr,
+    _previous_amount: Uint128,
+) -> StdResult<Vec<SubMsg>> {
+    Ok(vec![])
+}
+
+/// Returns a single controller call that accumulates the emissions of every request queued in
+/// `batch`. As with [`accum_emissions_msgs`], the call is capped at the factory gas limit and its
+/// failure is caught in the reply.
+#[cfg(not(feature = "no-emissions"))]
+fn accum_emissions_batch_msgs(
+    querier: &QuerierWrapper,
+    config: &Config,
+    pool_address: &Addr,
+    batch: AccumEmissionsBatch,
+) -> StdResult<Vec<SubMsg>> {
+    let Some(msg) = batch.into_msg() else {
+        return Ok(vec![]);
+    };
+    let fee_info = query_fee_info(
+        querier,
+        &config.factory_addr,
+        config.pair_info.pair_type.clone(),
+        pool_address,
+    )?;
+    let Some(controller) = fee_info.controller_address else {
+        return Ok(vec![]);
+    };
+
+    Ok(vec![SubMsg {
+        id: ACCUM_EMISSIONS_REPLY_ID,
+        msg: WasmMsg::Execute {
+            contract_addr: controller.to_string(),
+            msg: to_json_binary(&msg)?,
+            funds: vec![],
+        }
+        .into(),
+        gas_limit: fee_info.controller_gas_limit,
+        reply_on: ReplyOn::Error,
+    }])
+}
+
+/// Pairs built without emissions have no controller to notify.
+#[cfg(feature = "no-emissions")]
+fn accum_emissions_batch_msgs(
+    _querier: &QuerierWrapper,
+    _config: &Config,
+    _pool_address: &Addr,
+    _batch: AccumEmissionsBatch,
+) -> StdResult<Vec<SubMsg>> {
+    Ok(vec![])
+}
+
+/// Mint LP tokens for a beneficiary and auto stake the tokens in the Controller contract (if auto staking is specified).
+///
+/// A native LP mint replies to [`MINT_NATIVE_LP_REPLY_ID`], which checks that the LP supply
+/// grew by exactly **amount**. The expected supply is queued in [`PENDING_LP_MINTS`].
+///
+/// * **recipient** is the LP token recipient.
+///
+/// * **amount** is the amount of LP tokens that will be minted for the recipient.
+fn mint_liquidity_token_message(
+    storage: &mut dyn Storage,
+    querier: QuerierWrapper,
+    config: &Config,
+    contract_address: &Addr,
+    recipient: &Addr,
+    amount: Uint128,
+) -> Result<Vec<SubMsg>, ContractError> {
+    match &config.pair_info.liquidity_token {
+        AssetInfo::NativeToken { denom } => {
+            // Mints queued earlier in the same transaction are not in the supply yet
+            let mut pending = PENDING_LP_MINTS.may_load(storage)?.unwrap_or_default();
+            let supply = match pending.last() {
+                Some(expected_supply) => *expected_supply,
+                None => querier.query_supply(denom)?.amount,
+            };
+            pending.push(supply.checked_add(amount)?);
+            PENDING_LP_MINTS.save(storage, &pending)?;
+
+            let mut messages = vec![SubMsg::reply_on_success(
+                MsgMint {
+                    sender: contract_address.to_string(),
+                    amount: Some(crate::denom::Coin {
+                        denom: denom.clone(),
+                        amount: amount.to_string(),
+                    }),
+                },
+                MINT_NATIVE_LP_REPLY_ID,
+            )];
+            if recipient != contract_address {
+                messages.push(SubMsg::new(BankMsg::Send {
+                    to_address: recipient.to_string(),
+                    amount: vec![Coin {
+                        denom: denom.clone(),
+                        amount,
+                    }],
+                }));
+            }
+
+            Ok(messages)
+        }
+        AssetInfo::Token { contract_addr } => Ok(vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Mint {
+                recipient: recipient.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        })]),
+    }
+}
+
+/// Withdraw liquidity from the pool.
+/// * **sender** is the address that will receive assets back from the pair contract.
+///
+/// * **amount** is the amount of LP tokens to burn.
+///
+/// * **beneficiary** is the address the controller accrues the emissions of the sender's LP tokens to, the sender if not specified.
+pub fn withdraw_liquidity(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    sender: Addr,
+    amount: Uint128,
+    beneficiary: Option<Addr>,
+) -> Result<Response, ContractError> {
+    let config: Config = CONFIG.load(deps.storage).unwrap();
+
+    let (pools, total_share) = pool_info(deps.querier, &config)?;
+
+    let mut refund_assets = get_share_in_assets(&pools, amount, total_share);
+
+    // If the LP supply ever falls out of sync with the reserves, the proportional refund can
+    // exceed what the pool holds. Pay out what is left instead of failing the transfer, so the
+    // last LPs can still exit.
+    let mut clamped = false;
+    for (refund, pool) in refund_assets.iter_mut().zip(pools.iter()) {
+        if refund.amount > pool.amount {
+            refund.amount = pool.amount;
+            clamped = true;
+        }
+    }
+
+    let messages = assets_into_msgs(refund_assets.clone(), &sender)?;
+    let beneficiary = beneficiary.unwrap_or_else(|| sender.clone());
+    let burn_msgs = burn_withdrawn_share(
+        deps,
+        &env,
+        &config,
+        &sender,
+        amount,
+        total_share,
+        Some(&beneficiary),
+    )?;
+
+    let mut attrs = vec![
+        attr("action", "withdraw_liquidity"),
+        attr("sender", sender),
+        attr("withdrawn_share", amount),
+        attr(
+            "refund_assets",
+            format!("{}, {}", refund_assets[0], refund_assets[1]),
+        ),
+    ];
+    if clamped {
+        attrs.push(attr("clamped", "true"));
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(burn_msgs)
+        .add_attributes(attrs))
+}
+
+/// Withdraw liquidity from the pool without calling the controller to accumulate emissions.
+/// Lets LPs exit when the controller is broken, the emissions accrued so far are forfeited.
+/// The LP tokens provided for beneficiaries are untracked first, the tracking never blocks the refund.
+///
+/// * **sender** is the address that will receive assets back from the pair contract.
+///
+/// * **amount** is the amount of LP tokens to burn.
+pub fn emergency_withdraw(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    let (pools, total_share) = pool_info(deps.querier, &config)?;
+
+    let refund_assets = get_share_in_assets(&pools, amount, total_share);
+
+    let messages = assets_into_msgs(refund_assets.clone(), &sender)?;
+    let burn_msgs = burn_withdrawn_share(deps, &env, &config, &sender, amount, total_share, None)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(burn_msgs)
+        .add_attributes(vec![
+            attr("action", "emergency_withdraw"),
+            attr("sender", sender),
+            attr("withdrawn_share", amount),
+            attr(
+                "refund_assets",
+                format!("{}, {}", refund_assets[0], refund_assets[1]),
+            ),
+        ]))
+}
+
+/// Withdraw liquidity from the pool into a single asset. The other asset of the proportional
+/// refund is swapped into `ask_asset_info` against the pool that remains after the withdrawal.
+///
+/// * **sender** is the address that will receive assets back from the pair contract.
+///
+/// * **amount** is the amount of LP tokens to burn.
+///
+/// * **ask_asset_info** is the asset to receive.
+///
+/// * **max_spread** sets the maximum spread of the internal swap.
+pub fn withdraw_liquidity_one_sided(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    amount: Uint128,
+    ask_asset_info: AssetInfo,
+    max_spread: Option<Decimal>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let pool_address = env.contract.address.clone();
+
+    let (pools, total_share) = pool_info(deps.querier, &config)?;
+    let refund_assets = get_share_in_assets(&pools, amount, total_share);
+
+    let ask_index = config
+        .pair_info
+        .asset_index(&ask_asset_info)
+        .map_err(|_| ContractError::AssetMismatch {})?;
+    let offer_index = 1 - ask_index;
+    let swap_amount = refund_assets[offer_index].amount;
+
+    let mut return_amount = refund_assets[ask_index].amount;
+    if !swap_amount.is_zero() {
+        let fee_info = query_fee_info(
+            &deps.querier,
+            &config.factory_addr,
+            config.pair_info.pair_type.clone(),
+            &pool_address,
+        )?;
+        // The swap runs against the pools without the withdrawn share
+        let swap_pool = |i: usize| -> StdResult<Asset> {
+            let amount = pools[i].amount.checked_sub(refund_assets[i].amount)?;
+            Ok(pools[i].info.with_balance(amount))
+        };
+        let (offer_pool, ask_pool) = (swap_pool(offer_index)?, swap_pool(ask_index)?);
+        let (mut swap_return, spread_amount, mut commission_amount) = compute_swap(
+            offer_pool.amount,
+            ask_pool.amount,
+            swap_amount,
+            fee_info.total_fee_rate,
+        )?;
+        if fee_info.is_min_commission_enabled {
+            (swap_return, commission_amount) =
+                apply_min_commission(swap_return, commission_amount, fee_info.total_fee_rate);
+        }
+        assert_max_spread(
+            None,
+            max_spread,
+            swap_amount,
+            swap_return + commission_amount,
+            spread_amount,
+        )?;
+        assert_swap_limits(
+            &deps.querier,
+            &config,
+            fee_info.max_price_impact,
+            [&offer_pool, &ask_pool],
+            swap_amount,
+            swap_return,
+            swap_return + commission_amount,
+        )?;
+        accumulate_fees(deps.storage, &ask_pool.info, commission_amount)?;
+        return_amount = return_amount.checked_add(swap_return)?;
+    }
+
+    let messages = ask_asset_info
+        .with_balance(return_amount)
+        .into_msg_checked(&sender, &env.contract.address)?;
+    let burn_msgs = burn_withdrawn_share(
+        deps,
+        &env,
+        &config,
+        &sender,
+        amount,
+        total_share,
+        Some(&sender),
+    )?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(burn_msgs)
+        .add_attributes(vec![
+            attr("action", "withdraw_liquidity_one_sided"),
+            attr("sender", sender),
+            attr("withdrawn_share", amount),
+            attr("ask_asset", ask_asset_info.to_string()),
+            attr("swap_amount", swap_amount),
+            attr("return_amount", return_amount),
+        ]))
+}
+
+/// Burns the withdrawn LP tokens and updates the LP provider tracking used for emissions.
+/// The withdrawn LP tokens are untracked for **emissions_beneficiary**, which the sender must
+/// have provided them for unless it is the sender. Without another beneficiary the LP tokens
+/// the sender provided for others are untracked first, see [`untrack_lp_provider`]. The
+/// controller is only called to accumulate the emissions of the untracked addresses if
+/// **emissions_beneficiary** is set.
+///
+/// The share of the uncompounded commission withdrawn with the `amount` out of `total_share`
+/// LP tokens is settled, so that `CompoundFees` doesn't mint LP tokens against it.
+///
+/// The burn runs after the refunds and a failed burn reverts the whole withdraw with
+/// [`ContractError::LpBurnFailed`]. Ignoring the failure instead would leave the withdrawn LP
+/// tokens in the supply while their reserves are gone, diluting the remaining LPs.
+fn burn_withdrawn_share(
+    deps: DepsMut,
+    env: &Env,
+    config: &Config,
+    sender: &Addr,
+    amount: Uint128,
+    total_share: Uint128,
+    emissions_beneficiary: Option<&Addr>,
+) -> Result<Vec<SubMsg>, ContractError> {
+    settle_withdrawn_fees(
+        deps.storage,
+        &config.pair_info.asset_infos,
+        amount,
+        total_share,
+    )?;
+
+    let mut messages = vec![SubMsg::reply_on_error(
+        burn_lp_token_message(env, &config.pair_info.liquidity_token, amount)?,
+        BURN_LP_REPLY_ID,
+    )];
+
+    // Stores the amount of lp tokens is sent to the lp_provider for emission calculations
+    // Calls gauge controller to accum the emission rewards first
+    let tracked_for = emissions_beneficiary.unwrap_or(sender);
+    if tracked_for != sender
+        && DELEGATED_LP
+            .may_load(deps.storage, (sender, tracked_for))?
+            .unwrap_or_default()
+            < amount
+    {
+        return Err(ContractError::Dex(DexError::Unauthorized {}));
+    }
+    let untracked = untrack_lp_provider(deps.storage, sender, tracked_for, amount)?;
+    if emissions_beneficiary.is_some() {
+        for (address, lp_amount_before_withdrawing) in untracked {
+            messages.extend(accum_emissions_msgs(
+                &deps.querier,
+                config,
+                &env.contract.address,
+                &address,
+                lp_amount_before_withdrawing,
+            )?);
+        }
+    }
+
+    Ok(messages)
+}
+
+/// Returns a message burning `amount` of `lp_token` held by the pair.
+fn burn_lp_token_message(env: &Env, lp_token: &AssetInfo, amount: Uint128) -> StdResult<CosmosMsg> {
+    Ok(match lp_token {
+        AssetInfo::NativeToken { denom } => MsgBurn {
+            sender: env.contract.address.to_string(),
+            amount: Some(crate::denom::Coin {
+                denom: denom.clone(),
+                amount: amount.to_string(),
+            }),
+        }
+        .into(),
+        AssetInfo::Token { contract_addr } => CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Burn { amount })?,
+            funds: vec![],
+        }),
+    })
+}
+
+/// Returns the amount of pool assets that correspond to an amount of LP tokens.
+///
+/// * **pools** is the array with assets in the pool.
+///
+/// * **amount** is amount of LP tokens to compute a corresponding amount of assets for.
+///
+/// * **total_share** is the total amount of LP tokens currently minted.
+///
+/// Every amount is rounded down in favor of the pool, so the share price never drops on a
+/// withdrawal and splitting a withdrawal into smaller ones cannot return more assets.
+pub fn get_share_in_assets(pools: &[Asset], amount: Uint128, total_share: Uint128) -> Vec<Asset> {
+    pools
+        .iter()
+        .map(|a| Asset {
+            info: a.info.clone(),
+            amount: if total_share.is_zero() {
+                Uint128::zero()
+            } else {
+                a.amount.multiply_ratio(amount, total_share)
+            },
+        })
+        .collect()
+}
+
+/// Performs an swap operation with the specified parameters. The trader must approve the
+/// pool contract to transfer offer assets from their wallet.
+///
+/// * **sender** is the sender of the swap operation.
+///
+/// * **offer_asset** proposed asset for swapping.
+///
+/// * **ask_asset_info** asset to swap to. Must differ from the offer asset if set.
+///
+/// * **belief_price** is used to calculate the maximum swap spread.
+///
+/// * **max_spread** sets the maximum spread of the swap operation.
+///
+/// * **to** sets the recipient of the swap operation.
+///
+/// * **to_hook** delivers the swap output to the recipient contract along with this message.
+///
+/// NOTE - the address that wants to swap should approve the pair contract to pull the offer token.
+///
+/// The commission is always kept in the ask pool and accrues to the LPs. It is not routed to
+/// the gauge or the fee address, so a missing controller or a failed gauge lookup in
+/// [`query_fee_info`] does not change where the fees go. Pairs built with the `no-emissions`
+/// feature have no gauges and send the commission straight to the fee address of the ask asset
+/// instead, see [`ura::structs::fee_info::FeeInfo::fee_address_for`].
+#[allow(clippy::too_many_arguments)]
+#[cfg_attr(feature = "no-emissions", allow(unused_variables))]
+pub fn swap(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    sender: Addr,
+    offer_asset: Asset,
+    ask_asset_info: Option<AssetInfo>,
+    belief_price: Option<Decimal>,
+    max_spread: Option<Decimal>,
+    to: Option<Addr>,
+    to_hook: Option<Binary>,
+) -> Result<Response, ContractError> {
+    if ask_asset_info.as_ref() == Some(&offer_asset.info) {
+        return Err(ContractError::Dex(DexError::SameAssets {}));
+    }
+
+    if offer_asset.is_native_token() && info.funds.len() > 1 {
+        return Err(ContractError::Dex(DexError::InvalidFundsAttached {}));
+    }
+    offer_asset.assert_sent_native_token_balance(&info)?;
+
+    assert_pair_ready(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
+
+    // If the asset balance is already increased, we should subtract the user deposit from the pool amount
+    let pools = config
+        .pair_info
+        .query_pools(&deps.querier, &config.pair_info.contract_addr)?
+        .into_iter()
+        .map(|mut p| {
+            if p.info.equal(&offer_asset.info) {
+                p.amount = p.amount.checked_sub(offer_asset.amount)?;
+            }
+            Ok(p)
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let ask_asset_info = config
+        .pair_info
+        .other_asset(&offer_asset.info)
+        .map_err(|_| ContractError::AssetMismatch {})?;
+    let offer_pool = pools[config.pair_info.asset_index(&offer_asset.info)?].clone();
+    let ask_pool = pools[config.pair_info.asset_index(&ask_asset_info)?].clone();
+
+    // Get fee info from the factory
+    #[cfg(not(feature = "no-emissions"))]
+    let fee_info = query_fee_info(
+        &deps.querier,
+        &config.factory_addr,
+        config.pair_info.pair_type.clone(),
+        &env.contract.address,
+    )?;
+    #[cfg(feature = "no-emissions")]
+    let fee_info = query_fee_info_basic(
+        &deps.querier,
+        &config.factory_addr,
+        config.pair_info.pair_type.clone(),
+    )?;
+
+    let offer_amount = offer_asset.amount;
+    if fee_info
+        .min_swap_amount
+        .is_some_and(|min_swap_amount| offer_amount < min_swap_amount)
+    {
+        return Err(ContractError::Dex(DexError::SwapTooSmall {}));
+    }
+
+    let (mut return_amount, spread_amount, mut commission_amount) = compute_swap(
+        offer_pool.amount,
+        ask_pool.amount,
+        offer_amount,
+        fee_info.total_fee_rate,
+    )?;
+    if fee_info.is_min_commission_enabled {
+        (return_amount, commission_amount) =
+            apply_min_commission(return_amount, commission_amount, fee_info.total_fee_rate);
+    }
+
+    // Check the max spread limit (if it was specified)
+    assert_belief_price_near_market(
+        belief_price,
+        config.max_belief_price_deviation,
+        offer_pool.amount,
+        ask_pool.amount,
+    )?;
+    assert_max_spread(
+        belief_price,
+        max_spread,
+        offer_amount,
+        return_amount + commission_amount,
+        spread_amount,
+    )?;
+    // The commission stays in the pool unless it is paid out to the fee address
+    let ask_amount_out = if cfg!(feature = "no-emissions") {
+        return_amount + commission_amount
+    } else {
+        return_amount
+    };
+    assert_swap_limits(
+        &deps.querier,
+        &config,
+        fee_info.max_price_impact,
+        [&offer_pool, &ask_pool],
+        offer_amount,
+        ask_amount_out,
+        return_amount + commission_amount,
+    )?;
+    accumulate_fees(deps.storage, &ask_pool.info, commission_amount)?;
+
+    let return_asset = Asset {
+        info: ask_pool.info.clone(),
+        amount: return_amount,
+    };
+
+    let receiver = to.unwrap_or_else(|| sender.clone());
+    let mut messages = vec![];
+    match to_hook {
+        Some(hook) if !return_asset.amount.is_zero() => {
+            messages.push(return_asset.into_hook_msg(&receiver, &sender, hook)?)
+        }
+        _ => messages.extend(return_asset.into_msg_checked(&receiver, &env.contract.address)?),
+    }
+
+    // Without emissions there are no gauges, the commission goes straight to the fee address
+    // set for the ask asset, or the global one
+    #[cfg(feature = "no-emissions")]
+    messages.extend(
+        ask_pool
+            .info
+            .with_balance(commission_amount)
+            .into_msg_checked(
+                fee_info.fee_address_for(&ask_pool.info),
+                &env.contract.address,
+            )?,
+    );
+
+    // The commission deliberately stays in the pool, nothing is sent to the gauge
+    let gauge_fee_amount = Uint128::zero();
+    Ok(Response::new()
+        .add_messages(
+            // 1. send collateral tokens from the contract to a user
+            // 2. send fees to the Gauge contract
+            messages,
+        )
+        .add_attributes(vec![
+            attr("action", "swap"),
+            attr("sender", sender),
+            attr("receiver", receiver),
+            attr("offer_asset", offer_asset.info.to_string()),
+            attr("ask_asset", ask_pool.info.to_string()),
+            attr("offer_amount", offer_amount),
+            attr("return_amount", return_amount),
+            attr("spread_amount", spread_amount),
+            attr("commission_amount", commission_amount),
+            attr("gauge_fee_amount", gauge_fee_amount),
+        ]))
+}
+
+/// Checks that the output of a swap can be delivered with a hook to `receiver`. It must be a
+/// contract, and since the pair sends the hook, none of the contracts that trust messages from
+/// the pair: the pool assets, the LP tokens, the factory, the controller and the gauge.
+fn assert_hook_recipient(deps: Deps, env: &Env, receiver: &Addr) -> Result<(), ContractError> {
+    deps.querier
+        .query::<ContractInfoResponse>(
+            &WasmQuery::ContractInfo {
+                contract_addr: receiver.to_string(),
+            }
+            .into(),
+        )
+        .map_err(|_| ContractError::HookRecipientNotContract {})?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let factory_config = query_factory_config(&deps.querier, &config.factory_addr)?;
+    let mut trusted = vec![env.contract.address.clone(), config.factory_addr.clone()];
+    trusted.extend(factory_config.controller_address);
+    trusted.extend(
+        config
+            .pair_info
+            .asset_infos
+            .iter()
+            .chain([&config.pair_info.liquidity_token])
+            .chain(&config.legacy_lp_token)
+            .filter_map(|asset_info| match asset_info {
+                AssetInfo::Token { contract_addr } => Some(contract_addr.clone()),
+                AssetInfo::NativeToken { .. } => None,
+            }),
+    );
+    #[cfg(not(feature = "no-emissions"))]
+    trusted.extend(
+        query_fee_info(
+            &deps.querier,
+            &config.factory_addr,
+            config.pair_info.pair_type.clone(),
+            &env.contract.address,
+        )?
+        .gauge_address,
+    );
+
+    if trusted.contains(receiver) {
+        return Err(ContractError::ForbiddenHookRecipient {
+            recipient: receiver.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Updates the pool configuration with the specified parameters in the `params` variable.
+///
+/// * **params** new parameter values.
+pub fn update_config(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    params: Binary,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    let factory_config = query_factory_config(&deps.querier, &config.factory_addr)?;
+    if info.sender != factory_config.owner {
+        return Err(ContractError::Dex(DexError::Unauthorized {}));
+    }
+
+    match from_json::<XykPoolUpdateParams>(&params)? {
+        XykPoolUpdateParams::SetReserveCap { reserve_cap } => {
+            config.reserve_cap = reserve_cap;
+            CONFIG.save(deps.storage, &config)?;
+        }
+    }
+
+    Ok(Response::default())
+}
+
+/// Creates an LP token of the other kind to replace the current one, e.g when a chain gains
+/// or loses tokenfactory support. The pair switches to the new LP token once it is created and
+/// holders of the previous LP token exchange it 1:1 with [`claim_migrated_lp`].
+///
+/// Only the factory owner can migrate the LP token. A new CW20 LP token has the factory owner as
+/// its admin and takes no funds. A new native LP token requires the denom creation fee configured
+/// in the factory to be attached, the reply rejects the migration if the fee was charged from the
+/// pool reserves instead. Funds left over after the fee stay in the pool.
+///
+/// * **to_cw20** whether the new LP token is a CW20 token or a native token.
+///
+/// * **token_code_id** the CW20 token code id, required when migrating to a CW20 LP token.
+pub fn migrate_lp_token(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    to_cw20: bool,
+    token_code_id: Option<u64>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let factory_config = query_factory_config(&deps.querier, &config.factory_addr)?;
+    if info.sender != factory_config.owner {
+        return Err(ContractError::Dex(DexError::Unauthorized {}));
+    }
+    if config.legacy_lp_token.is_some() {
+        return Err(ContractError::LpMigrationInProgress {});
+    }
+    if matches!(config.pair_info.liquidity_token, AssetInfo::Token { .. }) == to_cw20 {
+        return Err(ContractError::LpTokenKindUnchanged {});
+    }
+
+    let token_name = format_lp_token_name(&config.pair_info.asset_infos, &deps.querier)?;
+    let sub_msg = if to_cw20 {
+        // Funds sent along would end up in the reserves
+        if !info.funds.is_empty() {
+            return Err(ContractError::LpMigrationFundsNotAccepted {});
+        }
+        let token_code_id = token_code_id.ok_or(ContractError::LpTokenCodeIdRequired {})?;
+        SubMsg {
+            msg: WasmMsg::Instantiate {
+                code_id: token_code_id,
+                msg: to_json_binary(&TokenInstantiateMsg {
+                    name: token_name,
+                    symbol: "uLP".to_string(),
+                    decimals: LP_TOKEN_DECIMALS,
+                    initial_balances: vec![],
+                    mint: Some(MinterResponse {
+                        minter: env.contract.address.to_string(),
+                        cap: None,
+                    }),
+                    marketing: None,
+                })?,
+                funds: vec![],
+                admin: Some(factory_config.owner.to_string()),
+                label: String::from("Ura LP Token"),
+            }
+            .into(),
+            id: MIGRATE_CW20_REPLY_ID,
+            gas_limit: None,
+            reply_on: ReplyOn::Success,
+        }
+    } else {
+        if !factory_config
+            .denom_creation_fee
+            .iter()
+            .all(|fee| has_coins(&info.funds, fee))
+        {
+            return Err(ContractError::Dex(
+                DexError::InsufficientDenomCreationFee {},
+            ));
+        }
+
+        // The denom creation fee is charged from the pair balance, which already holds the sent
+        // funds, so the reserves are the balances without them
+        let reserves = config
+            .pair_info
+            .query_pools(&deps.querier, &env.contract.address)?
+            .into_iter()
+            .map(|pool| {
+                let sent = match &pool.info {
+                    AssetInfo::NativeToken { denom } => info
+                        .funds
+                        .iter()
+                        .filter(|coin| &coin.denom == denom)
+                        .map(|coin| coin.amount)
+                        .sum(),
+                    AssetInfo::Token { .. } => Uint128::zero(),
+                };
+                pool.amount.checked_sub(sent)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        MIGRATION_RESERVES.save(deps.storage, &reserves)?;
+
+        SubMsg {
+            id: MIGRATE_NATIVE_REPLY_ID,
+            msg: MsgCreateDenom {
+                sender: env.contract.address.to_string(),
+                subdenom: token_name,
+            }
+            .into(),
+            gas_limit: None,
+            reply_on: ReplyOn::Success,
+        }
+    };
+
+    Ok(Response::new().add_submessage(sub_msg).add_attributes(vec![
+        attr("action", "migrate_lp_token"),
+        attr("to_cw20", to_cw20.to_string()),
+    ]))
+}
+
+/// Exchanges `amount` of the previous LP token, already received by the pair, 1:1 for the
+/// current LP token sent to `sender`.
+///
+/// Once no previous LP tokens are held outside of the pair, the ones held by the pair itself
+/// (e.g the minimum liquidity) are exchanged as well and the migration is over.
+pub fn claim_migrated_lp(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    if amount.is_zero() {
+        return Err(ContractError::Dex(DexError::InvalidZeroAmount {}));
+    }
+
+    let mut config = CONFIG.load(deps.storage)?;
+    let legacy_lp_token = config
+        .legacy_lp_token
+        .clone()
+        .ok_or(ContractError::NoLpMigration {})?;
+
+    let held_by_pair = legacy_lp_token.query_pool(&deps.querier, &env.contract.address)?;
+    let unclaimed =
+        query_total_supply(&deps.querier, &legacy_lp_token)?.checked_sub(held_by_pair)?;
+
+    let mut messages = mint_liquidity_token_message(
+        deps.storage,
+        deps.querier,
+        &config,
+        &env.contract.address,
+        &sender,
+        amount,
+    )?;
+    let burn_amount = if unclaimed.is_zero() {
+        let locked = held_by_pair.checked_sub(amount)?;
+        if !locked.is_zero() {
+            messages.extend(mint_liquidity_token_message(
+                deps.storage,
+                deps.querier,
+                &config,
+                &env.contract.address,
+                &env.contract.address,
+                locked,
+            )?);
+        }
+        config.legacy_lp_token = None;
+        CONFIG.save(deps.storage, &config)?;
+        held_by_pair
+    } else {
+        amount
+    };
+    messages.insert(
+        0,
+        SubMsg::new(burn_lp_token_message(&env, &legacy_lp_token, burn_amount)?),
+    );
+
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_attributes(vec![
+            attr("action", "claim_migrated_lp"),
+            attr("receiver", sender),
+            attr("amount", amount),
+            attr("migration_completed", unclaimed.is_zero().to_string()),
+        ]))
+}
+
+/// Sends an asset that was transferred to the pair by mistake to `recipient`.
+///
+/// Only the factory owner can rescue tokens. The pool assets and the LP token are rejected,
+/// so the reserves backing the LP tokens can never be moved this way.
+pub fn rescue_tokens(
+    deps: DepsMut,
+    info: MessageInfo,
+    asset_info: AssetInfo,
+    amount: Uint128,
+    recipient: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let factory_config = query_factory_config(&deps.querier, &config.factory_addr)?;
+    if info.sender != factory_config.owner {
+        return Err(ContractError::Dex(DexError::Unauthorized {}));
+    }
+
+    asset_info.check(deps.api)?;
+    if config
+        .pair_info
+        .asset_infos
+        .iter()
+        .chain([&config.pair_info.liquidity_token])
+        .chain(&config.legacy_lp_token)
+        .any(|pool_asset| pool_asset.equal(&asset_info))
+    {
+        return Err(ContractError::CannotRescuePoolAsset {});
+    }
+
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let asset = Asset {
+        info: asset_info,
+        amount,
+    };
+
+    Ok(Response::new()
+        .add_message(asset.clone().into_msg(&recipient)?)
+        .add_attributes(vec![
+            attr("action", "rescue_tokens"),
+            attr("recipient", recipient),
+            attr("asset", asset.to_string()),
+        ]))
+}
+
+/// Exposes all the queries available in the contract.
+///
+/// ## Queries
+/// * **QueryMsg::Pair {}** Returns information about the pair in an object of type [`PairInfo`].
+///
+/// * **QueryMsg::Pool {}** Returns information about the amount of assets in the pair contract as
+/// well as the amount of LP tokens issued using an object of type [`PoolResponse`].
+///
+/// * **QueryMsg::Share { amount }** Returns the amount of assets that could be withdrawn from the pool
+/// using a specific amount of LP tokens. The result is returned in a vector that contains objects of type [`Asset`].
+///
+/// * **QueryMsg::Simulation { offer_asset }** Returns the result of a swap simulation using a [`SimulationResponse`] object.
+///
+/// * **QueryMsg::ReverseSimulation { offer_asset_info, ask_asset }** Returns the result of a reverse swap simulation  using
+/// a [`ReverseSimulationResponse`] object.
+///
+/// * **QueryMsg::CumulativePrices {}** Returns information about cumulative prices for the assets in the
+/// pool using a [`CumulativePricesResponse`] object.
+///
+/// * **QueryMsg::Config {}** Returns the configuration for the pair contract using a [`ConfigResponse`] object.
+///
+/// * **QueryMsg::SwapToPrice { target_price, offer_asset_info }** Returns the offer amount that moves the price to `target_price` using a [`SwapToPriceResponse`] object.
+///
+/// * **QueryMsg::SwapRate { offer_asset, ask_asset_info }** Returns the marginal and after fee swap rates using a [`SwapRateResponse`] object.
+///
+/// * **QueryMsg::PriceImpact { offer_asset, ask_asset_info }** Returns the price impact of a swap before fees.
+///
+/// * **QueryMsg::AllLpReceived { start_after, limit }** Returns the tracked LP balances of all providers.
+///
+/// * **QueryMsg::WithdrawPreview { amount }** Returns the withdrawn assets and the value per LP token using a [`WithdrawPreviewResponse`] object.
+///
+/// * **QueryMsg::SimulateProvide { assets }** Returns the LP tokens minted for a deposit using a [`SimulateProvideResponse`] object.
+///
+/// * **QueryMsg::QuoteBoth { offer_asset, ask_asset_info }** Returns both swap directions using a [`QuoteBothResponse`] object.
+///
+/// * **QueryMsg::MaxSwapWithinSlippage { offer_asset_info, max_spread, ask_asset_info }** Returns the largest offer amount whose spread stays within `max_spread`.
+///
+/// * **QueryMsg::EffectiveFeeRate { offer_asset }** Returns the fee rate a swap of `offer_asset` would pay.
+///
+/// * **QueryMsg::CollectedFees {}** Returns the lifetime swap commission collected in each pool asset.
+///
+/// * **QueryMsg::AssetBalanceAt { asset_info, block_height }** Returns the balance of the specified asset that was in the pool
+/// just preceeding the moment of the specified block height creation.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Pair {} => to_json_binary(&CONFIG.load(deps.storage)?.pair_info),
+        QueryMsg::Pool {} => to_json_binary(&query_pool(deps)?),
+        QueryMsg::Share { amount } => to_json_binary(&query_share(deps, amount)?),
+        QueryMsg::Simulation {
+            offer_asset,
+            ask_asset_info,
+        } => to_json_binary(&query_simulation(deps, offer_asset, ask_asset_info)?),
+        QueryMsg::ReverseSimulation {
+            offer_asset_info,
+            ask_asset,
+        } => to_json_binary(&query_reverse_simulation(
+            deps,
+            ask_asset,
+            offer_asset_info,
+        )?),
+        QueryMsg::Config {} => to_json_binary(&query_config(deps)?),
+        QueryMsg::SwapToPrice {
+            target_price,
+            offer_asset_info,
+        } => to_json_binary(&query_swap_to_price(deps, target_price, offer_asset_info)?),
+        QueryMsg::SwapRate {
+            offer_asset,
+            ask_asset_info,
+        } => to_json_binary(&query_swap_rate(deps, offer_asset, ask_asset_info)?),
+        QueryMsg::PriceImpact {
+            offer_asset,
+            ask_asset_info,
+        } => to_json_binary(&query_price_impact(deps, offer_asset, ask_asset_info)?),
+        QueryMsg::AllLpReceived { start_after, limit } => {
+            to_json_binary(&query_all_lp_received(deps, start_after, limit)?)
+        }
+        QueryMsg::WithdrawPreview { amount } => {
+            to_json_binary(&query_withdraw_preview(deps, amount)?)
+        }
+        QueryMsg::SimulateProvide { assets } => {
+            to_json_binary(&query_simulate_provide(deps, assets)?)
+        }
+        QueryMsg::QuoteBoth {
+            offer_asset,
+            ask_asset_info,
+        } => to_json_binary(&query_quote_both(deps, offer_asset, ask_asset_info)?),
+        QueryMsg::MaxSwapWithinSlippage {
+            offer_asset_info,
+            max_spread,
+            ask_asset_info,
+        } => to_json_binary(&query_max_swap_within_slippage(
+            deps,
+            offer_asset_info,
+            max_spread,
+            ask_asset_info,
+        )?),
+        QueryMsg::EffectiveFeeRate { offer_asset } => {
+            to_json_binary(&query_effective_fee_rate(deps, offer_asset)?)
+        }
+        QueryMsg::CollectedFees {} => {
+            let config = CONFIG.load(deps.storage)?;
+            to_json_binary(&read_cumulative_fees(
+                deps.storage,
+                &config.pair_info.asset_infos,
+            )?)
+        }
+        QueryMsg::CanExecute { address, action } => {
+            to_json_binary(&query_can_execute(deps, env, address, action)?)
+        }
+        _ => Err(StdError::generic_err("Query is not supported")),
+    }
+}
+
+/// Returns whether `address` can currently perform `action` in a [`CanExecuteResponse`] object.
+///
+/// Swaps and provides wait for the native LP token to be created. While the launch window is
+/// running, an address that already holds `max_lp_per_address` tracked LP tokens can't provide.
+pub fn query_can_execute(
+    deps: Deps,
+    env: Env,
+    address: String,
+    action: PairAction,
+) -> StdResult<CanExecuteResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let config = CONFIG.load(deps.storage)?;
+
+    let check = match action {
+        PairAction::Provide => assert_pair_ready(deps.storage).and_then(|_| {
+            assert_launch_lp_cap(deps.storage, &env.block, &config, &address, Uint128::one())
+        }),
+        PairAction::Swap => assert_pair_ready(deps.storage),
+        PairAction::Withdraw => Ok(()),
+    };
+
+    Ok(match check {
+        Ok(()) => CanExecuteResponse {
+            allowed: true,
+            reason: None,
+        },
+        Err(err) => CanExecuteResponse {
+            allowed: false,
+            reason: Some(err.to_string()),
+        },
+    })
+}
+
+/// Returns the amounts of assets in the pair contract as well as the amount of LP
+/// tokens currently minted in an object of type [`PoolResponse`].
+pub fn query_pool(deps: Deps) -> StdResult<PoolResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let (assets, total_share) = pool_info(deps.querier, &config)?;
+
+    let resp = PoolResponse {
+        assets,
+        total_share,
+    };
+
+    Ok(resp)
+}
+
+/// Returns the amount of assets that could be withdrawn from the pool using a specific amount of LP tokens.
+/// The result is returned in a vector that contains objects of type [`Asset`].
+///
+/// * **amount** is the amount of LP tokens for which we calculate associated amounts of assets.
+pub fn query_share(deps: Deps, amount: Uint128) -> StdResult<Vec<Asset>> {
+    let config = CONFIG.load(deps.storage)?;
+    let (pools, total_share) = pool_info(deps.querier, &config)?;
+    let refund_assets = get_share_in_assets(&pools, amount, total_share);
+
+    Ok(refund_assets)
+}
+
+/// Returns the assets that could be withdrawn for `amount` LP tokens together with the pool
+/// value per LP token in a [`WithdrawPreviewResponse`] object.
+///
+/// The value per LP token is `sqrt(x * y) / total_share`. Swap commission stays in the pool,
+/// so it grows as fees accrue.
+pub fn query_withdraw_preview(deps: Deps, amount: Uint128) -> StdResult<WithdrawPreviewResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let (pools, total_share) = pool_info(deps.querier, &config)?;
+    let assets = get_share_in_assets(&pools, amount, total_share);
+
+    let lp_price = if total_share.is_zero() {
+        Decimal::zero()
+    } else {
+        let invariant = to_uint128(
+            (U256::from(pools[0].amount.u128()) * U256::from(pools[1].amount.u128()))
+                .integer_sqrt(),
+        )?;
+        Decimal::checked_from_ratio(invariant, total_share)
+            .map_err(|e| StdError::generic_err(e.to_string()))?
+    };
+
+    Ok(WithdrawPreviewResponse { assets, lp_price })
+}
+
+/// Returns the amount of LP tokens that providing `assets` would mint in a [`SimulateProvideResponse`] object.
+///
+/// The share is computed exactly like in `provide_liquidity`. The refund holds the part of the
+/// deposit that exceeds the pool ratio, which `provide_liquidity` leaves in the pool.
+pub fn query_simulate_provide(
+    deps: Deps,
+    assets: Vec<Asset>,
+) -> StdResult<SimulateProvideResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    if assets.len() != 2
+        || assets[0].info.equal(&assets[1].info)
+        || !assets
+            .iter()
+            .all(|a| config.pair_info.asset_infos.contains(&a.info))
+    {
+        return Err(StdError::generic_err(
+            ContractError::AssetMismatch {}.to_string(),
+        ));
+    }
+
+    let (pools, total_share) = pool_info(deps.querier, &config)?;
+    let deposits = [0, 1].map(|i| {
+        assets
+            .iter()
+            .find(|a| a.info.equal(&pools[i].info))
+            .map_or(Uint128::zero(), |a| a.amount)
+    });
+    if deposits[0].is_zero() || deposits[1].is_zero() {
+        return Err(StdError::generic_err(
+            ContractError::Dex(DexError::InvalidZeroAmount {}).to_string(),
+        ));
+    }
+
+    let share = compute_provide_share(&deposits, &pools, total_share)
+        .map_err(|e| StdError::generic_err(e.to_string()))?;
+
+    let refund = if total_share.is_zero() {
+        vec![]
+    } else {
+        pools
+            .iter()
+            .zip(deposits)
+            .filter_map(|(pool, deposit)| {
+                let used = share.multiply_ratio(pool.amount, total_share);
+                let refund = deposit.saturating_sub(used);
+                (!refund.is_zero()).then(|| pool.info.with_balance(refund))
+            })
+            .collect()
+    };
+
+    Ok(SimulateProvideResponse { share, refund })
+}
+
+/// Returns information about a swap simulation in a [`SimulationResponse`] object.
+///
+/// * **offer_asset** is the asset to swap as well as an amount of the said asset.
+///
+/// * **ask_asset_info** is the asset to swap to. Must differ from the offer asset if set.
+pub fn query_simulation(
+    deps: Deps,
+    offer_asset: Asset,
+    ask_asset_info: Option<AssetInfo>,
+) -> StdResult<SimulationResponse> {
+    if ask_asset_info.as_ref() == Some(&offer_asset.info) {
+        return Err(StdError::generic_err(
+            ContractError::Dex(DexError::SameAssets {}).to_string(),
+        ));
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let pools = config
+        .pair_info
+        .query_pools(&deps.querier, &config.pair_info.contract_addr)?;
+    let fee_info = query_fee_info_basic(
+        &deps.querier,
+        &config.factory_addr,
+        config.pair_info.pair_type.clone(),
+    )?;
+
+    simulate_swap(&config, &pools, &fee_info, &offer_asset)
+}
+
+/// Returns information about a reverse swap simulation in a [`ReverseSimulationResponse`] object.
+///
+/// * **ask_asset** is the asset to swap to as well as the desired amount of ask
+/// assets to receive from the swap.
+///
+/// * **offer_asset_info** is an optional asset to swap, it must be the other asset of the pair.
+pub fn query_reverse_simulation(
+    deps: Deps,
+    ask_asset: Asset,
+    offer_asset_info: Option<AssetInfo>,
+) -> StdResult<ReverseSimulationResponse> {
+    if offer_asset_info.as_ref() == Some(&ask_asset.info) {
+        return Err(StdError::generic_err(
+            ContractError::Dex(DexError::SameAssets {}).to_string(),
+        ));
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let pools = config
+        .pair_info
+        .query_pools(&deps.querier, &config.pair_info.contract_addr)?;
+    let fee_info = query_fee_info_basic(
+        &deps.querier,
+        &config.factory_addr,
+        config.pair_info.pair_type.clone(),
+    )?;
+
+    simulate_reverse_swap(&config, &pools, &fee_info, &ask_asset, offer_asset_info)
+}
+
+/// Returns a swap simulation of `offer_asset` and the reverse simulation of its return amount
+/// in a [`QuoteBothResponse`] object. Both sides use a single load of the pools and fee info.
+///
+/// * **offer_asset** is the asset to swap as well as an amount of the said asset.
+pub fn query_quote_both(
+    deps: Deps,
+    offer_asset: Asset,
+    ask_asset_info: Option<AssetInfo>,
+) -> StdResult<QuoteBothResponse> {
+    if ask_asset_info.as_ref() == Some(&offer_asset.info) {
+        return Err(StdError::generic_err(
+            ContractError::Dex(DexError::SameAssets {}).to_string(),
+        ));
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    let pools = config
+        .pair_info
+        .query_pools(&deps.querier, &config.pair_info.contract_addr)?;
+    let fee_info = query_fee_info_basic(
+        &deps.querier,
+        &config.factory_addr,
+        config.pair_info.pair_type.clone(),
+    )?;
+
+    let forward = simulate_swap(&config, &pools, &fee_info, &offer_asset)?;
+    let ask_asset_info = config
+        .pair_info
+        .other_asset(&offer_asset.info)
+        .map_err(|_| StdError::generic_err("Given offer asset does not belong in the pair"))?;
+    let reverse = simulate_reverse_swap(
+        &config,
+        &pools,
+        &fee_info,
+        &ask_asset_info.with_balance(forward.return_amount),
+        Some(offer_asset.info),
+    )?;
+
+    Ok(QuoteBothResponse { forward, reverse })
+}
+
+/// Simulates a swap of `offer_asset` against already loaded pools and fee info.
+fn simulate_swap(
+    config: &Config,
+    pools: &[Asset],
+    fee_info: &FeeInfo,
+    offer_asset: &Asset,
+) -> StdResult<SimulationResponse> {
+    let ask_asset_info = config
+        .pair_info
+        .other_asset(&offer_asset.info)
+        .map_err(|_| StdError::generic_err("Given offer asset does not belong in the pair"))?;
+    let offer_pool = &pools[config.pair_info.asset_index(&offer_asset.info)?];
+    let ask_pool = &pools[config.pair_info.asset_index(&ask_asset_info)?];
+
+    let (mut return_amount, spread_amount, mut commission_amount) = compute_swap(
+        offer_pool.amount,
+        ask_pool.amount,
+        offer_asset.amount,
+        fee_info.total_fee_rate,
+    )?;
+    if fee_info.is_min_commission_enabled {
+        (return_amount, commission_amount) =
+            apply_min_commission(return_amount, commission_amount, fee_info.total_fee_rate);
+    }
+
+    Ok(SimulationResponse {
+        return_amount,
+        spread_amount,
+        commission_amount,
+    })
+}
+
+/// Simulates a reverse swap for `ask_asset` against already loaded pools and fee info.
+fn simulate_reverse_swap(
+    config: &Config,
+    pools: &[Asset],
+    fee_info: &FeeInfo,
+    ask_asset: &Asset,
+    offer_asset_info: Option<AssetInfo>,
+) -> StdResult<ReverseSimulationResponse> {
+    let pair_offer_asset_info = config
+        .pair_info
+        .other_asset(&ask_asset.info)
+        .map_err(|_| StdError::generic_err("Given ask asset doesn't belong to pairs"))?;
+    if offer_asset_info.is_some_and(|offer_asset_info| offer_asset_info != pair_offer_asset_info) {
+        return Err(StdError::generic_err(
+            ContractError::AssetMismatch {}.to_string(),
+        ));
     }
-    Ok(Response::default())
+    let offer_pool = &pools[config.pair_info.asset_index(&pair_offer_asset_info)?];
+    let ask_pool = &pools[config.pair_info.asset_index(&ask_asset.info)?];
+
+    let compute_offer_amount = if fee_info.is_min_commission_enabled {
+        compute_offer_amount_with_min_commission
+    } else {
+        compute_offer_amount
+    };
+    let (offer_amount, spread_amount, commission_amount) = compute_offer_amount(
+        offer_pool.amount,
+        ask_pool.amount,
+        ask_asset.amount,
+        fee_info.total_fee_rate,
+    )?;
+
+    Ok(ReverseSimulationResponse {
+        offer_amount,
+        spread_amount,
+        commission_amount,
+    })
 }
 
-/// Exposes all the queries available in the contract.
-///
-/// ## Queries
-/// * **QueryMsg::Pair {}** Returns information about the pair in an object of type [`PairInfo`].
-///
-/// * **QueryMsg::Pool {}** Returns information about the amount of assets in the pair contract as
-/// well as the amount of LP tokens issued using an object of type [`PoolResponse`].
-///
-/// * **QueryMsg::Share { amount }** Returns the amount of assets that could be withdrawn from the pool
-/// using a specific amount of LP tokens. The result is returned in a vector that contains objects of type [`Asset`].
-///
-/// * **QueryMsg::Simulation { offer_asset }** Returns the result of a swap simulation using a [`SimulationResponse`] object.
-///
-/// * **QueryMsg::ReverseSimulation { ask_asset }** Returns the result of a reverse swap simulation  using
-/// a [`ReverseSimulationResponse`] object.
-///
-/// * **QueryMsg::CumulativePrices {}** Returns information about cumulative prices for the assets in the
-/// pool using a [`CumulativePricesResponse`] object.
+/// Returns the largest amount of `offer_asset_info` whose swap spread stays within `max_spread`.
 ///
-/// * **QueryMsg::Config {}** Returns the configuration for the pair contract using a [`ConfigResponse`] object.
+/// Swapping `o` into a pool holding `x` offer and `y` ask assets has a spread of `o / (x + o)`,
+/// independent of the commission, so the spread stays within `s` while `o <= x * s / (1 - s)`.
+/// The swap amounts are rounded to whole ask units, which can add up to one unit of spread, so
+/// the bound is lowered by `(1 + s) * x / ((1 - s) * s * y)`, the first order cost of that unit.
 ///
-/// * **QueryMsg::AssetBalanceAt { asset_info, block_height }** Returns the balance of the specified asset that was in the pool
-/// just preceeding the moment of the specified block height creation.
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::Pair {} => to_json_binary(&CONFIG.load(deps.storage)?.pair_info),
-        QueryMsg::Pool {} => to_json_binary(&query_pool(deps)?),
-        QueryMsg::Share { amount } => to_json_binary(&query_share(deps, amount)?),
-        QueryMsg::Simulation { offer_asset, .. } => {
-            to_json_binary(&query_simulation(deps, env, offer_asset)?)
-        }
-        QueryMsg::ReverseSimulation { ask_asset, .. } => {
-            to_json_binary(&query_reverse_simulation(deps, env, ask_asset)?)
-        }
-        QueryMsg::Config {} => to_json_binary(&query_config(deps)?),
-        _ => Err(StdError::generic_err("Query is not supported")),
+/// * **max_spread** maximum spread, at most [`MAX_ALLOWED_SLIPPAGE`].
+pub fn query_max_swap_within_slippage(
+    deps: Deps,
+    offer_asset_info: AssetInfo,
+    max_spread: Decimal,
+    ask_asset_info: Option<AssetInfo>,
+) -> StdResult<Uint128> {
+    if max_spread > MAX_ALLOWED_SLIPPAGE {
+        return Err(StdError::generic_err(
+            ContractError::Dex(DexError::AllowedSpreadAssertion {}).to_string(),
+        ));
+    }
+    if ask_asset_info.as_ref() == Some(&offer_asset_info) {
+        return Err(StdError::generic_err(
+            ContractError::Dex(DexError::SameAssets {}).to_string(),
+        ));
     }
-}
 
-/// Returns the amounts of assets in the pair contract as well as the amount of LP
-/// tokens currently minted in an object of type [`PoolResponse`].
-pub fn query_pool(deps: Deps) -> StdResult<PoolResponse> {
     let config = CONFIG.load(deps.storage)?;
-    let (assets, total_share) = pool_info(deps.querier, &config)?;
 
-    let resp = PoolResponse {
-        assets,
-        total_share,
-    };
+    let other_asset_info = config
+        .pair_info
+        .other_asset(&offer_asset_info)
+        .map_err(|_| StdError::generic_err("Given offer asset does not belong in the pair"))?;
+    if ask_asset_info.is_some_and(|ask_asset_info| ask_asset_info != other_asset_info) {
+        return Err(StdError::generic_err(
+            "Given ask asset doesn't belong to pairs",
+        ));
+    }
 
-    Ok(resp)
-}
+    let pools = config
+        .pair_info
+        .query_pools(&deps.querier, &config.pair_info.contract_addr)?;
+    let offer_pool = Uint256::from(pools[config.pair_info.asset_index(&offer_asset_info)?].amount);
+    let ask_pool = Uint256::from(pools[config.pair_info.asset_index(&other_asset_info)?].amount);
 
-/// Returns the amount of assets that could be withdrawn from the pool using a specific amount of LP tokens.
-/// The result is returned in a vector that contains objects of type [`Asset`].
-///
-/// * **amount** is the amount of LP tokens for which we calculate associated amounts of assets.
-pub fn query_share(deps: Deps, amount: Uint128) -> StdResult<Vec<Asset>> {
-    let config = CONFIG.load(deps.storage)?;
-    let (pools, total_share) = pool_info(deps.querier, &config)?;
-    let refund_assets = get_share_in_assets(&pools, amount, total_share);
+    // Every swap has some spread, and any amount stays within a 100% spread
+    if max_spread.is_zero() || ask_pool.is_zero() {
+        return Ok(Uint128::zero());
+    }
+    if max_spread == Decimal::one() {
+        return Ok(Uint128::MAX);
+    }
 
-    Ok(refund_assets)
+    let one = Uint256::from(Decimal::one().atomics());
+    let spread = Uint256::from(max_spread.atomics());
+    let bound = offer_pool.multiply_ratio(spread, one - spread);
+    let rounding_margin = (offer_pool * (one + spread) * one)
+        .checked_div(ask_pool * (one - spread) * spread)?
+        + Uint256::one();
+
+    Ok(bound.saturating_sub(rounding_margin).try_into()?)
 }
 
-/// Returns information about a swap simulation in a [`SimulationResponse`] object.
+/// Returns the amount of offer assets needed to move the pool price to `target_price`
+/// in a [`SwapToPriceResponse`] object.
 ///
-/// * **offer_asset** is the asset to swap as well as an amount of the said asset.
-pub fn query_simulation(deps: Deps, env: Env, offer_asset: Asset) -> StdResult<SimulationResponse> {
+/// * **target_price** marginal price (ask assets per offer asset, net of commission) to reach.
+///
+/// * **offer_asset_info** asset that is swapped into the pool.
+pub fn query_swap_to_price(
+    deps: Deps,
+    target_price: Decimal,
+    offer_asset_info: AssetInfo,
+) -> StdResult<SwapToPriceResponse> {
     let config = CONFIG.load(deps.storage)?;
 
     let pools = config
         .pair_info
         .query_pools(&deps.querier, &config.pair_info.contract_addr)?;
 
-    let offer_pool: Asset;
-    let ask_pool: Asset;
-    if offer_asset.info.equal(&pools[0].info) {
-        offer_pool = pools[0].clone();
-        ask_pool = pools[1].clone();
-    } else if offer_asset.info.equal(&pools[1].info) {
-        offer_pool = pools[1].clone();
-        ask_pool = pools[0].clone();
-    } else {
-        return Err(StdError::generic_err(
-            "Given offer asset does not belong in the pair",
-        ));
-    }
+    let ask_asset_info = config
+        .pair_info
+        .other_asset(&offer_asset_info)
+        .map_err(|_| StdError::generic_err("Given offer asset does not belong in the pair"))?;
+    let offer_pool = pools[config.pair_info.asset_index(&offer_asset_info)?].clone();
+    let ask_pool = pools[config.pair_info.asset_index(&ask_asset_info)?].clone();
 
     // Get fee info from the factory contract
-    let fee_info = query_fee_info(
+    let fee_info = query_fee_info_basic(
         &deps.querier,
         &config.factory_addr,
         config.pair_info.pair_type,
-        &env.contract.address,
     )?;
 
-    let (return_amount, spread_amount, commission_amount) = compute_swap(
+    let (offer_amount, resulting_price) = compute_swap_to_price(
         offer_pool.amount,
         ask_pool.amount,
-        offer_asset.amount,
+        target_price,
         fee_info.total_fee_rate,
     )?;
 
-    Ok(SimulationResponse {
-        return_amount,
-        spread_amount,
-        commission_amount,
+    Ok(SwapToPriceResponse {
+        offer_amount,
+        resulting_price,
     })
 }
 
-/// Returns information about a reverse swap simulation in a [`ReverseSimulationResponse`] object.
+/// Returns the marginal price of the pool and the average price of a swap net of fees in a
+/// [`SwapRateResponse`] object. A zero offer amount returns the marginal price net of fees.
 ///
-/// * **ask_asset** is the asset to swap to as well as the desired amount of ask
-/// assets to receive from the swap.
-pub fn query_reverse_simulation(
+/// * **offer_asset** is the asset to swap as well as an amount of the said asset.
+pub fn query_swap_rate(
     deps: Deps,
-    env: Env,
-    ask_asset: Asset,
-) -> StdResult<ReverseSimulationResponse> {
+    offer_asset: Asset,
+    ask_asset_info: Option<AssetInfo>,
+) -> StdResult<SwapRateResponse> {
+    if ask_asset_info.as_ref() == Some(&offer_asset.info) {
+        return Err(StdError::generic_err(
+            ContractError::Dex(DexError::SameAssets {}).to_string(),
+        ));
+    }
+
     let config = CONFIG.load(deps.storage)?;
 
     let pools = config
         .pair_info
         .query_pools(&deps.querier, &config.pair_info.contract_addr)?;
 
-    let offer_pool: Asset;
-    let ask_pool: Asset;
-    if ask_asset.info.equal(&pools[0].info) {
-        ask_pool = pools[0].clone();
-        offer_pool = pools[1].clone();
-    } else if ask_asset.info.equal(&pools[1].info) {
-        ask_pool = pools[1].clone();
-        offer_pool = pools[0].clone();
+    let ask_asset_info = config
+        .pair_info
+        .other_asset(&offer_asset.info)
+        .map_err(|_| StdError::generic_err("Given offer asset does not belong in the pair"))?;
+    let offer_pool = pools[config.pair_info.asset_index(&offer_asset.info)?].clone();
+    let ask_pool = pools[config.pair_info.asset_index(&ask_asset_info)?].clone();
+
+    let rate = if offer_pool.amount.is_zero() {
+        Decimal::zero()
     } else {
+        to_decimal(Decimal256::from_ratio(ask_pool.amount, offer_pool.amount))?
+    };
+
+    let rate_after_fees = if offer_asset.amount.is_zero() {
+        let fee_info = query_fee_info_basic(
+            &deps.querier,
+            &config.factory_addr,
+            config.pair_info.pair_type,
+        )?;
+        rate * (Decimal::one() - fee_info.total_fee_rate)
+    } else {
+        let offer_amount = offer_asset.amount;
+        let simulation = query_simulation(deps, offer_asset, Some(ask_asset_info))?;
+        to_decimal(Decimal256::from_ratio(
+            simulation.return_amount,
+            offer_amount,
+        ))?
+    };
+
+    Ok(SwapRateResponse {
+        rate,
+        rate_after_fees,
+    })
+}
+
+/// Returns the price impact of a swap before fees. For a constant product pool with offer pool
+/// `x` the spot price is `y / x` and the execution price is `y / (x + dx)`, so the impact is
+/// `dx / (x + dx)`.
+///
+/// * **offer_asset** is the asset to swap as well as an amount of the said asset.
+pub fn query_price_impact(
+    deps: Deps,
+    offer_asset: Asset,
+    ask_asset_info: Option<AssetInfo>,
+) -> StdResult<Decimal> {
+    if ask_asset_info.as_ref() == Some(&offer_asset.info) {
         return Err(StdError::generic_err(
-            "Given ask asset doesn't belong to pairs",
+            ContractError::Dex(DexError::SameAssets {}).to_string(),
         ));
     }
 
-    // Get fee info from factory
-    let fee_info = query_fee_info(
-        &deps.querier,
-        &config.factory_addr,
-        config.pair_info.pair_type,
-        &env.contract.address,
-    )?;
+    let config = CONFIG.load(deps.storage)?;
+    let pools = config
+        .pair_info
+        .query_pools(&deps.querier, &config.pair_info.contract_addr)?;
 
-    let (offer_amount, spread_amount, commission_amount) = compute_offer_amount(
-        offer_pool.amount,
-        ask_pool.amount,
-        ask_asset.amount,
-        fee_info.total_fee_rate,
-    )?;
+    config
+        .pair_info
+        .other_asset(&offer_asset.info)
+        .map_err(|_| StdError::generic_err("Given offer asset does not belong in the pair"))?;
+    let offer_pool = &pools[config.pair_info.asset_index(&offer_asset.info)?];
+    if pools.iter().any(|pool| pool.amount.is_zero()) {
+        return Err(StdError::generic_err("One of the pools is empty"));
+    }
 
-    Ok(ReverseSimulationResponse {
-        offer_amount,
-        spread_amount,
-        commission_amount,
-    })
+    Ok(Decimal::from_ratio(
+        offer_asset.amount,
+        offer_pool.amount.checked_add(offer_asset.amount)?,
+    ))
 }
 
 pub fn query_lp_received(deps: Deps, address: String) -> StdResult<LpReceivedResponse> {
@@ -941,6 +3053,21 @@ pub fn query_lp_received(deps: Deps, address: String) -> StdResult<LpReceivedRes
     Ok(resp)
 }
 
+/// Returns the tracked LP balances of all providers, ordered by address.
+///
+/// * **start_after** is the provider after which to start returning balances.
+///
+/// * **limit** is the maximum number of balances to return.
+pub fn query_all_lp_received(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(Addr, Uint128)>> {
+    let start_after = addr_opt_validate(deps.api, &start_after)?;
+
+    read_lp_providers(deps.storage, start_after.as_ref(), limit)
+}
+
 /// Returns the pair contract configuration in a [`ConfigResponse`] object.
 pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let config: Config = CONFIG.load(deps.storage)?;
@@ -948,135 +3075,179 @@ pub fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
     let factory_config = query_factory_config(&deps.querier, &config.factory_addr)?;
 
     Ok(ConfigResponse {
-        params: None,
+        params: Some(to_json_binary(&XykPoolParams {
+            launch_end_time: config.launch_end_time,
+            max_lp_per_address: config.max_lp_per_address,
+            max_belief_price_deviation: config.max_belief_price_deviation,
+            oracle_addr: config.oracle_addr.map(String::from),
+            max_oracle_deviation: config.max_oracle_deviation,
+            reserve_cap: config.reserve_cap,
+        })?),
         owner: factory_config.owner,
         factory_addr: config.factory_addr,
     })
 }
 
-/// Returns the result of a swap.
-///
-/// * **offer_pool** total amount of offer assets in the pool.
-///
-/// * **ask_pool** total amount of ask assets in the pool.
+/// Returns the fee rate a swap of `offer_asset` would pay. It is the share of the simulated
+/// return taken as commission, which exceeds the factory fee rate on dust swaps charged the
+/// minimum commission.
+pub fn query_effective_fee_rate(deps: Deps, offer_asset: Asset) -> StdResult<Decimal> {
+    let config = CONFIG.load(deps.storage)?;
+    config.pair_info.asset_index(&offer_asset.info)?;
+
+    let pools = config
+        .pair_info
+        .query_pools(&deps.querier, &config.pair_info.contract_addr)?;
+    let fee_info = query_fee_info_basic(
+        &deps.querier,
+        &config.factory_addr,
+        config.pair_info.pair_type.clone(),
+    )?;
+
+    let SimulationResponse {
+        return_amount,
+        commission_amount,
+        ..
+    } = simulate_swap(&config, &pools, &fee_info, &offer_asset)?;
+    if return_amount.is_zero() && commission_amount.is_zero() {
+        return Ok(fee_info.total_fee_rate);
+    }
+
+    Ok(Decimal::from_ratio(
+        commission_amount,
+        return_amount + commission_amount,
+    ))
+}
+
+/// Checks that `belief_price` is within `max_deviation` times the pool price in either
+/// direction. Both prices are expressed in offer assets per ask asset.
 ///
-/// * **offer_amount** amount of offer assets to swap.
+/// * **offer_pool** total amount of offer assets in the pool before the swap.
 ///
-/// * **commission_rate** total amount of fees charged for the swap.
-pub fn compute_swap(
+/// * **ask_pool** total amount of ask assets in the pool before the swap.
+pub fn assert_belief_price_near_market(
+    belief_price: Option<Decimal>,
+    max_deviation: Option<Decimal>,
     offer_pool: Uint128,
     ask_pool: Uint128,
-    offer_amount: Uint128,
-    commission_rate: Decimal,
-) -> StdResult<(Uint128, Uint128, Uint128)> {
-    // offer => ask
-    check_swap_parameters(vec![offer_pool, ask_pool], offer_amount)?;
-
-    let offer_pool: Uint256 = offer_pool.into();
-    let ask_pool: Uint256 = ask_pool.into();
-    let offer_amount: Uint256 = offer_amount.into();
-    let commission_rate = Decimal256::from(commission_rate);
-
-    // ask_amount = (ask_pool - cp / (offer_pool + offer_amount))
-    let cp: Uint256 = offer_pool * ask_pool;
-    let return_amount: Uint256 = (Decimal256::from_ratio(ask_pool, 1u8)
-        - Decimal256::from_ratio(cp, offer_pool + offer_amount))
-        * Uint256::from(1u8);
-
-    // Calculate spread & commission
-    let spread_amount: Uint256 =
-        (offer_amount * Decimal256::from_ratio(ask_pool, offer_pool)).saturating_sub(return_amount);
-    let commission_amount: Uint256 = return_amount * commission_rate;
-
-    // The commision (minus the part that goes to the Maker contract) will be absorbed by the pool
-    let return_amount: Uint256 = return_amount - commission_amount;
-    Ok((
-        return_amount.try_into()?,
-        spread_amount.try_into()?,
-        commission_amount.try_into()?,
-    ))
+) -> Result<(), ContractError> {
+    let (Some(belief_price), Some(max_deviation)) = (belief_price, max_deviation) else {
+        return Ok(());
+    };
+    if ask_pool.is_zero() {
+        return Ok(());
+    }
+
+    let pool_price = Decimal256::from_ratio(offer_pool, ask_pool);
+    let belief_price = Decimal256::from(belief_price);
+    let max_deviation = Decimal256::from(max_deviation);
+    if belief_price > pool_price.saturating_mul(max_deviation)
+        || pool_price > belief_price.saturating_mul(max_deviation)
+    {
+        return Err(ContractError::BeliefPriceTooFarOffMarket {});
+    }
+
+    Ok(())
 }
 
-/// Returns an amount of offer assets for a specified amount of ask assets.
+/// Checks the limits every swap executed by the pair is subject to, including the internal
+/// swaps balancing a deposit or a withdrawal: the maximum price impact of the pair type and,
+/// if the pair has an oracle, the maximum deviation from the oracle price.
 ///
-/// * **offer_pool** total amount of offer assets in the pool.
+/// * **pools** offer and ask pools before the swap.
 ///
-/// * **ask_pool** total amount of ask assets in the pool.
+/// * **offer_amount** amount of offer assets added to the pool.
 ///
-/// * **ask_amount** amount of ask assets to swap to.
+/// * **ask_amount** amount of ask assets leaving the pool.
 ///
-/// * **commission_rate** total amount of fees charged for the swap.
-pub fn compute_offer_amount(
-    offer_pool: Uint128,
-    ask_pool: Uint128,
+/// * **return_amount** amount of ask assets the swap returns before the commission.
+fn assert_swap_limits(
+    querier: &QuerierWrapper,
+    config: &Config,
+    max_price_impact: Option<Decimal>,
+    [offer_pool, ask_pool]: [&Asset; 2],
+    offer_amount: Uint128,
     ask_amount: Uint128,
-    commission_rate: Decimal,
-) -> StdResult<(Uint128, Uint128, Uint128)> {
-    // ask => offer
-    check_swap_parameters(vec![offer_pool, ask_pool], ask_amount)?;
-
-    // offer_amount = cp / (ask_pool - ask_amount / (1 - commission_rate)) - offer_pool
-    let cp = Uint256::from(offer_pool) * Uint256::from(ask_pool);
-    let one_minus_commission = Decimal256::one() - Decimal256::from(commission_rate);
-    let inv_one_minus_commission = Decimal256::one() / one_minus_commission;
-
-    let offer_amount: Uint128 = cp
-        .multiply_ratio(
-            Uint256::from(1u8),
-            Uint256::from(
-                ask_pool.checked_sub(
-                    (Uint256::from(ask_amount) * inv_one_minus_commission).try_into()?,
-                )?,
-            ),
-        )
-        .checked_sub(offer_pool.into())?
-        .try_into()?;
-
-    let before_commission_deduction = Uint256::from(ask_amount) * inv_one_minus_commission;
-    let spread_amount = (offer_amount * Decimal::from_ratio(ask_pool, offer_pool))
-        .saturating_sub(before_commission_deduction.try_into()?);
-    let commission_amount = before_commission_deduction * Decimal256::from(commission_rate);
-    Ok((offer_amount, spread_amount, commission_amount.try_into()?))
+    return_amount: Uint128,
+) -> Result<(), ContractError> {
+    assert_price_impact(
+        max_price_impact,
+        offer_pool.amount,
+        ask_pool.amount,
+        offer_amount,
+        ask_amount,
+    )?;
+    if let (Some(oracle_addr), Some(max_deviation)) =
+        (&config.oracle_addr, config.max_oracle_deviation)
+    {
+        let oracle_price =
+            query_oracle_price(querier, oracle_addr, &ask_pool.info, &offer_pool.info)?;
+        assert_near_oracle_price(oracle_price, max_deviation, offer_amount, return_amount)?;
+    }
+
+    Ok(())
 }
 
-/// If `belief_price` and `max_spread` are both specified, we compute a new spread,
-/// otherwise we just use the swap spread to check `max_spread`.
+/// Checks that the execution price of a swap is within `max_deviation` times the oracle
+/// price in either direction. Both prices are expressed in offer assets per ask asset.
 ///
-/// * **belief_price** belief price used in the swap.
+/// * **offer_amount** amount of offer assets added to the pool.
 ///
-/// * **max_spread** max spread allowed so that the swap can be executed successfully.
+/// * **return_amount** amount of ask assets the swap returns before the commission.
+pub fn assert_near_oracle_price(
+    oracle_price: Decimal,
+    max_deviation: Decimal,
+    offer_amount: Uint128,
+    return_amount: Uint128,
+) -> Result<(), ContractError> {
+    if return_amount.is_zero() {
+        return Err(ContractError::OraclePriceDeviationTooHigh {});
+    }
+
+    let swap_price = Decimal256::from_ratio(offer_amount, return_amount);
+    let oracle_price = Decimal256::from(oracle_price);
+    let max_deviation = Decimal256::from(max_deviation);
+    if swap_price > oracle_price.saturating_mul(max_deviation)
+        || oracle_price > swap_price.saturating_mul(max_deviation)
+    {
+        return Err(ContractError::OraclePriceDeviationTooHigh {});
+    }
+
+    Ok(())
+}
+
+/// Checks that a swap moves the pool price, `ask_pool / offer_pool`, down by at most
+/// `max_price_impact`.
 ///
-/// * **offer_amount** amount of assets to swap.
+/// * **offer_pool** total amount of offer assets in the pool before the swap.
 ///
-/// * **return_amount** amount of assets to receive from the swap.
+/// * **ask_pool** total amount of ask assets in the pool before the swap.
 ///
-/// * **spread_amount** spread used in the swap.
-pub fn assert_max_spread(
-    belief_price: Option<Decimal>,
-    max_spread: Option<Decimal>,
+/// * **offer_amount** amount of offer assets added to the pool.
+///
+/// * **ask_amount** amount of ask assets leaving the pool.
+pub fn assert_price_impact(
+    max_price_impact: Option<Decimal>,
+    offer_pool: Uint128,
+    ask_pool: Uint128,
     offer_amount: Uint128,
-    return_amount: Uint128,
-    spread_amount: Uint128,
+    ask_amount: Uint128,
 ) -> Result<(), ContractError> {
-    let max_spread = max_spread.unwrap_or(DEFAULT_SLIPPAGE);
-    if max_spread.gt(&MAX_ALLOWED_SLIPPAGE) {
-        return Err(ContractError::AllowedSpreadAssertion {});
+    let Some(max_price_impact) = max_price_impact else {
+        return Ok(());
+    };
+    if ask_pool.is_zero() {
+        return Ok(());
     }
 
-    if let Some(belief_price) = belief_price {
-        let expected_return = offer_amount
-            * belief_price
-                .inv()
-                .ok_or_else(|| StdError::generic_err("Belief price must not be zero!"))?;
-        let spread_amount = expected_return.saturating_sub(return_amount);
-
-        if return_amount < expected_return
-            && Decimal::from_ratio(spread_amount, expected_return) > max_spread
-        {
-            return Err(ContractError::MaxSpreadAssertion {});
-        }
-    } else if Decimal::from_ratio(spread_amount, return_amount + spread_amount) > max_spread {
-        return Err(ContractError::MaxSpreadAssertion {});
+    // price_after / price_before == (ask_after * offer_before) / (ask_before * offer_after)
+    let price_ratio = Decimal256::checked_from_ratio(
+        ask_pool.saturating_sub(ask_amount).full_mul(offer_pool),
+        ask_pool.full_mul(offer_pool.checked_add(offer_amount)?),
+    )
+    .map_err(|e| StdError::generic_err(e.to_string()))?;
+    if Decimal256::one().saturating_sub(price_ratio) > Decimal256::from(max_price_impact) {
+        return Err(ContractError::Dex(DexError::PriceImpactTooHigh {}));
     }
 
     Ok(())
@@ -1096,7 +3267,7 @@ pub fn assert_slippage_tolerance(
 ) -> Result<(), ContractError> {
     let slippage_tolerance = slippage_tolerance.unwrap_or(DEFAULT_SLIPPAGE);
     if slippage_tolerance.gt(&MAX_ALLOWED_SLIPPAGE) {
-        return Err(ContractError::AllowedSpreadAssertion {});
+        return Err(ContractError::Dex(DexError::AllowedSpreadAssertion {}));
     }
 
     let slippage_tolerance: Decimal256 = Decimal256::from(slippage_tolerance);
@@ -1138,13 +3309,18 @@ pub fn pool_info(querier: QuerierWrapper, config: &Config) -> StdResult<(Vec<Ass
         .pair_info
         .query_pools(&querier, &config.pair_info.contract_addr)?;
 
-    let total_share = match &config.pair_info.liquidity_token {
-        AssetInfo::NativeToken { denom } => querier.query_supply(denom)?.amount,
-        AssetInfo::Token { contract_addr } => {
-            let res: TokenInfoResponse =
-                querier.query_wasm_smart(contract_addr, &Cw20QueryMsg::TokenInfo {})?;
-            res.total_supply
-        }
-    };
+    let total_share = query_total_share(&querier, config)?;
     Ok((pools, total_share))
 }
+
+/// Returns the total amount of LP tokens, including the previous LP tokens that were not
+/// claimed yet during an LP token migration.
+fn query_total_share(querier: &QuerierWrapper, config: &Config) -> StdResult<Uint128> {
+    let total_share = query_total_supply(querier, &config.pair_info.liquidity_token)?;
+    match &config.legacy_lp_token {
+        Some(legacy_lp_token) => {
+            Ok(total_share.checked_add(query_total_supply(querier, legacy_lp_token)?)?)
+        }
+        None => Ok(total_share),
+    }
+}
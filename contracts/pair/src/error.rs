@@ -1,6 +1,6 @@
-use cosmwasm_std::{OverflowError, StdError};
+use cosmwasm_std::{OverflowError, StdError, Uint128};
 use thiserror::Error;
-use ura::contracts::pair::MINIMUM_LIQUIDITY_AMOUNT;
+use ura::errors::DexError;
 
 /// This enum describes pair contract errors
 #[derive(Error, Debug, PartialEq)]
@@ -8,47 +8,71 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
-    #[error("Unauthorized")]
-    Unauthorized {},
+    #[error("{0}")]
+    Dex(#[from] DexError),
 
-    #[error("CW20 tokens can be swapped via Cw20::Send message only")]
-    Cw20DirectSwap {},
+    #[error("Operation exceeds max splippage tolerance")]
+    MaxSlippageAssertion {},
 
-    #[error("Operation non supported")]
-    NonSupported {},
+    #[error("Asset mismatch between the requested and the stored asset in contract")]
+    AssetMismatch {},
 
-    #[error("Event of zero transfer")]
-    InvalidZeroAmount {},
+    #[error("Pair type mismatch. Check factory pair configs")]
+    PairTypeMismatch {},
 
-    #[error("Wrong token funds sent to withdraw liquidity")]
-    InvalidLiquidityToken {},
+    #[error("Pool assets and the LP token cannot be rescued")]
+    CannotRescuePoolAsset {},
 
-    #[error("Operation exceeds max spread limit")]
-    MaxSpreadAssertion {},
+    #[error("An address can receive at most {cap} LP tokens during the launch window")]
+    LaunchLpCapExceeded { cap: Uint128 },
 
-    #[error("Provided spread amount exceeds allowed limit")]
-    AllowedSpreadAssertion {},
+    #[error("The provide would take the pool reserves over the reserve cap")]
+    ReserveCapExceeded {},
 
-    #[error("Operation exceeds max splippage tolerance")]
-    MaxSlippageAssertion {},
+    #[error("Swap outputs can only be delivered with a hook to a contract")]
+    HookRecipientNotContract {},
 
-    #[error("Doubling assets in asset infos")]
-    DoublingAssets {},
+    #[error("Swap outputs can't be delivered with a hook to {recipient}, which trusts the pair")]
+    ForbiddenHookRecipient { recipient: String },
 
-    #[error("Asset mismatch between the requested and the stored asset in contract")]
-    AssetMismatch {},
+    #[error("The withdrawn LP tokens could not be burned")]
+    LpBurnFailed {},
 
-    #[error("Pair type mismatch. Check factory pair configs")]
-    PairTypeMismatch {},
+    #[error("The LP token supply is {actual} after the mint instead of {expected}")]
+    LpMintMismatch { expected: Uint128, actual: Uint128 },
+
+    #[error("The pair is not ready until its LP token is created")]
+    PairNotReady {},
+
+    #[error("The belief price deviation factor must be at least 1")]
+    InvalidBeliefPriceDeviation {},
+
+    #[error("The belief price is too far off the pool price")]
+    BeliefPriceTooFarOffMarket {},
+
+    #[error("An oracle requires an oracle deviation factor of at least 1")]
+    InvalidOracleDeviation {},
+
+    #[error("The swap price is too far off the oracle price")]
+    OraclePriceDeviationTooHigh {},
+
+    #[error("The LP token already is of the requested kind")]
+    LpTokenKindUnchanged {},
+
+    #[error("A token code id is required to migrate to a CW20 LP token")]
+    LpTokenCodeIdRequired {},
+
+    #[error("No funds can be sent to migrate to a CW20 LP token")]
+    LpMigrationFundsNotAccepted {},
 
-    #[error("Initial liquidity must be more than {}", MINIMUM_LIQUIDITY_AMOUNT)]
-    MinimumLiquidityAmountError {},
+    #[error("The previous LP token is still being claimed")]
+    LpMigrationInProgress {},
 
-    #[error("Failed to parse or process reply message")]
-    FailedToParseReply {},
+    #[error("There is no previous LP token to claim for")]
+    NoLpMigration {},
 
-    #[error("Invalid state")]
-    InvalidState {},
+    #[error("Providing liquidity on behalf of an owner requires a slippage tolerance")]
+    SlippageToleranceRequired {},
 }
 
 impl From<OverflowError> for ContractError {
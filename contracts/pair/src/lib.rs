@@ -1,6 +1,7 @@
 pub mod contract;
 pub mod denom;
 pub mod error;
+pub mod math;
 pub mod state;
 
 #[cfg(test)]
@@ -0,0 +1,391 @@
+use std::convert::TryInto;
+
+use cosmwasm_std::{Decimal, Decimal256, Fraction, StdError, StdResult, Uint128, Uint256};
+
+use ura::contracts::pair::{DEFAULT_SLIPPAGE, MAX_ALLOWED_SLIPPAGE};
+use ura::errors::DexError;
+use ura::utils::validation::check_swap_parameters;
+use ura::{to_uint128, U256};
+
+use crate::error::ContractError;
+
+/// Returns the result of a swap.
+///
+/// * **offer_pool** total amount of offer assets in the pool.
+///
+/// * **ask_pool** total amount of ask assets in the pool.
+///
+/// * **offer_amount** amount of offer assets to swap.
+///
+/// * **commission_rate** total amount of fees charged for the swap.
+pub fn compute_swap(
+    offer_pool: Uint128,
+    ask_pool: Uint128,
+    offer_amount: Uint128,
+    commission_rate: Decimal,
+) -> StdResult<(Uint128, Uint128, Uint128)> {
+    // offer => ask
+    check_swap_parameters(vec![offer_pool, ask_pool], offer_amount)?;
+
+    let offer_pool: Uint256 = offer_pool.into();
+    let ask_pool: Uint256 = ask_pool.into();
+    let offer_amount: Uint256 = offer_amount.into();
+    let commission_rate = Decimal256::from(commission_rate);
+
+    // ask_amount = (ask_pool - cp / (offer_pool + offer_amount))
+    let cp: Uint256 = offer_pool * ask_pool;
+    let return_amount: Uint256 = (Decimal256::from_ratio(ask_pool, 1u8)
+        - Decimal256::from_ratio(cp, offer_pool + offer_amount))
+        * Uint256::from(1u8);
+
+    // Calculate spread & commission
+    let spread_amount: Uint256 =
+        (offer_amount * Decimal256::from_ratio(ask_pool, offer_pool)).saturating_sub(return_amount);
+    let commission_amount: Uint256 = return_amount * commission_rate;
+
+    // The commision (minus the part that goes to the Maker contract) will be absorbed by the pool
+    let return_amount: Uint256 = return_amount - commission_amount;
+    Ok((
+        return_amount.try_into()?,
+        spread_amount.try_into()?,
+        commission_amount.try_into()?,
+    ))
+}
+
+/// Charges one unit of commission on swaps whose commission was truncated to zero, so that
+/// splitting a swap into dust does not avoid fees. Pools without a fee are left untouched.
+///
+/// * **return_amount** amount of ask assets returned by [`compute_swap`].
+///
+/// * **commission_amount** commission returned by [`compute_swap`].
+///
+/// * **commission_rate** total amount of fees charged for the swap.
+pub fn apply_min_commission(
+    return_amount: Uint128,
+    commission_amount: Uint128,
+    commission_rate: Decimal,
+) -> (Uint128, Uint128) {
+    if commission_rate.is_zero() || return_amount.is_zero() || !commission_amount.is_zero() {
+        return (return_amount, commission_amount);
+    }
+
+    (return_amount - Uint128::one(), Uint128::one())
+}
+
+/// Returns the amount of offer assets that moves the marginal price of the pool down to
+/// `target_price`, together with the marginal price reached after the swap.
+///
+/// * **offer_pool** total amount of offer assets in the pool.
+///
+/// * **ask_pool** total amount of ask assets in the pool.
+///
+/// * **target_price** marginal price (ask assets per offer asset, net of commission) to reach.
+///
+/// * **commission_rate** total amount of fees charged for the swap.
+pub fn compute_swap_to_price(
+    offer_pool: Uint128,
+    ask_pool: Uint128,
+    target_price: Decimal,
+    commission_rate: Decimal,
+) -> StdResult<(Uint128, Decimal)> {
+    if offer_pool.is_zero() || ask_pool.is_zero() {
+        return Err(StdError::generic_err("One of the pools is empty"));
+    }
+
+    let one_minus_commission = Decimal::one() - commission_rate;
+    let current_price = Decimal::from_ratio(ask_pool, offer_pool) * one_minus_commission;
+    if target_price.is_zero() || target_price >= current_price {
+        return Err(StdError::generic_err(format!(
+            "Target price must be positive and lower than the current price {current_price}"
+        )));
+    }
+
+    // The marginal price after a swap is (1 - commission_rate) * cp / new_offer_pool^2, so
+    // new_offer_pool = sqrt(cp * (1 - commission_rate) / target_price)
+    let cp = U256::from(offer_pool.u128()) * U256::from(ask_pool.u128());
+    let new_offer_pool_sq = cp
+        .checked_mul(U256::from(one_minus_commission.atomics().u128()))
+        .ok_or_else(|| StdError::generic_err("Pool is too large to compute the target price"))?
+        / U256::from(target_price.atomics().u128());
+
+    // Round up so the marginal price ends at or below the target
+    let mut new_offer_pool = new_offer_pool_sq.integer_sqrt();
+    if new_offer_pool * new_offer_pool < new_offer_pool_sq {
+        new_offer_pool += U256::one();
+    }
+    let new_offer_pool = to_uint128(new_offer_pool)
+        .map_err(|_| StdError::generic_err("Pool is too large to compute the target price"))?;
+
+    let offer_amount = new_offer_pool.checked_sub(offer_pool)?;
+    let new_ask_pool: Uint128 = Uint256::from(offer_pool)
+        .multiply_ratio(ask_pool, new_offer_pool)
+        .try_into()?;
+    let resulting_price = Decimal::from_ratio(new_ask_pool, new_offer_pool) * one_minus_commission;
+
+    Ok((offer_amount, resulting_price))
+}
+
+/// Returns an amount of offer assets for a specified amount of ask assets.
+///
+/// * **offer_pool** total amount of offer assets in the pool.
+///
+/// * **ask_pool** total amount of ask assets in the pool.
+///
+/// * **ask_amount** amount of ask assets to swap to.
+///
+/// * **commission_rate** total amount of fees charged for the swap.
+pub fn compute_offer_amount(
+    offer_pool: Uint128,
+    ask_pool: Uint128,
+    ask_amount: Uint128,
+    commission_rate: Decimal,
+) -> StdResult<(Uint128, Uint128, Uint128)> {
+    // ask => offer
+    check_swap_parameters(vec![offer_pool, ask_pool], ask_amount)?;
+
+    // offer_amount = cp / (ask_pool - ask_amount / (1 - commission_rate)) - offer_pool
+    //
+    // Everything stays in integers, with the commission rate as the ratio of its atomics to
+    // `Decimal::one()`, so no intermediate is truncated to 18 decimals.
+    let cp = Uint256::from(offer_pool) * Uint256::from(ask_pool);
+    let commission_denom = Uint256::from(Decimal::one().atomics());
+    let commission_numer = Uint256::from(commission_rate.atomics());
+    let one_minus_commission = commission_denom.checked_sub(commission_numer)?;
+
+    // `compute_swap` returns `gross - floor(gross * commission_rate)`, which reaches
+    // `ask_amount` first at `floor((ask_amount - 1) / (1 - commission_rate)) + 1`
+    let before_commission_deduction = (Uint256::from(ask_amount) - Uint256::one())
+        .checked_mul(commission_denom)?
+        .checked_div(one_minus_commission)?
+        + Uint256::one();
+
+    // Rounded up, so that swapping the offer amount returns at least the ask amount
+    let remaining_ask_pool = Uint256::from(ask_pool).checked_sub(before_commission_deduction)?;
+    let offer_amount: Uint128 = cp
+        .checked_add(remaining_ask_pool.checked_sub(Uint256::one())?)?
+        .checked_div(remaining_ask_pool)?
+        .checked_sub(offer_pool.into())?
+        .try_into()?;
+
+    let spread_amount = Uint256::from(offer_amount)
+        .multiply_ratio(ask_pool, offer_pool)
+        .saturating_sub(before_commission_deduction)
+        .try_into()?;
+    let commission_amount =
+        before_commission_deduction.multiply_ratio(commission_numer, commission_denom);
+    Ok((offer_amount, spread_amount, commission_amount.try_into()?))
+}
+
+/// Same as [`compute_offer_amount`] for pools charging the minimum commission of
+/// [`apply_min_commission`]. A swap whose commission is truncated to zero is quoted for one more
+/// ask unit, so that it still returns `ask_amount` once the minimum commission is charged.
+///
+/// * **ask_amount** amount of ask assets to receive from the swap.
+///
+/// * **commission_rate** total amount of fees charged for the swap.
+pub fn compute_offer_amount_with_min_commission(
+    offer_pool: Uint128,
+    ask_pool: Uint128,
+    ask_amount: Uint128,
+    commission_rate: Decimal,
+) -> StdResult<(Uint128, Uint128, Uint128)> {
+    let quote = compute_offer_amount(offer_pool, ask_pool, ask_amount, commission_rate)?;
+    if commission_rate.is_zero() || ask_amount.is_zero() || !quote.2.is_zero() {
+        return Ok(quote);
+    }
+
+    let (offer_amount, spread_amount, commission_amount) = compute_offer_amount(
+        offer_pool,
+        ask_pool,
+        ask_amount + Uint128::one(),
+        commission_rate,
+    )?;
+    Ok((
+        offer_amount,
+        spread_amount,
+        commission_amount.max(Uint128::one()),
+    ))
+}
+
+/// If `belief_price` and `max_spread` are both specified, we compute a new spread,
+/// otherwise we just use the swap spread to check `max_spread`.
+///
+/// * **belief_price** belief price used in the swap.
+///
+/// * **max_spread** max spread allowed so that the swap can be executed successfully.
+///
+/// * **offer_amount** amount of assets to swap.
+///
+/// * **return_amount** amount of assets to receive from the swap.
+///
+/// * **spread_amount** spread used in the swap.
+pub fn assert_max_spread(
+    belief_price: Option<Decimal>,
+    max_spread: Option<Decimal>,
+    offer_amount: Uint128,
+    return_amount: Uint128,
+    spread_amount: Uint128,
+) -> Result<(), ContractError> {
+    let max_spread = max_spread.unwrap_or(DEFAULT_SLIPPAGE);
+    if max_spread.gt(&MAX_ALLOWED_SLIPPAGE) {
+        return Err(ContractError::Dex(DexError::AllowedSpreadAssertion {}));
+    }
+
+    if let Some(belief_price) = belief_price {
+        let expected_return = offer_amount
+            * belief_price
+                .inv()
+                .ok_or_else(|| StdError::generic_err("Belief price must not be zero!"))?;
+        let spread_amount = expected_return.saturating_sub(return_amount);
+
+        if return_amount < expected_return
+            && Decimal::from_ratio(spread_amount, expected_return) > max_spread
+        {
+            return Err(ContractError::Dex(DexError::MaxSpreadAssertion {}));
+        }
+    } else if Decimal::from_ratio(spread_amount, return_amount + spread_amount) > max_spread {
+        return Err(ContractError::Dex(DexError::MaxSpreadAssertion {}));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn initial_share_sqrt_is_checked_near_max_reserves() {
+        let max = U256::from(u128::MAX);
+        assert_eq!(
+            to_uint128((max * max).integer_sqrt()).unwrap(),
+            Uint128::MAX
+        );
+
+        let err = to_uint128(max + U256::one()).unwrap_err();
+        assert!(matches!(err, StdError::ConversionOverflow { .. }));
+    }
+
+    #[test]
+    fn swaps_near_max_reserves_fail_cleanly() {
+        let fee = Decimal::permille(3);
+
+        let (return_amount, _, _) =
+            compute_swap(Uint128::MAX, Uint128::MAX, Uint128::MAX, fee).unwrap();
+        assert!(return_amount < Uint128::MAX / Uint128::new(2));
+
+        // The spread of a tiny offer pool doesn't fit in 128 bits
+        let err = compute_swap(Uint128::one(), Uint128::MAX, Uint128::MAX, fee).unwrap_err();
+        assert!(matches!(err, StdError::ConversionOverflow { .. }));
+
+        // Buying out almost the whole ask pool needs more offer assets than fit in 128 bits
+        let err = compute_offer_amount(
+            Uint128::MAX,
+            Uint128::MAX,
+            Uint128::MAX - Uint128::one(),
+            Decimal::zero(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, StdError::ConversionOverflow { .. }));
+
+        // A price above the `Decimal` range still quotes
+        let (offer_amount, _, _) = compute_offer_amount(
+            Uint128::one(),
+            Uint128::MAX,
+            Uint128::MAX / Uint128::new(2),
+            Decimal::zero(),
+        )
+        .unwrap();
+        assert_eq!(offer_amount, Uint128::one());
+    }
+
+    #[test]
+    fn offer_amount_round_trips_for_high_fees() {
+        let offer_pool = Uint128::new(2_000_000_000_000);
+        let ask_pool = Uint128::new(1_000_000_000_000);
+
+        for fee_bps in [0u64, 30, 100, 1_000, 2_500, 2_999, 3_000] {
+            let commission_rate = Decimal::from_ratio(fee_bps, 10_000u64);
+            for ask_amount in [1u128, 7, 999, 123_456, 1_000_000_007, 100_000_000_000] {
+                let ask_amount = Uint128::new(ask_amount);
+                let (offer_amount, _, commission_amount) =
+                    compute_offer_amount(offer_pool, ask_pool, ask_amount, commission_rate)
+                        .unwrap();
+
+                // The forward swap returns exactly the ask amount and charges the same commission
+                let (return_amount, _, swap_commission_amount) =
+                    compute_swap(offer_pool, ask_pool, offer_amount, commission_rate).unwrap();
+                assert_eq!(return_amount, ask_amount, "fee {fee_bps} bps");
+                assert_eq!(
+                    commission_amount, swap_commission_amount,
+                    "fee {fee_bps} bps"
+                );
+
+                // One offer unit less falls short, so the quote is not rounded up needlessly
+                let (return_amount, _, _) = compute_swap(
+                    offer_pool,
+                    ask_pool,
+                    offer_amount - Uint128::one(),
+                    commission_rate,
+                )
+                .unwrap();
+                assert!(return_amount < ask_amount, "fee {fee_bps} bps");
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn swap_never_decreases_the_constant_product(
+            offer_pool in 1_000..1_000_000_000_000_000u128,
+            ask_pool in 1_000..1_000_000_000_000_000u128,
+            offer_amount in 1..1_000_000_000_000u128,
+            fee_bps in 0..1_000u64,
+        ) {
+            let (offer_pool, ask_pool, offer_amount) =
+                (Uint128::new(offer_pool), Uint128::new(ask_pool), Uint128::new(offer_amount));
+            let commission_rate = Decimal::from_ratio(fee_bps, 10_000u64);
+            let Ok((return_amount, _, commission_amount)) =
+                compute_swap(offer_pool, ask_pool, offer_amount, commission_rate)
+            else {
+                return Ok(());
+            };
+
+            // Even if the commission leaves the pool x * y = k holds, up to rounding in favor
+            // of the pool
+            let k = offer_pool.full_mul(ask_pool);
+            let new_offer_pool = offer_pool + offer_amount;
+            let k_after_commission =
+                new_offer_pool.full_mul(ask_pool - return_amount - commission_amount);
+            prop_assert!(k_after_commission >= k);
+            // Rounding keeps at most one ask unit in the pool
+            prop_assert!(k_after_commission < k + Uint256::from(new_offer_pool));
+
+            // The commission kept in the pool grows the constant product
+            let k_after = new_offer_pool.full_mul(ask_pool - return_amount);
+            prop_assert!(k_after >= k_after_commission);
+        }
+
+        #[test]
+        fn offer_amount_returns_at_least_the_ask_amount(
+            offer_pool in 1_000_000..1_000_000_000_000_000u128,
+            ask_pool in 1_000_000..1_000_000_000_000_000u128,
+            ask_amount in 1..1_000_000u128,
+            fee_bps in 0..=3_000u64,
+        ) {
+            let (offer_pool, ask_pool, ask_amount) =
+                (Uint128::new(offer_pool), Uint128::new(ask_pool), Uint128::new(ask_amount));
+            let commission_rate = Decimal::from_ratio(fee_bps, 10_000u64);
+            let Ok((offer_amount, _, _)) =
+                compute_offer_amount(offer_pool, ask_pool, ask_amount, commission_rate)
+            else {
+                return Ok(());
+            };
+
+            let (return_amount, _, _) =
+                compute_swap(offer_pool, ask_pool, offer_amount, commission_rate).unwrap();
+            prop_assert!(return_amount >= ask_amount);
+        }
+    }
+}
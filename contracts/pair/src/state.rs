@@ -1,7 +1,11 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Uint128};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, Decimal, Empty, Order, StdResult, Storage, Uint128};
+use cw_storage_plus::{Bound, Item, Map};
+use ura::structs::asset::Asset;
+use ura::structs::asset_info::{AssetInfo, AssetInfoExt};
 use ura::structs::pair_info::PairInfo;
+use ura::utils::fees::read_cumulative_fees;
+use ura::utils::pagination::clamp_limit;
 
 /// This structure stores the main config parameters for a constant product pair contract.
 #[cw_serde]
@@ -10,10 +14,183 @@ pub struct Config {
     pub pair_info: PairInfo,
     /// The factory contract address
     pub factory_addr: Addr,
+    /// The launch window end (in seconds) during which `max_lp_per_address` applies
+    #[serde(default)]
+    pub launch_end_time: Option<u64>,
+    /// The maximum amount of LP tokens a single address can receive from providing during the launch window
+    #[serde(default)]
+    pub max_lp_per_address: Option<Uint128>,
+    /// The maximum factor by which a swap's `belief_price` may differ from the pool price
+    #[serde(default)]
+    pub max_belief_price_deviation: Option<Decimal>,
+    /// The price oracle swaps are checked against
+    #[serde(default)]
+    pub oracle_addr: Option<Addr>,
+    /// The maximum factor by which a swap's execution price may differ from the oracle price
+    #[serde(default)]
+    pub max_oracle_deviation: Option<Decimal>,
+    /// The LP token replaced by `MigrateLpToken` while some of it is still unclaimed
+    #[serde(default)]
+    pub legacy_lp_token: Option<AssetInfo>,
+    /// The maximum pool reserves after a provide, ordered like the pool assets
+    #[serde(default)]
+    pub reserve_cap: Option<[Uint128; 2]>,
 }
 
 /// Stores the config struct at the given key
 pub const CONFIG: Item<Config> = Item::new("config");
 
+/// Whether the native LP token created at instantiation exists. It is set to false at
+/// instantiation and to true in the reply to the denom creation, pairs without it are ready.
+pub const LP_TOKEN_READY: Item<bool> = Item::new("lp_token_ready");
+
+/// The native LP supplies expected after each LP mint of the current transaction, in the order
+/// of the mints. Every mint reply pops and checks the first one.
+pub const PENDING_LP_MINTS: Item<Vec<Uint128>> = Item::new("pending_lp_mints");
+
+/// The operators allowed to provide liquidity from an owner's CW20 allowance, keyed by
+/// (owner, operator)
+pub const PROVIDE_OPERATORS: Map<(&Addr, &Addr), Empty> = Map::new("provide_operators");
+
+/// The pool reserves before a native LP token migration. The denom creation fee must be paid
+/// with the funds sent along, so the reserves can't be lower once the denom is created.
+pub const MIGRATION_RESERVES: Item<Vec<Uint128>> = Item::new("migration_reserves");
+
 /// Keeps track of the lp_token for each lp_provider, this is used to calculate the emission rewards
 pub const LP_PROVIDERS: Map<&Addr, Uint128> = Map::new("lp_providers");
+
+/// The LP tokens a provider tracks for another emissions beneficiary, keyed by
+/// (provider, beneficiary). Withdrawals untrack them before the provider's own LP tokens
+pub const DELEGATED_LP: Map<(&Addr, &Addr), Uint128> = Map::new("delegated_lp");
+
+/// Swap commission already turned into protocol-owned liquidity by `CompoundFees` or paid out
+/// to LPs with their withdrawals, keyed by the asset it was charged in
+pub const COMPOUNDED_FEES: Map<String, Uint128> = Map::new("compounded_fees");
+
+/// Returns the commission collected in each of `asset_infos` that was not compounded yet.
+pub fn read_uncompounded_fees(
+    storage: &dyn Storage,
+    asset_infos: &[AssetInfo],
+) -> StdResult<Vec<Asset>> {
+    read_cumulative_fees(storage, asset_infos)?
+        .into_iter()
+        .map(|fees| {
+            let compounded = COMPOUNDED_FEES
+                .may_load(storage, fees.info.to_string())?
+                .unwrap_or_default();
+            Ok(fees.info.with_balance(fees.amount.checked_sub(compounded)?))
+        })
+        .collect()
+}
+
+/// Adds `asset.amount` to the commission compounded in `asset.info`.
+pub fn record_compounded_fees(storage: &mut dyn Storage, asset: &Asset) -> StdResult<()> {
+    COMPOUNDED_FEES.update(storage, asset.info.to_string(), |fees| -> StdResult<_> {
+        Ok(fees.unwrap_or_default().checked_add(asset.amount)?)
+    })?;
+
+    Ok(())
+}
+
+/// Settles the share of the uncompounded commission paid out with a withdrawal of `amount` out
+/// of `total_share` LP tokens, so that it is not compounded anymore.
+pub fn settle_withdrawn_fees(
+    storage: &mut dyn Storage,
+    asset_infos: &[AssetInfo],
+    amount: Uint128,
+    total_share: Uint128,
+) -> StdResult<()> {
+    if total_share.is_zero() {
+        return Ok(());
+    }
+    for fees in read_uncompounded_fees(storage, asset_infos)? {
+        let withdrawn = fees.amount.multiply_ratio(amount, total_share);
+        record_compounded_fees(storage, &fees.info.with_balance(withdrawn))?;
+    }
+
+    Ok(())
+}
+
+/// Reads LP providers and their tracked LP amounts from [`LP_PROVIDERS`], ordered by address.
+///
+/// `start_after` is the provider after which the function starts to fetch results.
+///
+/// `limit` is the number of items to retrieve.
+pub fn read_lp_providers(
+    storage: &dyn Storage,
+    start_after: Option<&Addr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(Addr, Uint128)>> {
+    let limit = clamp_limit(limit);
+
+    LP_PROVIDERS
+        .range(
+            storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect()
+}
+
+/// Untracks `amount` LP tokens withdrawn by `provider` for emission calculations.
+///
+/// A withdrawal for another `beneficiary` only untracks the LP tokens the provider tracks for
+/// it, the caller checks that they cover `amount`. Otherwise the LP tokens the provider tracks
+/// for other beneficiaries are untracked first and the rest from the provider's own, so LP
+/// tokens provided for a beneficiary can't leave the pool while still tracked for it.
+///
+/// Returns every address whose tracked LP tokens dropped with the amount tracked before.
+pub fn untrack_lp_provider(
+    storage: &mut dyn Storage,
+    provider: &Addr,
+    beneficiary: &Addr,
+    amount: Uint128,
+) -> StdResult<Vec<(Addr, Uint128)>> {
+    let delegations = if beneficiary == provider {
+        DELEGATED_LP
+            .prefix(provider)
+            .range(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?
+    } else {
+        vec![(
+            beneficiary.clone(),
+            DELEGATED_LP
+                .may_load(storage, (provider, beneficiary))?
+                .unwrap_or_default(),
+        )]
+    };
+
+    let mut untracked = vec![];
+    let mut remaining = amount;
+    for (delegate, delegated) in delegations {
+        let drawn = delegated.min(remaining);
+        if drawn.is_zero() {
+            continue;
+        }
+        if drawn == delegated {
+            DELEGATED_LP.remove(storage, (provider, &delegate));
+        } else {
+            DELEGATED_LP.save(storage, (provider, &delegate), &(delegated - drawn))?;
+        }
+        let previous_amount = untrack_lp(storage, &delegate, drawn)?;
+        untracked.push((delegate, previous_amount));
+        remaining -= drawn;
+    }
+    if beneficiary == provider && !remaining.is_zero() {
+        let previous_amount = untrack_lp(storage, provider, remaining)?;
+        untracked.push((provider.clone(), previous_amount));
+    }
+
+    Ok(untracked)
+}
+
+/// Subtracts `amount` from the LP tokens tracked for `address` and returns the amount tracked
+/// before. LP tokens received by a transfer were never tracked, so the tracking stops at zero.
+fn untrack_lp(storage: &mut dyn Storage, address: &Addr, amount: Uint128) -> StdResult<Uint128> {
+    let previous_amount = LP_PROVIDERS.may_load(storage, address)?.unwrap_or_default();
+    LP_PROVIDERS.save(storage, address, &previous_amount.saturating_sub(amount))?;
+
+    Ok(previous_amount)
+}
@@ -1,28 +1,45 @@
 use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    attr, to_json_binary, Addr, BankMsg, BlockInfo, Coin, CosmosMsg, Decimal, DepsMut, Env, Reply,
-    ReplyOn, Response, StdError, SubMsg, SubMsgResponse, SubMsgResult, Timestamp, Uint128, WasmMsg,
+    attr, coin, from_json, to_json_binary, Addr, BankMsg, BlockInfo, Coin, CosmosMsg, Decimal,
+    Deps, DepsMut, Env, Reply, ReplyOn, Response, StdError, Storage, SubMsg, SubMsgResponse,
+    SubMsgResult, Timestamp, Uint128, WasmMsg,
 };
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, MinterResponse};
 use proptest::prelude::*;
 
+#[cfg(not(feature = "no-emissions"))]
+use ura::contracts::controller::{AccumEmissionsRequest, ExecuteMsg as ControllerExecuteMsg};
+use ura::contracts::factory::ExecuteMsg as FactoryExecuteMsg;
+#[cfg(not(feature = "no-emissions"))]
+use ura::contracts::pair::LiquidityProvision;
 use ura::contracts::pair::{
-    Cw20HookMsg, ExecuteMsg, InstantiateMsg, PoolResponse, ReverseSimulationResponse,
-    SimulationResponse,
+    CanExecuteResponse, ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, PairAction,
+    PoolResponse, QueryMsg, ReverseSimulationResponse, SimulateProvideResponse, SimulationResponse,
+    SwapHookExecuteMsg, WithdrawPreviewResponse, XykPoolParams, XykPoolUpdateParams,
+    MINIMUM_LIQUIDITY_AMOUNT,
 };
 use ura::contracts::token::InstantiateMsg as TokenInstantiateMsg;
+use ura::errors::DexError;
 use ura::structs::asset::Asset;
-use ura::structs::asset_info::AssetInfo;
+use ura::structs::asset_info::{AssetInfo, AssetInfoExt};
 use ura_mock::querier::mock_dependencies;
+#[cfg(not(feature = "no-emissions"))]
+use ura_mock::querier::WasmMockQuerier;
 
-use crate::contract::compute_offer_amount;
 use crate::contract::reply;
 use crate::contract::{
-    assert_max_spread, compute_swap, execute, instantiate, query_pool, query_reverse_simulation,
-    query_share, query_simulation,
+    apply_min_commission, assert_max_spread, compute_auto_balance_swap, compute_swap,
+    compute_swap_to_price, execute, get_share_in_assets, instantiate, query, query_all_lp_received,
+    query_max_swap_within_slippage, query_pool, query_price_impact, query_quote_both,
+    query_reverse_simulation, query_share, query_simulate_provide, query_simulation,
+    query_swap_rate, query_withdraw_preview,
 };
+use crate::contract::{compute_offer_amount, compute_offer_amount_with_min_commission};
+use crate::denom::{DenomUnit, Metadata, MsgSetDenomMetadata};
 use crate::error::ContractError;
-use crate::state::CONFIG;
+#[cfg(not(feature = "no-emissions"))]
+use crate::state::read_uncompounded_fees;
+use crate::state::{CONFIG, DELEGATED_LP, LP_PROVIDERS};
 
 use prost::Message;
 
@@ -56,6 +73,61 @@ fn store_liquidity_token(deps: DepsMut, msg_id: u64, contract_addr: String) {
     let _res = reply(deps, mock_env(), reply_msg.clone()).unwrap();
 }
 
+#[test]
+fn immutable_lp_token_has_no_admin() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(123u128))],
+    )]);
+
+    let msg = InstantiateMsg {
+        factory_addr: String::from("factory"),
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: true,
+        init_params: None,
+    };
+    let res = instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    assert!(matches!(
+        &res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Instantiate { admin: None, .. })
+    ));
+}
+
+#[test]
+fn instantiate_rejects_case_variants_of_the_same_denom() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        factory_addr: String::from("factory"),
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2"
+                    .to_string(),
+            },
+            AssetInfo::NativeToken {
+                denom: "ibc/27394fb092d2eccd56123c74f36e4c1f926001ceada9ca97ea622b25f41e5eb2"
+                    .to_string(),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        init_params: None,
+    };
+    let err = instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::DoublingAssets {}));
+}
+
 #[test]
 fn proper_initialization() {
     let mut deps = mock_dependencies(&vec![]);
@@ -76,6 +148,8 @@ fn proper_initialization() {
             },
         ],
         token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
         init_params: None,
     };
 
@@ -102,7 +176,7 @@ fn proper_initialization() {
                 })
                 .unwrap(),
                 funds: vec![],
-                admin: None,
+                admin: Some(String::from("owner")),
                 label: String::from("Ura LP Token"),
             }
             .into(),
@@ -164,6 +238,8 @@ fn provide_liquidity() {
             },
         ],
         token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
         factory_addr: String::from("factory"),
         init_params: None,
     };
@@ -194,6 +270,8 @@ fn provide_liquidity() {
         ],
         slippage_tolerance: None,
         receiver: None,
+        owner: None,
+        beneficiary: None,
     };
 
     let env = mock_env();
@@ -308,6 +386,8 @@ fn provide_liquidity() {
         ],
         slippage_tolerance: Some(Decimal::percent(50)),
         receiver: None,
+        owner: None,
+        beneficiary: None,
     };
 
     let env = mock_env_with_block_time(env.block.time.seconds() + 1000);
@@ -379,6 +459,8 @@ fn provide_liquidity() {
         ],
         slippage_tolerance: None,
         receiver: None,
+        owner: None,
+        beneficiary: None,
     };
 
     let env = mock_env();
@@ -441,6 +523,8 @@ fn provide_liquidity() {
         ],
         slippage_tolerance: Some(Decimal::percent(1)),
         receiver: None,
+        owner: None,
+        beneficiary: None,
     };
 
     let env = mock_env_with_block_time(env.block.time.seconds() + 1000);
@@ -481,6 +565,8 @@ fn provide_liquidity() {
         ],
         slippage_tolerance: Some(Decimal::percent(1)),
         receiver: None,
+        owner: None,
+        beneficiary: None,
     };
 
     let env = mock_env_with_block_time(env.block.time.seconds() + 1000);
@@ -521,6 +607,8 @@ fn provide_liquidity() {
         ],
         slippage_tolerance: Some(Decimal::percent(1)),
         receiver: None,
+        owner: None,
+        beneficiary: None,
     };
 
     let env = mock_env_with_block_time(env.block.time.seconds() + 1000);
@@ -560,6 +648,8 @@ fn provide_liquidity() {
         ],
         slippage_tolerance: Some(Decimal::percent(1)),
         receiver: None,
+        owner: None,
+        beneficiary: None,
     };
 
     let env = mock_env_with_block_time(env.block.time.seconds() + 1000);
@@ -589,6 +679,8 @@ fn provide_liquidity() {
         ],
         slippage_tolerance: Some(Decimal::percent(1)),
         receiver: None,
+        owner: None,
+        beneficiary: None,
     };
     let info = mock_info(
         "addr0001",
@@ -598,27 +690,86 @@ fn provide_liquidity() {
         }],
     );
     let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-    assert_eq!(err, ContractError::InvalidZeroAmount {});
+    assert_eq!(err, ContractError::Dex(DexError::InvalidZeroAmount {}));
 }
 
 #[test]
-fn withdraw_liquidity() {
+fn provide_liquidity_rejects_stray_funds_in_token_pool() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(1_000))],
+        ),
+        (
+            &String::from("asset0001"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(1_000))],
+        ),
+    ]);
+
+    let asset_infos = vec![
+        AssetInfo::Token {
+            contract_addr: Addr::unchecked("asset0000"),
+        },
+        AssetInfo::Token {
+            contract_addr: Addr::unchecked("asset0001"),
+        },
+    ];
+    let msg = InstantiateMsg {
+        asset_infos: asset_infos.clone(),
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    let msg = ExecuteMsg::ProvideLiquidity {
+        assets: vec![
+            asset_infos[0].with_balance(100u128),
+            asset_infos[1].with_balance(100u128),
+        ],
+        slippage_tolerance: None,
+        receiver: None,
+        owner: None,
+        beneficiary: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(1),
+        }],
+    );
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Dex(DexError::ExtraFundsSent {
+            denom: "uluna".to_string()
+        })
+    );
+}
+
+#[test]
+fn provide_liquidity_launch_lp_cap() {
     let mut deps = mock_dependencies(&[Coin {
         denom: "uusd".to_string(),
-        amount: Uint128::new(100u128),
+        amount: Uint128::new(100_000),
     }]);
-
     deps.querier.with_token_balances(&[
         (
-            &String::from("liquidity0000"),
-            &[(&String::from("addr0000"), &Uint128::new(100u128))],
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(0))],
         ),
         (
-            &String::from("asset0000"),
-            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(100u128))],
+            &String::from("liquidity0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(0))],
         ),
     ]);
 
+    let launch_end_time = mock_env().block.time.seconds() + 100;
     let msg = InstantiateMsg {
         asset_infos: vec![
             AssetInfo::NativeToken {
@@ -630,113 +781,112 @@ fn withdraw_liquidity() {
         ],
         token_code_id: Some(10u64),
         factory_addr: String::from("factory"),
-        init_params: None,
+        init_params: Some(
+            to_json_binary(&XykPoolParams {
+                launch_end_time: Some(launch_end_time),
+                max_lp_per_address: Some(Uint128::new(150_000)),
+                ..Default::default()
+            })
+            .unwrap(),
+        ),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
     };
-
-    let env = mock_env();
-    let info = mock_info("addr0000", &[]);
-    // We can just call .unwrap() to assert this was a success
-    let _res = instantiate(deps.as_mut(), env, info, msg).unwrap();
-
-    // Store liquidity token
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
     store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
 
-    // Withdraw liquidity
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: String::from("addr0000"),
-        msg: to_json_binary(&Cw20HookMsg::WithdrawLiquidity {}).unwrap(),
-        amount: Uint128::new(100u128),
-    });
-
-    let env = mock_env();
-    let info = mock_info("liquidity0000", &[]);
-    let res = execute(deps.as_mut(), env, info, msg).unwrap();
-    let log_withdrawn_share = res.attributes.get(2).expect("no log");
-    let log_refund_assets = res.attributes.get(3).expect("no log");
-    let msg_refund_0 = res.messages.get(0).expect("no message");
-    let msg_refund_1 = res.messages.get(1).expect("no message");
-    let msg_burn_liquidity = res.messages.get(2).expect("no message");
-    assert_eq!(
-        msg_refund_0,
-        &SubMsg {
-            msg: CosmosMsg::Bank(BankMsg::Send {
-                to_address: String::from("addr0000"),
-                amount: vec![Coin {
+    let provide = || {
+        (
+            ExecuteMsg::ProvideLiquidity {
+                assets: vec![
+                    Asset {
+                        info: AssetInfo::Token {
+                            contract_addr: Addr::unchecked("asset0000"),
+                        },
+                        amount: Uint128::new(100_000),
+                    },
+                    Asset {
+                        info: AssetInfo::NativeToken {
+                            denom: "uusd".to_string(),
+                        },
+                        amount: Uint128::new(100_000),
+                    },
+                ],
+                slippage_tolerance: None,
+                receiver: None,
+                owner: None,
+                beneficiary: None,
+            },
+            mock_info(
+                "addr0000",
+                &[Coin {
                     denom: "uusd".to_string(),
-                    amount: Uint128::from(100u128),
+                    amount: Uint128::new(100_000),
                 }],
-            }),
-            id: 0,
-            gas_limit: None,
-            reply_on: ReplyOn::Never,
-        }
-    );
-    assert_eq!(
-        msg_refund_1,
-        &SubMsg {
-            msg: WasmMsg::Execute {
-                contract_addr: String::from("asset0000"),
-                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
-                    recipient: String::from("addr0000"),
-                    amount: Uint128::from(100u128),
-                })
-                .unwrap(),
-                funds: vec![],
-            }
-            .into(),
-            id: 0,
-            gas_limit: None,
-            reply_on: ReplyOn::Never,
-        }
-    );
+            ),
+        )
+    };
+
+    // The first provide mints 99_000 LP tokens, which is below the cap
+    let (msg, info) = provide();
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    deps.querier.with_balance(&[(
+        &String::from(MOCK_CONTRACT_ADDR),
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(200_000),
+        }],
+    )]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(100_000))],
+        ),
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(100_000))],
+        ),
+    ]);
+
+    // Another 100_000 LP tokens would go over the cap during the launch window
+    let (msg, info) = provide();
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
     assert_eq!(
-        msg_burn_liquidity,
-        &SubMsg {
-            msg: WasmMsg::Execute {
-                contract_addr: String::from("liquidity0000"),
-                msg: to_json_binary(&Cw20ExecuteMsg::Burn {
-                    amount: Uint128::from(100u128),
-                })
-                .unwrap(),
-                funds: vec![],
-            }
-            .into(),
-            id: 0,
-            gas_limit: None,
-            reply_on: ReplyOn::Never,
+        err,
+        ContractError::LaunchLpCapExceeded {
+            cap: Uint128::new(150_000)
         }
     );
 
-    assert_eq!(
-        log_withdrawn_share,
-        &attr("withdrawn_share", 100u128.to_string())
-    );
-    assert_eq!(
-        log_refund_assets,
-        &attr("refund_assets", "100uusd, 100asset0000")
-    );
+    // Once the window is over no cap applies
+    let (msg, info) = provide();
+    let res = execute(
+        deps.as_mut(),
+        mock_env_with_block_time(launch_end_time),
+        info,
+        msg,
+    )
+    .unwrap();
+    assert!(res
+        .attributes
+        .contains(&attr("share", Uint128::new(100_000).to_string())));
 }
 
 #[test]
-fn try_native_to_token() {
-    let total_share = Uint128::new(30000000000u128);
-    let asset_pool_amount = Uint128::new(20000000000u128);
-    let collateral_pool_amount = Uint128::new(30000000000u128);
-    let offer_amount = Uint128::new(1500000000u128);
-
+fn provide_liquidity_reserve_cap() {
     let mut deps = mock_dependencies(&[Coin {
         denom: "uusd".to_string(),
-        amount: collateral_pool_amount + offer_amount, /* user deposit must be pre-applied */
+        amount: Uint128::new(100_000),
     }]);
-
     deps.querier.with_token_balances(&[
         (
-            &String::from("liquidity0000"),
-            &[(&String::from(MOCK_CONTRACT_ADDR), &total_share)],
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(0))],
         ),
         (
-            &String::from("asset0000"),
-            &[(&String::from(MOCK_CONTRACT_ADDR), &asset_pool_amount)],
+            &String::from("liquidity0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(0))],
         ),
     ]);
 
@@ -752,204 +902,338 @@ fn try_native_to_token() {
         token_code_id: Some(10u64),
         factory_addr: String::from("factory"),
         init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
     };
-
-    let env = mock_env();
-    let info = mock_info("addr0000", &[]);
-    // we can just call .unwrap() to assert this was a success
-    let _res = instantiate(deps.as_mut(), env, info, msg).unwrap();
-
-    // Store liquidity token
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
     store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
 
-    // Normal swap
-    let msg = ExecuteMsg::Swap {
-        offer_asset: Asset {
-            info: AssetInfo::NativeToken {
-                denom: "uusd".to_string(),
-            },
-            amount: offer_amount,
-        },
-        ask_asset_info: None,
-        belief_price: None,
-        max_spread: Some(Decimal::percent(50)),
-        to: None,
+    // Only the factory owner sets the cap
+    let reserve_cap = [Uint128::new(150_000), Uint128::new(150_000)];
+    let msg = ExecuteMsg::UpdateConfig {
+        params: to_json_binary(&XykPoolUpdateParams::SetReserveCap {
+            reserve_cap: Some(reserve_cap),
+        })
+        .unwrap(),
     };
-    let env = mock_env_with_block_time(1000);
-    let info = mock_info(
-        "addr0000",
-        &[Coin {
-            denom: "uusd".to_string(),
-            amount: offer_amount,
-        }],
-    );
-
-    let res = execute(deps.as_mut(), env, info, msg).unwrap();
-    let msg_transfer = res.messages.get(0).expect("no message");
-
-    // Current price is 1.5, so expected return without spread is 1000
-    // 952380952 = 20000000000 - (30000000000 * 20000000000) / (30000000000 + 1500000000)
-    let expected_ret_amount = Uint128::new(952_380_952u128);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::Unauthorized {}));
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
 
-    // 47619047 = 1500000000 * (20000000000 / 30000000000) - 952380952
-    let expected_spread_amount = Uint128::new(47619047u128);
+    let res: ConfigResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    let params: XykPoolParams = from_json(res.params.unwrap()).unwrap();
+    assert_eq!(params.reserve_cap, Some(reserve_cap));
 
-    let expected_commission_amount = expected_ret_amount.multiply_ratio(3u128, 1000u128); // 0.3%
+    let provide = || {
+        (
+            ExecuteMsg::ProvideLiquidity {
+                assets: vec![
+                    Asset {
+                        info: AssetInfo::Token {
+                            contract_addr: Addr::unchecked("asset0000"),
+                        },
+                        amount: Uint128::new(100_000),
+                    },
+                    Asset {
+                        info: AssetInfo::NativeToken {
+                            denom: "uusd".to_string(),
+                        },
+                        amount: Uint128::new(100_000),
+                    },
+                ],
+                slippage_tolerance: None,
+                receiver: None,
+                owner: None,
+                beneficiary: None,
+            },
+            mock_info(
+                "addr0000",
+                &[Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::new(100_000),
+                }],
+            ),
+        )
+    };
 
-    let expected_return_amount = expected_ret_amount
-        .checked_sub(expected_commission_amount)
-        .unwrap();
+    // The first provide leaves 100_000 of each asset in the pool, which is below the cap
+    let (msg, info) = provide();
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    // Check simulation result
     deps.querier.with_balance(&[(
         &String::from(MOCK_CONTRACT_ADDR),
         &[Coin {
             denom: "uusd".to_string(),
-            amount: collateral_pool_amount, /* user deposit must be pre-applied */
+            amount: Uint128::new(200_000),
         }],
     )]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(100_000))],
+        ),
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(100_000))],
+        ),
+    ]);
 
-    let env = mock_env_with_block_time(1000);
-    let err = query_simulation(
-        deps.as_ref(),
-        env,
-        Asset {
-            info: AssetInfo::NativeToken {
-                denom: "cny".to_string(),
-            },
-            amount: offer_amount,
-        },
-    )
-    .unwrap_err();
-    assert_eq!(
-        err.to_string(),
-        "Generic error: Given offer asset does not belong in the pair"
-    );
-
-    let env = mock_env_with_block_time(1000);
-    let simulation_res: SimulationResponse = query_simulation(
-        deps.as_ref(),
-        env,
-        Asset {
-            info: AssetInfo::NativeToken {
+    // Another 100_000 of each asset would take the reserves over the cap
+    let (msg, info) = provide();
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(err, ContractError::ReserveCapExceeded {});
+
+    // Without the cap the provide goes through
+    let msg = ExecuteMsg::UpdateConfig {
+        params: to_json_binary(&XykPoolUpdateParams::SetReserveCap { reserve_cap: None }).unwrap(),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    let (msg, info) = provide();
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+}
+
+#[test]
+fn can_execute_reports_the_launch_lp_cap() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(0))],
+    )]);
+    let launch_end_time = mock_env().block.time.seconds() + 100;
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
                 denom: "uusd".to_string(),
             },
-            amount: offer_amount,
-        },
-    )
-    .unwrap();
-    assert_eq!(expected_return_amount, simulation_res.return_amount);
-    assert_eq!(expected_commission_amount, simulation_res.commission_amount);
-    assert_eq!(expected_spread_amount, simulation_res.spread_amount);
-
-    let env = mock_env_with_block_time(1000);
-    // Check reverse simulation result
-    let err = query_reverse_simulation(
-        deps.as_ref(),
-        env,
-        Asset {
-            info: AssetInfo::NativeToken {
-                denom: "cny".to_string(),
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
             },
-            amount: expected_return_amount,
-        },
-    )
-    .unwrap_err();
+        ],
+        token_code_id: Some(10u64),
+        factory_addr: String::from("factory"),
+        init_params: Some(
+            to_json_binary(&XykPoolParams {
+                launch_end_time: Some(launch_end_time),
+                max_lp_per_address: Some(Uint128::new(150_000)),
+                ..Default::default()
+            })
+            .unwrap(),
+        ),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let can_execute = |deps: Deps, env: Env, action: PairAction| -> CanExecuteResponse {
+        let msg = QueryMsg::CanExecute {
+            address: "addr0000".to_string(),
+            action,
+        };
+        from_json(query(deps, env, msg).unwrap()).unwrap()
+    };
+    let allowed = CanExecuteResponse {
+        allowed: true,
+        reason: None,
+    };
+
+    // Below the cap every action is allowed
+    LP_PROVIDERS
+        .save(
+            deps.as_mut().storage,
+            &Addr::unchecked("addr0000"),
+            &Uint128::new(149_999),
+        )
+        .unwrap();
+    for action in [PairAction::Swap, PairAction::Provide, PairAction::Withdraw] {
+        assert_eq!(can_execute(deps.as_ref(), mock_env(), action), allowed);
+    }
+
+    // At the cap the address can't provide until the launch window is over
+    LP_PROVIDERS
+        .save(
+            deps.as_mut().storage,
+            &Addr::unchecked("addr0000"),
+            &Uint128::new(150_000),
+        )
+        .unwrap();
     assert_eq!(
-        err.to_string(),
-        "Generic error: Given ask asset doesn't belong to pairs"
+        can_execute(deps.as_ref(), mock_env(), PairAction::Provide),
+        CanExecuteResponse {
+            allowed: false,
+            reason: Some(
+                ContractError::LaunchLpCapExceeded {
+                    cap: Uint128::new(150_000)
+                }
+                .to_string()
+            ),
+        }
     );
-
-    let env = mock_env_with_block_time(1000);
-    let reverse_simulation_res: ReverseSimulationResponse = query_reverse_simulation(
-        deps.as_ref(),
-        env,
-        Asset {
-            info: AssetInfo::Token {
-                contract_addr: Addr::unchecked("asset0000"),
-            },
-            amount: expected_return_amount,
-        },
-    )
-    .unwrap();
     assert_eq!(
-        (offer_amount.u128() as i128 - reverse_simulation_res.offer_amount.u128() as i128).abs()
-            < 5i128,
-        true
+        can_execute(deps.as_ref(), mock_env(), PairAction::Swap),
+        allowed
     );
     assert_eq!(
-        (expected_commission_amount.u128() as i128
-            - reverse_simulation_res.commission_amount.u128() as i128)
-            .abs()
-            < 5i128,
-        true
+        can_execute(deps.as_ref(), mock_env(), PairAction::Withdraw),
+        allowed
     );
     assert_eq!(
-        (expected_spread_amount.u128() as i128
-            - reverse_simulation_res.spread_amount.u128() as i128)
-            .abs()
-            < 5i128,
-        true
+        can_execute(
+            deps.as_ref(),
+            mock_env_with_block_time(launch_end_time),
+            PairAction::Provide
+        ),
+        allowed
     );
 
-    assert_eq!(
-        res.attributes,
+    // Other addresses are unaffected
+    let msg = QueryMsg::CanExecute {
+        address: "addr0001".to_string(),
+        action: PairAction::Provide,
+    };
+    let res: CanExecuteResponse =
+        from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res, allowed);
+}
+
+#[test]
+fn simulate_provide_matches_provide_liquidity() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(0))],
+        ),
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(0))],
+        ),
+    ]);
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let token = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![uusd.clone(), token.clone()],
+        token_code_id: Some(10u64),
+        factory_addr: String::from("factory"),
+        init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    let assets = |uusd_amount: u128, token_amount: u128| {
         vec![
-            attr("action", "swap"),
-            attr("sender", "addr0000"),
-            attr("receiver", "addr0000"),
-            attr("offer_asset", "uusd"),
-            attr("ask_asset", "asset0000"),
-            attr("offer_amount", offer_amount.to_string()),
-            attr("return_amount", expected_return_amount.to_string()),
-            attr("spread_amount", expected_spread_amount.to_string()),
-            attr("commission_amount", expected_commission_amount.to_string()),
-            attr("gauge_fee_amount", "0"),
+            uusd.with_balance(uusd_amount),
+            token.with_balance(token_amount),
         ]
+    };
+    let provide = |deps: DepsMut, uusd_amount: u128, token_amount: u128| -> String {
+        let res = execute(
+            deps,
+            mock_env(),
+            mock_info(
+                "addr0000",
+                &[Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::new(uusd_amount),
+                }],
+            ),
+            ExecuteMsg::ProvideLiquidity {
+                assets: assets(uusd_amount, token_amount),
+                slippage_tolerance: Some(Decimal::one()),
+                receiver: None,
+                owner: None,
+                beneficiary: None,
+            },
+        )
+        .unwrap();
+        res.attributes
+            .into_iter()
+            .find(|attr| attr.key == "share")
+            .unwrap()
+            .value
+    };
+
+    // The first deposit locks the minimum liquidity amount
+    let preview = query_simulate_provide(deps.as_ref(), assets(1_000_000, 1_000_000)).unwrap();
+    assert_eq!(
+        preview,
+        SimulateProvideResponse {
+            share: Uint128::new(1_000_000) - MINIMUM_LIQUIDITY_AMOUNT,
+            refund: vec![],
+        }
+    );
+    deps.querier.with_balance(&[(
+        &String::from(MOCK_CONTRACT_ADDR),
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(1_000_000),
+        }],
+    )]);
+    assert_eq!(
+        provide(deps.as_mut(), 1_000_000, 1_000_000),
+        preview.share.to_string()
     );
 
+    // An imbalanced deposit mints for the smaller side and reports the excess
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(1_000_000))],
+        ),
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from("addr0000"), &Uint128::new(1_000_000))],
+        ),
+    ]);
+    let preview = query_simulate_provide(deps.as_ref(), assets(100_000, 300_000)).unwrap();
     assert_eq!(
-        &SubMsg {
-            msg: WasmMsg::Execute {
-                contract_addr: String::from("asset0000"),
-                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
-                    recipient: String::from("addr0000"),
-                    amount: Uint128::from(expected_return_amount),
-                })
-                .unwrap(),
-                funds: vec![],
-            }
-            .into(),
-            id: 0,
-            gas_limit: None,
-            reply_on: ReplyOn::Never,
-        },
-        msg_transfer,
+        preview,
+        SimulateProvideResponse {
+            share: Uint128::new(100_000),
+            refund: vec![token.with_balance(200_000u128)],
+        }
+    );
+    deps.querier.with_balance(&[(
+        &String::from(MOCK_CONTRACT_ADDR),
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(1_100_000),
+        }],
+    )]);
+    assert_eq!(
+        provide(deps.as_mut(), 100_000, 300_000),
+        preview.share.to_string()
     );
 }
 
 #[test]
-fn try_token_to_native() {
-    let total_share = Uint128::new(20000000000u128);
-    let asset_pool_amount = Uint128::new(30000000000u128);
-    let collateral_pool_amount = Uint128::new(20000000000u128);
-    let offer_amount = Uint128::new(1500000000u128);
-
+fn provide_liquidity_on_behalf_of_owner() {
     let mut deps = mock_dependencies(&[Coin {
         denom: "uusd".to_string(),
-        amount: collateral_pool_amount,
+        amount: Uint128::new(100_000000u128),
     }]);
+
     deps.querier.with_token_balances(&[
         (
-            &String::from("liquidity0000"),
-            &[(&String::from(MOCK_CONTRACT_ADDR), &total_share)],
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(0))],
         ),
         (
-            &String::from("asset0000"),
-            &[(
-                &String::from(MOCK_CONTRACT_ADDR),
-                &(asset_pool_amount + offer_amount),
-            )],
+            &String::from("liquidity0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(0))],
         ),
     ]);
 
@@ -963,248 +1247,5037 @@ fn try_token_to_native() {
             },
         ],
         token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
         factory_addr: String::from("factory"),
         init_params: None,
     };
-
-    let env = mock_env();
-    let info = mock_info("addr0000", &[]);
-    // We can just call .unwrap() to assert this was a success
-    let _res = instantiate(deps.as_mut(), env, info, msg).unwrap();
-
-    // Store liquidity token
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
     store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
 
-    // Unauthorized access; can not execute swap directly for token swap
-    let msg = ExecuteMsg::Swap {
-        offer_asset: Asset {
-            info: AssetInfo::Token {
-                contract_addr: Addr::unchecked("asset0000"),
-            },
-            amount: offer_amount,
-        },
-        ask_asset_info: None,
-        belief_price: None,
-        max_spread: None,
-        to: None,
+    let provide_msg = |receiver: Option<&str>, slippage_tolerance: Option<Decimal>| {
+        ExecuteMsg::ProvideLiquidity {
+            assets: vec![
+                Asset {
+                    info: AssetInfo::Token {
+                        contract_addr: Addr::unchecked("asset0000"),
+                    },
+                    amount: Uint128::from(100_000000u128),
+                },
+                Asset {
+                    info: AssetInfo::NativeToken {
+                        denom: "uusd".to_string(),
+                    },
+                    amount: Uint128::from(100_000000u128),
+                },
+            ],
+            slippage_tolerance,
+            receiver: receiver.map(String::from),
+            owner: Some("user".to_string()),
+            beneficiary: None,
+        }
     };
-    let env = mock_env_with_block_time(1000);
-    let info = mock_info("addr0000", &[]);
-    let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
-    assert_eq!(res, ContractError::Cw20DirectSwap {});
+    let set_operator = |approved: bool| ExecuteMsg::SetProvideOperator {
+        operator: "router".to_string(),
+        approved,
+    };
+    let router_funds = [Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::from(100_000000u128),
+    }];
 
-    // Normal sell
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: String::from("addr0000"),
-        amount: offer_amount,
-        msg: to_json_binary(&Cw20HookMsg::Swap {
-            ask_asset_info: None,
-            belief_price: None,
-            max_spread: Some(Decimal::percent(50)),
-            to: None,
-        })
-        .unwrap(),
-    });
-    let env = mock_env_with_block_time(1000);
-    let info = mock_info("asset0000", &[]);
+    // The router cannot spend the allowance of a user that did not approve it
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("router", &router_funds),
+        provide_msg(None, Some(Decimal::percent(1))),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::Unauthorized {}));
 
-    let res = execute(deps.as_mut(), env, info, msg).unwrap();
-    let msg_transfer = res.messages.get(0).expect("no message");
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user", &[]),
+        set_operator(true),
+    )
+    .unwrap();
 
-    // Current price is 1.5, so expected return without spread is 1000
-    // 952380952,3809524 = 20000000000 - (30000000000 * 20000000000) / (30000000000 + 1500000000)
-    let expected_ret_amount = Uint128::new(952_380_952u128);
+    // The router cannot spend the user's allowance and keep the LP tokens
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("router", &router_funds),
+        provide_msg(Some("router"), Some(Decimal::percent(1))),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::Unauthorized {}));
 
-    // 47619047 = 1500000000 * (20000000000 / 30000000000) - 952380952,3809524
-    let expected_spread_amount = Uint128::new(47619047u128);
+    // The deposit of the user is protected by a mandatory slippage tolerance
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("router", &router_funds),
+        provide_msg(None, None),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::SlippageToleranceRequired {});
 
-    let expected_commission_amount = expected_ret_amount.multiply_ratio(3u128, 1000u128); // 0.3%
-    let expected_return_amount = expected_ret_amount
-        .checked_sub(expected_commission_amount)
-        .unwrap();
+    // Native funds must still be attached by the sender
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("router", &[]),
+        provide_msg(None, Some(Decimal::percent(1))),
+    )
+    .unwrap_err();
+
+    // The CW20 side is pulled from the user's allowance and LP goes to the user
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("router", &router_funds),
+        provide_msg(None, Some(Decimal::percent(1))),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        WasmMsg::Execute {
+            contract_addr: String::from("asset0000"),
+            msg: to_json_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: String::from("user"),
+                recipient: String::from(MOCK_CONTRACT_ADDR),
+                amount: Uint128::from(100_000000u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }
+        .into()
+    );
+    assert_eq!(
+        res.messages[2].msg,
+        WasmMsg::Execute {
+            contract_addr: String::from("liquidity0000"),
+            msg: to_json_binary(&Cw20ExecuteMsg::Mint {
+                recipient: String::from("user"),
+                amount: Uint128::from(99_999000u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }
+        .into()
+    );
+
+    // A revoked router can't provide for the user anymore
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user", &[]),
+        set_operator(false),
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("router", &router_funds),
+        provide_msg(None, Some(Decimal::percent(1))),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::Unauthorized {}));
+}
+
+#[test]
+fn withdraw_liquidity() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100u128),
+    }]);
 
-    // Check simulation res
-    // Return asset token balance as normal
     deps.querier.with_token_balances(&[
         (
             &String::from("liquidity0000"),
-            &[(&String::from(MOCK_CONTRACT_ADDR), &total_share)],
+            &[(&String::from("addr0000"), &Uint128::new(100u128))],
         ),
         (
             &String::from("asset0000"),
-            &[(&String::from(MOCK_CONTRACT_ADDR), &(asset_pool_amount))],
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(100u128))],
         ),
     ]);
 
-    let env = mock_env_with_block_time(1000);
-    let simulation_res: SimulationResponse = query_simulation(
-        deps.as_ref(),
-        env,
-        Asset {
-            amount: offer_amount,
-            info: AssetInfo::Token {
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
                 contract_addr: Addr::unchecked("asset0000"),
             },
-        },
-    )
-    .unwrap();
-    assert_eq!(expected_return_amount, simulation_res.return_amount);
-    assert_eq!(expected_commission_amount, simulation_res.commission_amount);
-    assert_eq!(expected_spread_amount, simulation_res.spread_amount);
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
 
-    let env = mock_env_with_block_time(1000);
-    // Check reverse simulation result
-    let reverse_simulation_res: ReverseSimulationResponse = query_reverse_simulation(
-        deps.as_ref(),
-        env,
-        Asset {
-            amount: expected_return_amount,
-            info: AssetInfo::NativeToken {
-                denom: "uusd".to_string(),
-            },
-        },
-    )
-    .unwrap();
-    assert_eq!(
-        (offer_amount.u128() as i128 - reverse_simulation_res.offer_amount.u128() as i128).abs()
-            < 5i128,
-        true
-    );
-    assert_eq!(
-        (expected_commission_amount.u128() as i128
-            - reverse_simulation_res.commission_amount.u128() as i128)
-            .abs()
-            < 5i128,
-        true
-    );
-    assert_eq!(
-        (expected_spread_amount.u128() as i128
-            - reverse_simulation_res.spread_amount.u128() as i128)
-            .abs()
-            < 5i128,
-        true
-    );
+    let env = mock_env();
+    let info = mock_info("addr0000", &[]);
+    // We can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), env, info, msg).unwrap();
 
-    assert_eq!(
-        res.attributes,
-        vec![
-            attr("action", "swap"),
-            attr("sender", "addr0000"),
-            attr("receiver", "addr0000"),
-            attr("offer_asset", "asset0000"),
-            attr("ask_asset", "uusd"),
-            attr("offer_amount", offer_amount.to_string()),
-            attr("return_amount", expected_return_amount.to_string()),
-            attr("spread_amount", expected_spread_amount.to_string()),
-            attr("commission_amount", expected_commission_amount.to_string()),
-            attr("gauge_fee_amount", "0"),
-        ]
-    );
+    // Store liquidity token
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    // Withdraw liquidity
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: String::from("addr0000"),
+        msg: to_json_binary(&Cw20HookMsg::WithdrawLiquidity { beneficiary: None }).unwrap(),
+        amount: Uint128::new(100u128),
+    });
 
+    let env = mock_env();
+    let info = mock_info("liquidity0000", &[]);
+    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    let log_withdrawn_share = res.attributes.get(2).expect("no log");
+    let log_refund_assets = res.attributes.get(3).expect("no log");
+    let msg_refund_0 = res.messages.get(0).expect("no message");
+    let msg_refund_1 = res.messages.get(1).expect("no message");
+    let msg_burn_liquidity = res.messages.get(2).expect("no message");
     assert_eq!(
+        msg_refund_0,
         &SubMsg {
             msg: CosmosMsg::Bank(BankMsg::Send {
                 to_address: String::from("addr0000"),
                 amount: vec![Coin {
                     denom: "uusd".to_string(),
-                    amount: expected_return_amount
+                    amount: Uint128::from(100u128),
                 }],
-            })
+            }),
+            id: 0,
+            gas_limit: None,
+            reply_on: ReplyOn::Never,
+        }
+    );
+    assert_eq!(
+        msg_refund_1,
+        &SubMsg {
+            msg: WasmMsg::Execute {
+                contract_addr: String::from("asset0000"),
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: String::from("addr0000"),
+                    amount: Uint128::from(100u128),
+                })
+                .unwrap(),
+                funds: vec![],
+            }
             .into(),
             id: 0,
             gas_limit: None,
             reply_on: ReplyOn::Never,
-        },
-        msg_transfer,
+        }
+    );
+    assert_eq!(
+        msg_burn_liquidity,
+        &SubMsg {
+            msg: WasmMsg::Execute {
+                contract_addr: String::from("liquidity0000"),
+                msg: to_json_binary(&Cw20ExecuteMsg::Burn {
+                    amount: Uint128::from(100u128),
+                })
+                .unwrap(),
+                funds: vec![],
+            }
+            .into(),
+            id: 7,
+            gas_limit: None,
+            reply_on: ReplyOn::Error,
+        }
     );
 
-    // Failed due to trying to swap a non token (specifying an address of a non token contract)
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: String::from("addr0000"),
-        amount: offer_amount,
-        msg: to_json_binary(&Cw20HookMsg::Swap {
-            ask_asset_info: None,
-            belief_price: None,
-            max_spread: None,
-            to: None,
-        })
-        .unwrap(),
-    });
-    let env = mock_env_with_block_time(1000);
-    let info = mock_info("liquidtity0000", &[]);
-    let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
-    assert_eq!(res, ContractError::Unauthorized {});
-}
-
-#[test]
-fn test_max_spread() {
-    assert_max_spread(
-        Some(Decimal::from_ratio(1200u128, 1u128)),
-        Some(Decimal::percent(1)),
-        Uint128::from(1200000000u128),
-        Uint128::from(989999u128),
-        Uint128::zero(),
-    )
-    .unwrap_err();
-
-    assert_max_spread(
-        Some(Decimal::from_ratio(1200u128, 1u128)),
-        Some(Decimal::percent(1)),
-        Uint128::from(1200000000u128),
-        Uint128::from(990000u128),
-        Uint128::zero(),
-    )
-    .unwrap();
-
-    assert_max_spread(
-        None,
-        Some(Decimal::percent(1)),
-        Uint128::zero(),
-        Uint128::from(989999u128),
-        Uint128::from(10001u128),
-    )
-    .unwrap_err();
-
-    assert_max_spread(
-        None,
-        Some(Decimal::percent(1)),
-        Uint128::zero(),
-        Uint128::from(990000u128),
-        Uint128::from(10000u128),
-    )
-    .unwrap();
-
-    assert_max_spread(
-        Some(Decimal::from_ratio(1200u128, 1u128)),
-        Some(Decimal::percent(69)),
-        Uint128::from(1200000000u128),
-        Uint128::from(989999u128),
-        Uint128::zero(),
-    )
-    .unwrap();
+    assert_eq!(
+        log_withdrawn_share,
+        &attr("withdrawn_share", 100u128.to_string())
+    );
+    assert_eq!(
+        log_refund_assets,
+        &attr("refund_assets", "100uusd, 100asset0000")
+    );
+    assert!(!res.attributes.iter().any(|a| a.key == "clamped"));
 }
 
 #[test]
-fn test_query_pool() {
-    let total_share_amount = Uint128::from(111u128);
-    let asset_0_amount = Uint128::from(222u128);
-    let asset_1_amount = Uint128::from(333u128);
+fn withdraw_liquidity_clamps_to_reserves() {
     let mut deps = mock_dependencies(&[Coin {
         denom: "uusd".to_string(),
-        amount: asset_0_amount,
+        amount: Uint128::new(100u128),
     }]);
 
+    // Only 100 LP tokens are reported as minted while 200 are withdrawn, so the
+    // proportional refund is twice the reserves
     deps.querier.with_token_balances(&[
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from("addr0000"), &Uint128::new(100u128))],
+        ),
         (
             &String::from("asset0000"),
-            &[(&String::from(MOCK_CONTRACT_ADDR), &asset_1_amount)],
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(50u128))],
+        ),
+    ]);
+
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: String::from("addr0000"),
+        msg: to_json_binary(&Cw20HookMsg::WithdrawLiquidity { beneficiary: None }).unwrap(),
+        amount: Uint128::new(200u128),
+    });
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("liquidity0000", &[]),
+        msg,
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: String::from("addr0000"),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(100u128),
+            }],
+        })
+    );
+    assert_eq!(
+        res.messages[1].msg,
+        WasmMsg::Execute {
+            contract_addr: String::from("asset0000"),
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: String::from("addr0000"),
+                amount: Uint128::new(50u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        }
+        .into()
+    );
+    assert!(res
+        .attributes
+        .contains(&attr("refund_assets", "100uusd, 50asset0000")));
+    assert!(res.attributes.contains(&attr("clamped", "true")));
+}
+
+#[test]
+fn swap_delivers_the_output_with_a_hook() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_000_000_000 + 1_000_000),
+    }]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("liquidity0000"),
+            &[(
+                &String::from(MOCK_CONTRACT_ADDR),
+                &Uint128::new(1_000_000_000),
+            )],
+        ),
+        (
+            &String::from("asset0000"),
+            &[(
+                &String::from(MOCK_CONTRACT_ADDR),
+                &Uint128::new(1_000_000_000),
+            )],
+        ),
+    ]);
+    deps.querier.with_contracts(&["vault"]);
+
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    let hook = to_json_binary(&"deposit").unwrap();
+    let swap = |to: &str| ExecuteMsg::Swap {
+        offer_asset: Asset {
+            info: AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            amount: Uint128::new(1_000_000),
+        },
+        ask_asset_info: None,
+        belief_price: None,
+        max_spread: None,
+        to: Some(to.to_string()),
+        to_hook: Some(hook.clone()),
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(1_000_000),
+        }],
+    );
+
+    // The hook needs a contract to receive it
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), swap("addr0001")).unwrap_err();
+    assert_eq!(err, ContractError::HookRecipientNotContract {});
+
+    let res = execute(deps.as_mut(), mock_env(), info, swap("vault")).unwrap();
+    let return_amount = res
+        .attributes
+        .iter()
+        .find(|a| a.key == "return_amount")
+        .unwrap()
+        .value
+        .parse::<u128>()
+        .unwrap();
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: String::from("asset0000"),
+            msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                contract: String::from("vault"),
+                amount: Uint128::new(return_amount),
+                msg: hook,
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+}
+
+#[test]
+fn swap_hook_cannot_target_contracts_trusting_the_pair() {
+    let mut deps = mock_dependencies(&[
+        coin(1_000_000_000 + 1, "uusd"),
+        coin(1_000_000_000, "uluna"),
+    ]);
+    deps.querier.with_token_balances(&[(
+        &String::from("liquidity0000"),
+        &[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &Uint128::new(1_000_000_000),
+        )],
+    )]);
+    deps.querier
+        .with_contracts(&["liquidity0000", "factory", "vault"]);
+
+    let msg = InstantiateMsg {
+        asset_infos: vec![AssetInfo::native("uusd"), AssetInfo::native("uluna")],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    // The pair is the minter of its CW20 LP token, a raw hook would mint LP to anyone
+    let mint = to_json_binary(&Cw20ExecuteMsg::Mint {
+        recipient: String::from("attacker"),
+        amount: Uint128::new(1_000_000_000_000),
+    })
+    .unwrap();
+    let swap = |to: &str, amount: u128| ExecuteMsg::Swap {
+        offer_asset: Asset::native("uusd", amount),
+        ask_asset_info: None,
+        belief_price: None,
+        max_spread: Some(Decimal::percent(50)),
+        to: Some(to.to_string()),
+        to_hook: Some(mint.clone()),
+    };
+    let info = mock_info("attacker", &[coin(1, "uusd")]);
+    for recipient in ["liquidity0000", "factory"] {
+        let err = execute(deps.as_mut(), mock_env(), info.clone(), swap(recipient, 1)).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::ForbiddenHookRecipient {
+                recipient: recipient.to_string(),
+            }
+        );
+    }
+
+    // Other contracts get the hook wrapped, never as a message from the pair to execute
+    deps.querier.with_balance(&[(
+        &String::from(MOCK_CONTRACT_ADDR),
+        &[
+            coin(1_000_000_000 + 1_000_000, "uusd"),
+            coin(1_000_000_000, "uluna"),
+        ],
+    )]);
+    let info = mock_info("attacker", &[coin(1_000_000, "uusd")]);
+    let res = execute(deps.as_mut(), mock_env(), info, swap("vault", 1_000_000)).unwrap();
+    let CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr, msg, ..
+    }) = &res.messages[0].msg
+    else {
+        panic!("Unexpected message {:?}", res.messages[0]);
+    };
+    assert_eq!(contract_addr, "vault");
+    let SwapHookExecuteMsg::SwapHook(hook) = from_json(msg).unwrap();
+    assert_eq!(hook.sender, "attacker");
+    assert_eq!(hook.msg, mint);
+}
+
+#[cfg(feature = "no-emissions")]
+#[test]
+fn swap_sends_the_commission_to_the_ask_asset_fee_address() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_000_000_000 + 1_000_000),
+    }]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("liquidity0000"),
+            &[(
+                &String::from(MOCK_CONTRACT_ADDR),
+                &Uint128::new(1_000_000_000),
+            )],
+        ),
+        (
+            &String::from("asset0000"),
+            &[(
+                &String::from(MOCK_CONTRACT_ADDR),
+                &Uint128::new(1_000_000_000),
+            )],
+        ),
+    ]);
+    deps.querier.with_asset_fee_addresses(&[
+        (AssetInfo::native("uusd"), "uusd_fee"),
+        (AssetInfo::cw20_unchecked("asset0000"), "token_fee"),
+    ]);
+
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    let commission_amount = |res: &Response| {
+        res.attributes
+            .iter()
+            .find(|a| a.key == "commission_amount")
+            .unwrap()
+            .value
+            .parse::<u128>()
+            .unwrap()
+    };
+
+    // Selling uusd charges the commission in asset0000
+    let msg = ExecuteMsg::Swap {
+        offer_asset: Asset {
+            info: AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            amount: Uint128::new(1_000_000),
+        },
+        ask_asset_info: None,
+        belief_price: None,
+        max_spread: None,
+        to: None,
+        to_hook: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(1_000_000),
+        }],
+    );
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: String::from("asset0000"),
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: String::from("token_fee"),
+                amount: Uint128::new(commission_amount(&res)),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+
+    // Selling asset0000 charges the commission in uusd
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: String::from("addr0000"),
+        amount: Uint128::new(1_000_000),
+        msg: to_json_binary(&Cw20HookMsg::Swap {
+            ask_asset_info: None,
+            belief_price: None,
+            max_spread: None,
+            to: None,
+        })
+        .unwrap(),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0000", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(BankMsg::Send {
+            to_address: String::from("uusd_fee"),
+            amount: vec![coin(commission_amount(&res), "uusd")],
+        })
+    );
+}
+
+#[cfg(not(feature = "no-emissions"))]
+#[test]
+fn compound_fees_mints_protocol_owned_liquidity() {
+    fn set_balances(querier: &mut WasmMockQuerier, pools: [u128; 2], protocol_lp: u128) {
+        querier.with_token_balances(&[
+            (
+                &String::from("liquidity0000"),
+                &[
+                    (
+                        &String::from(MOCK_CONTRACT_ADDR),
+                        &Uint128::new(1_000_000_000),
+                    ),
+                    (&String::from("owner"), &Uint128::new(protocol_lp)),
+                ],
+            ),
+            (
+                &String::from("asset0000"),
+                &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(pools[1]))][..],
+            ),
+        ]);
+        querier.with_balance(&[(&String::from(MOCK_CONTRACT_ADDR), &[coin(pools[0], "uusd")])]);
+    }
+
+    // Swaps 10_000_000 uusd and returns the pool balances after the swap
+    fn swap_uusd(deps: DepsMut, pools: [u128; 2]) -> [u128; 2] {
+        let msg = ExecuteMsg::Swap {
+            offer_asset: Asset {
+                info: AssetInfo::NativeToken {
+                    denom: "uusd".to_string(),
+                },
+                amount: Uint128::new(10_000_000),
+            },
+            ask_asset_info: None,
+            belief_price: None,
+            max_spread: Some(Decimal::percent(50)),
+            to: None,
+            to_hook: None,
+        };
+        let info = mock_info("addr0000", &[coin(10_000_000, "uusd")]);
+        let res = execute(deps, mock_env(), info, msg).unwrap();
+        let return_amount = res
+            .attributes
+            .iter()
+            .find(|a| a.key == "return_amount")
+            .unwrap()
+            .value
+            .parse::<u128>()
+            .unwrap();
+        [pools[0] + 10_000_000, pools[1] - return_amount]
+    }
+
+    fn compounded_share(res: &Response) -> u128 {
+        res.attributes
+            .iter()
+            .find(|a| a.key == "share")
+            .unwrap()
+            .value
+            .parse::<u128>()
+            .unwrap()
+    }
+
+    let mut deps = mock_dependencies(&[]);
+    set_balances(&mut deps.querier, [1_000_000_000, 1_000_000_000], 0);
+
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    let compound = ExecuteMsg::CompoundFees { max_spread: None };
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        compound.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::InvalidZeroAmount {}));
+
+    // The pool balance already includes the native offer during the swap
+    set_balances(&mut deps.querier, [1_010_000_000, 1_000_000_000], 0);
+    let pools = swap_uusd(deps.as_mut(), [1_000_000_000, 1_000_000_000]);
+    set_balances(&mut deps.querier, pools, 0);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        compound.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::Unauthorized {}));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        compound.clone(),
+    )
+    .unwrap();
+    let first_share = compounded_share(&res);
+    assert!(first_share > 0);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: String::from("liquidity0000"),
+            msg: to_json_binary(&Cw20ExecuteMsg::Mint {
+                recipient: String::from("owner"),
+                amount: Uint128::new(first_share),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+    assert_eq!(
+        LP_PROVIDERS
+            .load(&deps.storage, &Addr::unchecked("owner"))
+            .unwrap(),
+        Uint128::new(first_share)
+    );
+    set_balances(&mut deps.querier, pools, first_share);
+    let first_reserves = query_share(deps.as_ref(), Uint128::new(first_share)).unwrap();
+
+    // The same commission is compounded only once
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        compound.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::InvalidZeroAmount {}));
+
+    set_balances(
+        &mut deps.querier,
+        [pools[0] + 10_000_000, pools[1]],
+        first_share,
+    );
+    let pools = swap_uusd(deps.as_mut(), pools);
+    set_balances(&mut deps.querier, pools, first_share);
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), compound).unwrap();
+    let protocol_lp = first_share + compounded_share(&res);
+    assert_eq!(
+        LP_PROVIDERS
+            .load(&deps.storage, &Addr::unchecked("owner"))
+            .unwrap(),
+        Uint128::new(protocol_lp)
+    );
+    set_balances(&mut deps.querier, pools, protocol_lp);
+
+    // The reserves backing the protocol-owned liquidity grow with every compounding
+    let reserves = query_share(deps.as_ref(), Uint128::new(protocol_lp)).unwrap();
+    assert!(reserves[0].amount > first_reserves[0].amount);
+    assert!(reserves[1].amount > first_reserves[1].amount);
+}
+
+#[cfg(not(feature = "no-emissions"))]
+#[test]
+fn compound_fees_skips_the_commission_withdrawn_by_lps() {
+    let mut deps = mock_dependencies(&[coin(1_010_000_000, "uusd")]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("liquidity0000"),
+            &[(
+                &String::from(MOCK_CONTRACT_ADDR),
+                &Uint128::new(1_000_000_000),
+            )],
+        ),
+        (
+            &String::from("asset0000"),
+            &[(
+                &String::from(MOCK_CONTRACT_ADDR),
+                &Uint128::new(1_000_000_000),
+            )],
+        ),
+    ]);
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let token = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![uusd.clone(), token.clone()],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    let msg = ExecuteMsg::Swap {
+        offer_asset: uusd.with_balance(10_000_000u128),
+        ask_asset_info: None,
+        belief_price: None,
+        max_spread: Some(Decimal::percent(50)),
+        to: None,
+        to_hook: None,
+    };
+    let info = mock_info("addr0000", &[coin(10_000_000, "uusd")]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let attribute = |key: &str| {
+        res.attributes
+            .iter()
+            .find(|a| a.key == key)
+            .unwrap()
+            .value
+            .parse::<u128>()
+            .unwrap()
+    };
+    let (return_amount, commission) = (attribute("return_amount"), attribute("commission_amount"));
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("liquidity0000"),
+            &[(
+                &String::from(MOCK_CONTRACT_ADDR),
+                &Uint128::new(1_000_000_000),
+            )],
+        ),
+        (
+            &String::from("asset0000"),
+            &[(
+                &String::from(MOCK_CONTRACT_ADDR),
+                &Uint128::new(1_000_000_000 - return_amount),
+            )],
+        ),
+    ]);
+
+    // Half of the LP tokens leave the pool along with half of the commission
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: String::from("addr0001"),
+        msg: to_json_binary(&Cw20HookMsg::WithdrawLiquidity { beneficiary: None }).unwrap(),
+        amount: Uint128::new(500_000_000),
+    });
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("liquidity0000", &[]),
+        msg,
+    )
+    .unwrap();
+    let remaining = commission - commission / 2;
+    assert_eq!(
+        read_uncompounded_fees(&deps.storage, &[uusd.clone(), token.clone()]).unwrap(),
+        vec![uusd.with_balance(0u128), token.with_balance(remaining)]
+    );
+
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("liquidity0000"),
+            &[(
+                &String::from(MOCK_CONTRACT_ADDR),
+                &Uint128::new(500_000_000),
+            )],
+        ),
+        (
+            &String::from("asset0000"),
+            &[(
+                &String::from(MOCK_CONTRACT_ADDR),
+                &Uint128::new((1_000_000_000 - return_amount) / 2),
+            )],
+        ),
+    ]);
+    deps.querier.with_balance(&[(
+        &String::from(MOCK_CONTRACT_ADDR),
+        &[coin(505_000_000, "uusd")],
+    )]);
+
+    // Only the commission still in the pool is compounded
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::CompoundFees { max_spread: None },
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes[2],
+        attr(
+            "fees",
+            format!(
+                "{}, {}",
+                uusd.with_balance(0u128),
+                token.with_balance(remaining)
+            )
+        )
+    );
+}
+
+#[test]
+fn try_native_to_token() {
+    let total_share = Uint128::new(30000000000u128);
+    let asset_pool_amount = Uint128::new(20000000000u128);
+    let collateral_pool_amount = Uint128::new(30000000000u128);
+    let offer_amount = Uint128::new(1500000000u128);
+
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: collateral_pool_amount + offer_amount, /* user deposit must be pre-applied */
+    }]);
+
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &total_share)],
+        ),
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &asset_pool_amount)],
+        ),
+    ]);
+
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+
+    let env = mock_env();
+    let info = mock_info("addr0000", &[]);
+    // we can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    // Store liquidity token
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    // Normal swap
+    let msg = ExecuteMsg::Swap {
+        offer_asset: Asset {
+            info: AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            amount: offer_amount,
+        },
+        ask_asset_info: None,
+        belief_price: None,
+        max_spread: Some(Decimal::percent(50)),
+        to: None,
+        to_hook: None,
+    };
+    let env = mock_env_with_block_time(1000);
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: offer_amount,
+        }],
+    );
+
+    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    let msg_transfer = res.messages.get(0).expect("no message");
+
+    // Current price is 1.5, so expected return without spread is 1000
+    // 952380952 = 20000000000 - (30000000000 * 20000000000) / (30000000000 + 1500000000)
+    let expected_ret_amount = Uint128::new(952_380_952u128);
+
+    // 47619047 = 1500000000 * (20000000000 / 30000000000) - 952380952
+    let expected_spread_amount = Uint128::new(47619047u128);
+
+    let expected_commission_amount = expected_ret_amount.multiply_ratio(3u128, 1000u128); // 0.3%
+
+    let expected_return_amount = expected_ret_amount
+        .checked_sub(expected_commission_amount)
+        .unwrap();
+
+    // Check simulation result
+    deps.querier.with_balance(&[(
+        &String::from(MOCK_CONTRACT_ADDR),
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: collateral_pool_amount, /* user deposit must be pre-applied */
+        }],
+    )]);
+
+    let err = query_simulation(
+        deps.as_ref(),
+        Asset {
+            info: AssetInfo::NativeToken {
+                denom: "cny".to_string(),
+            },
+            amount: offer_amount,
+        },
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Given offer asset does not belong in the pair"
+    );
+
+    let simulation_res: SimulationResponse = query_simulation(
+        deps.as_ref(),
+        Asset {
+            info: AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            amount: offer_amount,
+        },
+        None,
+    )
+    .unwrap();
+    assert_eq!(expected_return_amount, simulation_res.return_amount);
+    assert_eq!(expected_commission_amount, simulation_res.commission_amount);
+    assert_eq!(expected_spread_amount, simulation_res.spread_amount);
+
+    // Check reverse simulation result
+    let err = query_reverse_simulation(
+        deps.as_ref(),
+        Asset {
+            info: AssetInfo::NativeToken {
+                denom: "cny".to_string(),
+            },
+            amount: expected_return_amount,
+        },
+        None,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Generic error: Given ask asset doesn't belong to pairs"
+    );
+
+    let reverse_simulation_res: ReverseSimulationResponse = query_reverse_simulation(
+        deps.as_ref(),
+        Asset {
+            info: AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+            amount: expected_return_amount,
+        },
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        (offer_amount.u128() as i128 - reverse_simulation_res.offer_amount.u128() as i128).abs()
+            < 5i128,
+        true
+    );
+    assert_eq!(
+        (expected_commission_amount.u128() as i128
+            - reverse_simulation_res.commission_amount.u128() as i128)
+            .abs()
+            < 5i128,
+        true
+    );
+    assert_eq!(
+        (expected_spread_amount.u128() as i128
+            - reverse_simulation_res.spread_amount.u128() as i128)
+            .abs()
+            < 5i128,
+        true
+    );
+
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "swap"),
+            attr("sender", "addr0000"),
+            attr("receiver", "addr0000"),
+            attr("offer_asset", "uusd"),
+            attr("ask_asset", "asset0000"),
+            attr("offer_amount", offer_amount.to_string()),
+            attr("return_amount", expected_return_amount.to_string()),
+            attr("spread_amount", expected_spread_amount.to_string()),
+            attr("commission_amount", expected_commission_amount.to_string()),
+            attr("gauge_fee_amount", "0"),
+        ]
+    );
+
+    assert_eq!(
+        &SubMsg {
+            msg: WasmMsg::Execute {
+                contract_addr: String::from("asset0000"),
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: String::from("addr0000"),
+                    amount: Uint128::from(expected_return_amount),
+                })
+                .unwrap(),
+                funds: vec![],
+            }
+            .into(),
+            id: 0,
+            gas_limit: None,
+            reply_on: ReplyOn::Never,
+        },
+        msg_transfer,
+    );
+}
+
+#[test]
+fn try_token_to_native() {
+    let total_share = Uint128::new(20000000000u128);
+    let asset_pool_amount = Uint128::new(30000000000u128);
+    let collateral_pool_amount = Uint128::new(20000000000u128);
+    let offer_amount = Uint128::new(1500000000u128);
+
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: collateral_pool_amount,
+    }]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &total_share)],
+        ),
+        (
+            &String::from("asset0000"),
+            &[(
+                &String::from(MOCK_CONTRACT_ADDR),
+                &(asset_pool_amount + offer_amount),
+            )],
+        ),
+    ]);
+
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+
+    let env = mock_env();
+    let info = mock_info("addr0000", &[]);
+    // We can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    // Store liquidity token
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    // Unauthorized access; can not execute swap directly for token swap
+    let msg = ExecuteMsg::Swap {
+        offer_asset: Asset {
+            info: AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+            amount: offer_amount,
+        },
+        ask_asset_info: None,
+        belief_price: None,
+        max_spread: None,
+        to: None,
+        to_hook: None,
+    };
+    let env = mock_env_with_block_time(1000);
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+    assert_eq!(res, ContractError::Dex(DexError::Cw20DirectSwap {}));
+
+    // Normal sell
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: String::from("addr0000"),
+        amount: offer_amount,
+        msg: to_json_binary(&Cw20HookMsg::Swap {
+            ask_asset_info: None,
+            belief_price: None,
+            max_spread: Some(Decimal::percent(50)),
+            to: None,
+        })
+        .unwrap(),
+    });
+    let env = mock_env_with_block_time(1000);
+    let info = mock_info("asset0000", &[]);
+
+    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    let msg_transfer = res.messages.get(0).expect("no message");
+
+    // Current price is 1.5, so expected return without spread is 1000
+    // 952380952,3809524 = 20000000000 - (30000000000 * 20000000000) / (30000000000 + 1500000000)
+    let expected_ret_amount = Uint128::new(952_380_952u128);
+
+    // 47619047 = 1500000000 * (20000000000 / 30000000000) - 952380952,3809524
+    let expected_spread_amount = Uint128::new(47619047u128);
+
+    let expected_commission_amount = expected_ret_amount.multiply_ratio(3u128, 1000u128); // 0.3%
+    let expected_return_amount = expected_ret_amount
+        .checked_sub(expected_commission_amount)
+        .unwrap();
+
+    // Check simulation res
+    // Return asset token balance as normal
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &total_share)],
+        ),
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &(asset_pool_amount))],
+        ),
+    ]);
+
+    let simulation_res: SimulationResponse = query_simulation(
+        deps.as_ref(),
+        Asset {
+            amount: offer_amount,
+            info: AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        },
+        None,
+    )
+    .unwrap();
+    assert_eq!(expected_return_amount, simulation_res.return_amount);
+    assert_eq!(expected_commission_amount, simulation_res.commission_amount);
+    assert_eq!(expected_spread_amount, simulation_res.spread_amount);
+
+    // Check reverse simulation result
+    let reverse_simulation_res: ReverseSimulationResponse = query_reverse_simulation(
+        deps.as_ref(),
+        Asset {
+            amount: expected_return_amount,
+            info: AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+        },
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        (offer_amount.u128() as i128 - reverse_simulation_res.offer_amount.u128() as i128).abs()
+            < 5i128,
+        true
+    );
+    assert_eq!(
+        (expected_commission_amount.u128() as i128
+            - reverse_simulation_res.commission_amount.u128() as i128)
+            .abs()
+            < 5i128,
+        true
+    );
+    assert_eq!(
+        (expected_spread_amount.u128() as i128
+            - reverse_simulation_res.spread_amount.u128() as i128)
+            .abs()
+            < 5i128,
+        true
+    );
+
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "swap"),
+            attr("sender", "addr0000"),
+            attr("receiver", "addr0000"),
+            attr("offer_asset", "asset0000"),
+            attr("ask_asset", "uusd"),
+            attr("offer_amount", offer_amount.to_string()),
+            attr("return_amount", expected_return_amount.to_string()),
+            attr("spread_amount", expected_spread_amount.to_string()),
+            attr("commission_amount", expected_commission_amount.to_string()),
+            attr("gauge_fee_amount", "0"),
+        ]
+    );
+
+    assert_eq!(
+        &SubMsg {
+            msg: CosmosMsg::Bank(BankMsg::Send {
+                to_address: String::from("addr0000"),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: expected_return_amount
+                }],
+            })
+            .into(),
+            id: 0,
+            gas_limit: None,
+            reply_on: ReplyOn::Never,
+        },
+        msg_transfer,
+    );
+
+    // Failed due to trying to swap a non token (specifying an address of a non token contract)
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: String::from("addr0000"),
+        amount: offer_amount,
+        msg: to_json_binary(&Cw20HookMsg::Swap {
+            ask_asset_info: None,
+            belief_price: None,
+            max_spread: None,
+            to: None,
+        })
+        .unwrap(),
+    });
+    let env = mock_env_with_block_time(1000);
+    let info = mock_info("liquidtity0000", &[]);
+    let res = execute(deps.as_mut(), env, info, msg).unwrap_err();
+    assert_eq!(res, ContractError::Dex(DexError::Unauthorized {}));
+}
+
+#[test]
+fn swap_rejects_belief_price_far_off_market() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_000_000_000),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &Uint128::new(1_000_000_000),
+        )],
+    )]);
+
+    let instantiate_msg = |max_belief_price_deviation: Decimal| InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        factory_addr: String::from("factory"),
+        init_params: Some(
+            to_json_binary(&XykPoolParams {
+                max_belief_price_deviation: Some(max_belief_price_deviation),
+                ..Default::default()
+            })
+            .unwrap(),
+        ),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    let info = mock_info("addr0000", &[]);
+    let err = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        instantiate_msg(Decimal::percent(50)),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidBeliefPriceDeviation {});
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        instantiate_msg(Decimal::percent(200)),
+    )
+    .unwrap();
+
+    // The pool holds the offer amount on top of the 1:1 reserves
+    let offer_amount = Uint128::new(1_000);
+    deps.querier.with_balance(&[(
+        &String::from(MOCK_CONTRACT_ADDR),
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(1_000_000_000) + offer_amount,
+        }],
+    )]);
+    let mut swap = |belief_price: Decimal| {
+        let msg = ExecuteMsg::Swap {
+            offer_asset: AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            }
+            .with_balance(offer_amount),
+            ask_asset_info: None,
+            belief_price: Some(belief_price),
+            max_spread: Some(Decimal::one()),
+            to: None,
+            to_hook: None,
+        };
+        let info = mock_info(
+            "addr0000",
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: offer_amount,
+            }],
+        );
+        execute(deps.as_mut(), mock_env(), info, msg)
+    };
+
+    // Within twice the pool price in either direction
+    swap(Decimal::percent(150)).unwrap();
+    swap(Decimal::percent(50)).unwrap();
+
+    assert_eq!(
+        swap(Decimal::percent(201)).unwrap_err(),
+        ContractError::BeliefPriceTooFarOffMarket {}
+    );
+    assert_eq!(
+        swap(Decimal::percent(49)).unwrap_err(),
+        ContractError::BeliefPriceTooFarOffMarket {}
+    );
+}
+
+#[test]
+fn swap_is_checked_against_the_oracle_price() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_000_000_000),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &Uint128::new(1_000_000_000),
+        )],
+    )]);
+
+    let instantiate_msg = |max_oracle_deviation: Option<Decimal>| InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        factory_addr: String::from("factory"),
+        init_params: Some(
+            to_json_binary(&XykPoolParams {
+                oracle_addr: Some("oracle".to_string()),
+                max_oracle_deviation,
+                ..Default::default()
+            })
+            .unwrap(),
+        ),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    let info = mock_info("addr0000", &[]);
+    let err = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        instantiate_msg(None),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidOracleDeviation {});
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        instantiate_msg(Some(Decimal::percent(110))),
+    )
+    .unwrap();
+
+    // The pool holds the offer amount on top of the 1:1 reserves
+    let offer_amount = Uint128::new(1_000);
+    deps.querier.with_balance(&[(
+        &String::from(MOCK_CONTRACT_ADDR),
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(1_000_000_000) + offer_amount,
+        }],
+    )]);
+    let swap = |deps: DepsMut| {
+        let msg = ExecuteMsg::Swap {
+            offer_asset: AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            }
+            .with_balance(offer_amount),
+            ask_asset_info: None,
+            belief_price: None,
+            max_spread: None,
+            to: None,
+            to_hook: None,
+        };
+        let info = mock_info(
+            "addr0000",
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: offer_amount,
+            }],
+        );
+        execute(deps, mock_env(), info, msg)
+    };
+
+    // The oracle agrees with the pool price
+    deps.querier
+        .with_oracle_price("oracle", Decimal::percent(105));
+    swap(deps.as_mut()).unwrap();
+
+    // The pool price is more than 10% below the oracle price
+    deps.querier
+        .with_oracle_price("oracle", Decimal::percent(120));
+    assert_eq!(
+        swap(deps.as_mut()).unwrap_err(),
+        ContractError::OraclePriceDeviationTooHigh {}
+    );
+}
+
+#[test]
+fn test_max_spread() {
+    assert_max_spread(
+        Some(Decimal::from_ratio(1200u128, 1u128)),
+        Some(Decimal::percent(1)),
+        Uint128::from(1200000000u128),
+        Uint128::from(989999u128),
+        Uint128::zero(),
+    )
+    .unwrap_err();
+
+    assert_max_spread(
+        Some(Decimal::from_ratio(1200u128, 1u128)),
+        Some(Decimal::percent(1)),
+        Uint128::from(1200000000u128),
+        Uint128::from(990000u128),
+        Uint128::zero(),
+    )
+    .unwrap();
+
+    assert_max_spread(
+        None,
+        Some(Decimal::percent(1)),
+        Uint128::zero(),
+        Uint128::from(989999u128),
+        Uint128::from(10001u128),
+    )
+    .unwrap_err();
+
+    assert_max_spread(
+        None,
+        Some(Decimal::percent(1)),
+        Uint128::zero(),
+        Uint128::from(990000u128),
+        Uint128::from(10000u128),
+    )
+    .unwrap();
+
+    assert_max_spread(
+        Some(Decimal::from_ratio(1200u128, 1u128)),
+        Some(Decimal::percent(69)),
+        Uint128::from(1200000000u128),
+        Uint128::from(989999u128),
+        Uint128::zero(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_query_pool() {
+    let total_share_amount = Uint128::from(111u128);
+    let asset_0_amount = Uint128::from(222u128);
+    let asset_1_amount = Uint128::from(333u128);
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: asset_0_amount,
+    }]);
+
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &asset_1_amount)],
+        ),
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &total_share_amount)],
+        ),
+    ]);
+
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+
+    let env = mock_env();
+    let info = mock_info("addr0000", &[]);
+    // We can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    // Store liquidity token
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    let res: PoolResponse = query_pool(deps.as_ref()).unwrap();
+
+    assert_eq!(
+        res.assets,
+        [
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: "uusd".to_string(),
+                },
+                amount: asset_0_amount
+            },
+            Asset {
+                info: AssetInfo::Token {
+                    contract_addr: Addr::unchecked("asset0000"),
+                },
+                amount: asset_1_amount
+            }
+        ]
+    );
+    assert_eq!(res.total_share, total_share_amount);
+}
+
+#[test]
+fn test_query_share() {
+    let total_share_amount = Uint128::from(500u128);
+    let asset_0_amount = Uint128::from(250u128);
+    let asset_1_amount = Uint128::from(1000u128);
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: asset_0_amount,
+    }]);
+
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &asset_1_amount)],
+        ),
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &total_share_amount)],
+        ),
+    ]);
+
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+
+    let env = mock_env();
+    let info = mock_info("addr0000", &[]);
+    // We can just call .unwrap() to assert this was a success
+    let _res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    // Store liquidity token
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    let res = query_share(deps.as_ref(), Uint128::new(250)).unwrap();
+
+    assert_eq!(res[0].amount, Uint128::new(125));
+    assert_eq!(res[1].amount, Uint128::new(500));
+}
+
+fn mock_env_with_block_time(time: u64) -> Env {
+    let mut env = mock_env();
+    env.block = BlockInfo {
+        height: 1,
+        time: Timestamp::from_seconds(time),
+        chain_id: "columbus".to_string(),
+    };
+    env
+}
+
+#[test]
+fn compute_swap_rounding() {
+    let offer_pool = Uint128::from(5_000_000_000_000_u128);
+    let ask_pool = Uint128::from(1_000_000_000_u128);
+    let return_amount = Uint128::from(0_u128);
+    let spread_amount = Uint128::from(0_u128);
+    let commission_amount = Uint128::from(0_u128);
+    let offer_amount = Uint128::from(1_u128);
+
+    assert_eq!(
+        compute_swap(offer_pool, ask_pool, offer_amount, Decimal::zero()),
+        Ok((return_amount, spread_amount, commission_amount))
+    );
+}
+
+proptest! {
+    #[test]
+    fn compute_swap_overflow_test(
+        offer_pool in 1_000_000..9_000_000_000_000_000_000u128,
+        ask_pool in 1_000_000..9_000_000_000_000_000_000u128,
+        offer_amount in 1..100_000_000000u128,
+    ) {
+
+        let offer_pool = Uint128::from(offer_pool);
+        let ask_pool = Uint128::from(ask_pool);
+        let offer_amount = Uint128::from(offer_amount);
+        let commission_amount = Decimal::zero();
+
+        // Make sure there are no overflows
+        compute_swap(
+            offer_pool,
+            ask_pool,
+            offer_amount,
+            commission_amount,
+        ).unwrap();
+    }
+
+    #[test]
+    fn split_withdrawals_never_exceed_a_single_withdrawal(
+        pool in 1..1_000_000_000_000_000u128,
+        total_share in 1_000..1_000_000_000_000u128,
+        amount in 1..1_000_000u128,
+        splits in 1..20u128,
+    ) {
+        prop_assume!(amount * splits <= total_share);
+        let uusd = AssetInfo::NativeToken {
+            denom: "uusd".to_string(),
+        };
+        let refund = |pool: u128, amount: u128, total_share: u128| {
+            get_share_in_assets(
+                &[uusd.with_balance(pool)],
+                Uint128::new(amount),
+                Uint128::new(total_share),
+            )[0]
+            .amount
+            .u128()
+        };
+
+        let single = refund(pool, amount * splits, total_share);
+        let (mut pool_left, mut shares_left, mut withdrawn) = (pool, total_share, 0);
+        for _ in 0..splits {
+            let refunded = refund(pool_left, amount, shares_left);
+            pool_left -= refunded;
+            shares_left -= amount;
+            withdrawn += refunded;
+        }
+        prop_assert!(withdrawn <= single);
+    }
+}
+
+#[test]
+fn ensure_useful_error_messages_are_given_on_swaps() {
+    const OFFER: Uint128 = Uint128::new(1_000_000_000000);
+    const ASK: Uint128 = Uint128::new(1_000_000_000000);
+    const AMOUNT: Uint128 = Uint128::new(1_000000);
+    const ZERO: Uint128 = Uint128::zero();
+    const DZERO: Decimal = Decimal::zero();
+
+    // Computing ask
+    assert_eq!(
+        compute_swap(ZERO, ZERO, ZERO, DZERO).unwrap_err(),
+        StdError::generic_err("One of the pools is empty")
+    );
+    assert_eq!(
+        compute_swap(ZERO, ZERO, AMOUNT, DZERO).unwrap_err(),
+        StdError::generic_err("One of the pools is empty")
+    );
+    assert_eq!(
+        compute_swap(ZERO, ASK, ZERO, DZERO).unwrap_err(),
+        StdError::generic_err("One of the pools is empty")
+    );
+    assert_eq!(
+        compute_swap(ZERO, ASK, AMOUNT, DZERO).unwrap_err(),
+        StdError::generic_err("One of the pools is empty")
+    );
+    assert_eq!(
+        compute_swap(OFFER, ZERO, ZERO, DZERO).unwrap_err(),
+        StdError::generic_err("One of the pools is empty")
+    );
+    assert_eq!(
+        compute_swap(OFFER, ZERO, AMOUNT, DZERO).unwrap_err(),
+        StdError::generic_err("One of the pools is empty")
+    );
+    assert_eq!(
+        compute_swap(OFFER, ASK, ZERO, DZERO).unwrap_err(),
+        StdError::generic_err("Swap amount must not be zero")
+    );
+    compute_swap(OFFER, ASK, AMOUNT, DZERO).unwrap();
+
+    // Computing offer
+    assert_eq!(
+        compute_offer_amount(ZERO, ZERO, ZERO, DZERO).unwrap_err(),
+        StdError::generic_err("One of the pools is empty")
+    );
+    assert_eq!(
+        compute_offer_amount(ZERO, ZERO, AMOUNT, DZERO).unwrap_err(),
+        StdError::generic_err("One of the pools is empty")
+    );
+    assert_eq!(
+        compute_offer_amount(ZERO, ASK, ZERO, DZERO).unwrap_err(),
+        StdError::generic_err("One of the pools is empty")
+    );
+    assert_eq!(
+        compute_offer_amount(ZERO, ASK, AMOUNT, DZERO).unwrap_err(),
+        StdError::generic_err("One of the pools is empty")
+    );
+    assert_eq!(
+        compute_offer_amount(OFFER, ZERO, ZERO, DZERO).unwrap_err(),
+        StdError::generic_err("One of the pools is empty")
+    );
+    assert_eq!(
+        compute_offer_amount(OFFER, ZERO, AMOUNT, DZERO).unwrap_err(),
+        StdError::generic_err("One of the pools is empty")
+    );
+    assert_eq!(
+        compute_offer_amount(OFFER, ASK, ZERO, DZERO).unwrap_err(),
+        StdError::generic_err("Swap amount must not be zero")
+    );
+    compute_offer_amount(OFFER, ASK, AMOUNT, DZERO).unwrap();
+}
+
+#[test]
+fn compute_swap_does_not_panic_on_spread_calc() {
+    let offer_pool = Uint128::from(u128::MAX / 2);
+    let ask_pool = Uint128::from(u128::MAX / 1000000000);
+    let offer_amount = Uint128::from(1000000000u128);
+    let commission_rate = Decimal::permille(3);
+
+    let (return_amount, spread_amount, commission_amount) =
+        compute_swap(offer_pool, ask_pool, offer_amount, commission_rate).unwrap();
+    assert_eq!(return_amount, Uint128::from(2u128));
+    assert_eq!(spread_amount, Uint128::zero());
+    assert_eq!(commission_amount, Uint128::zero());
+}
+
+#[test]
+fn compute_swap_to_price_lands_on_target() {
+    let offer_pool = Uint128::new(1_000_000_000_000);
+    let ask_pool = Uint128::new(2_000_000_000_000);
+    let commission_rate = Decimal::permille(3);
+    let target_price = Decimal::from_ratio(15u128, 10u128);
+
+    let (offer_amount, resulting_price) =
+        compute_swap_to_price(offer_pool, ask_pool, target_price, commission_rate).unwrap();
+    assert!(resulting_price <= target_price);
+
+    // Marginal price of the pool after swapping `amount` according to `compute_swap`
+    let price_after = |amount: Uint128| {
+        let (return_amount, _, commission_amount) =
+            compute_swap(offer_pool, ask_pool, amount, commission_rate).unwrap();
+        Decimal::from_ratio(
+            ask_pool - return_amount - commission_amount,
+            offer_pool + amount,
+        ) * (Decimal::one() - commission_rate)
+    };
+
+    // The target lies within one unit of offer asset from the returned amount
+    assert!(price_after(offer_amount + Uint128::one()) <= target_price);
+    assert!(price_after(offer_amount - Uint128::one()) >= target_price);
+}
+
+#[test]
+fn compute_swap_to_price_rejects_wrong_side_targets() {
+    let offer_pool = Uint128::new(1_000_000_000_000);
+    let ask_pool = Uint128::new(1_000_000_000_000);
+    let commission_rate = Decimal::permille(3);
+    let error = StdError::generic_err(
+        "Target price must be positive and lower than the current price 0.997",
+    );
+
+    assert_eq!(
+        compute_swap_to_price(offer_pool, ask_pool, Decimal::one(), commission_rate).unwrap_err(),
+        error
+    );
+    assert_eq!(
+        compute_swap_to_price(
+            offer_pool,
+            ask_pool,
+            Decimal::permille(997),
+            commission_rate
+        )
+        .unwrap_err(),
+        error
+    );
+    assert_eq!(
+        compute_swap_to_price(offer_pool, ask_pool, Decimal::zero(), commission_rate).unwrap_err(),
+        error
+    );
+    assert_eq!(
+        compute_swap_to_price(
+            Uint128::zero(),
+            ask_pool,
+            Decimal::percent(50),
+            commission_rate
+        )
+        .unwrap_err(),
+        StdError::generic_err("One of the pools is empty")
+    );
+}
+
+#[test]
+fn min_commission_is_charged_on_dust_swaps() {
+    let offer_pool = Uint128::new(1_000_000_000_000);
+    let ask_pool = Uint128::new(1_000_000_000_000);
+    let one_bps = Decimal::from_ratio(1u16, 10000u16);
+
+    // The truncated commission of a dust swap is zero
+    let (return_amount, _, commission_amount) =
+        compute_swap(offer_pool, ask_pool, Uint128::new(100), one_bps).unwrap();
+    assert_eq!(commission_amount, Uint128::zero());
+
+    let (return_amount, commission_amount) =
+        apply_min_commission(return_amount, commission_amount, one_bps);
+    assert_eq!(commission_amount, Uint128::one());
+    assert_eq!(return_amount, Uint128::new(98));
+
+    // Commissions above the floor are kept as is
+    let (return_amount, _, commission_amount) =
+        compute_swap(offer_pool, ask_pool, Uint128::new(1_000_000), one_bps).unwrap();
+    assert_eq!(
+        apply_min_commission(return_amount, commission_amount, one_bps),
+        (return_amount, commission_amount)
+    );
+
+    // Zero fee pools never charge a commission
+    let (return_amount, _, commission_amount) =
+        compute_swap(offer_pool, ask_pool, Uint128::new(100), Decimal::zero()).unwrap();
+    assert_eq!(
+        apply_min_commission(return_amount, commission_amount, Decimal::zero()),
+        (return_amount, Uint128::zero())
+    );
+}
+
+#[test]
+fn reverse_min_commission_still_returns_the_ask_amount() {
+    let offer_pool = Uint128::new(1_000_000_000_000);
+    let ask_pool = Uint128::new(1_000_000_000_000);
+    let one_bps = Decimal::from_ratio(1u16, 10000u16);
+
+    for ask_amount in [1u128, 98, 99, 9_999, 10_000, 1_000_000] {
+        let ask_amount = Uint128::new(ask_amount);
+        let (offer_amount, _, commission_amount) =
+            compute_offer_amount_with_min_commission(offer_pool, ask_pool, ask_amount, one_bps)
+                .unwrap();
+        assert!(!commission_amount.is_zero());
+
+        // The quoted offer returns the ask amount after the minimum commission
+        let (return_amount, _, swap_commission) =
+            compute_swap(offer_pool, ask_pool, offer_amount, one_bps).unwrap();
+        let (return_amount, _) = apply_min_commission(return_amount, swap_commission, one_bps);
+        assert!(
+            return_amount >= ask_amount,
+            "{return_amount} < {ask_amount}"
+        );
+    }
+
+    // The quote of a commission above the floor is left untouched
+    let ask_amount = Uint128::new(1_000_000);
+    assert_eq!(
+        compute_offer_amount_with_min_commission(offer_pool, ask_pool, ask_amount, one_bps),
+        compute_offer_amount(offer_pool, ask_pool, ask_amount, one_bps)
+    );
+}
+
+#[test]
+fn compute_auto_balance_swap_matches_pool_ratio() {
+    let pools = [Uint128::new(1_000_000_000), Uint128::new(4_000_000_000)];
+    let commission_rate = Decimal::permille(3);
+
+    for deposits in [
+        [Uint128::new(100_000_000), Uint128::zero()],
+        [Uint128::new(100_000_000), Uint128::new(10_000_000)],
+        [Uint128::zero(), Uint128::new(400_000_000)],
+    ] {
+        let (offer, swap_amount) =
+            compute_auto_balance_swap(deposits, pools, commission_rate).unwrap();
+        let ask = 1 - offer;
+        let (return_amount, ..) =
+            compute_swap(pools[offer], pools[ask], swap_amount, commission_rate).unwrap();
+
+        let deposit_ratio =
+            Decimal::from_ratio(deposits[offer] - swap_amount, deposits[ask] + return_amount);
+        let pool_ratio =
+            Decimal::from_ratio(pools[offer] + swap_amount, pools[ask] - return_amount);
+        let diff = if deposit_ratio > pool_ratio {
+            deposit_ratio - pool_ratio
+        } else {
+            pool_ratio - deposit_ratio
+        };
+        assert!(diff < Decimal::from_ratio(1u8, 1_000_000u32) * pool_ratio);
+    }
+
+    // Balanced deposits do not need a swap
+    assert_eq!(
+        compute_auto_balance_swap(
+            [Uint128::new(1_000), Uint128::new(4_000)],
+            pools,
+            commission_rate
+        )
+        .unwrap(),
+        (0, Uint128::zero())
+    );
+}
+
+#[test]
+fn provide_liquidity_auto_balance() {
+    let pool_amount = Uint128::new(1_000_000_000);
+    let deposit = Uint128::new(100_000_000);
+
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        // The user deposit is pre-applied
+        amount: pool_amount + deposit,
+    }]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &pool_amount)],
+        ),
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &pool_amount)],
+        ),
+    ]);
+
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    let minted_share = |res: &Response| {
+        res.messages
+            .iter()
+            .find_map(|sub_msg| match &sub_msg.msg {
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr, msg, ..
+                }) if contract_addr == "liquidity0000" => match from_json(msg).unwrap() {
+                    Cw20ExecuteMsg::Mint { amount, .. } => Some(amount),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .unwrap()
+    };
+
+    // A single-sided deposit is rejected by the naive path
+    let naive_msg = ExecuteMsg::ProvideLiquidity {
+        assets: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            }
+            .with_balance(deposit),
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            }
+            .with_balance(0u128),
+        ],
+        slippage_tolerance: None,
+        receiver: None,
+        owner: None,
+        beneficiary: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: deposit,
+        }],
+    );
+    assert_eq!(
+        execute(deps.as_mut(), mock_env(), info.clone(), naive_msg).unwrap_err(),
+        ContractError::Dex(DexError::InvalidZeroAmount {})
+    );
+
+    let msg = ExecuteMsg::ProvideLiquidityAutoBalance {
+        assets: vec![AssetInfo::NativeToken {
+            denom: "uusd".to_string(),
+        }
+        .with_balance(deposit)],
+        max_spread: Some(Decimal::percent(10)),
+        receiver: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    let share = minted_share(&res);
+    // Swapping the excess grows the pool by 10%, the share is slightly below (sqrt(1.1) - 1) * 1000
+    assert!(share > Uint128::new(48_500_000) && share < Uint128::new(48_810_000));
+    // Nothing is pulled from the token side and only rounding dust is refunded
+    assert!(res.messages.iter().all(|sub_msg| match &sub_msg.msg {
+        CosmosMsg::Bank(BankMsg::Send { amount, .. }) => amount[0].amount < Uint128::new(10),
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr, msg, ..
+        }) if contract_addr == "asset0000" => match from_json(msg).unwrap() {
+            Cw20ExecuteMsg::Transfer { amount, .. } => amount < Uint128::new(10),
+            _ => false,
+        },
+        _ => true,
+    }));
+
+    // An imbalanced two-sided deposit mints more than the naive path
+    let token_deposit = Uint128::new(10_000_000);
+    let naive_share = token_deposit.multiply_ratio(pool_amount, pool_amount);
+    let msg = ExecuteMsg::ProvideLiquidityAutoBalance {
+        assets: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            }
+            .with_balance(deposit),
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            }
+            .with_balance(token_deposit),
+        ],
+        max_spread: Some(Decimal::percent(10)),
+        receiver: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    assert!(minted_share(&res) > naive_share);
+
+    // The balancing swap respects max spread
+    let msg = ExecuteMsg::ProvideLiquidityAutoBalance {
+        assets: vec![AssetInfo::NativeToken {
+            denom: "uusd".to_string(),
+        }
+        .with_balance(deposit)],
+        max_spread: Some(Decimal::permille(1)),
+        receiver: None,
+    };
+    assert_eq!(
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap_err(),
+        ContractError::Dex(DexError::MaxSpreadAssertion {})
+    );
+}
+
+#[test]
+fn swap_rejects_same_assets() {
+    let mut deps = mock_dependencies(&[]);
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let offer_asset = uusd.with_balance(1_000u128);
+
+    let err = query_simulation(deps.as_ref(), offer_asset.clone(), Some(uusd.clone())).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("Source and target assets are the same")
+    );
+
+    let msg = ExecuteMsg::Swap {
+        offer_asset,
+        ask_asset_info: Some(uusd),
+        belief_price: None,
+        max_spread: None,
+        to: None,
+        to_hook: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(1_000),
+        }],
+    );
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::SameAssets {}));
+}
+
+#[test]
+fn withdraw_liquidity_one_sided() {
+    let pool_amount = Uint128::new(1_000_000_000);
+    let share = Uint128::new(100_000_000);
+
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: pool_amount,
+    }]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &pool_amount)],
+        ),
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from("addr0000"), &pool_amount)],
+        ),
+    ]);
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            uusd.clone(),
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    let withdraw_msg = |max_spread: Option<Decimal>| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from("addr0000"),
+            msg: to_json_binary(&Cw20HookMsg::WithdrawLiquidityOneSided {
+                ask_asset_info: uusd.clone(),
+                max_spread,
+            })
+            .unwrap(),
+            amount: share,
+        })
+    };
+
+    // Only the LP token can trigger the hook
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        withdraw_msg(None),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::Unauthorized {}));
+
+    // The 10% token leg swap is rejected by a tight max spread
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("liquidity0000", &[]),
+        withdraw_msg(Some(Decimal::permille(1))),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::MaxSpreadAssertion {}));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("liquidity0000", &[]),
+        withdraw_msg(Some(Decimal::percent(15))),
+    )
+    .unwrap();
+
+    // The only asset transfer is the uusd payout
+    assert!(res.messages.iter().all(|sub_msg| match &sub_msg.msg {
+        CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. }) => contract_addr != "asset0000",
+        _ => true,
+    }));
+    let payout = res
+        .messages
+        .iter()
+        .find_map(|sub_msg| match &sub_msg.msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+                assert_eq!(to_address, "addr0000");
+                assert_eq!(amount.len(), 1);
+                assert_eq!(amount[0].denom, "uusd");
+                Some(amount[0].amount)
+            }
+            _ => None,
+        })
+        .unwrap();
+    // 100 uusd refunded plus 100 tokens swapped against a 900/900 pool: 90 minus the 0.3% fee
+    assert!(payout > Uint128::new(189_700_000) && payout < Uint128::new(189_750_000));
+
+    assert!(res.messages.iter().any(|sub_msg| match &sub_msg.msg {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr, msg, ..
+        }) if contract_addr == "liquidity0000" => {
+            from_json::<Cw20ExecuteMsg>(msg).unwrap() == Cw20ExecuteMsg::Burn { amount: share }
+        }
+        _ => false,
+    }));
+}
+
+#[test]
+#[cfg(not(feature = "no-emissions"))]
+fn emergency_withdraw() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100u128),
+    }]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from("addr0000"), &Uint128::new(100u128))],
+        ),
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(100u128))],
+        ),
+    ]);
+    // The controller is broken, any message sent to it fails
+    deps.querier.with_controller("controller");
+
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+    LP_PROVIDERS
+        .save(
+            deps.as_mut().storage,
+            &Addr::unchecked("addr0000"),
+            &Uint128::new(100u128),
+        )
+        .unwrap();
+
+    let calls_controller = |res: &Response| {
+        res.messages.iter().any(|sub_msg| {
+            matches!(&sub_msg.msg, CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. }) if contract_addr == "controller")
+        })
+    };
+
+    // A regular withdrawal depends on the controller
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: String::from("addr0000"),
+        msg: to_json_binary(&Cw20HookMsg::WithdrawLiquidity { beneficiary: None }).unwrap(),
+        amount: Uint128::new(50u128),
+    });
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("liquidity0000", &[]),
+        msg,
+    )
+    .unwrap();
+    assert!(calls_controller(&res));
+
+    // Only the LP token can trigger the hook
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: String::from("addr0000"),
+        msg: to_json_binary(&Cw20HookMsg::EmergencyWithdraw {}).unwrap(),
+        amount: Uint128::new(50u128),
+    });
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::Unauthorized {}));
+
+    // The emergency withdrawal refunds and burns without calling the controller
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("liquidity0000", &[]),
+        msg,
+    )
+    .unwrap();
+    assert!(!calls_controller(&res));
+    assert_eq!(
+        res.messages
+            .iter()
+            .map(|sub_msg| sub_msg.msg.clone())
+            .collect::<Vec<_>>(),
+        vec![
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: String::from("addr0000"),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::new(50u128),
+                }],
+            }),
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: String::from("asset0000"),
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: String::from("addr0000"),
+                    amount: Uint128::new(50u128),
+                })
+                .unwrap(),
+                funds: vec![],
+            }),
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: String::from("liquidity0000"),
+                msg: to_json_binary(&Cw20ExecuteMsg::Burn {
+                    amount: Uint128::new(50u128),
+                })
+                .unwrap(),
+                funds: vec![],
+            }),
+        ]
+    );
+    assert_eq!(res.attributes[0], attr("action", "emergency_withdraw"));
+
+    // LP tracking is still updated
+    assert_eq!(
+        LP_PROVIDERS
+            .load(deps.as_ref().storage, &Addr::unchecked("addr0000"))
+            .unwrap(),
+        Uint128::zero()
+    );
+
+    // The native LP path is unsupported for a cw20 LP token
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::EmergencyWithdraw {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::NonSupported {}));
+}
+
+#[test]
+#[cfg(not(feature = "no-emissions"))]
+fn emergency_withdraw_untracks_delegated_lp() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100u128),
+    }]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from("addr0000"), &Uint128::new(100u128))],
+        ),
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(100u128))],
+        ),
+    ]);
+    deps.querier.with_controller("controller");
+
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+    // Most of the sender's LP tokens were provided for a beneficiary
+    let storage = deps.as_mut().storage;
+    LP_PROVIDERS
+        .save(storage, &Addr::unchecked("addr0000"), &Uint128::new(20u128))
+        .unwrap();
+    LP_PROVIDERS
+        .save(
+            storage,
+            &Addr::unchecked("delegate"),
+            &Uint128::new(1_000u128),
+        )
+        .unwrap();
+    DELEGATED_LP
+        .save(
+            storage,
+            (&Addr::unchecked("addr0000"), &Addr::unchecked("delegate")),
+            &Uint128::new(60u128),
+        )
+        .unwrap();
+
+    // The sender exits with more LP tokens than tracked for itself
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: String::from("addr0000"),
+        msg: to_json_binary(&Cw20HookMsg::EmergencyWithdraw {}).unwrap(),
+        amount: Uint128::new(100u128),
+    });
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("liquidity0000", &[]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 3);
+
+    // The beneficiary stops being credited for the withdrawn LP tokens
+    let tracked = |address: &str| {
+        LP_PROVIDERS
+            .load(deps.as_ref().storage, &Addr::unchecked(address))
+            .unwrap()
+    };
+    assert_eq!(tracked("delegate"), Uint128::new(940u128));
+    assert_eq!(tracked("addr0000"), Uint128::zero());
+    assert!(!DELEGATED_LP.has(
+        deps.as_ref().storage,
+        (&Addr::unchecked("addr0000"), &Addr::unchecked("delegate"))
+    ));
+}
+
+#[test]
+fn query_swap_rate_matches_simulation() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_000_000_000),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &Uint128::new(2_000_000_000),
+        )],
+    )]);
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            uusd.clone(),
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    for offer_amount in [1_000u128, 1_000_000, 100_000_000] {
+        let offer_asset = uusd.with_balance(offer_amount);
+        let simulation = query_simulation(deps.as_ref(), offer_asset.clone(), None).unwrap();
+        let res = query_swap_rate(deps.as_ref(), offer_asset, None).unwrap();
+
+        assert_eq!(res.rate, Decimal::from_ratio(2u8, 1u8));
+        assert!(res.rate_after_fees < res.rate);
+        let implied_return = Uint128::new(offer_amount) * res.rate_after_fees;
+        assert!(simulation.return_amount - implied_return <= Uint128::one());
+    }
+
+    // A zero offer returns the marginal price net of the 0.3% fee
+    let res = query_swap_rate(deps.as_ref(), uusd.with_balance(0u128), None).unwrap();
+    assert_eq!(res.rate_after_fees, Decimal::permille(1994));
+}
+
+#[test]
+fn effective_fee_rate_matches_simulated_commission() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_000_000_000),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &Uint128::new(1_000_000_000),
+        )],
+    )]);
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            uusd.clone(),
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    for offer_amount in [1_000_000u128, 100_000_000] {
+        let offer_asset = uusd.with_balance(offer_amount);
+        let fee_rate: Decimal = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::EffectiveFeeRate {
+                    offer_asset: offer_asset.clone(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let simulation = query_simulation(deps.as_ref(), offer_asset, None).unwrap();
+
+        // Above the minimum commission the rate is the factory fee rate up to truncation
+        assert!(fee_rate.abs_diff(Decimal::permille(3)) < Decimal::from_ratio(1u8, 100_000u32));
+        assert_eq!(
+            fee_rate,
+            Decimal::from_ratio(
+                simulation.commission_amount,
+                simulation.return_amount + simulation.commission_amount
+            )
+        );
+    }
+
+    // A dust swap charged the minimum commission pays more than the factory fee rate
+    deps.querier.with_min_commission();
+    let offer_asset = uusd.with_balance(100u128);
+    let fee_rate: Decimal = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::EffectiveFeeRate {
+                offer_asset: offer_asset.clone(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let simulation = query_simulation(deps.as_ref(), offer_asset, None).unwrap();
+    assert_eq!(simulation.commission_amount, Uint128::one());
+    assert_eq!(
+        fee_rate,
+        Decimal::from_ratio(1u8, simulation.return_amount + simulation.commission_amount)
+    );
+    assert!(fee_rate > Decimal::permille(3));
+
+    let err = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::EffectiveFeeRate {
+            offer_asset: AssetInfo::NativeToken {
+                denom: "uluna".to_string(),
+            }
+            .with_balance(1_000u128),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("Asset uluna does not belong to the pair")
+    );
+}
+
+#[test]
+fn reverse_simulation_quotes_an_offer_covering_the_ask_amount() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_000_000_007),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &Uint128::new(2_000_000_003),
+        )],
+    )]);
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let token = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![uusd.clone(), token.clone()],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    for ask_amount in [3u128, 997, 1_000_003, 333_333_333] {
+        let reverse =
+            query_reverse_simulation(deps.as_ref(), token.with_balance(ask_amount), None).unwrap();
+
+        // The quoted offer is rounded up so that the swap returns at least the ask amount
+        let forward =
+            query_simulation(deps.as_ref(), uusd.with_balance(reverse.offer_amount), None).unwrap();
+        assert!(forward.return_amount.u128() >= ask_amount);
+        let forward = query_simulation(
+            deps.as_ref(),
+            uusd.with_balance(reverse.offer_amount - Uint128::one()),
+            None,
+        )
+        .unwrap();
+        assert!(forward.return_amount.u128() < ask_amount);
+    }
+}
+
+#[test]
+fn reverse_simulation_checks_explicit_offer_asset() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_000_000_000),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &Uint128::new(2_000_000_000),
+        )],
+    )]);
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let token = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![uusd.clone(), token.clone()],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let reverse_simulation = |offer_asset_info: Option<AssetInfo>| {
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::ReverseSimulation {
+                offer_asset_info,
+                ask_asset: token.with_balance(1_000_000u128),
+            },
+        )
+        .map(|res| from_json::<ReverseSimulationResponse>(&res).unwrap())
+    };
+
+    // The correct offer asset gives the same result as the inferred one
+    let inferred = reverse_simulation(None).unwrap();
+    assert_eq!(reverse_simulation(Some(uusd)).unwrap(), inferred);
+
+    let err = reverse_simulation(Some(AssetInfo::NativeToken {
+        denom: "uluna".to_string(),
+    }))
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(ContractError::AssetMismatch {}.to_string())
+    );
+    let err = reverse_simulation(Some(token.clone())).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(ContractError::Dex(DexError::SameAssets {}).to_string())
+    );
+}
+
+#[test]
+fn quote_both_round_trips_the_offer() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_000_000_000),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &Uint128::new(2_000_000_000),
+        )],
+    )]);
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let token = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![uusd.clone(), token.clone()],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    for offer_amount in [1_000u128, 1_000_000, 100_000_000] {
+        let offer_asset = uusd.with_balance(offer_amount);
+        let res = query_quote_both(deps.as_ref(), offer_asset.clone(), None).unwrap();
+
+        let forward = query_simulation(deps.as_ref(), offer_asset.clone(), None).unwrap();
+        assert_eq!(res.forward, forward);
+        let reverse = query_reverse_simulation(
+            deps.as_ref(),
+            token.with_balance(forward.return_amount),
+            None,
+        )
+        .unwrap();
+        assert_eq!(res.reverse, reverse);
+
+        // The reverse of the forward return recovers the offer up to rounding
+        assert!(res.reverse.offer_amount.u128().abs_diff(offer_amount) <= 1);
+    }
+
+    // Both legs charge the minimum commission of dust swaps
+    deps.querier.with_min_commission();
+    let offer_asset = uusd.with_balance(100u128);
+    let res = query_quote_both(deps.as_ref(), offer_asset.clone(), None).unwrap();
+    assert_eq!(
+        res.forward,
+        query_simulation(deps.as_ref(), offer_asset, None).unwrap()
+    );
+    assert_eq!(res.forward.commission_amount, Uint128::one());
+    assert_eq!(
+        res.reverse,
+        query_reverse_simulation(
+            deps.as_ref(),
+            token.with_balance(res.forward.return_amount),
+            None,
+        )
+        .unwrap()
+    );
+    assert_eq!(res.reverse.commission_amount, Uint128::one());
+    assert!(res.reverse.offer_amount.u128().abs_diff(100) <= 1);
+
+    let err =
+        query_quote_both(deps.as_ref(), uusd.with_balance(1_000u128), Some(uusd)).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(ContractError::Dex(DexError::SameAssets {}).to_string())
+    );
+}
+
+#[test]
+fn max_swap_within_slippage_sits_at_the_spread_boundary() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_000_000_000),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &Uint128::new(2_000_000_000),
+        )],
+    )]);
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let token = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![uusd.clone(), token.clone()],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let within_spread = |offer_amount: Uint128, max_spread: Decimal| {
+        let simulation =
+            query_simulation(deps.as_ref(), uusd.with_balance(offer_amount), None).unwrap();
+        assert_max_spread(
+            None,
+            Some(max_spread),
+            offer_amount,
+            simulation.return_amount + simulation.commission_amount,
+            simulation.spread_amount,
+        )
+        .is_ok()
+    };
+
+    for max_spread in [
+        Decimal::permille(1),
+        Decimal::percent(1),
+        Decimal::percent(20),
+    ] {
+        let amount = query_max_swap_within_slippage(
+            deps.as_ref(),
+            uusd.clone(),
+            max_spread,
+            Some(token.clone()),
+        )
+        .unwrap();
+        assert!(within_spread(amount, max_spread));
+        assert!(!within_spread(
+            amount + amount / Uint128::new(1_000),
+            max_spread
+        ));
+    }
+
+    let err =
+        query_max_swap_within_slippage(deps.as_ref(), uusd.clone(), Decimal::percent(101), None)
+            .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(ContractError::Dex(DexError::AllowedSpreadAssertion {}).to_string())
+    );
+    let err = query_max_swap_within_slippage(
+        deps.as_ref(),
+        uusd.clone(),
+        Decimal::percent(1),
+        Some(uusd),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(ContractError::Dex(DexError::SameAssets {}).to_string())
+    );
+}
+
+#[test]
+fn provide_liquidity_rejects_wrong_assets() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100u128),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::zero())],
+    )]);
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let token = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![uusd.clone(), token],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(100u128),
+        }],
+    );
+
+    // An asset that does not belong to the pool
+    let msg = ExecuteMsg::ProvideLiquidity {
+        assets: vec![
+            uusd.with_balance(100u128),
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0001"),
+            }
+            .with_balance(100u128),
+        ],
+        slippage_tolerance: None,
+        receiver: None,
+        owner: None,
+        beneficiary: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap_err();
+    assert_eq!(err, ContractError::AssetMismatch {});
+
+    // The same pool asset twice
+    let msg = ExecuteMsg::ProvideLiquidity {
+        assets: vec![uusd.with_balance(50u128), uusd.with_balance(50u128)],
+        slippage_tolerance: None,
+        receiver: None,
+        owner: None,
+        beneficiary: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(err, ContractError::AssetMismatch {});
+}
+
+#[test]
+fn instantiate_pays_denom_creation_fee() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::zero())],
+    )]);
+    let fee = vec![Coin {
+        denom: "uosmo".to_string(),
+        amount: Uint128::new(10_000_000),
+    }];
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: None,
+        factory_addr: String::from("factory"),
+        init_params: None,
+        denom_creation_fee: fee.clone(),
+        immutable_lp: false,
+    };
+
+    // The fee must be covered by the instantiation funds
+    let err = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("factory", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Dex(DexError::InsufficientDenomCreationFee {})
+    );
+
+    let res = instantiate(deps.as_mut(), mock_env(), mock_info("factory", &fee), msg).unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(res.messages[0].id, 1);
+    assert!(matches!(
+        &res.messages[0].msg,
+        CosmosMsg::Stargate { type_url, .. } if type_url == "/osmosis.tokenfactory.v1beta1.MsgCreateDenom"
+    ));
+}
+
+#[test]
+fn native_lp_denom_gets_metadata() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::zero())],
+    )]);
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: None,
+        factory_addr: String::from("factory"),
+        init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("factory", &[]), msg).unwrap();
+
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 1,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: None,
+            }),
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    let CosmosMsg::Stargate { type_url, value } = &res.messages[0].msg else {
+        panic!("expected a stargate message");
+    };
+    assert_eq!(
+        type_url,
+        "/osmosis.tokenfactory.v1beta1.MsgSetDenomMetadata"
+    );
+
+    let denom = format!("factory/{MOCK_CONTRACT_ADDR}/{}", "UUSD-MAPP-LP");
+    let msg = MsgSetDenomMetadata::decode(value.as_slice()).unwrap();
+    assert_eq!(msg.sender, MOCK_CONTRACT_ADDR);
+    assert_eq!(
+        msg.metadata.unwrap(),
+        Metadata {
+            description: "Ura UUSD-MAPP-LP liquidity token".to_string(),
+            denom_units: vec![
+                DenomUnit {
+                    denom: denom.clone(),
+                    exponent: 0,
+                    aliases: vec![],
+                },
+                DenomUnit {
+                    denom: "UUSD-MAPP-LP".to_string(),
+                    exponent: 6,
+                    aliases: vec![],
+                },
+            ],
+            base: denom,
+            display: "UUSD-MAPP-LP".to_string(),
+            name: "UUSD-MAPP-LP".to_string(),
+            symbol: "UUSD-MAPP-LP".to_string(),
+            uri: String::new(),
+            uri_hash: String::new(),
+        }
+    );
+}
+
+#[test]
+fn native_lp_pair_is_not_ready_before_the_denom_reply() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100_000),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::zero())],
+    )]);
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: None,
+        factory_addr: String::from("factory"),
+        init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("factory", &[]), msg).unwrap();
+
+    let provide = ExecuteMsg::ProvideLiquidity {
+        assets: vec![
+            Asset {
+                info: AssetInfo::Token {
+                    contract_addr: Addr::unchecked("asset0000"),
+                },
+                amount: Uint128::new(100_000),
+            },
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: "uusd".to_string(),
+                },
+                amount: Uint128::new(100_000),
+            },
+        ],
+        slippage_tolerance: None,
+        receiver: None,
+        owner: None,
+        beneficiary: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(100_000),
+        }],
+    );
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), provide.clone()).unwrap_err();
+    assert_eq!(err, ContractError::PairNotReady {});
+
+    let swap = ExecuteMsg::Swap {
+        offer_asset: Asset {
+            info: AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            amount: Uint128::new(100_000),
+        },
+        ask_asset_info: None,
+        belief_price: None,
+        max_spread: None,
+        to: None,
+        to_hook: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), info.clone(), swap).unwrap_err();
+    assert_eq!(err, ContractError::PairNotReady {});
+    let res: CanExecuteResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::CanExecute {
+                address: "addr0000".to_string(),
+                action: PairAction::Swap,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        CanExecuteResponse {
+            allowed: false,
+            reason: Some(ContractError::PairNotReady {}.to_string()),
+        }
+    );
+
+    // Once the denom creation is confirmed the pair accepts liquidity
+    reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 1,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: None,
+            }),
+        },
+    )
+    .unwrap();
+    execute(deps.as_mut(), mock_env(), info, provide).unwrap();
+}
+
+#[test]
+fn native_lp_mint_reply_checks_the_minted_supply() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100_000),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::zero())],
+    )]);
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: None,
+        factory_addr: String::from("factory"),
+        init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("factory", &[]), msg).unwrap();
+    let mint_reply = |id: u64| Reply {
+        id,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+        }),
+    };
+    reply(deps.as_mut(), mock_env(), mint_reply(1)).unwrap();
+    let denom = CONFIG
+        .load(deps.as_ref().storage)
+        .unwrap()
+        .pair_info
+        .liquidity_token
+        .to_string();
+
+    let provide = ExecuteMsg::ProvideLiquidity {
+        assets: vec![
+            Asset {
+                info: AssetInfo::Token {
+                    contract_addr: Addr::unchecked("asset0000"),
+                },
+                amount: Uint128::new(100_000),
+            },
+            Asset {
+                info: AssetInfo::NativeToken {
+                    denom: "uusd".to_string(),
+                },
+                amount: Uint128::new(100_000),
+            },
+        ],
+        slippage_tolerance: None,
+        receiver: None,
+        owner: None,
+        beneficiary: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(100_000),
+        }],
+    );
+    let res = execute(deps.as_mut(), mock_env(), info, provide).unwrap();
+    let mint_replies = res
+        .messages
+        .iter()
+        .filter(|msg| msg.reply_on == ReplyOn::Success)
+        .map(|msg| msg.id)
+        .collect::<Vec<_>>();
+    assert_eq!(mint_replies, vec![6, 6]);
+
+    // The minimum liquidity is minted in full
+    deps.querier.with_balance(&[(
+        &String::from("lp_holder"),
+        &[Coin {
+            denom: denom.clone(),
+            amount: MINIMUM_LIQUIDITY_AMOUNT,
+        }],
+    )]);
+    reply(deps.as_mut(), mock_env(), mint_reply(6)).unwrap();
+
+    // while the provider's share is under-minted
+    deps.querier.with_balance(&[(
+        &String::from("lp_holder"),
+        &[Coin {
+            denom,
+            amount: Uint128::new(90_000),
+        }],
+    )]);
+    let err = reply(deps.as_mut(), mock_env(), mint_reply(6)).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::LpMintMismatch {
+            expected: Uint128::new(100_000),
+            actual: Uint128::new(90_000),
+        }
+    );
+}
+
+#[test]
+fn native_lp_withdraw_reports_a_failed_burn() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100_000),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(100_000))],
+    )]);
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: None,
+        factory_addr: String::from("factory"),
+        init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("factory", &[]), msg).unwrap();
+    reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 1,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: None,
+            }),
+        },
+    )
+    .unwrap();
+    let denom = CONFIG
+        .load(deps.as_ref().storage)
+        .unwrap()
+        .pair_info
+        .liquidity_token
+        .to_string();
+    deps.querier
+        .with_balance(&[(&String::from("addr0000"), &[coin(100_000, denom.clone())])]);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[coin(1_000, denom)]),
+        ExecuteMsg::WithdrawLiquidity { beneficiary: None },
+    )
+    .unwrap();
+
+    // The refunds go out first and the burn reports its failure
+    assert!(matches!(res.messages[0].msg, CosmosMsg::Bank(_)));
+    let burn = &res.messages[2];
+    assert!(matches!(
+        &burn.msg,
+        CosmosMsg::Stargate { type_url, .. } if type_url == "/osmosis.tokenfactory.v1beta1.MsgBurn"
+    ));
+    assert_eq!(burn.reply_on, ReplyOn::Error);
+
+    let err = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: burn.id,
+            result: SubMsgResult::Err("unauthorized account".to_string()),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::LpBurnFailed {});
+}
+
+#[test]
+fn reply_reports_liquidity_token_failure() {
+    let mut deps = mock_dependencies(&[]);
+
+    let err = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 1,
+            result: SubMsgResult::Err("denom creation fee not paid".to_string()),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Dex(DexError::LiquidityTokenInstantiationFailed {
+            reason: "denom creation fee not paid".to_string()
+        })
+    );
+    assert_eq!(
+        err.to_string(),
+        "Failed to create the liquidity token: denom creation fee not paid"
+    );
+}
+
+/// Instantiates a uusd/asset0000 pair with the CW20 LP token `liquidity0000` and migrates it to
+/// a native LP token. Returns the native LP denom.
+fn migrate_to_native_lp(deps: DepsMut) -> String {
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        factory_addr: String::from("factory"),
+        init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    let mut deps = deps;
+    instantiate(deps.branch(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.branch(), 2, "liquidity0000".to_string());
+
+    let msg = ExecuteMsg::MigrateLpToken {
+        to_cw20: false,
+        token_code_id: None,
+    };
+    let res = execute(deps.branch(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    assert!(matches!(
+        &res.messages[0].msg,
+        CosmosMsg::Stargate { type_url, .. } if type_url == "/osmosis.tokenfactory.v1beta1.MsgCreateDenom"
+    ));
+    let res = reply(
+        deps.branch(),
+        mock_env(),
+        Reply {
+            id: res.messages[0].id,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: None,
+            }),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages.last().unwrap().msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("factory"),
+            msg: to_json_binary(&FactoryExecuteMsg::UpdatePairLpToken {
+                previous_lp_token: AssetInfo::Token {
+                    contract_addr: Addr::unchecked("liquidity0000"),
+                },
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+
+    match CONFIG.load(deps.storage).unwrap().pair_info.liquidity_token {
+        AssetInfo::NativeToken { denom } => denom,
+        AssetInfo::Token { .. } => panic!("the LP token was not migrated"),
+    }
+}
+
+#[test]
+fn migrate_lp_token_state_machine() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::zero())],
+    )]);
+    let migrate = |to_cw20: bool, token_code_id: Option<u64>| ExecuteMsg::MigrateLpToken {
+        to_cw20,
+        token_code_id,
+    };
+
+    // Only the factory owner migrates, and only to the other kind of LP token
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        factory_addr: String::from("factory"),
+        init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        migrate(false, None),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::Unauthorized {}));
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        migrate(true, Some(10)),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::LpTokenKindUnchanged {});
+
+    // The denom creation fee must be sent along instead of being charged from the reserves
+    deps.querier.with_denom_creation_fee(&[coin(100, "uusd")]);
+    for funds in [vec![], vec![coin(99, "uusd")], vec![coin(100, "ufee")]] {
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &funds),
+            migrate(false, None),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::Dex(DexError::InsufficientDenomCreationFee {})
+        );
+    }
+    deps.querier
+        .with_balance(&[(&String::from(MOCK_CONTRACT_ADDR), &[coin(1_100, "uusd")])]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[coin(100, "uusd")]),
+        migrate(false, None),
+    )
+    .unwrap();
+    deps.querier
+        .with_balance(&[(&String::from(MOCK_CONTRACT_ADDR), &[coin(999, "uusd")])]);
+    let err = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: res.messages[0].id,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: None,
+            }),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Dex(DexError::InsufficientDenomCreationFee {})
+    );
+
+    // Once the native LP token is created the pair switches to it
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::zero())],
+    )]);
+    let denom = migrate_to_native_lp(deps.as_mut());
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        config.legacy_lp_token,
+        Some(AssetInfo::Token {
+            contract_addr: Addr::unchecked("liquidity0000"),
+        })
+    );
+    assert!(denom.starts_with(&format!("factory/{MOCK_CONTRACT_ADDR}/")));
+
+    // Another migration waits until all previous LP tokens are claimed
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        migrate(true, Some(10)),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::LpMigrationInProgress {});
+
+    // All previous LP tokens were claimed
+    let mut config = CONFIG.load(deps.as_ref().storage).unwrap();
+    config.legacy_lp_token = None;
+    CONFIG.save(deps.as_mut().storage, &config).unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        migrate(true, None),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::LpTokenCodeIdRequired {});
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[coin(100, "uusd")]),
+        migrate(true, Some(10)),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::LpMigrationFundsNotAccepted {});
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        migrate(true, Some(10)),
+    )
+    .unwrap();
+    assert_eq!(res.messages[0].reply_on, ReplyOn::Success);
+    store_liquidity_token(
+        deps.as_mut(),
+        res.messages[0].id,
+        "liquidity0001".to_string(),
+    );
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        config.pair_info.liquidity_token,
+        AssetInfo::Token {
+            contract_addr: Addr::unchecked("liquidity0001"),
+        }
+    );
+    assert_eq!(
+        config.legacy_lp_token,
+        Some(AssetInfo::NativeToken { denom })
+    );
+}
+
+#[test]
+fn claim_migrated_lp_exchanges_lp_tokens_one_to_one() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::zero())],
+    )]);
+    let denom = migrate_to_native_lp(deps.as_mut());
+
+    let claim = |sender: &str, amount: u128| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: sender.to_string(),
+            amount: Uint128::new(amount),
+            msg: to_json_binary(&Cw20HookMsg::ClaimMigratedLp {}).unwrap(),
+        })
+    };
+    let burn = |amount: u128| {
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: "liquidity0000".to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Burn {
+                amount: Uint128::new(amount),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    };
+    let is_mint = |msg: &SubMsg| {
+        matches!(
+            &msg.msg,
+            CosmosMsg::Stargate { type_url, .. } if type_url == "/osmosis.tokenfactory.v1beta1.MsgMint"
+        )
+    };
+    let sent = |recipient: &str, amount: u128| {
+        SubMsg::new(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin {
+                denom: denom.clone(),
+                amount: Uint128::new(amount),
+            }],
+        })
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("asset0000", &[]),
+        claim("addr0000", 100),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::Unauthorized {}));
+
+    // The pair holds the minimum liquidity and the claimed LP tokens
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::zero())],
+        ),
+        (
+            &String::from("liquidity0000"),
+            &[
+                (&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(1_100)),
+                (&String::from("addr0001"), &Uint128::new(500)),
+            ],
+        ),
+    ]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("liquidity0000", &[]),
+        claim("addr0000", 100),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(res.messages[0], burn(100));
+    assert!(is_mint(&res.messages[1]));
+    assert_eq!(res.messages[2], sent("addr0000", 100));
+    assert!(CONFIG
+        .load(deps.as_ref().storage)
+        .unwrap()
+        .legacy_lp_token
+        .is_some());
+
+    // The last claim also exchanges the LP tokens held by the pair and ends the migration
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::zero())],
+        ),
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(1_500))],
+        ),
+    ]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("liquidity0000", &[]),
+        claim("addr0001", 500),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 4);
+    assert_eq!(res.messages[0], burn(1_500));
+    assert!(is_mint(&res.messages[1]));
+    assert_eq!(res.messages[2], sent("addr0001", 500));
+    assert!(is_mint(&res.messages[3]));
+    assert!(res
+        .attributes
+        .contains(&attr("migration_completed", "true")));
+    assert_eq!(
+        CONFIG.load(deps.as_ref().storage).unwrap().legacy_lp_token,
+        None
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("liquidity0000", &[]),
+        claim("addr0001", 500),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NoLpMigration {});
+}
+
+#[test]
+#[cfg(not(feature = "no-emissions"))]
+fn provide_liquidity_survives_failing_controller() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_100),
+    }]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from("addr0000"), &Uint128::new(1_000))],
+        ),
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(1_000))],
+        ),
+    ]);
+    deps.querier.with_controller("controller");
+    deps.querier.with_controller_gas_limit(500_000);
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let token = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![uusd.clone(), token.clone()],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+    LP_PROVIDERS
+        .save(
+            deps.as_mut().storage,
+            &Addr::unchecked("addr0000"),
+            &Uint128::new(1_000),
+        )
+        .unwrap();
+
+    let msg = ExecuteMsg::ProvideLiquidity {
+        assets: vec![uusd.with_balance(100u128), token.with_balance(100u128)],
+        slippage_tolerance: None,
+        receiver: None,
+        owner: None,
+        beneficiary: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(100),
+        }],
+    );
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // The emission accounting call is capped and its failure is caught in the reply
+    let accum_msg = res.messages.last().unwrap();
+    assert_eq!(
+        accum_msg.msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("controller"),
+            msg: to_json_binary(&ControllerExecuteMsg::AccumUserEmissions(
+                AccumEmissionsRequest {
+                    address: String::from("addr0000"),
+                    previous_amount: Uint128::new(1_000),
+                }
+            ))
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+    assert_eq!(accum_msg.gas_limit, Some(500_000));
+    assert_eq!(accum_msg.reply_on, ReplyOn::Error);
+
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: accum_msg.id,
+            result: SubMsgResult::Err("out of gas".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "accum_emissions_failed"),
+            attr("reason", "out of gas")
+        ]
+    );
+    // The provide is kept, including the tracked LP balance
+    assert_eq!(
+        LP_PROVIDERS
+            .load(deps.as_ref().storage, &Addr::unchecked("addr0000"))
+            .unwrap(),
+        Uint128::new(1_100)
+    );
+}
+
+#[test]
+#[cfg(not(feature = "no-emissions"))]
+fn emissions_accrue_to_the_beneficiary() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_100),
+    }]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from("addr0001"), &Uint128::new(1_000))],
+        ),
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(1_000))],
+        ),
+    ]);
+    deps.querier.with_controller("controller");
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let token = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![uusd.clone(), token.clone()],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+    LP_PROVIDERS
+        .save(
+            deps.as_mut().storage,
+            &Addr::unchecked("delegate"),
+            &Uint128::new(1_000),
+        )
+        .unwrap();
+    let accum_msg = |previous_amount: u128| {
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("controller"),
+            msg: to_json_binary(&ControllerExecuteMsg::AccumUserEmissions(
+                AccumEmissionsRequest {
+                    address: String::from("delegate"),
+                    previous_amount: Uint128::new(previous_amount),
+                },
+            ))
+            .unwrap(),
+            funds: vec![],
+        })
+    };
+
+    let provide = |receiver: Option<&str>, beneficiary: &str| ExecuteMsg::ProvideLiquidity {
+        assets: vec![uusd.with_balance(100u128), token.with_balance(100u128)],
+        slippage_tolerance: None,
+        receiver: receiver.map(String::from),
+        owner: None,
+        beneficiary: Some(beneficiary.to_string()),
+    };
+    let funds = [Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100),
+    }];
+    let info = mock_info("addr0001", &funds);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        provide(None, "Delegate"),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        ContractError::Std(StdError::GenericErr { .. })
+    ));
+
+    // LP tokens minted to someone else keep accruing to their receiver
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &funds),
+        provide(Some("addr0001"), "delegate"),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::Unauthorized {}));
+
+    // The LP tokens go to the sender while they are tracked and accrue for the beneficiary
+    let res = execute(deps.as_mut(), mock_env(), info, provide(None, "delegate")).unwrap();
+    assert_eq!(
+        res.messages[1].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("liquidity0000"),
+            msg: to_json_binary(&Cw20ExecuteMsg::Mint {
+                recipient: String::from("addr0001"),
+                amount: Uint128::new(100),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+    assert_eq!(res.messages.last().unwrap().msg, accum_msg(1_000));
+    assert_eq!(
+        LP_PROVIDERS
+            .load(deps.as_ref().storage, &Addr::unchecked("delegate"))
+            .unwrap(),
+        Uint128::new(1_100)
+    );
+    assert!(!LP_PROVIDERS.has(deps.as_ref().storage, &Addr::unchecked("addr0001")));
+
+    let withdraw = |sender: &str| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from(sender),
+            msg: to_json_binary(&Cw20HookMsg::WithdrawLiquidity {
+                beneficiary: Some(String::from("delegate")),
+            })
+            .unwrap(),
+            amount: Uint128::new(100),
+        })
+    };
+
+    // Only the LP tokens provided for the beneficiary can be untracked for it
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("liquidity0000", &[]),
+        withdraw("addr0002"),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::Unauthorized {}));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("liquidity0000", &[]),
+        withdraw("addr0001"),
+    )
+    .unwrap();
+    assert_eq!(res.messages.last().unwrap().msg, accum_msg(1_100));
+    assert_eq!(
+        LP_PROVIDERS
+            .load(deps.as_ref().storage, &Addr::unchecked("delegate"))
+            .unwrap(),
+        Uint128::new(1_000)
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("liquidity0000", &[]),
+        withdraw("addr0001"),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::Unauthorized {}));
+}
+
+#[test]
+#[cfg(not(feature = "no-emissions"))]
+fn withdraw_without_beneficiary_untracks_delegated_lp() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_100),
+    }]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from("addr0001"), &Uint128::new(1_000))],
+        ),
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(1_000))],
+        ),
+    ]);
+    deps.querier.with_controller("controller");
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let token = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![uusd.clone(), token.clone()],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+    LP_PROVIDERS
+        .save(
+            deps.as_mut().storage,
+            &Addr::unchecked("delegate"),
+            &Uint128::new(1_000),
+        )
+        .unwrap();
+    LP_PROVIDERS
+        .save(
+            deps.as_mut().storage,
+            &Addr::unchecked("addr0001"),
+            &Uint128::new(50),
+        )
+        .unwrap();
+
+    let provide = ExecuteMsg::ProvideLiquidity {
+        assets: vec![uusd.with_balance(100u128), token.with_balance(100u128)],
+        slippage_tolerance: None,
+        receiver: None,
+        owner: None,
+        beneficiary: Some(String::from("delegate")),
+    };
+    let info = mock_info(
+        "addr0001",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(100),
+        }],
+    );
+    let withdraw = |amount: u128| {
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: String::from("addr0001"),
+            msg: to_json_binary(&Cw20HookMsg::WithdrawLiquidity { beneficiary: None }).unwrap(),
+            amount: Uint128::new(amount),
+        })
+    };
+    let tracked = |storage: &dyn Storage, address: &str| {
+        LP_PROVIDERS
+            .load(storage, &Addr::unchecked(address))
+            .unwrap()
+    };
+
+    // Providing for the beneficiary and withdrawing without naming it again can't grow the
+    // beneficiary's tracked LP tokens on the same capital
+    for _ in 0..2 {
+        execute(deps.as_mut(), mock_env(), info.clone(), provide.clone()).unwrap();
+        assert_eq!(
+            tracked(deps.as_ref().storage, "delegate"),
+            Uint128::new(1_100)
+        );
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("liquidity0000", &[]),
+            withdraw(100),
+        )
+        .unwrap();
+        assert_eq!(
+            tracked(deps.as_ref().storage, "delegate"),
+            Uint128::new(1_000)
+        );
+        assert_eq!(tracked(deps.as_ref().storage, "addr0001"), Uint128::new(50));
+        assert!(!DELEGATED_LP.has(
+            deps.as_ref().storage,
+            (&Addr::unchecked("addr0001"), &Addr::unchecked("delegate"))
+        ));
+        // The emissions are accumulated for the beneficiary the LP tokens were tracked for
+        assert_eq!(
+            res.messages.last().unwrap().msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: String::from("controller"),
+                msg: to_json_binary(&ControllerExecuteMsg::AccumUserEmissions(
+                    AccumEmissionsRequest {
+                        address: String::from("delegate"),
+                        previous_amount: Uint128::new(1_100),
+                    }
+                ))
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+    }
+
+    // Once the delegated LP tokens are untracked, the sender's own are
+    execute(deps.as_mut(), mock_env(), info, provide).unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("liquidity0000", &[]),
+        withdraw(130),
+    )
+    .unwrap();
+    assert_eq!(
+        tracked(deps.as_ref().storage, "delegate"),
+        Uint128::new(1_000)
+    );
+    assert_eq!(tracked(deps.as_ref().storage, "addr0001"), Uint128::new(20));
+}
+
+#[test]
+#[cfg(not(feature = "no-emissions"))]
+fn provide_liquidity_batch_notifies_the_controller_once() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_300),
+    }]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from("addr0000"), &Uint128::new(1_000))],
+        ),
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(1_000))],
+        ),
+    ]);
+    deps.querier.with_controller("controller");
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let token = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![uusd.clone(), token.clone()],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: None,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("zapper", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+    for (address, amount) in [("addr0000", 600u128), ("addr0001", 400)] {
+        LP_PROVIDERS
+            .save(
+                deps.as_mut().storage,
+                &Addr::unchecked(address),
+                &Uint128::new(amount),
+            )
+            .unwrap();
+    }
+
+    let provisions = ["addr0000", "addr0001", "addr0002"]
+        .into_iter()
+        .map(|receiver| LiquidityProvision {
+            assets: vec![uusd.with_balance(100u128), token.with_balance(100u128)],
+            receiver: Some(receiver.to_string()),
+        })
+        .collect();
+    let msg = ExecuteMsg::ProvideLiquidityBatch {
+        provisions,
+        slippage_tolerance: None,
+    };
+    let info = mock_info(
+        "zapper",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(300),
+        }],
+    );
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // One transfer for the whole batch and one mint per receiver
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("asset0000"),
+            msg: to_json_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: String::from("zapper"),
+                recipient: String::from(MOCK_CONTRACT_ADDR),
+                amount: Uint128::new(300),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+    assert_eq!(res.messages.len(), 5);
+
+    // The receivers that already provided are accumulated in a single controller call
+    let controller_msgs: Vec<_> = res
+        .messages
+        .iter()
+        .filter(|msg| {
+            matches!(&msg.msg, CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. })
+                if contract_addr == "controller")
+        })
+        .collect();
+    assert_eq!(controller_msgs.len(), 1);
+    assert_eq!(
+        controller_msgs[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("controller"),
+            msg: to_json_binary(&ControllerExecuteMsg::AccumUserEmissionsBatch(vec![
+                AccumEmissionsRequest {
+                    address: String::from("addr0000"),
+                    previous_amount: Uint128::new(600),
+                },
+                AccumEmissionsRequest {
+                    address: String::from("addr0001"),
+                    previous_amount: Uint128::new(400),
+                },
+            ]))
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+    assert_eq!(controller_msgs[0].reply_on, ReplyOn::Error);
+
+    for (address, amount) in [("addr0000", 700u128), ("addr0001", 500), ("addr0002", 100)] {
+        assert_eq!(
+            LP_PROVIDERS
+                .load(deps.as_ref().storage, &Addr::unchecked(address))
+                .unwrap(),
+            Uint128::new(amount)
+        );
+    }
+    assert!(res.attributes.contains(&attr("share", "300")));
+}
+
+#[test]
+fn swap_rejects_amounts_below_min_swap_amount() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_000_000_000),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &Uint128::new(1_000_000_000),
+        )],
+    )]);
+    deps.querier.with_min_swap_amount(Uint128::new(1_000));
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            uusd.clone(),
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        factory_addr: String::from("factory"),
+        init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let swap = |amount: u128| {
+        (
+            ExecuteMsg::Swap {
+                offer_asset: uusd.with_balance(amount),
+                ask_asset_info: None,
+                belief_price: None,
+                max_spread: None,
+                to: None,
+                to_hook: None,
+            },
+            mock_info(
+                "addr0000",
+                &[Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::new(amount),
+                }],
+            ),
+        )
+    };
+
+    // A dust swap would return nothing against this pool
+    let (msg, info) = swap(999);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::SwapTooSmall {}));
+
+    let (msg, info) = swap(1_000);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+}
+
+#[test]
+fn swap_rejects_price_impact_above_max_price_impact() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_000_000_000),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &Uint128::new(1_000_000_000),
+        )],
+    )]);
+    deps.querier.with_max_price_impact(Decimal::percent(1));
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            uusd.clone(),
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        factory_addr: String::from("factory"),
+        init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let swap = |amount: u128| {
+        (
+            ExecuteMsg::Swap {
+                offer_asset: uusd.with_balance(amount),
+                ask_asset_info: None,
+                belief_price: None,
+                max_spread: Some(Decimal::percent(50)),
+                to: None,
+                to_hook: None,
+            },
+            mock_info(
+                "addr0000",
+                &[Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::new(amount),
+                }],
+            ),
+        )
+    };
+
+    // Moves the price by about 20%
+    let (msg, info) = swap(100_000_000);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::PriceImpactTooHigh {}));
+
+    // Moves the price by about 0.2%
+    let (msg, info) = swap(1_000_000);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+}
+
+#[test]
+fn internal_swaps_reject_price_impact_above_max_price_impact() {
+    let mut deps = mock_dependencies(&[coin(1_200_000_000, "uusd")]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("liquidity0000"),
+            &[(
+                &String::from(MOCK_CONTRACT_ADDR),
+                &Uint128::new(1_000_000_000),
+            )],
+        ),
+        (
+            &String::from("asset0000"),
+            &[(
+                &String::from(MOCK_CONTRACT_ADDR),
+                &Uint128::new(1_000_000_000),
+            )],
+        ),
+    ]);
+    deps.querier.with_max_price_impact(Decimal::percent(1));
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            uusd.clone(),
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        factory_addr: String::from("factory"),
+        init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    // Balancing a one-sided deposit swaps about half of it
+    let msg = ExecuteMsg::ProvideLiquidityAutoBalance {
+        assets: vec![uusd.with_balance(200_000_000u128)],
+        max_spread: Some(Decimal::percent(50)),
+        receiver: None,
+    };
+    let info = mock_info("addr0000", &[coin(200_000_000, "uusd")]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::PriceImpactTooHigh {}));
+
+    // Withdrawing into one asset swaps the other half of the refund
+    deps.querier.with_balance(&[(
+        &String::from(MOCK_CONTRACT_ADDR),
+        &[coin(1_000_000_000, "uusd")],
+    )]);
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: String::from("addr0000"),
+        msg: to_json_binary(&Cw20HookMsg::WithdrawLiquidityOneSided {
+            ask_asset_info: uusd.clone(),
+            max_spread: Some(Decimal::percent(50)),
+        })
+        .unwrap(),
+        amount: Uint128::new(200_000_000),
+    });
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("liquidity0000", &[]),
+        msg,
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::PriceImpactTooHigh {}));
+}
+
+#[test]
+fn collected_fees_accumulate_per_ask_asset() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_000_000_000),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &Uint128::new(1_000_000_000),
+        )],
+    )]);
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let token = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![uusd.clone(), token.clone()],
+        token_code_id: Some(10u64),
+        factory_addr: String::from("factory"),
+        init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let commission = |res: Response| -> Uint128 {
+        let commission = res
+            .attributes
+            .iter()
+            .find(|attr| attr.key == "commission_amount")
+            .unwrap();
+        commission.value.parse().unwrap()
+    };
+
+    // Buy the token with uusd, the commission is charged in the token
+    let msg = ExecuteMsg::Swap {
+        offer_asset: uusd.with_balance(1_000_000u128),
+        ask_asset_info: None,
+        belief_price: None,
+        max_spread: None,
+        to: None,
+        to_hook: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(1_000_000),
+        }],
+    );
+    let token_fees = commission(execute(deps.as_mut(), mock_env(), info, msg).unwrap());
+
+    // Sell the token back, the commission is charged in uusd
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: String::from("addr0000"),
+        amount: Uint128::new(2_000_000),
+        msg: to_json_binary(&Cw20HookMsg::Swap {
+            ask_asset_info: None,
+            belief_price: None,
+            max_spread: None,
+            to: None,
+        })
+        .unwrap(),
+    });
+    let info = mock_info("asset0000", &[]);
+    let usd_fees = commission(execute(deps.as_mut(), mock_env(), info, msg).unwrap());
+    assert!(!token_fees.is_zero() && !usd_fees.is_zero());
+
+    let collected: Vec<Asset> =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::CollectedFees {}).unwrap()).unwrap();
+    assert_eq!(
+        collected,
+        vec![uusd.with_balance(usd_fees), token.with_balance(token_fees)]
+    );
+}
+
+#[test]
+fn internal_swap_commission_is_collected() {
+    let mut deps = mock_dependencies(&[coin(1_000_000_000, "uusd")]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("liquidity0000"),
+            &[(
+                &String::from(MOCK_CONTRACT_ADDR),
+                &Uint128::new(1_000_000_000),
+            )],
+        ),
+        (
+            &String::from("asset0000"),
+            &[(
+                &String::from(MOCK_CONTRACT_ADDR),
+                &Uint128::new(1_000_000_000),
+            )],
+        ),
+    ]);
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let token = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![uusd.clone(), token.clone()],
+        token_code_id: Some(10u64),
+        factory_addr: String::from("factory"),
+        init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+    let collected = |deps: Deps| -> Vec<Asset> {
+        from_json(query(deps, mock_env(), QueryMsg::CollectedFees {}).unwrap()).unwrap()
+    };
+
+    // Withdrawing into uusd swaps the token half of the refund
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: String::from("addr0000"),
+        msg: to_json_binary(&Cw20HookMsg::WithdrawLiquidityOneSided {
+            ask_asset_info: uusd.clone(),
+            max_spread: Some(Decimal::percent(50)),
+        })
+        .unwrap(),
+        amount: Uint128::new(10_000_000),
+    });
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("liquidity0000", &[]),
+        msg,
+    )
+    .unwrap();
+    let fees = collected(deps.as_ref());
+    assert!(!fees[0].amount.is_zero());
+    assert_eq!(fees[1], token.with_balance(0u128));
+
+    // Balancing a uusd deposit buys the token
+    deps.querier.with_balance(&[(
+        &String::from(MOCK_CONTRACT_ADDR),
+        &[coin(1_010_000_000, "uusd")],
+    )]);
+    let msg = ExecuteMsg::ProvideLiquidityAutoBalance {
+        assets: vec![uusd.with_balance(10_000_000u128)],
+        max_spread: Some(Decimal::percent(50)),
+        receiver: None,
+    };
+    let info = mock_info("addr0000", &[coin(10_000_000, "uusd")]);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let fees_after_provide = collected(deps.as_ref());
+    assert_eq!(fees_after_provide[0], fees[0]);
+    assert!(!fees_after_provide[1].amount.is_zero());
+}
+
+#[test]
+fn swap_rejects_extra_funds_attached() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_000_000_000),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &Uint128::new(1_000_000_000),
+        )],
+    )]);
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            uusd.clone(),
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        factory_addr: String::from("factory"),
+        init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::Swap {
+        offer_asset: uusd.with_balance(1_000u128),
+        ask_asset_info: None,
+        belief_price: None,
+        max_spread: None,
+        to: None,
+        to_hook: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[
+            Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(1_000),
+            },
+            Coin {
+                denom: "uluna".to_string(),
+                amount: Uint128::new(1_000),
+            },
+        ],
+    );
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::InvalidFundsAttached {}));
+}
+
+#[test]
+#[cfg(not(feature = "no-emissions"))]
+fn swap_keeps_commission_in_pool_without_gauge() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_000_000_000),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &Uint128::new(1_000_000_000),
+        )],
+    )]);
+    // The controller is set but every gauge lookup fails, so the fee info has no gauge
+    deps.querier.with_controller("controller");
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            uusd.clone(),
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        factory_addr: String::from("factory"),
+        init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let offer_amount = Uint128::new(1_000_000);
+    let msg = ExecuteMsg::Swap {
+        offer_asset: uusd.with_balance(offer_amount),
+        ask_asset_info: None,
+        belief_price: None,
+        max_spread: None,
+        to: None,
+        to_hook: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: offer_amount,
+        }],
+    );
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let attribute = |key: &str| {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .unwrap()
+            .value
+            .clone()
+    };
+    assert_ne!(attribute("commission_amount"), "0");
+    assert_eq!(attribute("gauge_fee_amount"), "0");
+    // Only the return amount leaves the pool, the commission stays with the LPs
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: String::from("asset0000"),
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: String::from("addr0000"),
+                amount: Uint128::new(attribute("return_amount").parse().unwrap()),
+            })
+            .unwrap(),
+            funds: vec![],
+        })]
+    );
+}
+
+#[test]
+#[cfg(not(feature = "no-emissions"))]
+fn swap_sends_no_fees_to_the_gauge() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_000_000_000),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &Uint128::new(1_000_000_000),
+        )],
+    )]);
+    // The gauge lookup succeeds, but the commission still stays in the pool for both ask assets
+    deps.querier.with_controller("controller");
+    deps.querier.with_gauge("gauge0000");
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let token = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![uusd.clone(), token.clone()],
+        token_code_id: Some(10u64),
+        factory_addr: String::from("factory"),
+        init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let offer_amount = Uint128::new(1_000_000);
+    let commission = |res: &Response| {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == "commission_amount")
+            .unwrap()
+            .value
+            .clone()
+    };
+
+    // Native offer, CW20 ask: only the return amount is transferred
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: offer_amount,
+        }],
+    );
+    let msg = ExecuteMsg::Swap {
+        offer_asset: uusd.with_balance(offer_amount),
+        ask_asset_info: None,
+        belief_price: None,
+        max_spread: None,
+        to: None,
+        to_hook: None,
+    };
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_ne!(commission(&res), "0");
+    assert!(res.attributes.contains(&attr("gauge_fee_amount", "0")));
+    assert_eq!(res.messages.len(), 1);
+    assert!(matches!(
+        &res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. }) if contract_addr == "asset0000"
+    ));
+
+    // CW20 offer, native ask: only the return amount is sent to the user
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: String::from("addr0000"),
+        amount: offer_amount,
+        msg: to_json_binary(&Cw20HookMsg::Swap {
+            ask_asset_info: None,
+            belief_price: None,
+            max_spread: None,
+            to: None,
+        })
+        .unwrap(),
+    });
+    let res = execute(deps.as_mut(), mock_env(), mock_info("asset0000", &[]), msg).unwrap();
+    assert_ne!(commission(&res), "0");
+    assert!(res.attributes.contains(&attr("gauge_fee_amount", "0")));
+    assert_eq!(res.messages.len(), 1);
+    assert!(matches!(
+        &res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send { to_address, .. }) if to_address == "addr0000"
+    ));
+}
+
+#[test]
+fn withdraw_preview_lp_price_grows_with_fees() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_000_000_000),
+    }]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("asset0000"),
+            &[(
+                &String::from(MOCK_CONTRACT_ADDR),
+                &Uint128::new(1_000_000_000),
+            )],
+        ),
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from("addr0000"), &Uint128::new(1_000_000_000))],
+        ),
+    ]);
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            uusd.clone(),
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        ],
+        token_code_id: Some(10u64),
+        factory_addr: String::from("factory"),
+        init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    let preview = |deps: Deps| -> WithdrawPreviewResponse {
+        query_withdraw_preview(deps, Uint128::new(100_000_000)).unwrap()
+    };
+    let initial = preview(deps.as_ref());
+    assert_eq!(initial.lp_price, Decimal::one());
+    assert_eq!(
+        initial.assets,
+        vec![
+            uusd.with_balance(100_000_000u128),
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            }
+            .with_balance(100_000_000u128),
+        ]
+    );
+
+    // The offer is already in the pool balance when the swap is executed
+    let offer_amount = Uint128::new(100_000_000);
+    deps.querier.with_balance(&[(
+        &String::from(MOCK_CONTRACT_ADDR),
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(1_000_000_000) + offer_amount,
+        }],
+    )]);
+    let msg = ExecuteMsg::Swap {
+        offer_asset: uusd.with_balance(offer_amount),
+        ask_asset_info: None,
+        belief_price: None,
+        max_spread: Some(Decimal::percent(50)),
+        to: None,
+        to_hook: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: offer_amount,
+        }],
+    );
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let return_amount: u128 = res
+        .attributes
+        .iter()
+        .find(|attr| attr.key == "return_amount")
+        .unwrap()
+        .value
+        .parse()
+        .unwrap();
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("asset0000"),
+            &[(
+                &String::from(MOCK_CONTRACT_ADDR),
+                &Uint128::new(1_000_000_000 - return_amount),
+            )],
         ),
         (
             &String::from("liquidity0000"),
-            &[(&String::from(MOCK_CONTRACT_ADDR), &total_share_amount)],
+            &[(&String::from("addr0000"), &Uint128::new(1_000_000_000))],
         ),
     ]);
 
+    // The commission stayed in the pool, so every LP token is worth more
+    assert!(preview(deps.as_ref()).lp_price > initial.lp_price);
+}
+
+#[test]
+fn rescue_tokens() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(0))],
+    )]);
+
     let msg = InstantiateMsg {
         asset_infos: vec![
             AssetInfo::NativeToken {
@@ -1217,216 +6290,255 @@ fn test_query_pool() {
         token_code_id: Some(10u64),
         factory_addr: String::from("factory"),
         init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
     };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
 
-    let env = mock_env();
-    let info = mock_info("addr0000", &[]);
-    // We can just call .unwrap() to assert this was a success
-    let _res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+    let rescue = |contract_addr: &str| ExecuteMsg::RescueTokens {
+        asset_info: AssetInfo::Token {
+            contract_addr: Addr::unchecked(contract_addr),
+        },
+        amount: Uint128::new(500),
+        recipient: String::from("addr0000"),
+    };
 
-    // Store liquidity token
-    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+    // Only the factory owner can rescue tokens
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        rescue("foreign0000"),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::Unauthorized {}));
 
-    let res: PoolResponse = query_pool(deps.as_ref()).unwrap();
+    // Neither the pool assets nor the LP token can be moved
+    for contract_addr in ["asset0000", "liquidity0000"] {
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            rescue(contract_addr),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::CannotRescuePoolAsset {});
+    }
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::RescueTokens {
+            asset_info: AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            amount: Uint128::new(500),
+            recipient: String::from("addr0000"),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::CannotRescuePoolAsset {});
 
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        rescue("foreign0000"),
+    )
+    .unwrap();
     assert_eq!(
-        res.assets,
-        [
-            Asset {
-                info: AssetInfo::NativeToken {
-                    denom: "uusd".to_string(),
-                },
-                amount: asset_0_amount
-            },
-            Asset {
-                info: AssetInfo::Token {
-                    contract_addr: Addr::unchecked("asset0000"),
-                },
-                amount: asset_1_amount
-            }
+        res.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: String::from("foreign0000"),
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: String::from("addr0000"),
+                amount: Uint128::new(500),
+            })
+            .unwrap(),
+            funds: vec![],
+        })]
+    );
+}
+
+#[test]
+fn query_all_lp_received_paginates() {
+    let mut deps = mock_dependencies(&[]);
+    let providers = ["addr0003", "addr0000", "addr0004", "addr0001", "addr0002"];
+    for (i, provider) in providers.iter().enumerate() {
+        LP_PROVIDERS
+            .save(
+                deps.as_mut().storage,
+                &Addr::unchecked(*provider),
+                &Uint128::new(100 * (i as u128 + 1)),
+            )
+            .unwrap();
+    }
+
+    let page = |start_after: Option<&str>, limit: Option<u32>| -> Vec<(Addr, Uint128)> {
+        query_all_lp_received(deps.as_ref(), start_after.map(String::from), limit).unwrap()
+    };
+
+    // Providers are returned ordered by address
+    let first = page(None, Some(2));
+    assert_eq!(
+        first,
+        vec![
+            (Addr::unchecked("addr0000"), Uint128::new(200)),
+            (Addr::unchecked("addr0001"), Uint128::new(400)),
         ]
     );
-    assert_eq!(res.total_share, total_share_amount);
+    let second = page(Some("addr0001"), Some(2));
+    assert_eq!(
+        second,
+        vec![
+            (Addr::unchecked("addr0002"), Uint128::new(500)),
+            (Addr::unchecked("addr0003"), Uint128::new(100)),
+        ]
+    );
+    assert_eq!(
+        page(Some("addr0003"), Some(2)),
+        vec![(Addr::unchecked("addr0004"), Uint128::new(300))]
+    );
+    assert_eq!(page(Some("addr0004"), None), vec![]);
+    // The default limit covers all providers, a large limit is capped
+    assert_eq!(page(None, None).len(), 5);
+    assert_eq!(page(None, Some(100)).len(), 5);
 }
 
 #[test]
-fn test_query_share() {
-    let total_share_amount = Uint128::from(500u128);
-    let asset_0_amount = Uint128::from(250u128);
-    let asset_1_amount = Uint128::from(1000u128);
+fn query_price_impact_grows_with_offer_size() {
     let mut deps = mock_dependencies(&[Coin {
         denom: "uusd".to_string(),
-        amount: asset_0_amount,
+        amount: Uint128::new(1_000_000_000_000),
     }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &Uint128::new(2_000_000_000_000),
+        )],
+    )]);
 
-    deps.querier.with_token_balances(&[
-        (
-            &String::from("asset0000"),
-            &[(&String::from(MOCK_CONTRACT_ADDR), &asset_1_amount)],
-        ),
-        (
-            &String::from("liquidity0000"),
-            &[(&String::from(MOCK_CONTRACT_ADDR), &total_share_amount)],
-        ),
-    ]);
-
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
     let msg = InstantiateMsg {
         asset_infos: vec![
-            AssetInfo::NativeToken {
-                denom: "uusd".to_string(),
-            },
+            uusd.clone(),
             AssetInfo::Token {
                 contract_addr: Addr::unchecked("asset0000"),
             },
         ],
         token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
         factory_addr: String::from("factory"),
         init_params: None,
     };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
 
-    let env = mock_env();
-    let info = mock_info("addr0000", &[]);
-    // We can just call .unwrap() to assert this was a success
-    let _res = instantiate(deps.as_mut(), env, info, msg).unwrap();
-
-    // Store liquidity token
-    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
-
-    let res = query_share(deps.as_ref(), Uint128::new(250)).unwrap();
-
-    assert_eq!(res[0].amount, Uint128::new(125));
-    assert_eq!(res[1].amount, Uint128::new(500));
-}
-
-fn mock_env_with_block_time(time: u64) -> Env {
-    let mut env = mock_env();
-    env.block = BlockInfo {
-        height: 1,
-        time: Timestamp::from_seconds(time),
-        chain_id: "columbus".to_string(),
+    let price_impact = |offer_amount: u128| {
+        query_price_impact(deps.as_ref(), uusd.with_balance(offer_amount), None).unwrap()
     };
-    env
-}
 
-#[test]
-fn compute_swap_rounding() {
-    let offer_pool = Uint128::from(5_000_000_000_000_u128);
-    let ask_pool = Uint128::from(1_000_000_000_u128);
-    let return_amount = Uint128::from(0_u128);
-    let spread_amount = Uint128::from(0_u128);
-    let commission_amount = Uint128::from(0_u128);
-    let offer_amount = Uint128::from(1_u128);
+    // Dust against the deep pool has no measurable impact
+    assert!(price_impact(1_000) < Decimal::from_ratio(1u8, 1_000_000u32));
+    // The impact is dx / (x + dx)
+    assert_eq!(price_impact(1_000_000_000_000), Decimal::percent(50));
+    let impacts = [1_000u128, 1_000_000, 1_000_000_000, 1_000_000_000_000].map(price_impact);
+    for pair in impacts.windows(2) {
+        assert!(pair[0] < pair[1]);
+    }
+    assert_eq!(price_impact(0), Decimal::zero());
 
+    let err = query_price_impact(
+        deps.as_ref(),
+        uusd.with_balance(1_000u128),
+        Some(uusd.clone()),
+    )
+    .unwrap_err();
     assert_eq!(
-        compute_swap(offer_pool, ask_pool, offer_amount, Decimal::zero()),
-        Ok((return_amount, spread_amount, commission_amount))
+        err,
+        StdError::generic_err("Source and target assets are the same")
     );
 }
 
-proptest! {
-    #[test]
-    fn compute_swap_overflow_test(
-        offer_pool in 1_000_000..9_000_000_000_000_000_000u128,
-        ask_pool in 1_000_000..9_000_000_000_000_000_000u128,
-        offer_amount in 1..100_000_000000u128,
-    ) {
-
-        let offer_pool = Uint128::from(offer_pool);
-        let ask_pool = Uint128::from(ask_pool);
-        let offer_amount = Uint128::from(offer_amount);
-        let commission_amount = Decimal::zero();
-
-        // Make sure there are no overflows
-        compute_swap(
-            offer_pool,
-            ask_pool,
-            offer_amount,
-            commission_amount,
-        ).unwrap();
-    }
-}
-
 #[test]
-fn ensure_useful_error_messages_are_given_on_swaps() {
-    const OFFER: Uint128 = Uint128::new(1_000_000_000000);
-    const ASK: Uint128 = Uint128::new(1_000_000_000000);
-    const AMOUNT: Uint128 = Uint128::new(1_000000);
-    const ZERO: Uint128 = Uint128::zero();
-    const DZERO: Decimal = Decimal::zero();
+#[cfg(feature = "no-emissions")]
+fn swap_sends_commission_to_fee_address_without_emissions() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(1_000_000_000),
+    }]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &Uint128::new(1_000_000_000),
+        )],
+    )]);
 
-    // Computing ask
-    assert_eq!(
-        compute_swap(ZERO, ZERO, ZERO, DZERO).unwrap_err(),
-        StdError::generic_err("One of the pools is empty")
-    );
-    assert_eq!(
-        compute_swap(ZERO, ZERO, AMOUNT, DZERO).unwrap_err(),
-        StdError::generic_err("One of the pools is empty")
-    );
-    assert_eq!(
-        compute_swap(ZERO, ASK, ZERO, DZERO).unwrap_err(),
-        StdError::generic_err("One of the pools is empty")
-    );
-    assert_eq!(
-        compute_swap(ZERO, ASK, AMOUNT, DZERO).unwrap_err(),
-        StdError::generic_err("One of the pools is empty")
-    );
-    assert_eq!(
-        compute_swap(OFFER, ZERO, ZERO, DZERO).unwrap_err(),
-        StdError::generic_err("One of the pools is empty")
-    );
-    assert_eq!(
-        compute_swap(OFFER, ZERO, AMOUNT, DZERO).unwrap_err(),
-        StdError::generic_err("One of the pools is empty")
-    );
-    assert_eq!(
-        compute_swap(OFFER, ASK, ZERO, DZERO).unwrap_err(),
-        StdError::generic_err("Swap amount must not be zero")
-    );
-    compute_swap(OFFER, ASK, AMOUNT, DZERO).unwrap();
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let token = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![uusd.clone(), token],
+        token_code_id: Some(10u64),
+        factory_addr: String::from("factory"),
+        init_params: None,
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
 
-    // Computing offer
-    assert_eq!(
-        compute_offer_amount(ZERO, ZERO, ZERO, DZERO).unwrap_err(),
-        StdError::generic_err("One of the pools is empty")
-    );
-    assert_eq!(
-        compute_offer_amount(ZERO, ZERO, AMOUNT, DZERO).unwrap_err(),
-        StdError::generic_err("One of the pools is empty")
-    );
-    assert_eq!(
-        compute_offer_amount(ZERO, ASK, ZERO, DZERO).unwrap_err(),
-        StdError::generic_err("One of the pools is empty")
-    );
-    assert_eq!(
-        compute_offer_amount(ZERO, ASK, AMOUNT, DZERO).unwrap_err(),
-        StdError::generic_err("One of the pools is empty")
-    );
-    assert_eq!(
-        compute_offer_amount(OFFER, ZERO, ZERO, DZERO).unwrap_err(),
-        StdError::generic_err("One of the pools is empty")
-    );
-    assert_eq!(
-        compute_offer_amount(OFFER, ZERO, AMOUNT, DZERO).unwrap_err(),
-        StdError::generic_err("One of the pools is empty")
+    let offer_amount = Uint128::new(1_000_000);
+    let msg = ExecuteMsg::Swap {
+        offer_asset: uusd.with_balance(offer_amount),
+        ask_asset_info: None,
+        belief_price: None,
+        max_spread: None,
+        to: None,
+        to_hook: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: offer_amount,
+        }],
     );
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let attribute = |key: &str| -> Uint128 {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .unwrap()
+            .value
+            .parse()
+            .unwrap()
+    };
+    let transfer = |recipient: &str, amount: Uint128| {
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: String::from("asset0000"),
+            msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: String::from(recipient),
+                amount,
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    };
+    // The factory fee address receives the commission
     assert_eq!(
-        compute_offer_amount(OFFER, ASK, ZERO, DZERO).unwrap_err(),
-        StdError::generic_err("Swap amount must not be zero")
+        res.messages,
+        vec![
+            transfer("addr0000", attribute("return_amount")),
+            transfer("owner", attribute("commission_amount")),
+        ]
     );
-    compute_offer_amount(OFFER, ASK, AMOUNT, DZERO).unwrap();
-}
-
-#[test]
-fn compute_swap_does_not_panic_on_spread_calc() {
-    let offer_pool = Uint128::from(u128::MAX / 2);
-    let ask_pool = Uint128::from(u128::MAX / 1000000000);
-    let offer_amount = Uint128::from(1000000000u128);
-    let commission_rate = Decimal::permille(3);
-
-    let (return_amount, spread_amount, commission_amount) =
-        compute_swap(offer_pool, ask_pool, offer_amount, commission_rate).unwrap();
-    assert_eq!(return_amount, Uint128::from(2u128));
-    assert_eq!(spread_amount, Uint128::zero());
-    assert_eq!(commission_amount, Uint128::zero());
 }
@@ -3,26 +3,30 @@ use std::vec;
 
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    attr, from_json, to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal, Decimal256,
-    Deps, DepsMut, Env, Fraction, MessageInfo, QuerierWrapper, Reply, ReplyOn, Response, StdError,
-    StdResult, SubMsg, SubMsgResult, Uint128, WasmMsg,
+    attr, from_json, has_coins, to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal,
+    Decimal256, Deps, DepsMut, Env, Fraction, MessageInfo, QuerierWrapper, Reply, ReplyOn,
+    Response, StdError, StdResult, SubMsg, SubMsgResult, Uint128, Uint64, WasmMsg,
 };
 use cw2::{get_contract_version, set_contract_version};
-use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, MinterResponse, TokenInfoResponse};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, MinterResponse};
 use cw_utils::parse_instantiate_response_data;
 use itertools::Itertools;
+#[cfg(not(feature = "no-emissions"))]
 use ura::contracts::controller::{AccumEmissionsRequest, ExecuteMsg as ControllerExecuteMsg};
+#[cfg(not(feature = "no-emissions"))]
 use ura::contracts::gauge::{Cw20HookMsg as GaugeHookMsg, ExecuteMsg as GaugeExecuteMsg};
 use ura::contracts::token::InstantiateMsg as TokenInstantiateMsg;
 
 use ura::contracts::pair::MINIMUM_LIQUIDITY_AMOUNT;
-use ura::structs::asset::Asset;
-use ura::structs::asset_info::AssetInfo;
+use ura::structs::asset::{assets_into_msgs, Asset};
+use ura::structs::asset_info::{AssetInfo, AssetInfoExt};
 use ura::structs::coin::CoinsExt;
 use ura::structs::decimal256::Decimal256Ext;
 use ura::structs::decimal256_asset::Decimal256Asset;
+use ura::structs::fee_info::FeeInfo;
 use ura::structs::pair_info::PairInfo;
-use ura::utils::format::format_lp_token_name;
+use ura::utils::fees::{accumulate_fees, read_cumulative_fees};
+use ura::utils::format::{format_lp_token_name, native_lp_denom};
 use ura::utils::validation::{addr_opt_validate, check_swap_parameters};
 
 use ura::contracts::factory::PairType;
@@ -30,23 +34,32 @@ use ura::contracts::pair::{
     ConfigResponse, InstantiateMsg, StablePoolParams, StablePoolUpdateParams, DEFAULT_SLIPPAGE,
     MAX_ALLOWED_SLIPPAGE,
 };
+use ura::errors::DexError;
 
 use crate::denom::{MsgBurn, MsgCreateDenom};
 use ura::contracts::pair::{
-    Cw20HookMsg, ExecuteMsg, MigrateMsg, PoolResponse, QueryMsg, ReverseSimulationResponse,
-    SimulationResponse, StablePoolConfig,
+    CanExecuteResponse, Cw20HookMsg, ExecuteMsg, MigrateMsg, PoolResponse, QueryMsg,
+    QuoteBothResponse, ReverseSimulationResponse, SimulateProvideResponse, SimulationResponse,
+    StablePoolConfig, SwapRateResponse, WithdrawPreviewResponse,
 };
-use ura::utils::querier::{query_factory_config, query_fee_info};
-use ura::DecimalCheckedOps;
+#[cfg(not(feature = "no-emissions"))]
+use ura::utils::querier::query_fee_info;
+use ura::utils::querier::{query_factory_config, query_fee_info_basic, query_total_supply};
+use ura::{to_decimal, DecimalCheckedOps};
 
 use crate::error::ContractError;
 use crate::math::{
-    calc_y, compute_d, AMP_PRECISION, MAX_AMP, MAX_AMP_CHANGE, MIN_AMP_CHANGING_TIME,
+    calc_y, compute_d, compute_spot_price, AMP_PRECISION, MAX_AMP, MAX_AMP_CHANGE, MIN_AMP,
+    MIN_AMP_CHANGING_TIME,
+};
+use crate::state::{
+    get_precision, get_precisions, read_d_snapshots, read_lp_providers, record_d_snapshot,
+    store_precisions, untrack_lp_provider, Config, CONFIG, DELEGATED_LP, LP_PROVIDERS,
 };
-use crate::state::{get_precision, store_precisions, Config, CONFIG, LP_PROVIDERS};
 use crate::utils::{
-    adjust_precision, check_asset_infos, check_assets, check_cw20_in_pool, compute_current_amp,
-    compute_swap, get_share_in_assets, mint_liquidity_token_message, select_pools, SwapResult,
+    check_asset_infos, check_assets, check_cw20_in_pool, compute_amp_at, compute_current_amp,
+    compute_fee_rate, compute_swap, get_share_in_assets, mint_liquidity_token_message,
+    select_pools, SwapResult,
 };
 
 /// Contract name that is used for migration.
@@ -56,15 +69,19 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 /// A `reply` call code ID of sub-message.
 const INSTANTIATE_NATIVE_REPLY_ID: u64 = 1;
 const INSTANTIATE_CW20_REPLY_ID: u64 = 2;
+#[cfg(not(feature = "no-emissions"))]
+const ACCUM_EMISSIONS_REPLY_ID: u64 = 3;
 /// Number of assets in the pool.
 const N_COINS: usize = 2;
+/// Maximum number of times a reverse swap is re-estimated at the dynamic fee of its result.
+const REVERSE_FEE_ITERATIONS: usize = 16;
 
 /// Creates a new contract with the specified parameters in [`InstantiateMsg`].
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     mut deps: DepsMut,
     env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
     check_asset_infos(deps.api, &msg.asset_infos)?;
@@ -79,10 +96,16 @@ pub fn instantiate(
 
     let params: StablePoolParams = from_json(&msg.init_params.unwrap())?;
 
-    if params.amp == 0 || params.amp > MAX_AMP {
+    if !(MIN_AMP..=MAX_AMP).contains(&params.amp) {
         return Err(ContractError::IncorrectAmp {});
     }
 
+    if let Some(dynamic_fee) = &params.dynamic_fee {
+        if dynamic_fee.mid_fee > dynamic_fee.out_fee || dynamic_fee.out_fee >= Decimal::one() {
+            return Err(ContractError::InvalidDynamicFee {});
+        }
+    }
+
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     let factory_addr = deps.api.addr_validate(&msg.factory_addr)?;
@@ -91,6 +114,16 @@ pub fn instantiate(
     let token_name = format_lp_token_name(&msg.asset_infos, &deps.querier)?;
     let mut sub_msgs: Vec<SubMsg> = vec![];
     let liquidity_token = if let Some(token_code_id) = msg.token_code_id {
+        // The factory owner can migrate the LP token unless the pair opts out
+        let lp_admin = if msg.immutable_lp {
+            None
+        } else {
+            Some(
+                query_factory_config(&deps.querier, &factory_addr)?
+                    .owner
+                    .to_string(),
+            )
+        };
         sub_msgs.push(SubMsg {
             msg: WasmMsg::Instantiate {
                 code_id: token_code_id,
@@ -106,7 +139,7 @@ pub fn instantiate(
                     marketing: None,
                 })?,
                 funds: vec![],
-                admin: None,
+                admin: lp_admin,
                 label: String::from("Ura LP token"),
             }
             .into(),
@@ -119,6 +152,17 @@ pub fn instantiate(
             contract_addr: Addr::unchecked(""),
         }
     } else {
+        if !msg
+            .denom_creation_fee
+            .iter()
+            .all(|fee| has_coins(&info.funds, fee))
+        {
+            return Err(ContractError::Dex(
+                DexError::InsufficientDenomCreationFee {},
+            ));
+        }
+
+        // The denom creation fee is charged from the pair balance, which holds the sent funds
         sub_msgs.push(SubMsg {
             id: INSTANTIATE_NATIVE_REPLY_ID,
             msg: MsgCreateDenom {
@@ -131,11 +175,7 @@ pub fn instantiate(
         });
 
         AssetInfo::NativeToken {
-            denom: format!(
-                "factory/{}/{}",
-                env.contract.address.to_string(),
-                token_name
-            ),
+            denom: native_lp_denom(&env.contract.address, &token_name),
         }
     };
 
@@ -152,6 +192,8 @@ pub fn instantiate(
         next_amp: params.amp * AMP_PRECISION,
         next_amp_time: env.block.time.seconds(),
         greatest_precision,
+        dynamic_fee: params.dynamic_fee,
+        owner: addr_opt_validate(deps.api, &params.owner)?,
     };
 
     CONFIG.save(deps.storage, &config)?;
@@ -163,6 +205,15 @@ pub fn instantiate(
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
     match msg {
+        // A failed emission accounting call must not revert the LP operation that triggered it
+        #[cfg(not(feature = "no-emissions"))]
+        Reply {
+            id: ACCUM_EMISSIONS_REPLY_ID,
+            result: SubMsgResult::Err(reason),
+        } => Ok(Response::new().add_attributes(vec![
+            attr("action", "accum_emissions_failed"),
+            attr("reason", reason),
+        ])),
         Reply {
             id: reply_id,
             result: SubMsgResult::Ok(res),
@@ -174,9 +225,9 @@ pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractE
             };
 
             if !is_cw20_in_config && reply_id != INSTANTIATE_NATIVE_REPLY_ID {
-                return Err(ContractError::InvalidState {});
+                return Err(ContractError::Dex(DexError::InvalidState {}));
             } else if is_cw20_in_config && reply_id != INSTANTIATE_CW20_REPLY_ID {
-                return Err(ContractError::InvalidState {});
+                return Err(ContractError::Dex(DexError::InvalidState {}));
             };
 
             let liquidity_token_addr = match config.pair_info.liquidity_token {
@@ -197,7 +248,12 @@ pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractE
 
             Ok(Response::new().add_attribute("liquidity_token_addr", liquidity_token_addr))
         }
-        _ => Err(ContractError::FailedToParseReply {}),
+        Reply {
+            result: SubMsgResult::Err(reason),
+            ..
+        } => Err(ContractError::Dex(
+            DexError::LiquidityTokenInstantiationFailed { reason },
+        )),
     }
 }
 
@@ -232,21 +288,46 @@ pub fn execute(
     match msg {
         ExecuteMsg::UpdateConfig { params } => update_config(deps, env, info, params),
         ExecuteMsg::Receive(msg) => receive_cw20(deps, env, info, msg),
+        ExecuteMsg::ProvideLiquidity { owner: Some(_), .. } => {
+            Err(ContractError::Dex(DexError::NonSupported {}))
+        }
         ExecuteMsg::ProvideLiquidity {
-            assets, receiver, ..
-        } => provide_liquidity(deps, env, info, assets, receiver),
-        ExecuteMsg::WithdrawLiquidity {} => {
+            assets,
+            receiver,
+            beneficiary,
+            ..
+        } => provide_liquidity(deps, env, info, assets, receiver, beneficiary),
+        ExecuteMsg::ProvideLiquidityBatch { .. } => {
+            Err(ContractError::Dex(DexError::NonSupported {}))
+        }
+        ExecuteMsg::ProvideLiquidityAutoBalance { .. } => {
+            Err(ContractError::Dex(DexError::NonSupported {}))
+        }
+        ExecuteMsg::RescueTokens { .. } => Err(ContractError::Dex(DexError::NonSupported {})),
+        ExecuteMsg::MigrateLpToken { .. } => Err(ContractError::Dex(DexError::NonSupported {})),
+        ExecuteMsg::ClaimMigratedLp {} => Err(ContractError::Dex(DexError::NonSupported {})),
+        ExecuteMsg::SetProvideOperator { .. } => Err(ContractError::Dex(DexError::NonSupported {})),
+        ExecuteMsg::CompoundFees { .. } => Err(ContractError::Dex(DexError::NonSupported {})),
+        ExecuteMsg::WithdrawLiquidityOneSided { .. } => {
+            Err(ContractError::Dex(DexError::NonSupported {}))
+        }
+        ExecuteMsg::EmergencyWithdraw {} => Err(ContractError::Dex(DexError::NonSupported {})),
+        ExecuteMsg::Swap {
+            to_hook: Some(_), ..
+        } => Err(ContractError::Dex(DexError::NonSupported {})),
+        ExecuteMsg::WithdrawLiquidity { beneficiary } => {
             let config = CONFIG.load(deps.storage)?;
             let lp_denom = match config.pair_info.liquidity_token {
                 AssetInfo::NativeToken { denom } => Ok(denom),
-                AssetInfo::Token { .. } => Err(ContractError::NonSupported {}),
+                AssetInfo::Token { .. } => Err(ContractError::Dex(DexError::NonSupported {})),
             }?;
             if info.funds.len() != 1 || !info.funds[0].denom.eq(&lp_denom) {
-                return Err(ContractError::InvalidLiquidityToken {});
+                return Err(ContractError::Dex(DexError::InvalidLiquidityToken {}));
             }
             let share = info.funds[0].amount;
             let sender = info.sender.clone();
-            withdraw_liquidity(deps, info, env, sender, share)
+            let beneficiary = addr_opt_validate(deps.api, &beneficiary)?;
+            withdraw_liquidity(deps, info, env, sender, share, beneficiary)
         }
         ExecuteMsg::Swap {
             offer_asset,
@@ -258,7 +339,10 @@ pub fn execute(
         } => {
             offer_asset.info.check(deps.api)?;
             if !offer_asset.is_native_token() {
-                return Err(ContractError::Cw20DirectSwap {});
+                return Err(ContractError::Dex(DexError::Cw20DirectSwap {}));
+            }
+            if info.funds.len() > 1 {
+                return Err(ContractError::Dex(DexError::InvalidFundsAttached {}));
             }
             offer_asset.assert_sent_native_token_balance(&info)?;
 
@@ -316,27 +400,34 @@ pub fn receive_cw20(
                 to_addr,
             )
         }
-        Cw20HookMsg::WithdrawLiquidity {} => {
+        Cw20HookMsg::WithdrawLiquidity { beneficiary } => {
             let config = CONFIG.load(deps.storage)?;
 
             match config.pair_info.liquidity_token {
-                AssetInfo::NativeToken { .. } => Err(ContractError::NonSupported {}),
+                AssetInfo::NativeToken { .. } => Err(ContractError::Dex(DexError::NonSupported {})),
                 AssetInfo::Token { contract_addr } => {
                     if info.sender != contract_addr {
-                        return Err(ContractError::Unauthorized {});
+                        return Err(ContractError::Dex(DexError::Unauthorized {}));
                     }
                     Ok(contract_addr)
                 }
             }?;
 
+            let beneficiary = addr_opt_validate(deps.api, &beneficiary)?;
             withdraw_liquidity(
                 deps,
                 info,
                 env,
                 Addr::unchecked(cw20_msg.sender),
                 cw20_msg.amount,
+                beneficiary,
             )
         }
+        Cw20HookMsg::WithdrawLiquidityOneSided { .. } => {
+            Err(ContractError::Dex(DexError::NonSupported {}))
+        }
+        Cw20HookMsg::EmergencyWithdraw {} => Err(ContractError::Dex(DexError::NonSupported {})),
+        Cw20HookMsg::ClaimMigratedLp {} => Err(ContractError::Dex(DexError::NonSupported {})),
     }
 }
 
@@ -346,6 +437,9 @@ pub fn receive_cw20(
 ///
 /// * **receiver** address that receives LP tokens. If this address isn't specified, the function will default to the caller.
 ///
+/// * **beneficiary** address the controller accrues the emissions of the receiver's LP tokens to. If this address isn't specified, the function will default to the receiver.
+///   Another beneficiary is only allowed if the LP tokens go to the caller.
+///
 /// NOTE - the address that wants to provide liquidity should approve the pair contract to pull its relevant tokens.
 pub fn provide_liquidity(
     deps: DepsMut,
@@ -353,11 +447,14 @@ pub fn provide_liquidity(
     info: MessageInfo,
     assets: Vec<Asset>,
     receiver: Option<String>,
+    beneficiary: Option<String>,
 ) -> Result<Response, ContractError> {
     check_assets(deps.api, &assets)?;
 
     let config = CONFIG.load(deps.storage)?;
     let pool_address = env.clone().contract.address;
+    info.funds
+        .assert_no_extra_funds(&config.pair_info.asset_infos)?;
     info.funds
         .assert_coins_properly_sent(&assets, &config.pair_info.asset_infos)?;
 
@@ -409,7 +506,7 @@ pub fn provide_liquidity(
     });
 
     if !non_zero_flag {
-        return Err(ContractError::InvalidZeroAmount {});
+        return Err(ContractError::Dex(DexError::InvalidZeroAmount {}));
     }
 
     let mut messages = vec![];
@@ -452,7 +549,90 @@ pub fn provide_liquidity(
         })
         .collect::<StdResult<Vec<(Decimal256Asset, Decimal256)>>>()?;
 
-    let amp = compute_current_amp(&config, &env)?;
+    let total_share = query_total_supply(&deps.querier, &config.pair_info.liquidity_token)?;
+    let share = compute_provide_share(&config, &env, &assets_collection, total_share)?;
+
+    if total_share.is_zero() {
+        messages.extend(mint_liquidity_token_message(
+            deps.querier,
+            &config,
+            &env.contract.address,
+            &env.contract.address,
+            MINIMUM_LIQUIDITY_AMOUNT,
+        )?);
+    }
+
+    // Mint LP token for the caller (or for the receiver if it was set)
+    let receiver = addr_opt_validate(deps.api, &receiver)?.unwrap_or_else(|| info.sender.clone());
+    let beneficiary =
+        addr_opt_validate(deps.api, &beneficiary)?.unwrap_or_else(|| receiver.clone());
+    // Only LP tokens minted to the sender can accrue their emissions to another address
+    if beneficiary != receiver && receiver != info.sender {
+        return Err(ContractError::Dex(DexError::Unauthorized {}));
+    }
+    messages.extend(mint_liquidity_token_message(
+        deps.querier,
+        &config,
+        &env.contract.address,
+        &receiver,
+        share,
+    )?);
+
+    // Stores the amount of lp tokens is sent to the lp_provider for emission calculations
+    // Calls gauge controller to accum the emission rewards first
+    // The LP tokens are tracked for the address the emissions accrue to
+    if beneficiary != receiver {
+        DELEGATED_LP.update(
+            deps.storage,
+            (&receiver, &beneficiary),
+            |delegated| -> StdResult<_> { Ok(delegated.unwrap_or_default().checked_add(share)?) },
+        )?;
+    }
+    let mut lp_amount_before_providing = Uint128::zero();
+    LP_PROVIDERS.update(
+        deps.storage,
+        &beneficiary,
+        |current_amount| -> StdResult<_> {
+            if let Some(current_amount) = current_amount {
+                lp_amount_before_providing = current_amount;
+                Ok(current_amount.checked_add(share)?)
+            } else {
+                Ok(share)
+            }
+        },
+    )?;
+    let accum_msgs = accum_emissions_msgs(
+        &deps.querier,
+        &config,
+        &pool_address,
+        &beneficiary,
+        lp_amount_before_providing,
+    )?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(accum_msgs)
+        .add_attributes(vec![
+            attr("action", "provide_liquidity"),
+            attr("sender", info.sender),
+            attr("receiver", receiver),
+            attr("assets", assets.iter().join(", ")),
+            attr("share", share),
+        ]))
+}
+
+/// Returns the amount of LP tokens minted for depositing `assets_collection`, a list of deposits
+/// together with the pool amounts before the deposit.
+///
+/// The first deposit mints the D invariant minus [`MINIMUM_LIQUIDITY_AMOUNT`], which is locked in
+/// the pool. Later deposits mint in proportion to the growth of D.
+fn compute_provide_share(
+    config: &Config,
+    env: &Env,
+    assets_collection: &[(Decimal256Asset, Decimal256)],
+    total_share: Uint128,
+) -> Result<Uint128, ContractError> {
+    let amp = compute_current_amp(config, env)?;
 
     // Invariant (D) after deposit added
     let new_balances = assets_collection
@@ -461,36 +641,18 @@ pub fn provide_liquidity(
         .collect::<StdResult<Vec<_>>>()?;
     let deposit_d = compute_d(amp, &new_balances)?;
 
-    let total_share = match &config.pair_info.liquidity_token {
-        AssetInfo::NativeToken { denom } => deps.querier.query_supply(denom)?.amount,
-        AssetInfo::Token { contract_addr } => {
-            let res: TokenInfoResponse = deps
-                .querier
-                .query_wasm_smart(contract_addr, &Cw20QueryMsg::TokenInfo {})?;
-            res.total_supply
-        }
-    };
-
-    let share = if total_share.is_zero() {
+    if total_share.is_zero() {
         let share = deposit_d
             .to_uint128_with_precision(config.greatest_precision)?
             .checked_sub(MINIMUM_LIQUIDITY_AMOUNT)
-            .map_err(|_| ContractError::MinimumLiquidityAmountError {})?;
+            .map_err(|_| ContractError::Dex(DexError::MinimumLiquidityAmountError {}))?;
 
         // share cannot become zero after minimum liquidity subtraction
         if share.is_zero() {
-            return Err(ContractError::MinimumLiquidityAmountError {});
+            return Err(ContractError::Dex(DexError::MinimumLiquidityAmountError {}));
         }
 
-        messages.extend(mint_liquidity_token_message(
-            deps.querier,
-            &config,
-            &env.contract.address,
-            &env.contract.address,
-            MINIMUM_LIQUIDITY_AMOUNT,
-        )?);
-
-        share
+        Ok(share)
     } else {
         // Initial invariant (D)
         let old_balances = assets_collection
@@ -507,70 +669,23 @@ pub fn provide_liquidity(
             return Err(ContractError::LiquidityAmountTooSmall {});
         }
 
-        share
-    };
-
-    // Mint LP token for the caller (or for the receiver if it was set)
-    let receiver = addr_opt_validate(deps.api, &receiver)?.unwrap_or_else(|| info.sender.clone());
-    messages.extend(mint_liquidity_token_message(
-        deps.querier,
-        &config,
-        &env.contract.address,
-        &receiver,
-        share,
-    )?);
-
-    // Stores the amount of lp tokens is sent to the lp_provider for emission calculations
-    // Calls gauge controller to accum the emission rewards first
-    let mut lp_amount_before_providing = Uint128::zero();
-    LP_PROVIDERS.update(deps.storage, &receiver, |current_amount| -> StdResult<_> {
-        if let Some(current_amount) = current_amount {
-            lp_amount_before_providing = current_amount;
-            Ok(current_amount.checked_add(share)?)
-        } else {
-            Ok(share)
-        }
-    })?;
-    let fee_info = query_fee_info(
-        &deps.querier,
-        &config.factory_addr,
-        config.pair_info.pair_type.clone(),
-        &pool_address,
-    )?;
-    let controller = fee_info.controller_address;
-    if lp_amount_before_providing.ne(&Uint128::zero()) && controller.is_some() {
-        let controller = controller.unwrap();
-        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: controller.to_string(),
-            msg: to_json_binary(&ControllerExecuteMsg::AccumUserEmissions(
-                AccumEmissionsRequest {
-                    address: receiver.to_string(),
-                    previous_amount: lp_amount_before_providing,
-                },
-            ))?,
-            funds: vec![],
-        }));
+        Ok(share)
     }
-
-    Ok(Response::new().add_messages(messages).add_attributes(vec![
-        attr("action", "provide_liquidity"),
-        attr("sender", info.sender),
-        attr("receiver", receiver),
-        attr("assets", assets.iter().join(", ")),
-        attr("share", share),
-    ]))
 }
 
 /// Withdraw liquidity from the pool.
 /// * **sender** is the address that will receive assets back from the pair contract.
 ///
 /// * **amount** is the amount of LP tokens to burn.
+///
+/// * **beneficiary** is the address the controller accrues the emissions of the sender's LP tokens to, the sender if not specified.
 pub fn withdraw_liquidity(
     deps: DepsMut,
     _info: MessageInfo,
     env: Env,
     sender: Addr,
     amount: Uint128,
+    beneficiary: Option<Addr>,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     let pool_address = env.clone().contract.address;
@@ -579,13 +694,9 @@ pub fn withdraw_liquidity(
 
     let refund_assets = get_share_in_assets(&pools, amount, total_share);
 
-    let mut messages = refund_assets
-        .clone()
-        .into_iter()
-        .map(|asset| asset.into_msg(&sender))
-        .collect::<StdResult<Vec<_>>>()?;
+    let mut messages = assets_into_msgs(refund_assets.clone(), &sender)?;
 
-    let burn_msg: CosmosMsg = match config.pair_info.liquidity_token {
+    let burn_msg: CosmosMsg = match config.pair_info.liquidity_token.clone() {
         AssetInfo::NativeToken { denom } => MsgBurn {
             sender: env.contract.address.to_string(),
             amount: Some(crate::denom::Coin {
@@ -605,42 +716,94 @@ pub fn withdraw_liquidity(
 
     // Stores the amount of lp tokens is sent to the lp_provider for emission calculations
     // Calls gauge controller to accum the emission rewards first
-    let mut lp_amount_before_withdrawing = Uint128::zero();
-    LP_PROVIDERS.update(deps.storage, &sender, |current_amount| -> StdResult<_> {
-        if let Some(current_amount) = current_amount {
-            lp_amount_before_withdrawing = current_amount;
-            Ok(current_amount.checked_sub(amount.clone())?)
-        } else {
-            Ok(Uint128::zero())
-        }
-    })?;
+    // The LP tokens the sender provided for others are untracked first without another beneficiary
+    let tracked_for = beneficiary.unwrap_or_else(|| sender.clone());
+    if tracked_for != sender
+        && DELEGATED_LP
+            .may_load(deps.storage, (&sender, &tracked_for))?
+            .unwrap_or_default()
+            < amount
+    {
+        return Err(ContractError::Dex(DexError::Unauthorized {}));
+    }
+    let mut accum_msgs = vec![];
+    for (address, lp_amount_before_withdrawing) in
+        untrack_lp_provider(deps.storage, &sender, &tracked_for, amount)?
+    {
+        accum_msgs.extend(accum_emissions_msgs(
+            &deps.querier,
+            &config,
+            &pool_address,
+            &address,
+            lp_amount_before_withdrawing,
+        )?);
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(accum_msgs)
+        .add_attributes(vec![
+            attr("action", "withdraw_liquidity"),
+            attr("sender", sender),
+            attr("withdrawn_share", amount),
+            attr("refund_assets", refund_assets.iter().join(", ")),
+        ]))
+}
+
+/// Returns the controller call that accumulates the emissions of `address`, if the pool has a
+/// controller and the address held LP tokens before. The call is capped at the gas limit
+/// configured in the factory and its failure is caught in the reply.
+///
+/// * **previous_amount** LP tokens tracked for the address before the current operation.
+#[cfg(not(feature = "no-emissions"))]
+fn accum_emissions_msgs(
+    querier: &QuerierWrapper,
+    config: &Config,
+    pool_address: &Addr,
+    address: &Addr,
+    previous_amount: Uint128,
+) -> StdResult<Vec<SubMsg>> {
     let fee_info = query_fee_info(
-        &deps.querier,
+        querier,
         &config.factory_addr,
         config.pair_info.pair_type.clone(),
-        &pool_address,
+        pool_address,
     )?;
-    let controller = fee_info.controller_address;
-    if lp_amount_before_withdrawing.ne(&Uint128::zero()) && controller.is_some() {
-        let controller = controller.unwrap();
-        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+    let Some(controller) = fee_info.controller_address else {
+        return Ok(vec![]);
+    };
+    if previous_amount.is_zero() {
+        return Ok(vec![]);
+    }
+
+    Ok(vec![SubMsg {
+        id: ACCUM_EMISSIONS_REPLY_ID,
+        msg: WasmMsg::Execute {
             contract_addr: controller.to_string(),
             msg: to_json_binary(&ControllerExecuteMsg::AccumUserEmissions(
                 AccumEmissionsRequest {
-                    address: sender.to_string(),
-                    previous_amount: lp_amount_before_withdrawing,
+                    address: address.to_string(),
+                    previous_amount,
                 },
             ))?,
             funds: vec![],
-        }));
-    }
+        }
+        .into(),
+        gas_limit: fee_info.controller_gas_limit,
+        reply_on: ReplyOn::Error,
+    }])
+}
 
-    Ok(Response::new().add_messages(messages).add_attributes(vec![
-        attr("action", "withdraw_liquidity"),
-        attr("sender", sender),
-        attr("withdrawn_share", amount),
-        attr("refund_assets", refund_assets.iter().join(", ")),
-    ]))
+/// Pairs built without emissions have no controller to notify.
+#[cfg(feature = "no-emissions")]
+fn accum_emissions_msgs(
+    _querier: &QuerierWrapper,
+    _config: &Config,
+    _pool_address: &Addr,
+    _address: &Addr,
+    _previous_amount: Uint128,
+) -> StdResult<Vec<SubMsg>> {
+    Ok(vec![])
 }
 
 /// Performs an swap operation with the specified parameters.
@@ -656,6 +819,9 @@ pub fn withdraw_liquidity(
 /// * **to** sets the recipient of the swap operation.
 ///
 /// NOTE - the address that wants to swap should approve the pair contract to pull the offer token.
+///
+/// The commission stays in the pool, unless the pair is built with the `no-emissions` feature.
+/// Such pairs have no gauges and send the commission straight to the fee address.
 #[allow(clippy::too_many_arguments)]
 pub fn swap(
     deps: DepsMut,
@@ -720,13 +886,35 @@ pub fn swap(
     )?;
 
     // Get fee info from the factory
+    #[cfg(not(feature = "no-emissions"))]
     let fee_info = query_fee_info(
         &deps.querier,
         &config.factory_addr,
         config.pair_info.pair_type.clone(),
         &env.contract.address,
     )?;
-    let commission_amount = fee_info.total_fee_rate.checked_mul_uint128(return_amount)?;
+    #[cfg(feature = "no-emissions")]
+    let fee_info = query_fee_info_basic(
+        &deps.querier,
+        &config.factory_addr,
+        config.pair_info.pair_type.clone(),
+    )?;
+    if fee_info
+        .min_swap_amount
+        .is_some_and(|min_swap_amount| offer_asset.amount < min_swap_amount)
+    {
+        return Err(ContractError::Dex(DexError::SwapTooSmall {}));
+    }
+    let ask_precision = get_precision(deps.storage, &ask_pool.info)?;
+    let fee_rate = compute_fee_rate(
+        &config,
+        fee_info.total_fee_rate,
+        offer_pool.amount + offer_asset_dec.amount,
+        ask_pool
+            .amount
+            .saturating_sub(Decimal256::with_precision(return_amount, ask_precision)?),
+    )?;
+    let commission_amount = fee_rate.checked_mul_uint128(return_amount)?;
     let return_amount = return_amount.saturating_sub(commission_amount);
 
     // Check the max spread limit (if it was specified)
@@ -737,6 +925,42 @@ pub fn swap(
         return_amount + commission_amount,
         spread_amount,
     )?;
+    // The commission stays in the pool unless it is paid out to the fee address
+    let ask_amount_out = if cfg!(feature = "no-emissions") {
+        return_amount + commission_amount
+    } else {
+        return_amount
+    };
+    assert_price_impact(
+        fee_info.max_price_impact,
+        compute_current_amp(&config, &env)?,
+        offer_pool.amount,
+        ask_pool.amount,
+        offer_asset_dec.amount,
+        Decimal256::with_precision(ask_amount_out, ask_precision)?,
+    )?;
+    accumulate_fees(deps.storage, &ask_pool.info, commission_amount)?;
+
+    // Snapshot the post-swap D, the commission stays in the pool
+    record_d_snapshot(deps.storage, env.block.time.seconds(), || {
+        let returned = Decimal256::with_precision(return_amount, ask_precision)?;
+        let pools = pools
+            .iter()
+            .map(|pool| {
+                if pool.info.equal(&offer_pool.info) {
+                    pool.amount + offer_asset_dec.amount
+                } else if pool.info.equal(&ask_pool.info) {
+                    pool.amount.saturating_sub(returned)
+                } else {
+                    pool.amount
+                }
+            })
+            .collect::<Vec<_>>();
+
+        compute_d(compute_current_amp(&config, &env)?, &pools)
+            .map_err(|_| StdError::generic_err("Failed to calculate the D"))?
+            .to_uint128_with_precision(config.greatest_precision)
+    })?;
 
     let receiver = to.unwrap_or_else(|| sender.clone());
 
@@ -746,9 +970,20 @@ pub fn swap(
     };
 
     let mut messages = vec![];
-    if !return_amount.is_zero() {
-        messages.push(return_asset.into_msg(receiver.clone())?)
-    }
+    messages.extend(return_asset.into_msg_checked(&receiver, &env.contract.address)?);
+
+    // Without emissions there are no gauges, the commission goes straight to the fee address
+    // set for the ask asset, or the global one
+    #[cfg(feature = "no-emissions")]
+    messages.extend(
+        ask_pool
+            .info
+            .with_balance(commission_amount)
+            .into_msg_checked(
+                fee_info.fee_address_for(&ask_pool.info),
+                &env.contract.address,
+            )?,
+    );
 
     // Compute the fee for gauge
     let gauge_fee_amount = Uint128::zero();
@@ -792,6 +1027,30 @@ pub fn swap(
 /// pool using a [`CumulativePricesResponse`] object.
 ///
 /// * **QueryMsg::Config {}** Returns the configuration for the pair contract using a [`ConfigResponse`] object.
+///
+/// * **QueryMsg::SwapRate { offer_asset, ask_asset_info }** Returns the marginal and after fee swap rates using a [`SwapRateResponse`] object.
+///
+/// * **QueryMsg::PriceImpact { offer_asset, ask_asset_info }** Returns the price impact of a swap before fees.
+///
+/// * **QueryMsg::AllLpReceived { start_after, limit }** Returns the tracked LP balances of all providers.
+///
+/// * **QueryMsg::Precisions {}** Returns the stored precision of every pool asset.
+///
+/// * **QueryMsg::WithdrawPreview { amount }** Returns the withdrawn assets and the value per LP token using a [`WithdrawPreviewResponse`] object.
+///
+/// * **QueryMsg::SimulateProvide { assets }** Returns the LP tokens minted for a deposit using a [`SimulateProvideResponse`] object.
+///
+/// * **QueryMsg::AmpAt { timestamp }** Returns the amplification coefficient in effect at the given timestamp.
+///
+/// * **QueryMsg::QuoteBoth { offer_asset, ask_asset_info }** Returns both swap directions using a [`QuoteBothResponse`] object.
+///
+/// * **QueryMsg::EffectiveFeeRate { offer_asset }** Returns the fee rate a swap of `offer_asset` would pay.
+///
+/// * **QueryMsg::DHistory { limit }** Returns the most recent D snapshots, oldest first.
+///
+/// * **QueryMsg::CollectedFees {}** Returns the lifetime swap commission collected in each pool asset.
+///
+/// * **QueryMsg::SimulateAmpChange { target_amp, offer_asset, ask_asset_info }** Returns a swap simulation at `target_amp` using a [`SimulationResponse`] object.
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
@@ -813,10 +1072,73 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         )?),
         QueryMsg::Config {} => to_json_binary(&query_config(deps, env)?),
         QueryMsg::QueryComputeD {} => to_json_binary(&query_compute_d(deps, env)?),
+        QueryMsg::SwapRate {
+            offer_asset,
+            ask_asset_info,
+        } => to_json_binary(&query_swap_rate(deps, env, offer_asset, ask_asset_info)?),
+        QueryMsg::PriceImpact {
+            offer_asset,
+            ask_asset_info,
+        } => to_json_binary(&query_price_impact(deps, env, offer_asset, ask_asset_info)?),
+        QueryMsg::AllLpReceived { start_after, limit } => {
+            to_json_binary(&query_all_lp_received(deps, start_after, limit)?)
+        }
+        QueryMsg::Precisions {} => {
+            let config = CONFIG.load(deps.storage)?;
+            to_json_binary(&get_precisions(
+                deps.storage,
+                &config.pair_info.asset_infos,
+            )?)
+        }
+        QueryMsg::WithdrawPreview { amount } => {
+            to_json_binary(&query_withdraw_preview(deps, env, amount)?)
+        }
+        QueryMsg::SimulateProvide { assets } => {
+            to_json_binary(&query_simulate_provide(deps, env, assets)?)
+        }
+        QueryMsg::AmpAt { timestamp } => to_json_binary(&query_amp_at(deps, timestamp)?),
+        QueryMsg::QuoteBoth {
+            offer_asset,
+            ask_asset_info,
+        } => to_json_binary(&query_quote_both(deps, env, offer_asset, ask_asset_info)?),
+        QueryMsg::EffectiveFeeRate { offer_asset } => {
+            to_json_binary(&query_effective_fee_rate(deps, env, offer_asset)?)
+        }
+        QueryMsg::DHistory { limit } => to_json_binary(&read_d_snapshots(deps.storage, limit)?),
+        QueryMsg::CollectedFees {} => {
+            let config = CONFIG.load(deps.storage)?;
+            to_json_binary(&read_cumulative_fees(
+                deps.storage,
+                &config.pair_info.asset_infos,
+            )?)
+        }
+        QueryMsg::SimulateAmpChange {
+            target_amp,
+            offer_asset,
+            ask_asset_info,
+        } => to_json_binary(&query_simulate_amp_change(
+            deps,
+            env,
+            target_amp,
+            offer_asset,
+            ask_asset_info,
+        )?),
+        QueryMsg::CanExecute { address, .. } => to_json_binary(&query_can_execute(deps, address)?),
         _ => Err(StdError::generic_err("Query is not supported")),
     }
 }
 
+/// Returns whether `address` can currently perform an action in a [`CanExecuteResponse`] object.
+/// Stableswap pools don't restrict swaps, provides or withdrawals to particular addresses.
+pub fn query_can_execute(deps: Deps, address: String) -> StdResult<CanExecuteResponse> {
+    deps.api.addr_validate(&address)?;
+
+    Ok(CanExecuteResponse {
+        allowed: true,
+        reason: None,
+    })
+}
+
 /// Returns the amounts of assets in the pair contract as well as the amount of LP
 /// tokens currently minted in an object of type [`PoolResponse`].
 pub fn query_pool(deps: Deps) -> StdResult<PoolResponse> {
@@ -843,6 +1165,112 @@ pub fn query_share(deps: Deps, amount: Uint128) -> StdResult<Vec<Asset>> {
     Ok(refund_assets)
 }
 
+/// Returns the assets that could be withdrawn for `amount` LP tokens together with the pool
+/// value per LP token in a [`WithdrawPreviewResponse`] object.
+///
+/// The value per LP token is the D invariant divided by the total share, which grows as
+/// swap fees accrue in the pool.
+pub fn query_withdraw_preview(
+    deps: Deps,
+    env: Env,
+    amount: Uint128,
+) -> StdResult<WithdrawPreviewResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let (pools, total_share) = pool_info(deps.querier, &config)?;
+    let assets = get_share_in_assets(&pools, amount, total_share);
+
+    let lp_price = if total_share.is_zero() {
+        Decimal::zero()
+    } else {
+        Decimal::checked_from_ratio(query_compute_d(deps, env)?, total_share)
+            .map_err(|e| StdError::generic_err(e.to_string()))?
+    };
+
+    Ok(WithdrawPreviewResponse { assets, lp_price })
+}
+
+/// Returns the amount of LP tokens that providing `assets` would mint in a [`SimulateProvideResponse`] object.
+///
+/// The share is computed exactly like in `provide_liquidity`. The whole deposit backs the
+/// minted share, so the refund is always empty.
+pub fn query_simulate_provide(
+    deps: Deps,
+    env: Env,
+    assets: Vec<Asset>,
+) -> StdResult<SimulateProvideResponse> {
+    let to_std_err = |e: ContractError| StdError::generic_err(e.to_string());
+
+    check_assets(deps.api, &assets).map_err(to_std_err)?;
+    let config = CONFIG.load(deps.storage)?;
+    if assets.len() != config.pair_info.asset_infos.len() {
+        return Err(to_std_err(ContractError::InvalidNumberOfAssets(
+            config.pair_info.asset_infos.len(),
+        )));
+    }
+    if let Some(asset) = assets
+        .iter()
+        .find(|asset| !config.pair_info.asset_infos.contains(&asset.info))
+    {
+        return Err(to_std_err(ContractError::InvalidAsset(
+            asset.info.to_string(),
+        )));
+    }
+    if assets.iter().all(|asset| asset.amount.is_zero()) {
+        return Err(to_std_err(ContractError::Dex(
+            DexError::InvalidZeroAmount {},
+        )));
+    }
+
+    let assets_collection = config
+        .pair_info
+        .query_pools(&deps.querier, &env.contract.address)?
+        .into_iter()
+        .map(|pool| {
+            let deposit = assets
+                .iter()
+                .find(|asset| asset.info.equal(&pool.info))
+                .map_or(Uint128::zero(), |asset| asset.amount);
+            if deposit.is_zero() && pool.amount.is_zero() {
+                return Err(to_std_err(
+                    ContractError::InvalidProvideLPsWithSingleToken {},
+                ));
+            }
+
+            let coin_precision = get_precision(deps.storage, &pool.info)?;
+            Ok((
+                pool.info
+                    .with_balance(deposit)
+                    .to_decimal_asset(coin_precision)?,
+                Decimal256::with_precision(pool.amount, coin_precision)?,
+            ))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let total_share = query_total_supply(&deps.querier, &config.pair_info.liquidity_token)?;
+    let share = compute_provide_share(&config, &env, &assets_collection, total_share)
+        .map_err(to_std_err)?;
+
+    Ok(SimulateProvideResponse {
+        share,
+        refund: vec![],
+    })
+}
+
+/// Returns the tracked LP balances of all providers, ordered by address.
+///
+/// * **start_after** is the provider after which to start returning balances.
+///
+/// * **limit** is the maximum number of balances to return.
+pub fn query_all_lp_received(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(Addr, Uint128)>> {
+    let start_after = addr_opt_validate(deps.api, &start_after)?;
+
+    read_lp_providers(deps.storage, start_after.as_ref(), limit)
+}
+
 /// Returns information about a swap simulation in a [`SimulationResponse`] object.
 ///
 /// * **offer_asset** is the asset to swap as well as an amount of the said asset.
@@ -858,9 +1286,118 @@ pub fn query_simulation(
         &config.pair_info.contract_addr,
         &config.factory_addr,
     )?;
+    let fee_info = query_fee_info_basic(
+        &deps.querier,
+        &config.factory_addr,
+        config.pair_info.pair_type.clone(),
+    )?;
+
+    simulate_swap(
+        deps,
+        &env,
+        &config,
+        &pools,
+        &fee_info,
+        offer_asset,
+        ask_asset_info,
+    )
+}
+
+/// Returns the information about a swap simulation in a [`SimulationResponse`] object, computed
+/// as if the pool amplification was already `target_amp`. The pool state is left untouched.
+pub fn query_simulate_amp_change(
+    deps: Deps,
+    env: Env,
+    target_amp: u64,
+    offer_asset: Asset,
+    ask_asset_info: Option<AssetInfo>,
+) -> StdResult<SimulationResponse> {
+    if !(MIN_AMP..=MAX_AMP).contains(&target_amp) {
+        return Err(StdError::generic_err(
+            ContractError::IncorrectAmp {}.to_string(),
+        ));
+    }
+
+    let mut config = CONFIG.load(deps.storage)?;
+    config.init_amp = target_amp * AMP_PRECISION;
+    config.next_amp = target_amp * AMP_PRECISION;
+    let pools = config.pair_info.query_pools_decimal(
+        &deps.querier,
+        &config.pair_info.contract_addr,
+        &config.factory_addr,
+    )?;
+    let fee_info = query_fee_info_basic(
+        &deps.querier,
+        &config.factory_addr,
+        config.pair_info.pair_type.clone(),
+    )?;
+
+    simulate_swap(
+        deps,
+        &env,
+        &config,
+        &pools,
+        &fee_info,
+        offer_asset,
+        ask_asset_info,
+    )
+}
+
+/// Returns the fee rate a swap of `offer_asset` would pay. It differs from the factory fee rate
+/// when the pool charges a dynamic fee.
+pub fn query_effective_fee_rate(deps: Deps, env: Env, offer_asset: Asset) -> StdResult<Decimal> {
+    let config = CONFIG.load(deps.storage)?;
+    let pools = config.pair_info.query_pools_decimal(
+        &deps.querier,
+        &config.pair_info.contract_addr,
+        &config.factory_addr,
+    )?;
+    let fee_info = query_fee_info_basic(
+        &deps.querier,
+        &config.factory_addr,
+        config.pair_info.pair_type.clone(),
+    )?;
+
+    let (_, fee_rate) =
+        simulate_swap_with_fee_rate(deps, &env, &config, &pools, &fee_info, offer_asset, None)?;
+
+    Ok(fee_rate)
+}
+
+/// Simulates a swap of `offer_asset` against already loaded pools and fee info.
+fn simulate_swap(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    pools: &[Decimal256Asset],
+    fee_info: &FeeInfo,
+    offer_asset: Asset,
+    ask_asset_info: Option<AssetInfo>,
+) -> StdResult<SimulationResponse> {
+    simulate_swap_with_fee_rate(
+        deps,
+        env,
+        config,
+        pools,
+        fee_info,
+        offer_asset,
+        ask_asset_info,
+    )
+    .map(|(simulation, _)| simulation)
+}
 
+/// Same as [`simulate_swap`], also returning the fee rate applied to the swap.
+fn simulate_swap_with_fee_rate(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    pools: &[Decimal256Asset],
+    fee_info: &FeeInfo,
+    offer_asset: Asset,
+    ask_asset_info: Option<AssetInfo>,
+) -> StdResult<(SimulationResponse, Decimal)> {
     let (offer_pool, ask_pool) =
-        select_pools(Some(&offer_asset.info), ask_asset_info.as_ref(), &pools)
+        select_pools(Some(&offer_asset.info), ask_asset_info.as_ref(), pools)
             .map_err(|err| StdError::generic_err(format!("{err}")))?;
 
     let offer_precision = get_precision(deps.storage, &offer_pool.info)?;
@@ -877,45 +1414,181 @@ pub fn query_simulation(
     )
     .is_err()
     {
-        return Ok(SimulationResponse {
+        let simulation = SimulationResponse {
             return_amount: Uint128::zero(),
             spread_amount: Uint128::zero(),
             commission_amount: Uint128::zero(),
-        });
+        };
+        return Ok((simulation, fee_info.total_fee_rate));
     }
 
+    let offer_asset_dec = offer_asset.to_decimal_asset(offer_precision)?;
     let SwapResult {
         return_amount,
         spread_amount,
     } = compute_swap(
         deps.storage,
-        &env,
-        &config,
-        &offer_asset.to_decimal_asset(offer_precision)?,
+        env,
+        config,
+        &offer_asset_dec,
         &offer_pool,
         &ask_pool,
-        &pools,
+        pools,
     )
     .map_err(|err| StdError::generic_err(format!("{err}")))?;
 
-    // Get fee info from factory
-    let fee_info = query_fee_info(
-        &deps.querier,
-        &config.factory_addr,
-        config.pair_info.pair_type.clone(),
-        &env.contract.address,
+    let ask_precision = get_precision(deps.storage, &ask_pool.info)?;
+    let fee_rate = compute_fee_rate(
+        config,
+        fee_info.total_fee_rate,
+        offer_pool.amount + offer_asset_dec.amount,
+        ask_pool
+            .amount
+            .saturating_sub(Decimal256::with_precision(return_amount, ask_precision)?),
     )?;
 
-    let commission_amount = fee_info.total_fee_rate.checked_mul_uint128(return_amount)?;
+    let commission_amount = fee_rate.checked_mul_uint128(return_amount)?;
     let return_amount = return_amount.saturating_sub(commission_amount);
 
-    Ok(SimulationResponse {
+    let simulation = SimulationResponse {
         return_amount,
         spread_amount,
         commission_amount,
+    };
+    Ok((simulation, fee_rate))
+}
+
+/// Returns the marginal price of the pool and the average price of a swap net of fees in a
+/// [`SwapRateResponse`] object. The marginal price is approximated by swapping one whole offer
+/// token without fees. A zero offer amount returns the marginal price net of fees.
+///
+/// * **offer_asset** is the asset to swap as well as an amount of the said asset.
+pub fn query_swap_rate(
+    deps: Deps,
+    env: Env,
+    offer_asset: Asset,
+    ask_asset_info: Option<AssetInfo>,
+) -> StdResult<SwapRateResponse> {
+    let simulation = query_simulation(
+        deps,
+        env.clone(),
+        offer_asset.clone(),
+        ask_asset_info.clone(),
+    )?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let pools = config.pair_info.query_pools_decimal(
+        &deps.querier,
+        &config.pair_info.contract_addr,
+        &config.factory_addr,
+    )?;
+
+    let (offer_pool, ask_pool) =
+        select_pools(Some(&offer_asset.info), ask_asset_info.as_ref(), &pools)
+            .map_err(|err| StdError::generic_err(format!("{err}")))?;
+
+    let rate = if pools.iter().any(|pool| pool.amount.is_zero()) {
+        Decimal::zero()
+    } else {
+        let offer_precision = get_precision(deps.storage, &offer_pool.info)?;
+        let probe = Decimal256Asset {
+            info: offer_pool.info.clone(),
+            amount: Decimal256::one(),
+        };
+        let SwapResult { return_amount, .. } = compute_swap(
+            deps.storage,
+            &env,
+            &config,
+            &probe,
+            &offer_pool,
+            &ask_pool,
+            &pools,
+        )
+        .map_err(|err| StdError::generic_err(format!("{err}")))?;
+        to_decimal(Decimal256::from_ratio(
+            return_amount,
+            10u128.pow(offer_precision.into()),
+        ))?
+    };
+
+    let rate_after_fees = if offer_asset.amount.is_zero() {
+        let fee_info = query_fee_info_basic(
+            &deps.querier,
+            &config.factory_addr,
+            config.pair_info.pair_type.clone(),
+        )?;
+        let fee_rate = compute_fee_rate(
+            &config,
+            fee_info.total_fee_rate,
+            offer_pool.amount,
+            ask_pool.amount,
+        )?;
+        rate * (Decimal::one() - fee_rate)
+    } else {
+        to_decimal(Decimal256::from_ratio(
+            simulation.return_amount,
+            offer_asset.amount,
+        ))?
+    };
+
+    Ok(SwapRateResponse {
+        rate,
+        rate_after_fees,
     })
 }
 
+/// Returns the price impact of a swap before fees. The spot price is derived from the
+/// invariant (see [`compute_spot_price`]) and the execution price from [`compute_swap`].
+///
+/// * **offer_asset** is the asset to swap as well as an amount of the said asset.
+pub fn query_price_impact(
+    deps: Deps,
+    env: Env,
+    offer_asset: Asset,
+    ask_asset_info: Option<AssetInfo>,
+) -> StdResult<Decimal> {
+    let config = CONFIG.load(deps.storage)?;
+    let pools = config.pair_info.query_pools_decimal(
+        &deps.querier,
+        &config.pair_info.contract_addr,
+        &config.factory_addr,
+    )?;
+
+    let (offer_pool, ask_pool) =
+        select_pools(Some(&offer_asset.info), ask_asset_info.as_ref(), &pools)
+            .map_err(|err| StdError::generic_err(format!("{err}")))?;
+    if pools.iter().any(|pool| pool.amount.is_zero()) {
+        return Err(StdError::generic_err("One of the pools is empty"));
+    }
+    if offer_asset.amount.is_zero() {
+        return Ok(Decimal::zero());
+    }
+
+    let spot_price = compute_spot_price(
+        compute_current_amp(&config, &env)?,
+        offer_pool.amount,
+        ask_pool.amount,
+    )?;
+
+    let offer_precision = get_precision(deps.storage, &offer_pool.info)?;
+    let ask_precision = get_precision(deps.storage, &ask_pool.info)?;
+    let offer_asset_dec = offer_asset.to_decimal_asset(offer_precision)?;
+    let SwapResult { return_amount, .. } = compute_swap(
+        deps.storage,
+        &env,
+        &config,
+        &offer_asset_dec,
+        &offer_pool,
+        &ask_pool,
+        &pools,
+    )
+    .map_err(|err| StdError::generic_err(format!("{err}")))?;
+    let execution_price =
+        Decimal256::with_precision(return_amount, ask_precision)? / offer_asset_dec.amount;
+
+    to_decimal(spot_price.saturating_sub(execution_price) / spot_price)
+}
+
 /// Returns information about a reverse swap simulation in a [`ReverseSimulationResponse`] object.
 ///
 /// * **ask_asset** is the asset to swap to as well as the desired amount of ask
@@ -935,8 +1608,35 @@ pub fn query_reverse_simulation(
         &config.pair_info.contract_addr,
         &config.factory_addr,
     )?;
+    let fee_info = query_fee_info_basic(
+        &deps.querier,
+        &config.factory_addr,
+        config.pair_info.pair_type.clone(),
+    )?;
+
+    simulate_reverse_swap(
+        deps,
+        &env,
+        &config,
+        &pools,
+        &fee_info,
+        ask_asset,
+        offer_asset_info,
+    )
+}
+
+/// Simulates a reverse swap for `ask_asset` against already loaded pools and fee info.
+fn simulate_reverse_swap(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    pools: &[Decimal256Asset],
+    fee_info: &FeeInfo,
+    ask_asset: Asset,
+    offer_asset_info: Option<AssetInfo>,
+) -> StdResult<ReverseSimulationResponse> {
     let (offer_pool, ask_pool) =
-        select_pools(offer_asset_info.as_ref(), Some(&ask_asset.info), &pools)
+        select_pools(offer_asset_info.as_ref(), Some(&ask_asset.info), pools)
             .map_err(|err| StdError::generic_err(format!("{err}")))?;
 
     let offer_precision = get_precision(deps.storage, &offer_pool.info)?;
@@ -962,44 +1662,115 @@ pub fn query_reverse_simulation(
         });
     }
 
-    // Get fee info from the factory
-    let fee_info = query_fee_info(
-        &deps.querier,
-        &config.factory_addr,
-        config.pair_info.pair_type.clone(),
-        &env.contract.address,
-    )?;
-    let before_commission = (Decimal256::one()
-        - Decimal256::new(fee_info.total_fee_rate.atomics().into()))
-    .inv()
-    .ok_or_else(|| StdError::generic_err("The pool must have less than 100% fee!"))?
-    .checked_mul(Decimal256::with_precision(ask_asset.amount, ask_precision)?)?;
-
-    let xp = pools.into_iter().map(|pool| pool.amount).collect_vec();
-    let new_offer_pool_amount = calc_y(
-        compute_current_amp(&config, &env)?,
-        ask_pool.amount - before_commission,
-        &xp,
-        config.greatest_precision,
-    )?;
+    let amp = compute_current_amp(config, env)?;
+    let xp = pools.iter().map(|pool| pool.amount).collect_vec();
+    let ask_amount = Decimal256::with_precision(ask_asset.amount, ask_precision)?;
+    // Returns the ask amount before commission and the offer pool after the swap
+    let reverse_swap = |fee_rate: Decimal| -> StdResult<(Decimal256, Decimal256)> {
+        let before_commission = (Decimal256::one() - Decimal256::from(fee_rate))
+            .inv()
+            .ok_or_else(|| StdError::generic_err("The pool must have less than 100% fee!"))?
+            .checked_mul(ask_amount)?;
+        let new_offer_pool_amount = calc_y(
+            amp,
+            ask_pool.amount - before_commission,
+            &xp,
+            config.greatest_precision,
+        )?;
+        Ok((
+            before_commission,
+            Decimal256::with_precision(new_offer_pool_amount, config.greatest_precision)?,
+        ))
+    };
 
-    let offer_amount = new_offer_pool_amount.checked_sub(
-        offer_pool
-            .amount
-            .to_uint128_with_precision(config.greatest_precision)?,
+    // The dynamic fee depends on the balances after the swap, so the swap is repeated at the
+    // fee of the previous estimate until the fee settles
+    let mut fee_rate = compute_fee_rate(
+        config,
+        fee_info.total_fee_rate,
+        offer_pool.amount,
+        ask_pool.amount,
     )?;
-    let offer_amount = adjust_precision(offer_amount, config.greatest_precision, offer_precision)?;
+    let (mut before_commission, mut new_offer_pool) = reverse_swap(fee_rate)?;
+    for _ in 0..REVERSE_FEE_ITERATIONS {
+        let next_fee_rate = compute_fee_rate(
+            config,
+            fee_info.total_fee_rate,
+            new_offer_pool,
+            ask_pool.amount.saturating_sub(before_commission),
+        )?;
+        if next_fee_rate == fee_rate {
+            break;
+        }
+        fee_rate = next_fee_rate;
+        (before_commission, new_offer_pool) = reverse_swap(fee_rate)?;
+    }
+
+    // Round the required offer up so that the quoted amount never underfunds the swap
+    let offer_amount = new_offer_pool
+        .checked_sub(offer_pool.amount)?
+        .to_uint128_with_precision_ceil(offer_precision)?;
 
     Ok(ReverseSimulationResponse {
         offer_amount,
         spread_amount: offer_amount
             .saturating_sub(before_commission.to_uint128_with_precision(offer_precision)?),
-        commission_amount: fee_info
-            .total_fee_rate
+        commission_amount: fee_rate
             .checked_mul_uint128(before_commission.to_uint128_with_precision(ask_precision)?)?,
     })
 }
 
+/// Returns a swap simulation of `offer_asset` and the reverse simulation of its return amount
+/// in a [`QuoteBothResponse`] object. Both sides use a single load of the pools and fee info.
+///
+/// * **offer_asset** is the asset to swap as well as an amount of the said asset.
+pub fn query_quote_both(
+    deps: Deps,
+    env: Env,
+    offer_asset: Asset,
+    ask_asset_info: Option<AssetInfo>,
+) -> StdResult<QuoteBothResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let pools = config.pair_info.query_pools_decimal(
+        &deps.querier,
+        &config.pair_info.contract_addr,
+        &config.factory_addr,
+    )?;
+    let fee_info = query_fee_info_basic(
+        &deps.querier,
+        &config.factory_addr,
+        config.pair_info.pair_type.clone(),
+    )?;
+
+    let (offer_pool, ask_pool) =
+        select_pools(Some(&offer_asset.info), ask_asset_info.as_ref(), &pools)
+            .map_err(|err| StdError::generic_err(format!("{err}")))?;
+
+    let forward = simulate_swap(
+        deps,
+        &env,
+        &config,
+        &pools,
+        &fee_info,
+        offer_asset,
+        Some(ask_pool.info.clone()),
+    )?;
+    let reverse = simulate_reverse_swap(
+        deps,
+        &env,
+        &config,
+        &pools,
+        &fee_info,
+        Asset {
+            info: ask_pool.info,
+            amount: forward.return_amount,
+        },
+        Some(offer_pool.info),
+    )?;
+
+    Ok(QuoteBothResponse { forward, reverse })
+}
+
 /// Returns the pair contract configuration in a [`ConfigResponse`] object.
 pub fn query_config(deps: Deps, env: Env) -> StdResult<ConfigResponse> {
     let config = CONFIG.load(deps.storage)?;
@@ -1007,8 +1778,9 @@ pub fn query_config(deps: Deps, env: Env) -> StdResult<ConfigResponse> {
     Ok(ConfigResponse {
         params: Some(to_json_binary(&StablePoolConfig {
             amp: Decimal::from_ratio(compute_current_amp(&config, &env)?, AMP_PRECISION),
+            dynamic_fee: config.dynamic_fee,
         })?),
-        owner: factory_config.owner,
+        owner: config.owner.unwrap_or(factory_config.owner),
         factory_addr: config.factory_addr,
     })
 }
@@ -1034,7 +1806,7 @@ pub fn assert_max_spread(
 ) -> Result<(), ContractError> {
     let max_spread = max_spread.unwrap_or(DEFAULT_SLIPPAGE);
     if max_spread.gt(&MAX_ALLOWED_SLIPPAGE) {
-        return Err(ContractError::AllowedSpreadAssertion {});
+        return Err(ContractError::Dex(DexError::AllowedSpreadAssertion {}));
     }
 
     if let Some(belief_price) = belief_price {
@@ -1050,21 +1822,86 @@ pub fn assert_max_spread(
         if return_amount < expected_return
             && Decimal::from_ratio(spread_amount, expected_return) > max_spread
         {
-            return Err(ContractError::MaxSpreadAssertion {});
+            return Err(ContractError::Dex(DexError::MaxSpreadAssertion {}));
         }
     } else if Decimal::from_ratio(spread_amount, return_amount + spread_amount) > max_spread {
-        return Err(ContractError::MaxSpreadAssertion {});
+        return Err(ContractError::Dex(DexError::MaxSpreadAssertion {}));
+    }
+
+    Ok(())
+}
+
+/// Checks that a swap moves the marginal pool price (see [`compute_spot_price`]) down by at most
+/// `max_price_impact`.
+///
+/// * **offer_pool** total amount of offer assets in the pool before the swap.
+///
+/// * **ask_pool** total amount of ask assets in the pool before the swap.
+///
+/// * **offer_amount** amount of offer assets added to the pool.
+///
+/// * **ask_amount** amount of ask assets leaving the pool.
+pub fn assert_price_impact(
+    max_price_impact: Option<Decimal>,
+    amp: Uint64,
+    offer_pool: Decimal256,
+    ask_pool: Decimal256,
+    offer_amount: Decimal256,
+    ask_amount: Decimal256,
+) -> Result<(), ContractError> {
+    let Some(max_price_impact) = max_price_impact else {
+        return Ok(());
+    };
+
+    let price_before = compute_spot_price(amp, offer_pool, ask_pool)?;
+    let price_after = compute_spot_price(
+        amp,
+        offer_pool.checked_add(offer_amount)?,
+        ask_pool.checked_sub(ask_amount)?,
+    )?;
+    if price_before.saturating_sub(price_after)
+        > price_before.checked_mul(Decimal256::from(max_price_impact))?
+    {
+        return Err(ContractError::Dex(DexError::PriceImpactTooHigh {}));
     }
 
     Ok(())
 }
 
 /// Manages the contract migration.
+///
+/// Asset precisions are re-queried from the coin registry so that fixed registrations
+/// are picked up. No precision can change while the pool has liquidity, the stored amounts are
+/// scaled by them.
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+pub fn migrate(mut deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
     let contract_version = get_contract_version(deps.storage)?;
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    let mut config = CONFIG.load(deps.storage)?;
+    let (_, total_share) = pool_info(deps.querier, &config)?;
+    if !total_share.is_zero() {
+        for (asset_info, current) in get_precisions(deps.storage, &config.pair_info.asset_infos)? {
+            let new = asset_info.decimals(&deps.querier, &config.factory_addr)?;
+            if new != current {
+                return Err(ContractError::PrecisionChange {
+                    asset: asset_info.to_string(),
+                    current,
+                    new,
+                });
+            }
+        }
+    }
+    let greatest_precision = store_precisions(
+        deps.branch(),
+        &config.pair_info.asset_infos,
+        &config.factory_addr,
+    )?;
+    config.greatest_precision = greatest_precision;
+    CONFIG.save(deps.storage, &config)?;
+
     Ok(Response::new()
+        .add_attribute("greatest_precision", greatest_precision.to_string())
         .add_attribute("previous_contract_name", &contract_version.contract)
         .add_attribute("previous_contract_version", &contract_version.version)
         .add_attribute("new_contract_name", CONTRACT_NAME)
@@ -1077,18 +1914,12 @@ pub fn pool_info(querier: QuerierWrapper, config: &Config) -> StdResult<(Vec<Ass
         .pair_info
         .query_pools(&querier, &config.pair_info.contract_addr)?;
 
-    let total_share = match &config.pair_info.liquidity_token {
-        AssetInfo::NativeToken { denom } => querier.query_supply(denom)?.amount,
-        AssetInfo::Token { contract_addr } => {
-            let res: TokenInfoResponse =
-                querier.query_wasm_smart(contract_addr, &Cw20QueryMsg::TokenInfo {})?;
-            res.total_supply
-        }
-    };
+    let total_share = query_total_supply(&querier, &config.pair_info.liquidity_token)?;
     Ok((pools, total_share))
 }
 
 /// Updates the pool configuration with the specified parameters in the `params` variable.
+/// Only the factory owner or the pool owner can update it.
 ///
 /// * **params** new parameter values.
 pub fn update_config(
@@ -1099,8 +1930,8 @@ pub fn update_config(
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     let factory_config = query_factory_config(&deps.querier, &config.factory_addr)?;
-    if info.sender != factory_config.owner {
-        return Err(ContractError::Unauthorized {});
+    if info.sender != factory_config.owner && Some(&info.sender) != config.owner.as_ref() {
+        return Err(ContractError::Dex(DexError::Unauthorized {}));
     }
 
     match from_json::<StablePoolUpdateParams>(&params)? {
@@ -1126,7 +1957,7 @@ fn start_changing_amp(
     next_amp: u64,
     next_amp_time: u64,
 ) -> Result<(), ContractError> {
-    if next_amp == 0 || next_amp > MAX_AMP {
+    if !(MIN_AMP..=MAX_AMP).contains(&next_amp) {
         return Err(ContractError::IncorrectAmp {});
     }
 
@@ -1173,6 +2004,20 @@ fn stop_changing_amp(mut config: Config, deps: DepsMut, env: Env) -> StdResult<(
 
     Ok(())
 }
+
+/// Returns the amplification coefficient in effect at `timestamp` as a [`Decimal`], read off the
+/// stored amp ramp. Timestamps past the end of the ramp return its target amp.
+///
+/// * **timestamp** unix time in seconds, it may lie in the past or in the future.
+pub fn query_amp_at(deps: Deps, timestamp: u64) -> StdResult<Decimal> {
+    let config = CONFIG.load(deps.storage)?;
+
+    Ok(Decimal::from_ratio(
+        compute_amp_at(&config, timestamp)?,
+        AMP_PRECISION,
+    ))
+}
+
 /// Compute the current pool D value.
 fn query_compute_d(deps: Deps, env: Env) -> StdResult<Uint128> {
     let config = CONFIG.load(deps.storage)?;
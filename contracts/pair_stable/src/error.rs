@@ -1,9 +1,9 @@
 use cosmwasm_std::{ConversionOverflowError, OverflowError, StdError};
 use thiserror::Error;
+use ura::errors::DexError;
 
-use ura::contracts::pair::MINIMUM_LIQUIDITY_AMOUNT;
-
-use crate::math::{MAX_AMP, MAX_AMP_CHANGE, MIN_AMP_CHANGING_TIME};
+use crate::math::{MAX_AMP, MAX_AMP_CHANGE, MIN_AMP, MIN_AMP_CHANGING_TIME};
+use crate::state::{MAX_PRECISION, MIN_PRECISION};
 
 /// This enum describes stableswap pair contract errors
 #[derive(Error, Debug, PartialEq)]
@@ -11,43 +11,16 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
-    #[error("Unauthorized")]
-    Unauthorized {},
-
-    #[error("Operation non supported")]
-    NonSupported {},
-
-    #[error("Invalid state")]
-    InvalidState {},
-
-    #[error("CW20 tokens can be swapped via Cw20::Send message only")]
-    Cw20DirectSwap {},
-
-    #[error("Doubling assets in asset infos")]
-    DoublingAssets {},
-
-    #[error("Event of zero transfer")]
-    InvalidZeroAmount {},
-
-    #[error("Wrong token funds sent to withdraw liquidity")]
-    InvalidLiquidityToken {},
+    #[error("{0}")]
+    Dex(#[from] DexError),
 
     #[error("Insufficient amount of liquidity")]
     LiquidityAmountTooSmall {},
 
-    #[error("Provided spread amount exceeds allowed limit")]
-    AllowedSpreadAssertion {},
-
-    #[error("Operation exceeds max spread limit")]
-    MaxSpreadAssertion {},
-
     #[error("Native token balance mismatch between the argument and the transferred")]
     AssetMismatch {},
 
-    #[error(
-        "Amp coefficient must be greater than 0 and less than or equal to {}",
-        MAX_AMP
-    )]
+    #[error("Amp coefficient must be between {} and {}", MIN_AMP, MAX_AMP)]
     IncorrectAmp {},
 
     #[error(
@@ -74,17 +47,23 @@ pub enum ContractError {
     #[error("Ask or offer asset is missed")]
     VariableAssetMissed {},
 
-    #[error("Source and target assets are the same")]
-    SameAssets {},
+    #[error("Dynamic fee must satisfy mid_fee <= out_fee < 1")]
+    InvalidDynamicFee {},
 
-    #[error("Invalid number of assets. This pair support only {0} assets")]
-    InvalidNumberOfAssets(usize),
+    #[error(
+        "Precision of {asset} cannot change from {current} to {new} while the pool has liquidity"
+    )]
+    PrecisionChange { asset: String, current: u8, new: u8 },
 
-    #[error("Initial liquidity must be more than {}", MINIMUM_LIQUIDITY_AMOUNT)]
-    MinimumLiquidityAmountError {},
+    #[error(
+        "Asset {denom} has {decimals} decimals, only {} to {} decimals are supported",
+        MIN_PRECISION,
+        MAX_PRECISION
+    )]
+    UnsupportedPrecision { denom: String, decimals: u8 },
 
-    #[error("Failed to parse or process reply message")]
-    FailedToParseReply {},
+    #[error("Invalid number of assets. This pair support only {0} assets")]
+    InvalidNumberOfAssets(usize),
 }
 
 impl From<OverflowError> for ContractError {
@@ -5,6 +5,9 @@ use ura::structs::decimal256::Decimal256Ext;
 /// The maximum number of calculation steps for Newton's method.
 const ITERATIONS: u8 = 64;
 
+/// The lowest amp a pool can be created with or ramped to. Lower amps price the pool almost like
+/// a constant product pool, which defeats the point of a stableswap pool
+pub const MIN_AMP: u64 = 10;
 pub const MAX_AMP: u64 = 1_000_000;
 pub const MAX_AMP_CHANGE: u64 = 10;
 pub const MIN_AMP_CHANGING_TIME: u64 = 86400;
@@ -75,6 +78,28 @@ fn calculate_step(
         .map_err(|e| StdError::generic_err(e.to_string()))
 }
 
+/// Computes the marginal price (ask assets per offer asset) of the invariant used in [`compute_d`].
+///
+/// * **Equation**
+///
+/// With `f = Ann * sum(x_i) + D - Ann * D - D**(n+1) / (n**n * prod(x_i))` and
+/// `c = D**(n+1) / (n**n * prod(x_i))`, the price is `df/dx_offer / df/dx_ask`, i.e.
+/// `(Ann + c / x_offer) / (Ann + c / x_ask)`
+pub(crate) fn compute_spot_price(
+    amp: Uint64,
+    offer_pool: Decimal256,
+    ask_pool: Decimal256,
+) -> StdResult<Decimal256> {
+    let leverage = Decimal256::from_ratio(amp, AMP_PRECISION) * N_COINS;
+    let d = compute_d(amp, &[offer_pool, ask_pool])?;
+    let c = d.checked_pow(3)? / (N_COINS * N_COINS * offer_pool * ask_pool);
+
+    leverage
+        .checked_add(c / offer_pool)?
+        .checked_div(leverage.checked_add(c / ask_pool)?)
+        .map_err(|e| StdError::generic_err(e.to_string()))
+}
+
 /// Compute the swap amount `y` in proportion to `x`.
 ///
 /// * **Solve for y**
@@ -1,8 +1,12 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, DepsMut, StdResult, Storage, Uint128};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, DepsMut, Order, StdError, StdResult, Storage, Uint128};
+use cw_storage_plus::{Bound, Item, Map};
+use ura::contracts::pair::{DSnapshot, DynamicFeeParams};
 use ura::structs::asset_info::AssetInfo;
 use ura::structs::pair_info::PairInfo;
+use ura::utils::pagination::{clamp_limit, DEFAULT_LIMIT};
+
+use crate::error::ContractError;
 
 /// This structure stores the main stableswap pair parameters.
 #[cw_serde]
@@ -21,23 +25,42 @@ pub struct Config {
     pub next_amp_time: u64,
     /// The greatest precision of assets in the pool
     pub greatest_precision: u8,
+    /// The dynamic fee parameters. The factory fee is used if not set
+    #[serde(default)]
+    pub dynamic_fee: Option<DynamicFeeParams>,
+    /// The pool owner, allowed to update the pool configuration next to the factory owner.
+    /// The factory owner is the pool owner if not set
+    #[serde(default)]
+    pub owner: Option<Addr>,
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
 
+/// The smallest asset precision supported by the pool
+pub const MIN_PRECISION: u8 = 1;
+/// The greatest asset precision supported by the pool, which is the precision of [`cosmwasm_std::Decimal256`]
+pub const MAX_PRECISION: u8 = 18;
+
 /// Stores map of AssetInfo (as String) -> precision
 pub const PRECISIONS: Map<String, u8> = Map::new("precisions");
 
-/// Store all token precisions and return the greatest one.
+/// Store all token precisions and return the greatest one. Precisions must be within
+/// [`MIN_PRECISION`] and [`MAX_PRECISION`].
 pub(crate) fn store_precisions(
     deps: DepsMut,
     asset_infos: &[AssetInfo],
     factory_addr: &Addr,
-) -> StdResult<u8> {
+) -> Result<u8, ContractError> {
     let mut max = 0u8;
 
     for asset_info in asset_infos {
         let precision = asset_info.decimals(&deps.querier, factory_addr)?;
+        if !(MIN_PRECISION..=MAX_PRECISION).contains(&precision) {
+            return Err(ContractError::UnsupportedPrecision {
+                denom: asset_info.to_string(),
+                decimals: precision,
+            });
+        }
         max = max.max(precision);
         PRECISIONS.save(deps.storage, asset_info.to_string(), &precision)?;
     }
@@ -50,5 +73,157 @@ pub(crate) fn get_precision(storage: &dyn Storage, asset_info: &AssetInfo) -> St
     PRECISIONS.load(storage, asset_info.to_string())
 }
 
+/// Loads all stored precisions. Stored keys are resolved back to the given pool asset infos.
+pub(crate) fn get_precisions(
+    storage: &dyn Storage,
+    asset_infos: &[AssetInfo],
+) -> StdResult<Vec<(AssetInfo, u8)>> {
+    PRECISIONS
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (key, precision) = item?;
+            let asset_info = asset_infos
+                .iter()
+                .find(|asset_info| asset_info.to_string() == key)
+                .ok_or_else(|| {
+                    StdError::generic_err(format!("Precision is stored for unknown asset {key}"))
+                })?;
+
+            Ok((asset_info.clone(), precision))
+        })
+        .collect()
+}
+
+/// The maximum number of D snapshots kept, older ones are overwritten
+pub const MAX_D_SNAPSHOTS: u64 = 100;
+
+/// Ring buffer of D snapshots keyed by their sequence number modulo [`MAX_D_SNAPSHOTS`]
+pub const D_SNAPSHOTS: Map<u64, DSnapshot> = Map::new("d_snapshots");
+/// The number of D snapshots taken so far
+pub const D_SNAPSHOT_COUNT: Item<u64> = Item::new("d_snapshot_count");
+
+/// Stores a D snapshot for `time` if the block time advanced since the last snapshot. `compute_d`
+/// is only called when a snapshot is stored.
+pub(crate) fn record_d_snapshot(
+    storage: &mut dyn Storage,
+    time: u64,
+    compute_d: impl FnOnce() -> StdResult<Uint128>,
+) -> StdResult<()> {
+    let count = D_SNAPSHOT_COUNT.may_load(storage)?.unwrap_or_default();
+    if count > 0 {
+        let last = D_SNAPSHOTS.load(storage, (count - 1) % MAX_D_SNAPSHOTS)?;
+        if last.time >= time {
+            return Ok(());
+        }
+    }
+
+    let snapshot = DSnapshot {
+        time,
+        d: compute_d()?,
+    };
+    D_SNAPSHOTS.save(storage, count % MAX_D_SNAPSHOTS, &snapshot)?;
+    D_SNAPSHOT_COUNT.save(storage, &(count + 1))
+}
+
+/// Reads the `limit` most recent D snapshots, oldest first.
+pub fn read_d_snapshots(storage: &dyn Storage, limit: Option<u32>) -> StdResult<Vec<DSnapshot>> {
+    let count = D_SNAPSHOT_COUNT.may_load(storage)?.unwrap_or_default();
+    let limit = (limit.unwrap_or(DEFAULT_LIMIT) as u64)
+        .min(MAX_D_SNAPSHOTS)
+        .min(count);
+
+    (count - limit..count)
+        .map(|seq| D_SNAPSHOTS.load(storage, seq % MAX_D_SNAPSHOTS))
+        .collect()
+}
+
 /// Keeps track of the lp_token for each lp_provider, this is used to calculate the emission rewards
 pub const LP_PROVIDERS: Map<&Addr, Uint128> = Map::new("lp_providers");
+
+/// The LP tokens a provider tracks for another emissions beneficiary, keyed by
+/// (provider, beneficiary). Withdrawals untrack them before the provider's own LP tokens
+pub const DELEGATED_LP: Map<(&Addr, &Addr), Uint128> = Map::new("delegated_lp");
+
+/// Reads LP providers and their tracked LP amounts from [`LP_PROVIDERS`], ordered by address.
+///
+/// `start_after` is the provider after which the function starts to fetch results.
+///
+/// `limit` is the number of items to retrieve.
+pub fn read_lp_providers(
+    storage: &dyn Storage,
+    start_after: Option<&Addr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(Addr, Uint128)>> {
+    let limit = clamp_limit(limit);
+
+    LP_PROVIDERS
+        .range(
+            storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect()
+}
+
+/// Untracks `amount` LP tokens withdrawn by `provider` for emission calculations.
+///
+/// A withdrawal for another `beneficiary` only untracks the LP tokens the provider tracks for
+/// it, the caller checks that they cover `amount`. Otherwise the LP tokens the provider tracks
+/// for other beneficiaries are untracked first and the rest from the provider's own, so LP
+/// tokens provided for a beneficiary can't leave the pool while still tracked for it.
+///
+/// Returns every address whose tracked LP tokens dropped with the amount tracked before.
+pub fn untrack_lp_provider(
+    storage: &mut dyn Storage,
+    provider: &Addr,
+    beneficiary: &Addr,
+    amount: Uint128,
+) -> StdResult<Vec<(Addr, Uint128)>> {
+    let delegations = if beneficiary == provider {
+        DELEGATED_LP
+            .prefix(provider)
+            .range(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?
+    } else {
+        vec![(
+            beneficiary.clone(),
+            DELEGATED_LP
+                .may_load(storage, (provider, beneficiary))?
+                .unwrap_or_default(),
+        )]
+    };
+
+    let mut untracked = vec![];
+    let mut remaining = amount;
+    for (delegate, delegated) in delegations {
+        let drawn = delegated.min(remaining);
+        if drawn.is_zero() {
+            continue;
+        }
+        if drawn == delegated {
+            DELEGATED_LP.remove(storage, (provider, &delegate));
+        } else {
+            DELEGATED_LP.save(storage, (provider, &delegate), &(delegated - drawn))?;
+        }
+        let previous_amount = untrack_lp(storage, &delegate, drawn)?;
+        untracked.push((delegate, previous_amount));
+        remaining -= drawn;
+    }
+    if beneficiary == provider && !remaining.is_zero() {
+        let previous_amount = untrack_lp(storage, provider, remaining)?;
+        untracked.push((provider.clone(), previous_amount));
+    }
+
+    Ok(untracked)
+}
+
+/// Subtracts `amount` from the LP tokens tracked for `address` and returns the amount tracked
+/// before. LP tokens received by a transfer were never tracked, so the tracking stops at zero.
+fn untrack_lp(storage: &mut dyn Storage, address: &Addr, amount: Uint128) -> StdResult<Uint128> {
+    let previous_amount = LP_PROVIDERS.may_load(storage, address)?.unwrap_or_default();
+    LP_PROVIDERS.save(storage, address, &previous_amount.saturating_sub(amount))?;
+
+    Ok(previous_amount)
+}
@@ -1,22 +1,35 @@
-use crate::contract::{assert_max_spread, execute, instantiate, query_pool, query_share, reply};
+use crate::contract::{
+    assert_max_spread, execute, instantiate, migrate, query, query_pool, query_share, reply,
+};
 use crate::error::ContractError;
+use crate::math::{AMP_PRECISION, MAX_AMP, MIN_AMP, MIN_AMP_CHANGING_TIME};
 
-use crate::state::CONFIG;
+use crate::state::{get_precision, CONFIG, DELEGATED_LP, LP_PROVIDERS};
+use crate::utils::{compute_current_amp, get_share_in_assets, select_pools};
+use ura::errors::DexError;
 use ura::structs::asset::Asset;
-use ura::structs::asset_info::AssetInfo;
+use ura::structs::asset_info::{AssetInfo, AssetInfoExt};
+use ura::structs::decimal256_asset::Decimal256Asset;
+use ura::DecimalCheckedOps;
 
-use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    attr, to_json_binary, Addr, BankMsg, BlockInfo, Coin, CosmosMsg, Decimal, DepsMut, Env, Reply,
-    ReplyOn, Response, StdError, SubMsg, SubMsgResponse, SubMsgResult, Timestamp, Uint128, WasmMsg,
+    attr, from_json, to_json_binary, Addr, BankMsg, BlockInfo, Coin, CosmosMsg, Decimal,
+    Decimal256, Deps, DepsMut, Env, OwnedDeps, Reply, ReplyOn, Response, StdError, SubMsg,
+    SubMsgResponse, SubMsgResult, Timestamp, Uint128, Uint64, WasmMsg,
 };
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, MinterResponse};
 use prost::Message;
+#[cfg(not(feature = "no-emissions"))]
+use ura::contracts::controller::{AccumEmissionsRequest, ExecuteMsg as ControllerExecuteMsg};
 use ura::contracts::pair::{
-    Cw20HookMsg, ExecuteMsg, InstantiateMsg, PoolResponse, StablePoolParams,
+    CanExecuteResponse, ConfigResponse, Cw20HookMsg, DSnapshot, DynamicFeeParams, ExecuteMsg,
+    InstantiateMsg, MigrateMsg, PairAction, PoolResponse, QueryMsg, QuoteBothResponse,
+    ReverseSimulationResponse, SimulateProvideResponse, SimulationResponse, StablePoolParams,
+    StablePoolUpdateParams, SwapRateResponse, WithdrawPreviewResponse, MINIMUM_LIQUIDITY_AMOUNT,
 };
 use ura::contracts::token::InstantiateMsg as TokenInstantiateMsg;
-use ura_mock::querier::mock_dependencies;
+use ura_mock::querier::{mock_dependencies, WasmMockQuerier};
 
 #[derive(Clone, PartialEq, Message)]
 struct MsgInstantiateContractResponse {
@@ -68,10 +81,13 @@ fn proper_initialization() {
             },
         ],
         token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
         init_params: Some(
             to_json_binary(&StablePoolParams {
                 amp: 100,
                 owner: None,
+                dynamic_fee: None,
             })
             .unwrap(),
         ),
@@ -100,7 +116,7 @@ fn proper_initialization() {
                 })
                 .unwrap(),
                 funds: vec![],
-                admin: None,
+                admin: Some(String::from("owner")),
                 label: String::from("Ura LP token"),
             }
             .into(),
@@ -162,11 +178,14 @@ fn provide_liquidity() {
             },
         ],
         token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
         factory_addr: String::from("factory"),
         init_params: Some(
             to_json_binary(&StablePoolParams {
                 amp: 100,
                 owner: None,
+                dynamic_fee: None,
             })
             .unwrap(),
         ),
@@ -198,6 +217,8 @@ fn provide_liquidity() {
         ],
         slippage_tolerance: None,
         receiver: None,
+        owner: None,
+        beneficiary: None,
     };
 
     let env = mock_env();
@@ -314,6 +335,8 @@ fn provide_liquidity() {
         ],
         slippage_tolerance: None,
         receiver: None,
+        owner: None,
+        beneficiary: None,
     };
 
     let env = mock_env_with_block_time(env.block.time.seconds() + 1000);
@@ -384,6 +407,8 @@ fn provide_liquidity() {
         ],
         slippage_tolerance: None,
         receiver: None,
+        owner: None,
+        beneficiary: None,
     };
 
     let env = mock_env();
@@ -465,6 +490,8 @@ fn provide_liquidity() {
         ],
         slippage_tolerance: Some(Decimal::percent(1)),
         receiver: None,
+        owner: None,
+        beneficiary: None,
     };
 
     let env = mock_env_with_block_time(env.block.time.seconds() + 1000);
@@ -504,6 +531,8 @@ fn provide_liquidity() {
         ],
         slippage_tolerance: Some(Decimal::percent(1)),
         receiver: None,
+        owner: None,
+        beneficiary: None,
     };
 
     let env = mock_env_with_block_time(env.block.time.seconds() + 1000);
@@ -517,6 +546,126 @@ fn provide_liquidity() {
     let _res = execute(deps.as_mut(), env, info, msg).unwrap();
 }
 
+#[test]
+fn provide_liquidity_tracks_the_beneficiary() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uusd".to_string(),
+        amount: Uint128::new(100_000000),
+    }]);
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(0))],
+        ),
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(0))],
+        ),
+    ]);
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let token = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![uusd.clone(), token.clone()],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: Some(
+            to_json_binary(&StablePoolParams {
+                amp: 100,
+                owner: None,
+                dynamic_fee: None,
+            })
+            .unwrap(),
+        ),
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+
+    let provide = |receiver: Option<&str>| ExecuteMsg::ProvideLiquidity {
+        assets: vec![
+            uusd.with_balance(100_000000u128),
+            token.with_balance(100_000000u128),
+        ],
+        slippage_tolerance: None,
+        receiver: receiver.map(String::from),
+        owner: None,
+        beneficiary: Some("delegate".to_string()),
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(100_000000),
+        }],
+    );
+
+    // LP tokens minted to someone else keep accruing to their receiver
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        provide(Some("addr0001")),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::Unauthorized {}));
+
+    execute(deps.as_mut(), mock_env(), info, provide(None)).unwrap();
+    let tracked = LP_PROVIDERS
+        .load(deps.as_ref().storage, &Addr::unchecked("delegate"))
+        .unwrap();
+    assert!(!tracked.is_zero());
+    assert!(!LP_PROVIDERS.has(deps.as_ref().storage, &Addr::unchecked("addr0000")));
+    assert_eq!(
+        DELEGATED_LP
+            .load(
+                deps.as_ref().storage,
+                (&Addr::unchecked("addr0000"), &Addr::unchecked("delegate"))
+            )
+            .unwrap(),
+        tracked
+    );
+
+    // Withdrawing without naming the beneficiary again untracks the delegated LP tokens
+    deps.querier.with_token_balances(&[
+        (
+            &String::from("asset0000"),
+            &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(100_000000))],
+        ),
+        (
+            &String::from("liquidity0000"),
+            &[(&String::from("addr0000"), &tracked)],
+        ),
+    ]);
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: String::from("addr0000"),
+        msg: to_json_binary(&Cw20HookMsg::WithdrawLiquidity { beneficiary: None }).unwrap(),
+        amount: tracked,
+    });
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("liquidity0000", &[]),
+        msg,
+    )
+    .unwrap();
+    assert_eq!(
+        LP_PROVIDERS
+            .load(deps.as_ref().storage, &Addr::unchecked("delegate"))
+            .unwrap(),
+        Uint128::zero()
+    );
+    assert!(!DELEGATED_LP.has(
+        deps.as_ref().storage,
+        (&Addr::unchecked("addr0000"), &Addr::unchecked("delegate"))
+    ));
+}
+
 #[test]
 fn withdraw_liquidity() {
     let mut deps = mock_dependencies(&[Coin {
@@ -545,11 +694,14 @@ fn withdraw_liquidity() {
             },
         ],
         token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
         factory_addr: String::from("factory"),
         init_params: Some(
             to_json_binary(&StablePoolParams {
                 amp: 100,
                 owner: None,
+                dynamic_fee: None,
             })
             .unwrap(),
         ),
@@ -566,7 +718,7 @@ fn withdraw_liquidity() {
     // Withdraw liquidity
     let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
         sender: String::from("addr0000"),
-        msg: to_json_binary(&Cw20HookMsg::WithdrawLiquidity {}).unwrap(),
+        msg: to_json_binary(&Cw20HookMsg::WithdrawLiquidity { beneficiary: None }).unwrap(),
         amount: Uint128::new(100u128),
     });
 
@@ -709,11 +861,14 @@ fn test_query_pool() {
             },
         ],
         token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
         factory_addr: String::from("factory"),
         init_params: Some(
             to_json_binary(&StablePoolParams {
                 amp: 100,
                 owner: None,
+                dynamic_fee: None,
             })
             .unwrap(),
         ),
@@ -780,11 +935,14 @@ fn test_query_share() {
             },
         ],
         token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
         factory_addr: String::from("factory"),
         init_params: Some(
             to_json_binary(&StablePoolParams {
                 amp: 100,
                 owner: None,
+                dynamic_fee: None,
             })
             .unwrap(),
         ),
@@ -813,3 +971,1544 @@ fn mock_env_with_block_time(time: u64) -> Env {
     };
     env
 }
+
+#[test]
+fn select_pools_rejects_same_assets() {
+    let pools: Vec<_> = ["uusd", "uluna", "uatom"]
+        .iter()
+        .map(|denom| Decimal256Asset {
+            info: AssetInfo::NativeToken {
+                denom: denom.to_string(),
+            },
+            amount: Decimal256::one(),
+        })
+        .collect();
+    let uusd = pools[0].info.clone();
+
+    for pools in [&pools[..2], &pools[..]] {
+        assert_eq!(
+            select_pools(Some(&uusd), Some(&uusd), pools).unwrap_err(),
+            ContractError::Dex(DexError::SameAssets {})
+        );
+    }
+}
+
+#[test]
+fn migrate_refreshes_precisions() {
+    let uusd_amount = Uint128::new(1_000_000_000);
+    let uluna_amount = Uint128::new(100_000_000_000);
+    let mut deps = mock_dependencies(&[
+        Coin {
+            denom: "uusd".to_string(),
+            amount: uusd_amount,
+        },
+        Coin {
+            denom: "uluna".to_string(),
+            amount: uluna_amount,
+        },
+    ]);
+
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let uluna = AssetInfo::NativeToken {
+        denom: "uluna".to_string(),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![uusd.clone(), uluna.clone()],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: Some(
+            to_json_binary(&StablePoolParams {
+                amp: 100,
+                owner: None,
+                dynamic_fee: None,
+            })
+            .unwrap(),
+        ),
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+    assert_eq!(CONFIG.load(&deps.storage).unwrap().greatest_precision, 6);
+
+    // The coin registry fixes the uluna registration before any liquidity is provided
+    deps.querier.with_token_balances(&[(
+        &String::from("liquidity0000"),
+        &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::zero())],
+    )]);
+    deps.querier.with_coin_decimals(&[("uluna", 8)]);
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(res.attributes[0], attr("greatest_precision", "8"));
+    assert_eq!(CONFIG.load(&deps.storage).unwrap().greatest_precision, 8);
+    assert_eq!(get_precision(&deps.storage, &uluna).unwrap(), 8);
+
+    // 1 uusd is priced at ~1 uluna, minus the 0.3% fee
+    deps.querier.with_token_balances(&[(
+        &String::from("liquidity0000"),
+        &[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &Uint128::new(1_000_000_000),
+        )],
+    )]);
+    let res: SimulationResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Simulation {
+                offer_asset: uusd.with_balance(1_000_000u128),
+                ask_asset_info: Some(uluna.clone()),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(
+        res.return_amount > Uint128::new(99_690_000)
+            && res.return_amount <= Uint128::new(99_700_000),
+        "unexpected return amount {}",
+        res.return_amount
+    );
+
+    // No precision can change while the pool has liquidity, including the one that isn't the
+    // greatest
+    deps.querier.with_coin_decimals(&[("uluna", 6)]);
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::PrecisionChange {
+            asset: "uluna".to_string(),
+            current: 8,
+            new: 6
+        }
+    );
+    deps.querier
+        .with_coin_decimals(&[("uluna", 8), ("uusd", 7)]);
+    let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::PrecisionChange {
+            asset: "uusd".to_string(),
+            current: 6,
+            new: 7
+        }
+    );
+    deps.querier.with_coin_decimals(&[("uusd", 6)]);
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+}
+
+fn instantiate_pool_with_dynamic_fee(
+    dynamic_fee: Option<DynamicFeeParams>,
+) -> Result<OwnedDeps<MockStorage, MockApi, WasmMockQuerier>, ContractError> {
+    let mut deps = mock_dependencies(&[
+        Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(1_000_000_000),
+        },
+        Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(1_000_000_000),
+        },
+    ]);
+
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::NativeToken {
+                denom: "uluna".to_string(),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: Some(
+            to_json_binary(&StablePoolParams {
+                amp: 100,
+                owner: None,
+                dynamic_fee,
+            })
+            .unwrap(),
+        ),
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg)?;
+
+    Ok(deps)
+}
+
+fn simulated_fee_rate(deps: Deps, offer_amount: u128) -> Decimal {
+    let res: SimulationResponse = from_json(
+        query(
+            deps,
+            mock_env(),
+            QueryMsg::Simulation {
+                offer_asset: Asset {
+                    info: AssetInfo::NativeToken {
+                        denom: "uusd".to_string(),
+                    },
+                    amount: Uint128::new(offer_amount),
+                },
+                ask_asset_info: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    Decimal::from_ratio(
+        res.commission_amount,
+        res.return_amount + res.commission_amount,
+    )
+}
+
+#[test]
+fn dynamic_fee_rises_with_imbalance() {
+    let deps = instantiate_pool_with_dynamic_fee(Some(DynamicFeeParams {
+        mid_fee: Decimal::permille(1),
+        out_fee: Decimal::percent(5),
+    }))
+    .unwrap();
+
+    let fee_rates: Vec<_> = [1_000_000, 100_000_000, 500_000_000, 2_000_000_000]
+        .into_iter()
+        .map(|offer_amount| simulated_fee_rate(deps.as_ref(), offer_amount))
+        .collect();
+
+    // A small swap keeps the pool balanced and pays roughly mid_fee
+    assert!(fee_rates[0] < Decimal::permille(2), "{}", fee_rates[0]);
+    assert!(
+        fee_rates.windows(2).all(|rates| rates[0] < rates[1]),
+        "{fee_rates:?}"
+    );
+    assert!(fee_rates[3] <= Decimal::percent(5), "{}", fee_rates[3]);
+}
+
+#[test]
+fn effective_fee_rate_matches_simulated_commission() {
+    let deps = instantiate_pool_with_dynamic_fee(Some(DynamicFeeParams {
+        mid_fee: Decimal::permille(1),
+        out_fee: Decimal::percent(5),
+    }))
+    .unwrap();
+
+    for offer_amount in [1_000_000u128, 500_000_000, 2_000_000_000] {
+        let offer_asset = AssetInfo::NativeToken {
+            denom: "uusd".to_string(),
+        }
+        .with_balance(offer_amount);
+        let fee_rate: Decimal = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::EffectiveFeeRate {
+                    offer_asset: offer_asset.clone(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let simulation: SimulationResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::Simulation {
+                    offer_asset,
+                    ask_asset_info: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        // The dynamic fee moves away from mid_fee as the swap grows
+        assert!(fee_rate > Decimal::permille(1));
+        assert_eq!(
+            simulation.commission_amount,
+            fee_rate
+                .checked_mul_uint128(simulation.return_amount + simulation.commission_amount)
+                .unwrap()
+        );
+    }
+}
+
+#[test]
+fn reverse_simulation_charges_the_dynamic_fee() {
+    let deps = instantiate_pool_with_dynamic_fee(Some(DynamicFeeParams {
+        mid_fee: Decimal::permille(1),
+        out_fee: Decimal::percent(5),
+    }))
+    .unwrap();
+
+    for ask_amount in [1_000_000u128, 400_000_000, 800_000_000] {
+        let reverse: ReverseSimulationResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::ReverseSimulation {
+                    offer_asset_info: None,
+                    ask_asset: AssetInfo::NativeToken {
+                        denom: "uluna".to_string(),
+                    }
+                    .with_balance(ask_amount),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let forward: SimulationResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::Simulation {
+                    offer_asset: AssetInfo::NativeToken {
+                        denom: "uusd".to_string(),
+                    }
+                    .with_balance(reverse.offer_amount),
+                    ask_asset_info: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        // Swapping the quoted offer returns the requested amount at the same dynamic fee
+        assert!(
+            forward.return_amount.u128().abs_diff(ask_amount) <= ask_amount / 100_000 + 1,
+            "{} != {ask_amount}",
+            forward.return_amount
+        );
+        assert!(
+            reverse
+                .commission_amount
+                .u128()
+                .abs_diff(forward.commission_amount.u128())
+                <= forward.commission_amount.u128() / 1_000 + 1,
+            "{} != {}",
+            reverse.commission_amount,
+            forward.commission_amount
+        );
+    }
+}
+
+#[test]
+fn dynamic_fee_is_flat_when_out_fee_equals_mid_fee() {
+    let deps = instantiate_pool_with_dynamic_fee(Some(DynamicFeeParams {
+        mid_fee: Decimal::percent(1),
+        out_fee: Decimal::percent(1),
+    }))
+    .unwrap();
+
+    for offer_amount in [1_000_000, 500_000_000, 2_000_000_000] {
+        let fee_rate = simulated_fee_rate(deps.as_ref(), offer_amount);
+        assert!(
+            fee_rate.abs_diff(Decimal::percent(1)) < Decimal::from_ratio(1u128, 100_000u128),
+            "{fee_rate}"
+        );
+    }
+
+    // Without dynamic fee parameters the factory fee of 0.3% is charged
+    let deps = instantiate_pool_with_dynamic_fee(None).unwrap();
+    let fee_rate = simulated_fee_rate(deps.as_ref(), 500_000_000);
+    assert!(
+        fee_rate.abs_diff(Decimal::permille(3)) < Decimal::from_ratio(1u128, 100_000u128),
+        "{fee_rate}"
+    );
+
+    let err = instantiate_pool_with_dynamic_fee(Some(DynamicFeeParams {
+        mid_fee: Decimal::percent(2),
+        out_fee: Decimal::percent(1),
+    }))
+    .err()
+    .unwrap();
+    assert_eq!(err, ContractError::InvalidDynamicFee {});
+}
+
+#[test]
+fn query_precisions() {
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_coin_decimals(&[("uluna", 8)]);
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::zero())],
+    )]);
+
+    let uluna = AssetInfo::NativeToken {
+        denom: "uluna".to_string(),
+    };
+    let token = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    let msg = InstantiateMsg {
+        asset_infos: vec![uluna.clone(), token.clone()],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: Some(
+            to_json_binary(&StablePoolParams {
+                amp: 100,
+                owner: None,
+                dynamic_fee: None,
+            })
+            .unwrap(),
+        ),
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let mut precisions: Vec<(AssetInfo, u8)> =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::Precisions {}).unwrap()).unwrap();
+    precisions.sort_by_key(|(_, precision)| *precision);
+    assert_eq!(precisions, vec![(token, 6), (uluna, 8)]);
+}
+
+#[test]
+fn query_swap_rate_matches_simulation() {
+    let deps = instantiate_pool_with_dynamic_fee(None).unwrap();
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+
+    for offer_amount in [1_000_000u128, 100_000_000] {
+        let offer_asset = uusd.with_balance(offer_amount);
+        let simulation: SimulationResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::Simulation {
+                    offer_asset: offer_asset.clone(),
+                    ask_asset_info: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let res: SwapRateResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::SwapRate {
+                    offer_asset,
+                    ask_asset_info: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        // The balanced pool trades close to 1:1
+        assert!(res.rate > Decimal::percent(99) && res.rate <= Decimal::one());
+        assert!(res.rate_after_fees < res.rate);
+        let implied_return = Uint128::new(offer_amount) * res.rate_after_fees;
+        assert!(simulation.return_amount - implied_return <= Uint128::one());
+    }
+
+    // A zero offer returns the marginal price net of the 0.3% fee
+    let res: SwapRateResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::SwapRate {
+                offer_asset: uusd.with_balance(0u128),
+                ask_asset_info: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.rate_after_fees, res.rate * Decimal::permille(997));
+}
+
+#[test]
+fn reply_reports_liquidity_token_failure() {
+    let mut deps = mock_dependencies(&[]);
+
+    let err = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 1,
+            result: SubMsgResult::Err("denom creation fee not paid".to_string()),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Dex(DexError::LiquidityTokenInstantiationFailed {
+            reason: "denom creation fee not paid".to_string()
+        })
+    );
+    assert_eq!(
+        err.to_string(),
+        "Failed to create the liquidity token: denom creation fee not paid"
+    );
+}
+
+#[test]
+fn swap_rejects_amounts_below_min_swap_amount() {
+    let mut deps = instantiate_pool_with_dynamic_fee(None).unwrap();
+    deps.querier.with_min_swap_amount(Uint128::new(1_000));
+
+    let swap = |amount: u128| {
+        (
+            ExecuteMsg::Swap {
+                offer_asset: AssetInfo::NativeToken {
+                    denom: "uusd".to_string(),
+                }
+                .with_balance(amount),
+                ask_asset_info: None,
+                belief_price: None,
+                max_spread: None,
+                to: None,
+                to_hook: None,
+            },
+            mock_info(
+                "addr0000",
+                &[Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::new(amount),
+                }],
+            ),
+        )
+    };
+
+    let (msg, info) = swap(999);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::SwapTooSmall {}));
+
+    let (msg, info) = swap(1_000);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+}
+
+#[test]
+fn swap_rejects_price_impact_above_max_price_impact() {
+    let mut deps = instantiate_pool_with_dynamic_fee(None).unwrap();
+    deps.querier.with_max_price_impact(Decimal::percent(1));
+
+    let swap = |amount: u128| {
+        (
+            ExecuteMsg::Swap {
+                offer_asset: AssetInfo::NativeToken {
+                    denom: "uusd".to_string(),
+                }
+                .with_balance(amount),
+                ask_asset_info: None,
+                belief_price: None,
+                max_spread: Some(Decimal::percent(50)),
+                to: None,
+                to_hook: None,
+            },
+            mock_info(
+                "addr0000",
+                &[Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::new(amount),
+                }],
+            ),
+        )
+    };
+
+    let (msg, info) = swap(500_000_000);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::PriceImpactTooHigh {}));
+
+    let (msg, info) = swap(1_000_000);
+    execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+}
+
+#[test]
+#[cfg(feature = "no-emissions")]
+fn swap_sends_commission_to_fee_address_without_emissions() {
+    let mut deps = instantiate_pool_with_dynamic_fee(None).unwrap();
+
+    let offer_amount = Uint128::new(1_000_000);
+    let msg = ExecuteMsg::Swap {
+        offer_asset: AssetInfo::NativeToken {
+            denom: "uusd".to_string(),
+        }
+        .with_balance(offer_amount),
+        ask_asset_info: None,
+        belief_price: None,
+        max_spread: None,
+        to: None,
+        to_hook: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: offer_amount,
+        }],
+    );
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    let attribute = |key: &str| -> u128 {
+        res.attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .unwrap()
+            .value
+            .parse()
+            .unwrap()
+    };
+    let transfer = |to_address: &str, amount: u128| {
+        SubMsg::new(BankMsg::Send {
+            to_address: String::from(to_address),
+            amount: vec![Coin {
+                denom: "uluna".to_string(),
+                amount: Uint128::new(amount),
+            }],
+        })
+    };
+    // The factory fee address receives the commission
+    assert_eq!(
+        res.messages,
+        vec![
+            transfer("addr0000", attribute("return_amount")),
+            transfer("owner", attribute("commission_amount")),
+        ]
+    );
+}
+
+#[test]
+fn d_history_keeps_one_snapshot_per_block_time() {
+    let mut deps = instantiate_pool_with_dynamic_fee(None).unwrap();
+    let start = mock_env().block.time.seconds();
+
+    let swap = |deps: DepsMut, time: u64| {
+        let msg = ExecuteMsg::Swap {
+            offer_asset: AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            }
+            .with_balance(1_000u128),
+            ask_asset_info: None,
+            belief_price: None,
+            max_spread: None,
+            to: None,
+            to_hook: None,
+        };
+        let info = mock_info(
+            "addr0000",
+            &[Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(1_000),
+            }],
+        );
+        execute(deps, mock_env_with_block_time(start + time), info, msg).unwrap();
+    };
+    let d_history = |deps: Deps, limit: Option<u32>| -> Vec<DSnapshot> {
+        from_json(query(deps, mock_env(), QueryMsg::DHistory { limit }).unwrap()).unwrap()
+    };
+    assert_eq!(d_history(deps.as_ref(), None), vec![]);
+
+    // The second swap in the same block doesn't add a snapshot
+    for time in [0, 0, 10, 20] {
+        swap(deps.as_mut(), time);
+    }
+
+    let history = d_history(deps.as_ref(), None);
+    assert_eq!(
+        history
+            .iter()
+            .map(|snapshot| snapshot.time)
+            .collect::<Vec<_>>(),
+        vec![start, start + 10, start + 20]
+    );
+    assert!(history.iter().all(|snapshot| !snapshot.d.is_zero()));
+    assert_eq!(d_history(deps.as_ref(), Some(2)), history[1..].to_vec());
+}
+
+#[test]
+fn collected_fees_accumulate_per_ask_asset() {
+    let mut deps = instantiate_pool_with_dynamic_fee(None).unwrap();
+
+    let mut swap = |offer_denom: &str, amount: u128| -> Uint128 {
+        let msg = ExecuteMsg::Swap {
+            offer_asset: AssetInfo::NativeToken {
+                denom: offer_denom.to_string(),
+            }
+            .with_balance(amount),
+            ask_asset_info: None,
+            belief_price: None,
+            max_spread: None,
+            to: None,
+            to_hook: None,
+        };
+        let info = mock_info(
+            "addr0000",
+            &[Coin {
+                denom: offer_denom.to_string(),
+                amount: Uint128::new(amount),
+            }],
+        );
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let commission = res
+            .attributes
+            .iter()
+            .find(|attr| attr.key == "commission_amount")
+            .unwrap();
+        commission.value.parse().unwrap()
+    };
+    let luna_fees = swap("uusd", 1_000_000);
+    let usd_fees = swap("uluna", 2_000_000) + swap("uluna", 1_000_000);
+    assert!(!luna_fees.is_zero() && !usd_fees.is_zero());
+
+    let collected: Vec<Asset> =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::CollectedFees {}).unwrap()).unwrap();
+    assert_eq!(
+        collected,
+        vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            }
+            .with_balance(usd_fees),
+            AssetInfo::NativeToken {
+                denom: "uluna".to_string(),
+            }
+            .with_balance(luna_fees),
+        ]
+    );
+}
+
+#[test]
+fn swap_rejects_extra_funds_attached() {
+    let mut deps = instantiate_pool_with_dynamic_fee(None).unwrap();
+
+    let msg = ExecuteMsg::Swap {
+        offer_asset: AssetInfo::NativeToken {
+            denom: "uusd".to_string(),
+        }
+        .with_balance(1_000u128),
+        ask_asset_info: None,
+        belief_price: None,
+        max_spread: None,
+        to: None,
+        to_hook: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[
+            Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(1_000),
+            },
+            Coin {
+                denom: "uluna".to_string(),
+                amount: Uint128::new(1_000),
+            },
+        ],
+    );
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::InvalidFundsAttached {}));
+}
+
+#[test]
+fn query_all_lp_received_paginates() {
+    let mut deps = mock_dependencies(&[]);
+    let providers = ["addr0003", "addr0000", "addr0004", "addr0001", "addr0002"];
+    for (i, provider) in providers.iter().enumerate() {
+        LP_PROVIDERS
+            .save(
+                deps.as_mut().storage,
+                &Addr::unchecked(*provider),
+                &Uint128::new(100 * (i as u128 + 1)),
+            )
+            .unwrap();
+    }
+
+    let page = |start_after: Option<&str>, limit: Option<u32>| -> Vec<(Addr, Uint128)> {
+        from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::AllLpReceived {
+                    start_after: start_after.map(String::from),
+                    limit,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    // Providers are returned ordered by address
+    let first = page(None, Some(2));
+    assert_eq!(
+        first,
+        vec![
+            (Addr::unchecked("addr0000"), Uint128::new(200)),
+            (Addr::unchecked("addr0001"), Uint128::new(400)),
+        ]
+    );
+    let second = page(Some("addr0001"), Some(2));
+    assert_eq!(
+        second,
+        vec![
+            (Addr::unchecked("addr0002"), Uint128::new(500)),
+            (Addr::unchecked("addr0003"), Uint128::new(100)),
+        ]
+    );
+    assert_eq!(
+        page(Some("addr0003"), Some(2)),
+        vec![(Addr::unchecked("addr0004"), Uint128::new(300))]
+    );
+    assert_eq!(page(Some("addr0004"), None), vec![]);
+    // The default limit covers all providers, a large limit is capped
+    assert_eq!(page(None, None).len(), 5);
+    assert_eq!(page(None, Some(100)).len(), 5);
+}
+
+#[test]
+fn query_price_impact_grows_with_offer_size() {
+    let deps = instantiate_pool_with_dynamic_fee(None).unwrap();
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let price_impact = |offer_amount: u128| -> Decimal {
+        from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::PriceImpact {
+                    offer_asset: uusd.with_balance(offer_amount),
+                    ask_asset_info: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+
+    let impacts = [1_000u128, 10_000_000, 100_000_000, 500_000_000].map(price_impact);
+    // Dust against the deep pool has no measurable impact
+    assert!(impacts[0] < Decimal::from_ratio(1u8, 1_000_000u32));
+    for pair in impacts.windows(2) {
+        assert!(pair[0] < pair[1]);
+    }
+    // The stableswap curve keeps the impact of a large swap well below xyk's dx / (x + dx)
+    assert!(impacts[3] < Decimal::from_ratio(500u32, 1_500u32));
+    assert_eq!(price_impact(0), Decimal::zero());
+}
+
+#[test]
+fn withdraw_preview_lp_price_grows_with_fees() {
+    let mut deps = instantiate_pool_with_dynamic_fee(None).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+    deps.querier.with_token_balances(&[(
+        &String::from("liquidity0000"),
+        &[(&String::from("addr0000"), &Uint128::new(2_000_000_000))],
+    )]);
+
+    let preview = |deps: Deps| -> WithdrawPreviewResponse {
+        from_json(
+            query(
+                deps,
+                mock_env(),
+                QueryMsg::WithdrawPreview {
+                    amount: Uint128::new(200_000_000),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    let initial = preview(deps.as_ref());
+    assert_eq!(initial.lp_price, Decimal::one());
+    assert_eq!(
+        initial.assets,
+        vec![
+            native_asset("uusd", 100_000_000),
+            native_asset("uluna", 100_000_000),
+        ]
+    );
+
+    // The offer is already in the pool balance when the swap is executed
+    let offer_amount = Uint128::new(100_000_000);
+    deps.querier.with_balance(&[(
+        &String::from(MOCK_CONTRACT_ADDR),
+        &[
+            Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(1_000_000_000) + offer_amount,
+            },
+            Coin {
+                denom: "uluna".to_string(),
+                amount: Uint128::new(1_000_000_000),
+            },
+        ],
+    )]);
+    let msg = ExecuteMsg::Swap {
+        offer_asset: native_asset("uusd", offer_amount.u128()),
+        ask_asset_info: None,
+        belief_price: None,
+        max_spread: None,
+        to: None,
+        to_hook: None,
+    };
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uusd".to_string(),
+            amount: offer_amount,
+        }],
+    );
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let return_amount: u128 = res
+        .attributes
+        .iter()
+        .find(|attr| attr.key == "return_amount")
+        .unwrap()
+        .value
+        .parse()
+        .unwrap();
+    deps.querier.with_balance(&[(
+        &String::from(MOCK_CONTRACT_ADDR),
+        &[
+            Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(1_000_000_000) + offer_amount,
+            },
+            Coin {
+                denom: "uluna".to_string(),
+                amount: Uint128::new(1_000_000_000 - return_amount),
+            },
+        ],
+    )]);
+
+    // The commission stayed in the pool, so every LP token is worth more
+    assert!(preview(deps.as_ref()).lp_price > initial.lp_price);
+}
+
+#[test]
+fn simulate_provide_matches_provide_liquidity() {
+    let mut deps = instantiate_pool_with_dynamic_fee(None).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+    deps.querier.with_token_balances(&[(
+        &String::from("liquidity0000"),
+        &[(&String::from(MOCK_CONTRACT_ADDR), &Uint128::new(0))],
+    )]);
+
+    let set_pool_balances = |deps: &mut OwnedDeps<MockStorage, MockApi, WasmMockQuerier>,
+                             uusd_amount: u128,
+                             uluna_amount: u128| {
+        deps.querier.with_balance(&[(
+            &String::from(MOCK_CONTRACT_ADDR),
+            &[
+                Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::new(uusd_amount),
+                },
+                Coin {
+                    denom: "uluna".to_string(),
+                    amount: Uint128::new(uluna_amount),
+                },
+            ],
+        )]);
+    };
+    let simulate = |deps: Deps, uusd_amount: u128, uluna_amount: u128| {
+        from_json::<SimulateProvideResponse>(
+            query(
+                deps,
+                mock_env(),
+                QueryMsg::SimulateProvide {
+                    assets: vec![
+                        native_asset("uusd", uusd_amount),
+                        native_asset("uluna", uluna_amount),
+                    ],
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    let provide = |deps: DepsMut, uusd_amount: u128, uluna_amount: u128| -> String {
+        let res = execute(
+            deps,
+            mock_env(),
+            mock_info(
+                "addr0000",
+                &[
+                    Coin {
+                        denom: "uusd".to_string(),
+                        amount: Uint128::new(uusd_amount),
+                    },
+                    Coin {
+                        denom: "uluna".to_string(),
+                        amount: Uint128::new(uluna_amount),
+                    },
+                ],
+            ),
+            ExecuteMsg::ProvideLiquidity {
+                assets: vec![
+                    native_asset("uusd", uusd_amount),
+                    native_asset("uluna", uluna_amount),
+                ],
+                slippage_tolerance: None,
+                receiver: None,
+                owner: None,
+                beneficiary: None,
+            },
+        )
+        .unwrap();
+        res.attributes
+            .into_iter()
+            .find(|attr| attr.key == "share")
+            .unwrap()
+            .value
+    };
+
+    // The first deposit locks the minimum liquidity amount
+    set_pool_balances(&mut deps, 0, 0);
+    let preview = simulate(deps.as_ref(), 1_000_000, 1_000_000);
+    assert_eq!(
+        preview,
+        SimulateProvideResponse {
+            share: Uint128::new(2_000_000) - MINIMUM_LIQUIDITY_AMOUNT,
+            refund: vec![],
+        }
+    );
+    set_pool_balances(&mut deps, 1_000_000, 1_000_000);
+    assert_eq!(
+        provide(deps.as_mut(), 1_000_000, 1_000_000),
+        preview.share.to_string()
+    );
+
+    // An imbalanced deposit still matches the minted share
+    deps.querier.with_token_balances(&[(
+        &String::from("liquidity0000"),
+        &[(&String::from("addr0000"), &Uint128::new(2_000_000))],
+    )]);
+    let preview = simulate(deps.as_ref(), 500_000, 100_000);
+    assert!(preview.refund.is_empty());
+    set_pool_balances(&mut deps, 1_500_000, 1_100_000);
+    assert_eq!(
+        provide(deps.as_mut(), 500_000, 100_000),
+        preview.share.to_string()
+    );
+}
+
+#[test]
+fn query_amp_at_interpolates_ramp() {
+    let mut deps = instantiate_pool_with_dynamic_fee(None).unwrap();
+    let mut config = CONFIG.load(deps.as_ref().storage).unwrap();
+    config.init_amp = 10 * AMP_PRECISION;
+    config.init_amp_time = 1_000;
+    config.next_amp = 30 * AMP_PRECISION;
+    config.next_amp_time = 2_000;
+    CONFIG.save(deps.as_mut().storage, &config).unwrap();
+
+    let amp_at = |deps: Deps, timestamp: u64| -> Decimal {
+        from_json(query(deps, mock_env(), QueryMsg::AmpAt { timestamp }).unwrap()).unwrap()
+    };
+
+    // Before and at the start of the ramp
+    assert_eq!(amp_at(deps.as_ref(), 500), Decimal::from_ratio(10u8, 1u8));
+    assert_eq!(amp_at(deps.as_ref(), 1_000), Decimal::from_ratio(10u8, 1u8));
+    // Midpoint
+    assert_eq!(amp_at(deps.as_ref(), 1_500), Decimal::from_ratio(20u8, 1u8));
+    // End of the ramp and beyond it
+    assert_eq!(amp_at(deps.as_ref(), 2_000), Decimal::from_ratio(30u8, 1u8));
+    assert_eq!(
+        amp_at(deps.as_ref(), 10_000),
+        Decimal::from_ratio(30u8, 1u8)
+    );
+
+    // A ramp down interpolates the same way
+    config.init_amp = 30 * AMP_PRECISION;
+    config.next_amp = 10 * AMP_PRECISION;
+    CONFIG.save(deps.as_mut().storage, &config).unwrap();
+    assert_eq!(amp_at(deps.as_ref(), 1_500), Decimal::from_ratio(20u8, 1u8));
+    assert_eq!(amp_at(deps.as_ref(), 2_000), Decimal::from_ratio(10u8, 1u8));
+}
+
+#[test]
+fn instantiate_rejects_unsupported_precisions() {
+    let msg = InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::NativeToken {
+                denom: "uluna".to_string(),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: Some(
+            to_json_binary(&StablePoolParams {
+                amp: 100,
+                owner: None,
+                dynamic_fee: None,
+            })
+            .unwrap(),
+        ),
+    };
+
+    for decimals in [0, 24] {
+        let mut deps = mock_dependencies(&[]);
+        deps.querier.with_coin_decimals(&[("uluna", decimals)]);
+        let err = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("addr0000", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::UnsupportedPrecision {
+                denom: "uluna".to_string(),
+                decimals
+            }
+        );
+    }
+
+    let mut deps = mock_dependencies(&[]);
+    deps.querier.with_coin_decimals(&[("uluna", 18)]);
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+}
+
+#[test]
+fn pool_owner_can_change_amp() {
+    let mut deps = mock_dependencies(&[]);
+    let msg = |owner: &str| InstantiateMsg {
+        asset_infos: vec![
+            AssetInfo::NativeToken {
+                denom: "uusd".to_string(),
+            },
+            AssetInfo::NativeToken {
+                denom: "uluna".to_string(),
+            },
+        ],
+        token_code_id: Some(10u64),
+        denom_creation_fee: vec![],
+        immutable_lp: false,
+        factory_addr: String::from("factory"),
+        init_params: Some(
+            to_json_binary(&StablePoolParams {
+                amp: 100,
+                owner: Some(owner.to_string()),
+                dynamic_fee: None,
+            })
+            .unwrap(),
+        ),
+    };
+
+    // The pool owner must be a valid address
+    let err = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        msg("Pool_Owner"),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        ContractError::Std(StdError::GenericErr { .. })
+    ));
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        msg("pool_owner"),
+    )
+    .unwrap();
+
+    let res: ConfigResponse =
+        from_json(query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!(res.owner, Addr::unchecked("pool_owner"));
+
+    let start = mock_env().block.time.seconds();
+    let day = MIN_AMP_CHANGING_TIME;
+    let change_amp = |deps: DepsMut, sender: &str, time: u64, next_amp: u64| {
+        execute(
+            deps,
+            mock_env_with_block_time(time),
+            mock_info(sender, &[]),
+            ExecuteMsg::UpdateConfig {
+                params: to_json_binary(&StablePoolUpdateParams::StartChangingAmp {
+                    next_amp,
+                    next_amp_time: time + day,
+                })
+                .unwrap(),
+            },
+        )
+    };
+
+    let err = change_amp(deps.as_mut(), "addr0000", start + day, 200).unwrap_err();
+    assert_eq!(err, ContractError::Dex(DexError::Unauthorized {}));
+
+    // Both the pool owner and the factory owner can change the amp
+    change_amp(deps.as_mut(), "pool_owner", start + day, 200).unwrap();
+    assert_eq!(
+        CONFIG.load(deps.as_ref().storage).unwrap().next_amp,
+        200 * AMP_PRECISION
+    );
+    change_amp(deps.as_mut(), "owner", start + 3 * day, 300).unwrap();
+    assert_eq!(
+        CONFIG.load(deps.as_ref().storage).unwrap().next_amp,
+        300 * AMP_PRECISION
+    );
+}
+
+#[test]
+fn amp_must_stay_within_bounds() {
+    let instantiate_with_amp = |amp: u64| {
+        let mut deps = mock_dependencies(&[]);
+        let msg = InstantiateMsg {
+            asset_infos: vec![
+                AssetInfo::NativeToken {
+                    denom: "uusd".to_string(),
+                },
+                AssetInfo::NativeToken {
+                    denom: "uluna".to_string(),
+                },
+            ],
+            token_code_id: Some(10u64),
+            denom_creation_fee: vec![],
+            immutable_lp: false,
+            factory_addr: String::from("factory"),
+            init_params: Some(
+                to_json_binary(&StablePoolParams {
+                    amp,
+                    owner: None,
+                    dynamic_fee: None,
+                })
+                .unwrap(),
+            ),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).map(|_| deps)
+    };
+
+    for amp in [0, 1, MIN_AMP - 1, MAX_AMP + 1] {
+        let err = instantiate_with_amp(amp).err();
+        assert_eq!(err, Some(ContractError::IncorrectAmp {}));
+    }
+    instantiate_with_amp(MAX_AMP).unwrap();
+    let mut deps = instantiate_with_amp(MIN_AMP).unwrap();
+
+    let start = mock_env().block.time.seconds();
+    let day = MIN_AMP_CHANGING_TIME;
+    let change_amp = |deps: DepsMut, time: u64, next_amp: u64| {
+        execute(
+            deps,
+            mock_env_with_block_time(time),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateConfig {
+                params: to_json_binary(&StablePoolUpdateParams::StartChangingAmp {
+                    next_amp,
+                    next_amp_time: time + day,
+                })
+                .unwrap(),
+            },
+        )
+    };
+
+    let err = change_amp(deps.as_mut(), start + day, MIN_AMP - 1).unwrap_err();
+    assert_eq!(err, ContractError::IncorrectAmp {});
+    let err = change_amp(deps.as_mut(), start + day, 1).unwrap_err();
+    assert_eq!(err, ContractError::IncorrectAmp {});
+    let err = change_amp(deps.as_mut(), start + day, MAX_AMP + 1).unwrap_err();
+    assert_eq!(err, ContractError::IncorrectAmp {});
+
+    // Ramping up and back down to the floor is fine
+    change_amp(deps.as_mut(), start + day, 10 * MIN_AMP).unwrap();
+    change_amp(deps.as_mut(), start + 3 * day, MIN_AMP).unwrap();
+    assert_eq!(
+        CONFIG.load(deps.as_ref().storage).unwrap().next_amp,
+        MIN_AMP * AMP_PRECISION
+    );
+}
+
+#[test]
+fn change_amp_mid_ramp_is_bounded_by_interpolated_amp() {
+    let mut deps = instantiate_pool_with_dynamic_fee(None).unwrap();
+    let start = mock_env().block.time.seconds();
+    let day = MIN_AMP_CHANGING_TIME;
+
+    let change_amp = |deps: DepsMut, time: u64, next_amp: u64, next_amp_time: u64| {
+        execute(
+            deps,
+            mock_env_with_block_time(time),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateConfig {
+                params: to_json_binary(&StablePoolUpdateParams::StartChangingAmp {
+                    next_amp,
+                    next_amp_time,
+                })
+                .unwrap(),
+            },
+        )
+    };
+
+    // Ramp from 100 to 1000 over ten days
+    change_amp(deps.as_mut(), start + day, 1_000, start + 11 * day).unwrap();
+
+    // One day into the ramp the live amp is 190, while the stored amps are 100 and 1000
+    let mid_ramp = start + 2 * day;
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        compute_current_amp(&config, &mock_env_with_block_time(mid_ramp)).unwrap(),
+        Uint64::new(190 * AMP_PRECISION)
+    );
+
+    // Allowed against the stored target amp, but more than 10x the live amp
+    let err = change_amp(deps.as_mut(), mid_ramp, 5_000, mid_ramp + day).unwrap_err();
+    assert_eq!(err, ContractError::MaxAmpChangeAssertion {});
+    // Allowed against the stored initial amp, but less than a tenth of the live amp
+    let err = change_amp(deps.as_mut(), mid_ramp, 15, mid_ramp + day).unwrap_err();
+    assert_eq!(err, ContractError::MaxAmpChangeAssertion {});
+
+    // A 10x ramp from the live amp is accepted and starts from it
+    change_amp(deps.as_mut(), mid_ramp, 1_900, mid_ramp + day).unwrap();
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(config.init_amp, 190 * AMP_PRECISION);
+    assert_eq!(config.init_amp_time, mid_ramp);
+    assert_eq!(config.next_amp, 1_900 * AMP_PRECISION);
+}
+
+#[test]
+fn can_execute_allows_every_action() {
+    let deps = instantiate_pool_with_dynamic_fee(None).unwrap();
+
+    for action in [PairAction::Swap, PairAction::Provide, PairAction::Withdraw] {
+        let res: CanExecuteResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::CanExecute {
+                    address: "addr0000".to_string(),
+                    action,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            res,
+            CanExecuteResponse {
+                allowed: true,
+                reason: None,
+            }
+        );
+    }
+}
+
+#[test]
+fn simulate_amp_change_tightens_quotes_with_higher_amp() {
+    let deps = instantiate_pool_with_dynamic_fee(None).unwrap();
+    let offer_asset = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    }
+    .with_balance(100_000_000u128);
+    let simulate = |target_amp: u64| {
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::SimulateAmpChange {
+                target_amp,
+                offer_asset: offer_asset.clone(),
+                ask_asset_info: None,
+            },
+        )
+        .map(|res| from_json::<SimulationResponse>(res).unwrap())
+    };
+
+    // The current amp reproduces the regular simulation
+    let current: SimulationResponse = from_json(
+        query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Simulation {
+                offer_asset: offer_asset.clone(),
+                ask_asset_info: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(simulate(100).unwrap(), current);
+
+    // A flatter curve returns more for the same offer
+    let low = simulate(10).unwrap();
+    let high = simulate(1_000).unwrap();
+    assert!(low.spread_amount > current.spread_amount);
+    assert!(high.spread_amount < current.spread_amount);
+    assert!(high.return_amount > low.return_amount);
+
+    let err = simulate(0).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err(ContractError::IncorrectAmp {}.to_string())
+    );
+}
+
+#[test]
+fn quote_both_round_trips_the_offer() {
+    let deps = instantiate_pool_with_dynamic_fee(None).unwrap();
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+
+    for offer_amount in [1_000_000u128, 100_000_000] {
+        let offer_asset = uusd.with_balance(offer_amount);
+        let res: QuoteBothResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::QuoteBoth {
+                    offer_asset: offer_asset.clone(),
+                    ask_asset_info: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let forward: SimulationResponse = from_json(
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::Simulation {
+                    offer_asset,
+                    ask_asset_info: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.forward, forward);
+
+        // The reverse of the forward return recovers the offer up to rounding
+        assert!(res.reverse.offer_amount.u128().abs_diff(offer_amount) <= 1);
+    }
+}
+
+#[test]
+#[cfg(not(feature = "no-emissions"))]
+fn provide_liquidity_survives_failing_controller() {
+    let mut deps = instantiate_pool_with_dynamic_fee(None).unwrap();
+    store_liquidity_token(deps.as_mut(), 2, "liquidity0000".to_string());
+    deps.querier.with_token_balances(&[(
+        &String::from("liquidity0000"),
+        &[(&String::from("addr0000"), &Uint128::new(2_000_000_000))],
+    )]);
+    deps.querier.with_controller("controller");
+    deps.querier.with_controller_gas_limit(500_000);
+    LP_PROVIDERS
+        .save(
+            deps.as_mut().storage,
+            &Addr::unchecked("addr0000"),
+            &Uint128::new(2_000_000_000),
+        )
+        .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(
+            "addr0000",
+            &[
+                Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::new(1_000_000),
+                },
+                Coin {
+                    denom: "uluna".to_string(),
+                    amount: Uint128::new(1_000_000),
+                },
+            ],
+        ),
+        ExecuteMsg::ProvideLiquidity {
+            assets: vec![
+                native_asset("uusd", 1_000_000),
+                native_asset("uluna", 1_000_000),
+            ],
+            slippage_tolerance: None,
+            receiver: None,
+            owner: None,
+            beneficiary: None,
+        },
+    )
+    .unwrap();
+
+    // The emission accounting call is capped and its failure is caught in the reply
+    let accum_msg = res.messages.last().unwrap();
+    assert_eq!(
+        accum_msg.msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: String::from("controller"),
+            msg: to_json_binary(&ControllerExecuteMsg::AccumUserEmissions(
+                AccumEmissionsRequest {
+                    address: String::from("addr0000"),
+                    previous_amount: Uint128::new(2_000_000_000),
+                }
+            ))
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+    assert_eq!(accum_msg.gas_limit, Some(500_000));
+    assert_eq!(accum_msg.reply_on, ReplyOn::Error);
+
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: accum_msg.id,
+            result: SubMsgResult::Err("out of gas".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "accum_emissions_failed"),
+            attr("reason", "out of gas")
+        ]
+    );
+}
+
+fn native_asset(denom: &str, amount: u128) -> Asset {
+    AssetInfo::NativeToken {
+        denom: denom.to_string(),
+    }
+    .with_balance(amount)
+}
+
+#[test]
+fn split_withdrawals_never_exceed_a_single_withdrawal() {
+    let uusd = AssetInfo::NativeToken {
+        denom: "uusd".to_string(),
+    };
+    let refund = |pool: u128, amount: u128, total_share: u128| {
+        get_share_in_assets(
+            &[uusd.with_balance(pool)],
+            Uint128::new(amount),
+            Uint128::new(total_share),
+        )[0]
+        .amount
+        .u128()
+    };
+
+    for (pool, total_share, amount, splits) in [
+        (1_000_001u128, 3_000u128, 1u128, 3_000u128),
+        (7, 1_000_000, 333, 3_000),
+        (999_999_999_999, 1_000_000_000_007, 1_000_003, 19),
+        (1_000_000_000, 1_000_000_000, 1, 1_000),
+    ] {
+        let single = refund(pool, amount * splits, total_share);
+        let (mut pool_left, mut shares_left, mut withdrawn) = (pool, total_share, 0);
+        for _ in 0..splits {
+            let refunded = refund(pool_left, amount, shares_left);
+            pool_left -= refunded;
+            shares_left -= amount;
+            withdrawn += refunded;
+        }
+        assert!(withdrawn <= single);
+    }
+}
@@ -1,11 +1,12 @@
 use cosmwasm_std::{
-    to_json_binary, Addr, Api, BankMsg, Coin, CosmosMsg, Decimal, Env, QuerierWrapper, StdResult,
-    Storage, Uint128, Uint64, WasmMsg,
+    to_json_binary, Addr, Api, BankMsg, Coin, CosmosMsg, Decimal, Decimal256, Env, QuerierWrapper,
+    StdError, StdResult, Storage, Uint128, Uint64, WasmMsg,
 };
 use cw20::Cw20ExecuteMsg;
 use itertools::Itertools;
-use std::cmp::Ordering;
 
+use ura::contracts::pair::DynamicFeeParams;
+use ura::errors::DexError;
 use ura::structs::asset::Asset;
 use ura::structs::asset_info::AssetInfo;
 use ura::structs::decimal256::Decimal256Ext;
@@ -21,8 +22,12 @@ pub(crate) fn check_asset_infos(
     api: &dyn Api,
     asset_infos: &[AssetInfo],
 ) -> Result<(), ContractError> {
-    if !asset_infos.iter().all_unique() {
-        return Err(ContractError::DoublingAssets {});
+    if asset_infos
+        .iter()
+        .tuple_combinations()
+        .any(|(a, b)| a.equal(b))
+    {
+        return Err(ContractError::Dex(DexError::DoublingAssets {}));
     }
 
     asset_infos
@@ -48,7 +53,7 @@ pub(crate) fn check_cw20_in_pool(config: &Config, cw20_sender: &Addr) -> Result<
         }
     }
 
-    Err(ContractError::Unauthorized {})
+    Err(ContractError::Dex(DexError::Unauthorized {}))
 }
 
 /// Select offer and ask pools based on given offer and ask infos.
@@ -65,6 +70,12 @@ pub(crate) fn select_pools(
     ask_asset_info: Option<&AssetInfo>,
     pools: &[Decimal256Asset],
 ) -> Result<(Decimal256Asset, Decimal256Asset), ContractError> {
+    if let (Some(offer_asset_info), Some(ask_asset_info)) = (offer_asset_info, ask_asset_info) {
+        if ask_asset_info.eq(offer_asset_info) {
+            return Err(ContractError::Dex(DexError::SameAssets {}));
+        }
+    }
+
     if pools.len() == 2 {
         match (offer_asset_info, ask_asset_info) {
             (Some(offer_asset_info), _) => {
@@ -86,10 +97,6 @@ pub(crate) fn select_pools(
     } else if let (Some(offer_asset_info), Some(ask_asset_info)) =
         (offer_asset_info, ask_asset_info)
     {
-        if ask_asset_info.eq(offer_asset_info) {
-            return Err(ContractError::SameAssets {});
-        }
-
         let offer_pool = pools
             .iter()
             .find(|pool| pool.info.eq(offer_asset_info))
@@ -107,9 +114,14 @@ pub(crate) fn select_pools(
 
 /// Compute the current pool amplification coefficient (AMP).
 pub fn compute_current_amp(config: &Config, env: &Env) -> StdResult<Uint64> {
-    let block_time = env.block.time.seconds();
-    if block_time < config.next_amp_time {
-        let elapsed_time: Uint128 = block_time.saturating_sub(config.init_amp_time).into();
+    compute_amp_at(config, env.block.time.seconds())
+}
+
+/// Compute the amp that was (or will be) in effect at `timestamp`.
+/// Beyond the end of the ramp the amp is clamped to `next_amp`.
+pub fn compute_amp_at(config: &Config, timestamp: u64) -> StdResult<Uint64> {
+    if timestamp < config.next_amp_time {
+        let elapsed_time: Uint128 = timestamp.saturating_sub(config.init_amp_time).into();
         let time_range = config
             .next_amp_time
             .saturating_sub(config.init_amp_time)
@@ -131,29 +143,6 @@ pub fn compute_current_amp(config: &Config, env: &Env) -> StdResult<Uint64> {
     }
 }
 
-/// Returns a value using a newly specified precision.
-///
-/// * **value** value that will have its precision adjusted.
-///
-/// * **current_precision** `value`'s current precision
-///
-/// * **new_precision** new precision to use when returning the `value`.
-pub(crate) fn adjust_precision(
-    value: Uint128,
-    current_precision: u8,
-    new_precision: u8,
-) -> StdResult<Uint128> {
-    Ok(match current_precision.cmp(&new_precision) {
-        Ordering::Equal => value,
-        Ordering::Less => value.checked_mul(Uint128::new(
-            10_u128.pow((new_precision - current_precision) as u32),
-        ))?,
-        Ordering::Greater => value.checked_div(Uint128::new(
-            10_u128.pow((current_precision - new_precision) as u32),
-        ))?,
-    })
-}
-
 /// Mint LP tokens for a beneficiary and auto stake the tokens in the Controller contract (if auto staking is specified).
 ///
 /// * **recipient** LP token recipient.
@@ -219,21 +208,23 @@ pub(crate) fn mint_liquidity_token_message(
 /// * **amount** amount of LP tokens to calculate underlying amounts for.
 ///
 /// * **total_share** total amount of LP tokens currently issued by the pool.
+///
+/// Every amount is rounded down in favor of the pool, so the share price never drops on a
+/// withdrawal and splitting a withdrawal into smaller ones cannot return more assets.
 pub(crate) fn get_share_in_assets(
     pools: &[Asset],
     amount: Uint128,
     total_share: Uint128,
 ) -> Vec<Asset> {
-    let mut share_ratio = Decimal::zero();
-    if !total_share.is_zero() {
-        share_ratio = Decimal::from_ratio(amount, total_share);
-    }
-
     pools
         .iter()
         .map(|pool| Asset {
             info: pool.info.clone(),
-            amount: pool.amount * share_ratio,
+            amount: if total_share.is_zero() {
+                Uint128::zero()
+            } else {
+                pool.amount.multiply_ratio(amount, total_share)
+            },
         })
         .collect()
 }
@@ -285,3 +276,29 @@ pub(crate) fn compute_swap(
         spread_amount,
     })
 }
+
+/// Returns the fee rate of a swap that leaves the pool with `offer_pool` and `ask_pool` balances.
+/// Without dynamic fee parameters this is the factory fee. Otherwise the fee moves linearly from
+/// `mid_fee` to `out_fee` as the balance ratio `4xy / (x + y)^2` drops from one to zero.
+pub(crate) fn compute_fee_rate(
+    config: &Config,
+    factory_fee_rate: Decimal,
+    offer_pool: Decimal256,
+    ask_pool: Decimal256,
+) -> StdResult<Decimal> {
+    let Some(DynamicFeeParams { mid_fee, out_fee }) = config.dynamic_fee else {
+        return Ok(factory_fee_rate);
+    };
+
+    let sum = offer_pool.atomics().checked_add(ask_pool.atomics())?;
+    if sum.is_zero() {
+        return Ok(out_fee);
+    }
+    let offer_ratio = Decimal256::from_ratio(offer_pool.atomics(), sum);
+    let balance =
+        Decimal256::from_ratio(4u8, 1u8) * offer_ratio * (Decimal256::one() - offer_ratio);
+
+    let fee_range = Decimal256::from(out_fee) - Decimal256::from(mid_fee);
+    Decimal::try_from(Decimal256::from(out_fee) - fee_range * balance)
+        .map_err(|err| StdError::generic_err(err.to_string()))
+}
@@ -1,20 +1,27 @@
 use cosmwasm_std::{
-    entry_point, from_json, to_json_binary, Addr, Api, Binary, CosmosMsg, Deps, DepsMut, Env,
-    MessageInfo, Response, StdResult, Uint128, WasmMsg,
+    attr, entry_point, from_json, to_json_binary, Addr, Api, Binary, CosmosMsg, Deps, DepsMut, Env,
+    MessageInfo, Response, StdResult, SubMsg, Uint128, WasmMsg,
 };
 use cw2::set_contract_version;
-use cw20::Cw20ReceiveMsg;
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
+use std::collections::{HashMap, VecDeque};
 
-use ura::structs::asset::Asset;
-use ura::structs::asset_info::AssetInfo;
+use ura::structs::asset::{token_asset, Asset};
+use ura::structs::asset_info::{AssetInfo, AssetInfoExt};
 use ura::utils::validation::addr_opt_validate;
 
-use ura::contracts::pair::{QueryMsg as PairQueryMsg, SimulationResponse};
+use ura::contracts::pair::{
+    ExecuteMsg as PairExecuteMsg, QueryMsg as PairQueryMsg, ReverseSimulationResponse,
+    SimulationResponse,
+};
 use ura::contracts::router::{
-    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg,
-    SimulateSwapOperationsResponse, SwapOperation, MAX_SWAP_OPERATIONS,
+    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, PostSwapAction, QueryMsg,
+    SimulateReverseSwapOperationsResponse, SimulateSwapOperationsResponse, SwapOperation,
+    MAX_ASSET_VISITS, MAX_SWAP_OPERATIONS,
 };
-use ura::utils::querier::query_pair_info;
+use ura::errors::DexError;
+use ura::utils::pagination::MAX_LIMIT;
+use ura::utils::querier::{query_pair_info, query_pairs_info};
 
 use crate::error::ContractError;
 use crate::operations::execute_swap_operation;
@@ -51,11 +58,9 @@ pub fn instantiate(
 /// * **ExecuteMsg::Receive(msg)** Receives a message of type [`Cw20ReceiveMsg`] and processes
 /// it depending on the received template.
 ///
-/// * **ExecuteMsg::ExecuteSwapOperations {
-///             operations,
-///             minimum_receive,
-///             to
-///         }** Performs swap operations with the specified parameters.
+/// * **ExecuteMsg::ExecuteSwapOperations { operations, minimum_receive, minimum_receive_human, to, post_swap_action, allowed_output_assets }** Performs swap operations with the specified parameters.
+///
+/// * **ExecuteMsg::ExecuteSwapOperationsExactOut { operations, ask_amount, max_offer, to }** Performs swap operations that return at least `ask_amount`, refunding the unused offer.
 ///
 /// * **ExecuteMsg::ExecuteSwapOperation { operation, to }** Execute a single swap operation.
 ///
@@ -65,6 +70,8 @@ pub fn instantiate(
 ///             minimum_receive,
 ///             receiver
 ///         }** Checks if an ask amount is higher than or equal to the minimum amount to receive.
+///
+/// * **ExecuteMsg::ExecutePostSwapAction { asset_info, prev_balance, action, receiver }** Forwards the output of the last swap operation according to the post swap action.
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
@@ -73,12 +80,59 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::Receive(msg) => receive_cw20(deps, env, msg),
+        ExecuteMsg::Receive(msg) => receive_cw20(deps, env, info, msg),
         ExecuteMsg::ExecuteSwapOperations {
             operations,
             to,
             minimum_receive,
-        } => execute_swap_operations(deps, env, info.sender, operations, to, minimum_receive),
+            minimum_receive_human,
+            post_swap_action,
+            allowed_output_assets,
+        } => {
+            // The offer of native swap operations is attached as funds
+            let offer_amount = match operations.first().map(|op| &op.offer_asset_info) {
+                Some(AssetInfo::NativeToken { denom }) => info
+                    .funds
+                    .iter()
+                    .find(|coin| &coin.denom == denom)
+                    .map(|coin| coin.amount)
+                    .unwrap_or_default(),
+                _ => Uint128::zero(),
+            };
+            execute_swap_operations(
+                deps,
+                env,
+                info.sender,
+                offer_amount,
+                operations,
+                to,
+                minimum_receive,
+                minimum_receive_human,
+                post_swap_action,
+                allowed_output_assets,
+            )
+        }
+        ExecuteMsg::ExecuteSwapOperationsExactOut {
+            operations,
+            ask_amount,
+            max_offer,
+            to,
+        } => {
+            let sent = match info.funds.as_slice() {
+                [coin] => Asset::from(coin),
+                _ => return Err(ContractError::Dex(DexError::InvalidFundsAttached {})),
+            };
+            execute_swap_operations_exact_out(
+                deps,
+                env,
+                info.sender,
+                sent,
+                operations,
+                ask_amount,
+                max_offer,
+                to,
+            )
+        }
         ExecuteMsg::ExecuteSwapOperation { operation, to } => {
             execute_swap_operation(deps, env, info, operation, to)
         }
@@ -94,6 +148,20 @@ pub fn execute(
             minimum_receive,
             deps.api.addr_validate(&receiver)?,
         ),
+        ExecuteMsg::ExecutePostSwapAction {
+            asset_info,
+            prev_balance,
+            action,
+            receiver,
+        } => execute_post_swap_action(
+            deps.as_ref(),
+            env,
+            info,
+            asset_info,
+            prev_balance,
+            action,
+            receiver,
+        ),
     }
 }
 
@@ -103,6 +171,7 @@ pub fn execute(
 pub fn receive_cw20(
     deps: DepsMut,
     env: Env,
+    info: MessageInfo,
     cw20_msg: Cw20ReceiveMsg,
 ) -> Result<Response, ContractError> {
     match from_json(&cw20_msg.msg)? {
@@ -110,13 +179,35 @@ pub fn receive_cw20(
             operations,
             to,
             minimum_receive,
+            minimum_receive_human,
+            post_swap_action,
+            allowed_output_assets,
         } => execute_swap_operations(
             deps,
             env,
             Addr::unchecked(cw20_msg.sender),
+            cw20_msg.amount,
             operations,
             to,
             minimum_receive,
+            minimum_receive_human,
+            post_swap_action,
+            allowed_output_assets,
+        ),
+        Cw20HookMsg::ExecuteSwapOperationsExactOut {
+            operations,
+            ask_amount,
+            max_offer,
+            to,
+        } => execute_swap_operations_exact_out(
+            deps,
+            env,
+            Addr::unchecked(cw20_msg.sender),
+            token_asset(info.sender, cw20_msg.amount),
+            operations,
+            ask_amount,
+            max_offer,
+            to,
         ),
     }
 }
@@ -125,26 +216,67 @@ pub fn receive_cw20(
 ///
 /// * **sender** address that swaps tokens.
 ///
+/// * **offer_amount** amount of the first offer asset sent to the router.
+///
 /// * **operations** all swap operations to perform.
 ///
 /// * **minimum_receive** used to guarantee that the ask amount is above a minimum amount.
 ///
+/// * **minimum_receive_human** same as `minimum_receive` but as a human amount, the stricter of both bounds applies.
+///
 /// * **to** recipient of the ask tokens.
+///
+/// * **post_swap_action** if set, the router receives the ask tokens and forwards them after the minimum amount assertion.
+///
+/// * **allowed_output_assets** if set, the ask asset of the last swap operation must be one of these assets.
 #[allow(clippy::too_many_arguments)]
 pub fn execute_swap_operations(
     deps: DepsMut,
     env: Env,
     sender: Addr,
+    offer_amount: Uint128,
     operations: Vec<SwapOperation>,
     to: Option<String>,
     minimum_receive: Option<Uint128>,
+    minimum_receive_human: Option<(Uint128, u8)>,
+    post_swap_action: Option<PostSwapAction>,
+    allowed_output_assets: Option<Vec<AssetInfo>>,
 ) -> Result<Response, ContractError> {
     assert_operations(deps.api, &operations)?;
 
     let to = addr_opt_validate(deps.api, &to)?.unwrap_or(sender);
+    let offer_asset_info = operations[0].offer_asset_info.clone();
     let target_asset_info = operations.last().unwrap().ask_asset_info.clone();
+    if let Some(allowed_output_assets) = &allowed_output_assets {
+        if !allowed_output_assets.contains(&target_asset_info) {
+            return Err(ContractError::DisallowedOutputAsset {});
+        }
+    }
     let operations_len = operations.len();
 
+    let minimum_receive = match minimum_receive_human {
+        Some(minimum_receive_human) => {
+            let config = CONFIG.load(deps.storage)?;
+            let decimals = target_asset_info.decimals(&deps.querier, &config.ura_factory)?;
+            let minimum_receive_human = to_base_units(minimum_receive_human, decimals)?;
+            Some(minimum_receive_human.max(minimum_receive.unwrap_or_default()))
+        }
+        None => minimum_receive,
+    };
+
+    if let Some(action) = &post_swap_action {
+        match action {
+            PostSwapAction::SendTo { recipient } => deps.api.addr_validate(recipient)?,
+            PostSwapAction::ProvideLiquidity { pair, .. } => deps.api.addr_validate(pair)?,
+        };
+    }
+    // The router holds the ask tokens until the post swap action forwards them
+    let ask_receiver = if post_swap_action.is_some() {
+        env.contract.address.clone()
+    } else {
+        to.clone()
+    };
+
     let mut messages = operations
         .into_iter()
         .enumerate()
@@ -155,7 +287,7 @@ pub fn execute_swap_operations(
                 msg: to_json_binary(&ExecuteMsg::ExecuteSwapOperation {
                     operation: op,
                     to: if operation_index == operations_len - 1 {
-                        Some(to.to_string())
+                        Some(ask_receiver.to_string())
                     } else {
                         None
                     },
@@ -164,16 +296,34 @@ pub fn execute_swap_operations(
         })
         .collect::<StdResult<Vec<CosmosMsg>>>()?;
 
+    let mut receiver_balance = target_asset_info.query_pool(&deps.querier, &ask_receiver)?;
+    // On a cyclic route the router still holds the offer the first swap operation spends
+    if ask_receiver == env.contract.address && offer_asset_info.equal(&target_asset_info) {
+        receiver_balance = receiver_balance.checked_sub(offer_amount)?;
+    }
+
     // Execute minimum amount assertion
     if let Some(minimum_receive) = minimum_receive {
-        let receiver_balance = target_asset_info.query_pool(&deps.querier, &to)?;
         messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: env.contract.address.to_string(),
             funds: vec![],
             msg: to_json_binary(&ExecuteMsg::AssertMinimumReceive {
-                asset_info: target_asset_info,
+                asset_info: target_asset_info.clone(),
                 prev_balance: receiver_balance,
                 minimum_receive,
+                receiver: ask_receiver.to_string(),
+            })?,
+        }));
+    }
+
+    if let Some(action) = post_swap_action {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            funds: vec![],
+            msg: to_json_binary(&ExecuteMsg::ExecutePostSwapAction {
+                asset_info: target_asset_info,
+                prev_balance: receiver_balance,
+                action,
                 receiver: to.to_string(),
             })?,
         }));
@@ -182,6 +332,80 @@ pub fn execute_swap_operations(
     Ok(Response::new().add_messages(messages))
 }
 
+/// Performs swap operations that return at least `ask_amount` of the last ask asset. The offer
+/// needed is found by reverse simulating the operations, the rest of `sent` is refunded to
+/// `sender` before the operations are executed.
+///
+/// * **sent** offer asset sent along with the message.
+///
+/// * **ask_amount** amount of ask assets to receive from the last swap operation.
+///
+/// * **max_offer** maximum amount of `sent` to spend on the swap operations.
+#[allow(clippy::too_many_arguments)]
+pub fn execute_swap_operations_exact_out(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    sent: Asset,
+    operations: Vec<SwapOperation>,
+    ask_amount: Uint128,
+    max_offer: Uint128,
+    to: Option<String>,
+) -> Result<Response, ContractError> {
+    assert_operations(deps.api, &operations)?;
+    if !operations[0].offer_asset_info.equal(&sent.info) {
+        return Err(ContractError::OfferAssetMismatch {});
+    }
+
+    let offer_amount =
+        simulate_reverse_swap_operations(deps.as_ref(), ask_amount, operations.clone())?
+            .offer_amount;
+    let max_offer = max_offer.min(sent.amount);
+    if offer_amount > max_offer {
+        return Err(ContractError::MaxOfferExceeded {
+            max_offer,
+            offer: offer_amount,
+        });
+    }
+    let refund_amount = sent.amount - offer_amount;
+
+    let mut response = execute_swap_operations(
+        deps,
+        env,
+        sender.clone(),
+        offer_amount,
+        operations,
+        to,
+        Some(ask_amount),
+        None,
+        None,
+        None,
+    )?;
+    // The refund leaves the router with the exact offer the first swap operation consumes
+    if !refund_amount.is_zero() {
+        let refund = sent.info.with_balance(refund_amount).into_msg(&sender)?;
+        response.messages.insert(0, SubMsg::new(refund));
+    }
+
+    Ok(response.add_attributes([
+        attr("action", "execute_swap_operations_exact_out"),
+        attr("offer_amount", offer_amount),
+        attr("refund_amount", refund_amount),
+    ]))
+}
+
+/// Converts a human amount with `precision` decimal places into base units of an asset with
+/// `decimals` decimals. Digits the asset cannot represent round up, so the bound never loosens.
+fn to_base_units((amount, precision): (Uint128, u8), decimals: u8) -> StdResult<Uint128> {
+    if decimals >= precision {
+        let scale = Uint128::new(10).checked_pow((decimals - precision) as u32)?;
+        Ok(amount.checked_mul(scale)?)
+    } else {
+        let scale = Uint128::new(10).checked_pow((precision - decimals) as u32)?;
+        Ok(amount.checked_add(scale - Uint128::one())? / scale)
+    }
+}
+
 /// Checks if an ask amount is equal to or above a minimum amount.
 ///
 /// * **asset_info** asset to check the ask amount for.
@@ -212,6 +436,74 @@ fn assert_minimum_receive(
     }
 }
 
+/// Forwards the amount of tokens the router received since `prev_balance`.
+///
+/// * **asset_info** asset received from the last swap operation.
+///
+/// * **prev_balance** router balance before the swap operations were executed.
+///
+/// * **action** what to do with the received assets.
+///
+/// * **receiver** address that receives LP tokens when providing liquidity.
+fn execute_post_swap_action(
+    deps: Deps,
+    env: Env,
+    info: MessageInfo,
+    asset_info: AssetInfo,
+    prev_balance: Uint128,
+    action: PostSwapAction,
+    receiver: String,
+) -> Result<Response, ContractError> {
+    if env.contract.address != info.sender {
+        return Err(ContractError::Dex(DexError::Unauthorized {}));
+    }
+
+    let router_balance = asset_info.query_pool(&deps.querier, &env.contract.address)?;
+    let received = asset_info.with_balance(router_balance.checked_sub(prev_balance)?);
+
+    let mut messages = vec![];
+    match &action {
+        PostSwapAction::SendTo { recipient } => {
+            messages.push(
+                received
+                    .clone()
+                    .into_msg(deps.api.addr_validate(recipient)?)?,
+            );
+        }
+        PostSwapAction::ProvideLiquidity { pair, max_spread } => {
+            let pair = deps.api.addr_validate(pair)?;
+            let funds = if let AssetInfo::Token { contract_addr } = &received.info {
+                messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: contract_addr.to_string(),
+                    funds: vec![],
+                    msg: to_json_binary(&Cw20ExecuteMsg::IncreaseAllowance {
+                        spender: pair.to_string(),
+                        amount: received.amount,
+                        expires: None,
+                    })?,
+                }));
+                vec![]
+            } else {
+                vec![received.as_coin()?]
+            };
+            messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: pair.to_string(),
+                funds,
+                msg: to_json_binary(&PairExecuteMsg::ProvideLiquidityAutoBalance {
+                    assets: vec![received.clone()],
+                    max_spread: *max_spread,
+                    receiver: Some(receiver),
+                })?,
+            }));
+        }
+    }
+
+    Ok(Response::new().add_messages(messages).add_attributes([
+        ("action", "post_swap_action"),
+        ("asset", &received.to_string()),
+    ]))
+}
+
 /// Exposes all the queries available in the contract.
 /// ## Queries
 /// * **QueryMsg::Config {}** Returns general router parameters using a [`ConfigResponse`] object.
@@ -219,6 +511,7 @@ fn assert_minimum_receive(
 ///             offer_amount,
 ///             operations,
 ///         }** Simulates one or multiple swap operations and returns the end result in a [`SimulateSwapOperationsResponse`] object.
+/// * **QueryMsg::FindPath { offer_asset_info, ask_asset_info, max_hops }** Searches the factory pairs for a route and returns it as a vector of [`SwapOperation`].
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
     match msg {
@@ -231,6 +524,22 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<Binary, ContractErr
             offer_amount,
             operations,
         )?)?),
+        QueryMsg::SimulateReverseSwapOperations {
+            ask_amount,
+            operations,
+        } => Ok(to_json_binary(&simulate_reverse_swap_operations(
+            deps, ask_amount, operations,
+        )?)?),
+        QueryMsg::FindPath {
+            offer_asset_info,
+            ask_asset_info,
+            max_hops,
+        } => Ok(to_json_binary(&find_path(
+            deps,
+            offer_asset_info,
+            ask_asset_info,
+            max_hops,
+        )?)?),
     }
 }
 
@@ -295,6 +604,143 @@ fn simulate_swap_operations(
     })
 }
 
+/// Returns the offer amount needed to receive `ask_amount` from one or multiple swap
+/// operations using a [`SimulateReverseSwapOperationsResponse`] object. The operations are
+/// walked backward, each pair's reverse simulation giving the ask amount of the previous hop.
+///
+/// * **ask_amount** amount of final ask assets to receive.
+///
+/// * **operations** is a vector that contains objects of type [`SwapOperation`]. These are all the swap operations for which we perform a reverse simulation.
+fn simulate_reverse_swap_operations(
+    deps: Deps,
+    ask_amount: Uint128,
+    operations: Vec<SwapOperation>,
+) -> Result<SimulateReverseSwapOperationsResponse, ContractError> {
+    assert_operations(deps.api, &operations)?;
+
+    let config = CONFIG.load(deps.storage)?;
+    let ura_factory = config.ura_factory;
+    let mut offer_amount = ask_amount;
+
+    for operation in operations.into_iter().rev() {
+        let offer_asset_info = operation.offer_asset_info;
+        let ask_asset_info = operation.ask_asset_info;
+        let pair_info = query_pair_info(
+            &deps.querier,
+            ura_factory.clone(),
+            &[offer_asset_info.clone(), ask_asset_info.clone()],
+        )?;
+        let res: ReverseSimulationResponse = deps.querier.query_wasm_smart(
+            pair_info.contract_addr,
+            &PairQueryMsg::ReverseSimulation {
+                offer_asset_info: Some(offer_asset_info),
+                ask_asset: Asset {
+                    info: ask_asset_info,
+                    amount: offer_amount,
+                },
+            },
+        )?;
+
+        offer_amount = res.offer_amount;
+    }
+
+    Ok(SimulateReverseSwapOperationsResponse { offer_amount })
+}
+
+/// Returns the shortest route of [`SwapOperation`]s from `offer_asset_info` to `ask_asset_info`
+/// over the pairs registered in the factory, using at most `max_hops` operations.
+///
+/// All factory pairs are paged in and searched breadth-first, which is why this is only
+/// exposed as a query.
+fn find_path(
+    deps: Deps,
+    offer_asset_info: AssetInfo,
+    ask_asset_info: AssetInfo,
+    max_hops: Option<u32>,
+) -> Result<Vec<SwapOperation>, ContractError> {
+    if offer_asset_info.equal(&ask_asset_info) {
+        return Err(ContractError::DoublingAssetsPath {
+            offer_asset: offer_asset_info.to_string(),
+            ask_asset: ask_asset_info.to_string(),
+        });
+    }
+
+    let max_hops = max_hops
+        .unwrap_or(MAX_SWAP_OPERATIONS as u32)
+        .min(MAX_SWAP_OPERATIONS as u32);
+    let config = CONFIG.load(deps.storage)?;
+
+    let mut neighbours: HashMap<AssetInfo, Vec<AssetInfo>> = HashMap::new();
+    let mut start_after = None;
+    loop {
+        let pairs = query_pairs_info(
+            &deps.querier,
+            &config.ura_factory,
+            start_after,
+            Some(MAX_LIMIT),
+        )?
+        .pairs;
+
+        for pair in &pairs {
+            for offer in &pair.asset_infos {
+                for ask in &pair.asset_infos {
+                    if !offer.equal(ask) {
+                        neighbours
+                            .entry(offer.clone())
+                            .or_default()
+                            .push(ask.clone());
+                    }
+                }
+            }
+        }
+
+        match pairs.last() {
+            Some(last) if pairs.len() == MAX_LIMIT as usize => {
+                start_after = Some(last.asset_infos.clone())
+            }
+            _ => break,
+        }
+    }
+
+    // Each visited asset maps to the asset it was reached from
+    let mut parents: HashMap<AssetInfo, Option<AssetInfo>> =
+        HashMap::from([(offer_asset_info.clone(), None)]);
+    let mut queue = VecDeque::from([(offer_asset_info.clone(), 0u32)]);
+
+    while let Some((asset, hops)) = queue.pop_front() {
+        if asset.equal(&ask_asset_info) {
+            let mut operations = vec![];
+            let mut current = asset;
+            while let Some(Some(parent)) = parents.get(&current) {
+                operations.push(SwapOperation {
+                    offer_asset_info: parent.clone(),
+                    ask_asset_info: current.clone(),
+                });
+                current = parent.clone();
+            }
+            operations.reverse();
+            return Ok(operations);
+        }
+
+        if hops == max_hops {
+            continue;
+        }
+
+        for next in neighbours.get(&asset).into_iter().flatten() {
+            if !parents.contains_key(next) {
+                parents.insert(next.clone(), Some(asset.clone()));
+                queue.push_back((next.clone(), hops + 1));
+            }
+        }
+    }
+
+    Err(ContractError::NoPathFound {
+        offer_asset: offer_asset_info.to_string(),
+        ask_asset: ask_asset_info.to_string(),
+        max_hops,
+    })
+}
+
 /// Validates swap operations.
 ///
 /// * **operations** is a vector that contains objects of type [`SwapOperation`]. These are all the swap operations we check.
@@ -336,6 +782,35 @@ fn assert_operations(api: &dyn Api, operations: &[SwapOperation]) -> Result<(),
         prev_ask_asset = Some(ask_asset);
     }
 
+    // Reject routes that repeat a hop or keep coming back to the same asset
+    let mut visits: Vec<(&AssetInfo, usize)> = vec![];
+    let path = std::iter::once(&operations[0].offer_asset_info)
+        .chain(operations.iter().map(|operation| &operation.ask_asset_info));
+    for asset in path {
+        let count = match visits.iter_mut().find(|(visited, _)| *visited == asset) {
+            Some((_, count)) => {
+                *count += 1;
+                *count
+            }
+            None => {
+                visits.push((asset, 1));
+                1
+            }
+        };
+        if count > MAX_ASSET_VISITS {
+            return Err(ContractError::CyclicPath {
+                asset: asset.to_string(),
+            });
+        }
+    }
+    for (i, operation) in operations.iter().enumerate() {
+        if operations[..i].contains(operation) {
+            return Err(ContractError::CyclicPath {
+                asset: operation.offer_asset_info.to_string(),
+            });
+        }
+    }
+
     Ok(())
 }
 
@@ -447,4 +922,55 @@ mod testing {
             .is_err()
         );
     }
+
+    fn route(assets: &[&str]) -> Vec<SwapOperation> {
+        assets
+            .windows(2)
+            .map(|pair| SwapOperation {
+                offer_asset_info: AssetInfo::NativeToken {
+                    denom: pair[0].to_string(),
+                },
+                ask_asset_info: AssetInfo::NativeToken {
+                    denom: pair[1].to_string(),
+                },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cyclic_operations() {
+        use cosmwasm_std::testing::mock_dependencies;
+        let deps = mock_dependencies();
+
+        // A long route passing through assets at most twice
+        assert_eq!(
+            assert_operations(
+                deps.as_ref().api,
+                &route(&["ukrw", "uluna", "uusd", "uaud", "uluna", "ueur", "ukrw"])
+            ),
+            Ok(())
+        );
+
+        // Repeating a hop
+        assert_eq!(
+            assert_operations(
+                deps.as_ref().api,
+                &route(&["ukrw", "uluna", "ukrw", "uluna"])
+            ),
+            Err(ContractError::CyclicPath {
+                asset: "ukrw".to_string()
+            })
+        );
+
+        // Visiting an asset more than MAX_ASSET_VISITS times
+        assert_eq!(
+            assert_operations(
+                deps.as_ref().api,
+                &route(&["ukrw", "uluna", "ukrw", "uusd", "ukrw"])
+            ),
+            Err(ContractError::CyclicPath {
+                asset: "ukrw".to_string()
+            })
+        );
+    }
 }
@@ -1,5 +1,6 @@
 use cosmwasm_std::{OverflowError, StdError, Uint128};
 use thiserror::Error;
+use ura::errors::DexError;
 
 /// This enum describes oracle contract errors
 #[derive(Error, Debug, PartialEq)]
@@ -7,8 +8,8 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
-    #[error("Unauthorized")]
-    Unauthorized {},
+    #[error("{0}")]
+    Dex(#[from] DexError),
 
     #[error(
         "The next offer asset must be the same as the previous ask asset; \
@@ -26,12 +27,31 @@ pub enum ContractError {
         ask_asset: String,
     },
 
+    #[error("The swap route is cyclic; {asset} is visited too often")]
+    CyclicPath { asset: String },
+
+    #[error("The ask asset of the last swap operation is not allowed")]
+    DisallowedOutputAsset {},
+
+    #[error("The sent asset is not the offer asset of the first swap operation")]
+    OfferAssetMismatch {},
+
+    #[error("Exact output swap needs {offer} offer tokens, at most {max_offer} can be spent")]
+    MaxOfferExceeded { max_offer: Uint128, offer: Uint128 },
+
     #[error("Must specify swap operations!")]
     MustProvideOperations {},
 
     #[error("Assertion failed; minimum receive amount: {receive}, swap amount: {amount}")]
     AssertionMinimumReceive { receive: Uint128, amount: Uint128 },
 
+    #[error("No swap route found from {offer_asset} to {ask_asset} within {max_hops} hops")]
+    NoPathFound {
+        offer_asset: String,
+        ask_asset: String,
+        max_hops: u32,
+    },
+
     #[error("The swap operation limit was exceeded!")]
     SwapLimitExceeded {},
 
@@ -5,6 +5,7 @@ use cosmwasm_std::{
 use cw20::Cw20ExecuteMsg;
 use ura::contracts::pair::ExecuteMsg as PairExecuteMsg;
 use ura::contracts::router::SwapOperation;
+use ura::errors::DexError;
 use ura::structs::asset::Asset;
 use ura::structs::asset_info::AssetInfo;
 use ura::utils::querier::{query_balance, query_pair_info, query_token_balance};
@@ -25,7 +26,7 @@ pub fn execute_swap_operation(
     to: Option<String>,
 ) -> Result<Response, ContractError> {
     if env.contract.address != info.sender {
-        return Err(ContractError::Unauthorized {});
+        return Err(ContractError::Dex(DexError::Unauthorized {}));
     }
 
     let config = CONFIG.load(deps.storage)?;
@@ -98,6 +99,7 @@ pub fn asset_into_swap_msg(
                 belief_price: None,
                 max_spread,
                 to,
+                to_hook: None,
             })?,
         })),
         AssetInfo::Token { contract_addr } => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
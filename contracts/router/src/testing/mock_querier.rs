@@ -7,7 +7,7 @@ use cosmwasm_std::{
 use std::collections::HashMap;
 
 use cw20::{BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
-use ura::contracts::factory::PairType;
+use ura::contracts::factory::{PairType, PairsResponse};
 use ura::contracts::pair::SimulationResponse;
 use ura::structs::asset::Asset;
 use ura::structs::asset_info::AssetInfo;
@@ -22,6 +22,10 @@ pub enum QueryMsg {
         offer_asset: Asset,
         ask_asset_info: Option<AssetInfo>,
     },
+    Pairs {
+        start_after: Option<Vec<AssetInfo>>,
+        limit: Option<u32>,
+    },
 }
 
 /// mock_dependencies is a drop-in replacement for cosmwasm_std::testing::mock_dependencies.
@@ -78,12 +82,14 @@ pub(crate) fn balances_to_map(
 #[derive(Clone, Default)]
 pub struct UraFactoryQuerier {
     pairs: HashMap<String, String>,
+    pairs_info: Vec<PairInfo>,
 }
 
 impl UraFactoryQuerier {
     pub fn new(pairs: &[(&String, &String)]) -> Self {
         UraFactoryQuerier {
             pairs: pairs_to_map(pairs),
+            pairs_info: vec![],
         }
     }
 }
@@ -159,6 +165,27 @@ impl WasmMockQuerier {
                     }),
                 }
             }
+            QueryMsg::Pairs { start_after, limit } => {
+                let start = start_after
+                    .and_then(|start_after| {
+                        self.ura_factory_querier
+                            .pairs_info
+                            .iter()
+                            .position(|pair| pair.asset_infos == start_after)
+                    })
+                    .map_or(0, |position| position + 1);
+                let pairs = self
+                    .ura_factory_querier
+                    .pairs_info
+                    .iter()
+                    .skip(start)
+                    .take(limit.unwrap_or(10) as usize)
+                    .cloned()
+                    .collect();
+                SystemResult::Ok(ContractResult::from(to_json_binary(&PairsResponse {
+                    pairs,
+                })))
+            }
             QueryMsg::Simulation { offer_asset, .. } => {
                 SystemResult::Ok(ContractResult::from(to_json_binary(&SimulationResponse {
                     return_amount: offer_asset.amount,
@@ -240,4 +267,19 @@ impl WasmMockQuerier {
     pub fn with_ura_pairs(&mut self, pairs: &[(&String, &String)]) {
         self.ura_factory_querier = UraFactoryQuerier::new(pairs);
     }
+
+    pub fn with_pairs_info(&mut self, pairs: &[Vec<AssetInfo>]) {
+        self.ura_factory_querier.pairs_info = pairs
+            .iter()
+            .enumerate()
+            .map(|(i, asset_infos)| PairInfo {
+                contract_addr: Addr::unchecked(format!("pair{:04}", i)),
+                liquidity_token: AssetInfo::Token {
+                    contract_addr: Addr::unchecked(format!("liquidity{:04}", i)),
+                },
+                asset_infos: asset_infos.clone(),
+                pair_type: PairType::Xyk,
+            })
+            .collect();
+    }
 }
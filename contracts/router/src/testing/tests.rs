@@ -1,6 +1,7 @@
 use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_json, to_json_binary, Addr, Coin, Decimal, ReplyOn, SubMsg, Uint128, WasmMsg,
+    from_json, to_json_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, ReplyOn, Response, SubMsg,
+    Uint128, WasmMsg,
 };
 
 use crate::contract::{execute, instantiate, query};
@@ -9,10 +10,11 @@ use crate::testing::mock_querier::mock_dependencies;
 
 use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg};
 
-use ura::structs::asset_info::{native_asset_info, AssetInfo};
+use ura::errors::DexError;
+use ura::structs::asset_info::{native_asset_info, AssetInfo, AssetInfoExt};
 
 use ura::contracts::router::{
-    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, QueryMsg,
+    ConfigResponse, Cw20HookMsg, ExecuteMsg, InstantiateMsg, PostSwapAction, QueryMsg,
     SimulateSwapOperationsResponse, SwapOperation, MAX_SWAP_OPERATIONS,
 };
 
@@ -53,6 +55,9 @@ fn execute_swap_operations() {
         operations: vec![],
         to: None,
         minimum_receive: None,
+        minimum_receive_human: None,
+        post_swap_action: None,
+        allowed_output_assets: None,
     };
 
     let env = mock_env();
@@ -89,6 +94,9 @@ fn execute_swap_operations() {
         ],
         to: None,
         minimum_receive: Some(Uint128::from(1000000u128)),
+        minimum_receive_human: None,
+        post_swap_action: None,
+        allowed_output_assets: None,
     };
 
     let env = mock_env();
@@ -217,6 +225,9 @@ fn execute_swap_operations() {
             ],
             to: Some(String::from("addr0002")),
             minimum_receive: None,
+            minimum_receive_human: None,
+            post_swap_action: None,
+            allowed_output_assets: None,
         })
         .unwrap(),
     });
@@ -427,6 +438,83 @@ fn query_buy_with_routes() {
     );
 }
 
+#[test]
+fn find_path_over_factory_pairs() {
+    let mut deps = mock_dependencies(&[]);
+
+    let msg = InstantiateMsg {
+        ura_factory: String::from("urafactory"),
+    };
+
+    let env = mock_env();
+    let info = mock_info("addr0000", &[]);
+    instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let token = AssetInfo::Token {
+        contract_addr: Addr::unchecked("asset0000"),
+    };
+    deps.querier.with_pairs_info(&[
+        vec![
+            native_asset_info("uusd".to_string()),
+            native_asset_info("ukrw".to_string()),
+        ],
+        vec![
+            native_asset_info("uluna".to_string()),
+            native_asset_info("uatom".to_string()),
+        ],
+        vec![native_asset_info("ukrw".to_string()), token.clone()],
+    ]);
+
+    let msg = QueryMsg::FindPath {
+        offer_asset_info: native_asset_info("uusd".to_string()),
+        ask_asset_info: token.clone(),
+        max_hops: None,
+    };
+    let res: Vec<SwapOperation> =
+        from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+    assert_eq!(
+        res,
+        vec![
+            SwapOperation {
+                offer_asset_info: native_asset_info("uusd".to_string()),
+                ask_asset_info: native_asset_info("ukrw".to_string()),
+            },
+            SwapOperation {
+                offer_asset_info: native_asset_info("ukrw".to_string()),
+                ask_asset_info: token.clone(),
+            },
+        ]
+    );
+
+    let msg = QueryMsg::FindPath {
+        offer_asset_info: native_asset_info("uusd".to_string()),
+        ask_asset_info: token.clone(),
+        max_hops: Some(1),
+    };
+    assert_eq!(
+        query(deps.as_ref(), env.clone(), msg).unwrap_err(),
+        ContractError::NoPathFound {
+            offer_asset: "uusd".to_string(),
+            ask_asset: "asset0000".to_string(),
+            max_hops: 1,
+        }
+    );
+
+    let msg = QueryMsg::FindPath {
+        offer_asset_info: native_asset_info("uusd".to_string()),
+        ask_asset_info: native_asset_info("uatom".to_string()),
+        max_hops: None,
+    };
+    assert_eq!(
+        query(deps.as_ref(), env, msg).unwrap_err(),
+        ContractError::NoPathFound {
+            offer_asset: "uusd".to_string(),
+            ask_asset: "uatom".to_string(),
+            max_hops: MAX_SWAP_OPERATIONS as u32,
+        }
+    );
+}
+
 #[test]
 fn assert_minimum_receive_native_token() {
     let mut deps = mock_dependencies(&[]);
@@ -538,6 +626,9 @@ fn assert_maximum_receive_swap_operations() {
         ],
         to: None,
         minimum_receive: None,
+        minimum_receive_human: None,
+        post_swap_action: None,
+        allowed_output_assets: None,
     };
 
     let env = mock_env();
@@ -546,3 +637,403 @@ fn assert_maximum_receive_swap_operations() {
 
     assert_eq!(res, ContractError::SwapLimitExceeded {});
 }
+
+#[test]
+fn execute_swap_operations_with_send_to() {
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uluna".to_string(),
+        amount: Uint128::new(100),
+    }]);
+    let msg = InstantiateMsg {
+        ura_factory: String::from("urafactory"),
+    };
+
+    let env = mock_env();
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    let operation = SwapOperation {
+        offer_asset_info: native_asset_info("ukrw".to_string()),
+        ask_asset_info: native_asset_info("uluna".to_string()),
+    };
+    let action = PostSwapAction::SendTo {
+        recipient: String::from("recipient0000"),
+    };
+    let msg = ExecuteMsg::ExecuteSwapOperations {
+        operations: vec![operation.clone()],
+        to: None,
+        minimum_receive: Some(Uint128::new(1000)),
+        minimum_receive_human: None,
+        post_swap_action: Some(action.clone()),
+        allowed_output_assets: None,
+    };
+
+    let env = mock_env();
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    // The router receives the ask tokens and forwards them after the minimum receive assertion
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: String::from(MOCK_CONTRACT_ADDR),
+                funds: vec![],
+                msg: to_json_binary(&ExecuteMsg::ExecuteSwapOperation {
+                    operation,
+                    to: Some(String::from(MOCK_CONTRACT_ADDR)),
+                })
+                .unwrap(),
+            }),
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: String::from(MOCK_CONTRACT_ADDR),
+                funds: vec![],
+                msg: to_json_binary(&ExecuteMsg::AssertMinimumReceive {
+                    asset_info: native_asset_info("uluna".to_string()),
+                    prev_balance: Uint128::new(100),
+                    minimum_receive: Uint128::new(1000),
+                    receiver: String::from(MOCK_CONTRACT_ADDR),
+                })
+                .unwrap(),
+            }),
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: String::from(MOCK_CONTRACT_ADDR),
+                funds: vec![],
+                msg: to_json_binary(&ExecuteMsg::ExecutePostSwapAction {
+                    asset_info: native_asset_info("uluna".to_string()),
+                    prev_balance: Uint128::new(100),
+                    action: action.clone(),
+                    receiver: String::from("addr0000"),
+                })
+                .unwrap(),
+            }),
+        ]
+    );
+
+    // The swap delivered 5000 uluna to the router
+    deps.querier.with_balance(&[(
+        &String::from(MOCK_CONTRACT_ADDR),
+        &[Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(5100),
+        }],
+    )]);
+
+    let msg = ExecuteMsg::ExecutePostSwapAction {
+        asset_info: native_asset_info("uluna".to_string()),
+        prev_balance: Uint128::new(100),
+        action,
+        receiver: String::from("addr0000"),
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap_err();
+    assert_eq!(res, ContractError::Dex(DexError::Unauthorized {}));
+
+    let info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: String::from("recipient0000"),
+            amount: vec![Coin {
+                denom: "uluna".to_string(),
+                amount: Uint128::new(5000),
+            }],
+        })]
+    );
+}
+
+#[test]
+fn post_swap_action_provides_liquidity_with_max_spread() {
+    let mut deps = mock_dependencies(&[]);
+    let msg = InstantiateMsg {
+        ura_factory: String::from("urafactory"),
+    };
+    let env = mock_env();
+    instantiate(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg).unwrap();
+
+    // The swap delivered 5000 uluna to the router
+    deps.querier.with_balance(&[(
+        &String::from(MOCK_CONTRACT_ADDR),
+        &[Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(5100),
+        }],
+    )]);
+
+    let msg = ExecuteMsg::ExecutePostSwapAction {
+        asset_info: native_asset_info("uluna".to_string()),
+        prev_balance: Uint128::new(100),
+        action: PostSwapAction::ProvideLiquidity {
+            pair: String::from("pair0000"),
+            max_spread: Some(Decimal::percent(1)),
+        },
+        receiver: String::from("addr0000"),
+    };
+    let res = execute(deps.as_mut(), env, mock_info(MOCK_CONTRACT_ADDR, &[]), msg).unwrap();
+    let received = Coin {
+        denom: "uluna".to_string(),
+        amount: Uint128::new(5000),
+    };
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: String::from("pair0000"),
+            funds: vec![received.clone()],
+            msg: to_json_binary(
+                &ura::contracts::pair::ExecuteMsg::ProvideLiquidityAutoBalance {
+                    assets: vec![
+                        native_asset_info("uluna".to_string()).with_balance(received.amount)
+                    ],
+                    max_spread: Some(Decimal::percent(1)),
+                    receiver: Some(String::from("addr0000")),
+                }
+            )
+            .unwrap(),
+        })]
+    );
+}
+
+#[test]
+fn cyclic_route_post_swap_action_excludes_the_offer() {
+    // The router already holds the 100 uluna sent along with the message
+    let mut deps = mock_dependencies(&[Coin {
+        denom: "uluna".to_string(),
+        amount: Uint128::new(100),
+    }]);
+    let msg = InstantiateMsg {
+        ura_factory: String::from("urafactory"),
+    };
+
+    let env = mock_env();
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    let action = PostSwapAction::SendTo {
+        recipient: String::from("recipient0000"),
+    };
+    let msg = ExecuteMsg::ExecuteSwapOperations {
+        operations: vec![
+            SwapOperation {
+                offer_asset_info: native_asset_info("uluna".to_string()),
+                ask_asset_info: native_asset_info("ukrw".to_string()),
+            },
+            SwapOperation {
+                offer_asset_info: native_asset_info("ukrw".to_string()),
+                ask_asset_info: native_asset_info("uluna".to_string()),
+            },
+        ],
+        to: None,
+        minimum_receive: None,
+        minimum_receive_human: None,
+        post_swap_action: Some(action.clone()),
+        allowed_output_assets: None,
+    };
+
+    let env = mock_env();
+    let info = mock_info(
+        "addr0000",
+        &[Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(100),
+        }],
+    );
+    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    // The offer is spent by the first swap operation, it is not part of the previous balance
+    assert_eq!(
+        res.messages.last().unwrap(),
+        &SubMsg::new(WasmMsg::Execute {
+            contract_addr: String::from(MOCK_CONTRACT_ADDR),
+            funds: vec![],
+            msg: to_json_binary(&ExecuteMsg::ExecutePostSwapAction {
+                asset_info: native_asset_info("uluna".to_string()),
+                prev_balance: Uint128::zero(),
+                action: action.clone(),
+                receiver: String::from("addr0000"),
+            })
+            .unwrap(),
+        })
+    );
+
+    // The route returned 95 uluna to the router, all of it is forwarded
+    deps.querier.with_balance(&[(
+        &String::from(MOCK_CONTRACT_ADDR),
+        &[Coin {
+            denom: "uluna".to_string(),
+            amount: Uint128::new(95),
+        }],
+    )]);
+
+    let msg = ExecuteMsg::ExecutePostSwapAction {
+        asset_info: native_asset_info("uluna".to_string()),
+        prev_balance: Uint128::zero(),
+        action,
+        receiver: String::from("addr0000"),
+    };
+    let info = mock_info(MOCK_CONTRACT_ADDR, &[]);
+    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: String::from("recipient0000"),
+            amount: vec![Coin {
+                denom: "uluna".to_string(),
+                amount: Uint128::new(95),
+            }],
+        })]
+    );
+}
+
+#[test]
+fn execute_swap_operations_with_allowed_output_assets() {
+    let mut deps = mock_dependencies(&[]);
+    let msg = InstantiateMsg {
+        ura_factory: String::from("urafactory"),
+    };
+
+    let env = mock_env();
+    let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), env, info, msg).unwrap();
+
+    let operations = vec![
+        SwapOperation {
+            offer_asset_info: native_asset_info("ukrw".to_string()),
+            ask_asset_info: AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0001"),
+            },
+        },
+        SwapOperation {
+            offer_asset_info: AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0001"),
+            },
+            ask_asset_info: native_asset_info("uluna".to_string()),
+        },
+    ];
+
+    // The final ask asset is not in the allowed set
+    let msg = ExecuteMsg::ExecuteSwapOperations {
+        operations: operations.clone(),
+        to: None,
+        minimum_receive: None,
+        minimum_receive_human: None,
+        post_swap_action: None,
+        allowed_output_assets: Some(vec![
+            native_asset_info("ukrw".to_string()),
+            AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0001"),
+            },
+        ]),
+    };
+    let info = mock_info("addr0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(err, ContractError::DisallowedOutputAsset {});
+
+    // The same check applies to swaps started from a CW20 hook
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: String::from("addr0000"),
+        amount: Uint128::from(1000000u128),
+        msg: to_json_binary(&Cw20HookMsg::ExecuteSwapOperations {
+            operations: operations.clone(),
+            to: None,
+            minimum_receive: None,
+            minimum_receive_human: None,
+            post_swap_action: None,
+            allowed_output_assets: Some(vec![]),
+        })
+        .unwrap(),
+    });
+    let info = mock_info("asset0000", &[]);
+    let err = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
+    assert_eq!(err, ContractError::DisallowedOutputAsset {});
+
+    // The final ask asset is allowed
+    let msg = ExecuteMsg::ExecuteSwapOperations {
+        operations,
+        to: None,
+        minimum_receive: None,
+        minimum_receive_human: None,
+        post_swap_action: None,
+        allowed_output_assets: Some(vec![native_asset_info("uluna".to_string())]),
+    };
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    assert_eq!(res.messages.len(), 2);
+}
+
+#[test]
+fn minimum_receive_human_matches_base_units() {
+    let mut deps = mock_dependencies(&[]);
+    let msg = InstantiateMsg {
+        ura_factory: String::from("urafactory"),
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    // The ask token has 6 decimals
+    deps.querier.with_token_balances(&[(
+        &String::from("asset0000"),
+        &[(&String::from("addr0000"), &Uint128::new(100))],
+    )]);
+
+    let swap = |minimum_receive, minimum_receive_human| ExecuteMsg::ExecuteSwapOperations {
+        operations: vec![SwapOperation {
+            offer_asset_info: native_asset_info("ukrw".to_string()),
+            ask_asset_info: AssetInfo::Token {
+                contract_addr: Addr::unchecked("asset0000"),
+            },
+        }],
+        to: None,
+        minimum_receive,
+        minimum_receive_human,
+        post_swap_action: None,
+        allowed_output_assets: None,
+    };
+    let assert_minimum_receive = |res: Response| match &res.messages[1].msg {
+        CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => match from_json(msg).unwrap() {
+            ExecuteMsg::AssertMinimumReceive {
+                minimum_receive, ..
+            } => minimum_receive,
+            _ => panic!("unexpected message"),
+        },
+        _ => panic!("unexpected message"),
+    };
+
+    let base = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        swap(Some(Uint128::new(1_500_000)), None),
+    )
+    .unwrap();
+    // 1.5 tokens
+    let human = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        swap(None, Some((Uint128::new(15), 1))),
+    )
+    .unwrap();
+    assert_eq!(base.messages, human.messages);
+    assert_eq!(assert_minimum_receive(human), Uint128::new(1_500_000));
+
+    // Decimal places the token cannot represent round the bound up
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        swap(None, Some((Uint128::new(15_000_001), 7))),
+    )
+    .unwrap();
+    assert_eq!(assert_minimum_receive(res), Uint128::new(1_500_001));
+
+    // The stricter of both bounds applies
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        swap(Some(Uint128::new(2_000_000)), Some((Uint128::new(15), 1))),
+    )
+    .unwrap();
+    assert_eq!(assert_minimum_receive(res), Uint128::new(2_000_000));
+}
@@ -78,6 +78,9 @@ impl FactoryHelper {
                     total_fee_bps: 0,
                     is_disabled: false,
                     is_controller_disabled: false,
+                    is_min_commission_enabled: false,
+                    min_swap_amount: None,
+                    max_price_impact: None,
                 },
                 PairConfig {
                     code_id: pair_code_id,
@@ -85,11 +88,18 @@ impl FactoryHelper {
                     total_fee_bps: 0,
                     is_disabled: false,
                     is_controller_disabled: false,
+                    is_min_commission_enabled: false,
+                    min_swap_amount: None,
+                    max_price_impact: None,
                 },
             ],
             controller_address: None,
             owner: owner.to_string(),
             coin_registry_address: "coin_registry".to_string(),
+            disallow_lp_assets: None,
+            controller_gas_limit: None,
+            trusted_denom_admins: None,
+            denom_creation_fee: None,
             token_code_id: cw20_token_code_id,
             fee_address: None,
         };
@@ -135,6 +145,7 @@ impl FactoryHelper {
             asset_infos,
             init_params,
             toggle_cw20_token: Some(true),
+            immutable_lp: None,
         };
 
         router.execute_contract(sender.clone(), self.factory.clone(), &msg, &[])
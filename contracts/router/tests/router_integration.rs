@@ -7,7 +7,7 @@ use cosmwasm_std::{to_json_binary, Addr, Empty, StdError};
 use cw20::Cw20ExecuteMsg;
 use cw_multi_test::{App, Contract, ContractWrapper, Executor};
 use ura::contracts::factory::PairType;
-use ura::contracts::router::{ExecuteMsg, InstantiateMsg, SwapOperation};
+use ura::contracts::router::{Cw20HookMsg, ExecuteMsg, InstantiateMsg, SwapOperation};
 use ura::structs::asset_info::token_asset_info;
 
 fn router_contract() -> Box<dyn Contract<Empty>> {
@@ -28,14 +28,8 @@ fn router_does_not_enforce_spread_assertion() {
     let token_x = instantiate_token(&mut app, helper.cw20_token_code_id, &owner, "TOX", None);
     let token_y = instantiate_token(&mut app, helper.cw20_token_code_id, &owner, "TOY", None);
     let token_z = instantiate_token(&mut app, helper.cw20_token_code_id, &owner, "TOZ", None);
-    assert_eq!(
-        token_x,
-       Addr::unchecked("contract2")
-    );
-    assert_eq!(
-        token_y,
-       Addr::unchecked("contract3")
-    );
+    assert_eq!(token_x, Addr::unchecked("contract2"));
+    assert_eq!(token_y, Addr::unchecked("contract3"));
     for (a, b, typ, liq) in [
         (&token_x, &token_y, PairType::Xyk, 100_000_000000),
         (&token_y, &token_z, PairType::Stable, 1_000_000_000000),
@@ -82,6 +76,9 @@ fn router_does_not_enforce_spread_assertion() {
                     },
                 ],
                 minimum_receive: None,
+                minimum_receive_human: None,
+                post_swap_action: None,
+                allowed_output_assets: None,
                 to: None,
             })
             .unwrap(),
@@ -255,6 +252,9 @@ fn test_swap_route() {
             msg: to_json_binary(&ExecuteMsg::ExecuteSwapOperations {
                 operations: swap_operations.clone(),
                 minimum_receive: None,
+                minimum_receive_human: None,
+                post_swap_action: None,
+                allowed_output_assets: None,
                 to: None,
             })
             .unwrap(),
@@ -341,6 +341,9 @@ fn test_swap_route() {
             msg: to_json_binary(&ExecuteMsg::ExecuteSwapOperations {
                 operations: swap_operations.clone(),
                 minimum_receive: None,
+                minimum_receive_human: None,
+                post_swap_action: None,
+                allowed_output_assets: None,
                 to: None,
             })
             .unwrap(),
@@ -432,6 +435,9 @@ fn test_swap_route() {
                     },
                 }],
                 minimum_receive: Some(Uint128::new(9_997_000)),
+                minimum_receive_human: None,
+                post_swap_action: None,
+                allowed_output_assets: None,
                 to: None,
             },
             &[],
@@ -563,6 +569,9 @@ fn test_swap_route() {
             msg: to_json_binary(&ExecuteMsg::ExecuteSwapOperations {
                 operations: swap_operations.clone(),
                 minimum_receive: Some(donated_atom),
+                minimum_receive_human: None,
+                post_swap_action: None,
+                allowed_output_assets: None,
                 to: None,
             })
             .unwrap(),
@@ -620,6 +629,9 @@ fn test_swap_route() {
                     },
                 }],
                 minimum_receive: None,
+                minimum_receive_human: None,
+                post_swap_action: None,
+                allowed_output_assets: None,
                 to: None,
             },
             &[],
@@ -654,3 +666,193 @@ fn test_swap_route() {
     let profit = balance_res.balance.saturating_sub(donated_atom);
     println!("Attacker2's profit: {:?}", profit);
 }
+
+#[test]
+fn reverse_simulation_covers_the_ask_amount() {
+    use cosmwasm_std::Uint128;
+    use ura::contracts::router::{
+        QueryMsg, SimulateReverseSwapOperationsResponse, SimulateSwapOperationsResponse,
+    };
+
+    let mut app = App::default();
+
+    let owner = Addr::unchecked("owner");
+    let mut helper = FactoryHelper::init(&mut app, &owner);
+
+    let token_x = instantiate_token(&mut app, helper.cw20_token_code_id, &owner, "TOX", None);
+    let token_y = instantiate_token(&mut app, helper.cw20_token_code_id, &owner, "TOY", None);
+    let token_z = instantiate_token(&mut app, helper.cw20_token_code_id, &owner, "TOZ", None);
+    for (a, b, liq_a, liq_b) in [
+        (&token_x, &token_y, 100_000_000_000, 200_000_000_000),
+        (&token_y, &token_z, 300_000_000_000, 100_000_000_000),
+    ] {
+        let pair = helper
+            .create_pair_with_addr(&mut app, &owner, PairType::Xyk, [a, b], None)
+            .unwrap();
+        mint(&mut app, &owner, a, liq_a, &pair).unwrap();
+        mint(&mut app, &owner, b, liq_b, &pair).unwrap();
+    }
+
+    let router_code = app.store_code(router_contract());
+    let router = app
+        .instantiate_contract(
+            router_code,
+            owner.clone(),
+            &InstantiateMsg {
+                ura_factory: helper.factory.to_string(),
+            },
+            &[],
+            "router",
+            None,
+        )
+        .unwrap();
+
+    let operations = vec![
+        SwapOperation {
+            offer_asset_info: token_asset_info(token_x.clone()),
+            ask_asset_info: token_asset_info(token_y.clone()),
+        },
+        SwapOperation {
+            offer_asset_info: token_asset_info(token_y.clone()),
+            ask_asset_info: token_asset_info(token_z.clone()),
+        },
+    ];
+
+    let ask_amount = Uint128::new(1_000_000_000);
+    let reverse: SimulateReverseSwapOperationsResponse = app
+        .wrap()
+        .query_wasm_smart(
+            router.clone(),
+            &QueryMsg::SimulateReverseSwapOperations {
+                ask_amount,
+                operations: operations.clone(),
+            },
+        )
+        .unwrap();
+    // Two xyk hops at 2:1 and 1:3 prices, so roughly 1.5 X per Z plus fees and spread
+    assert!(reverse.offer_amount > Uint128::new(1_500_000_000));
+
+    let forward: SimulateSwapOperationsResponse = app
+        .wrap()
+        .query_wasm_smart(
+            router,
+            &QueryMsg::SimulateSwapOperations {
+                offer_amount: reverse.offer_amount,
+                operations,
+            },
+        )
+        .unwrap();
+    assert!(forward.amount >= ask_amount);
+}
+
+#[test]
+fn exact_out_swap_refunds_the_unused_offer() {
+    use cosmwasm_std::Uint128;
+    use cw20::{BalanceResponse, Cw20QueryMsg};
+    use ura::contracts::router::{QueryMsg, SimulateReverseSwapOperationsResponse};
+
+    let mut app = App::default();
+
+    let owner = Addr::unchecked("owner");
+    let mut helper = FactoryHelper::init(&mut app, &owner);
+
+    let token_x = instantiate_token(&mut app, helper.cw20_token_code_id, &owner, "TOX", None);
+    let token_y = instantiate_token(&mut app, helper.cw20_token_code_id, &owner, "TOY", None);
+    let token_z = instantiate_token(&mut app, helper.cw20_token_code_id, &owner, "TOZ", None);
+    for (a, b, liq_a, liq_b) in [
+        (&token_x, &token_y, 100_000_000_000, 200_000_000_000),
+        (&token_y, &token_z, 300_000_000_000, 100_000_000_000),
+    ] {
+        let pair = helper
+            .create_pair_with_addr(&mut app, &owner, PairType::Xyk, [a, b], None)
+            .unwrap();
+        mint(&mut app, &owner, a, liq_a, &pair).unwrap();
+        mint(&mut app, &owner, b, liq_b, &pair).unwrap();
+    }
+
+    let router_code = app.store_code(router_contract());
+    let router = app
+        .instantiate_contract(
+            router_code,
+            owner.clone(),
+            &InstantiateMsg {
+                ura_factory: helper.factory.to_string(),
+            },
+            &[],
+            "router",
+            None,
+        )
+        .unwrap();
+
+    let operations = vec![
+        SwapOperation {
+            offer_asset_info: token_asset_info(token_x.clone()),
+            ask_asset_info: token_asset_info(token_y.clone()),
+        },
+        SwapOperation {
+            offer_asset_info: token_asset_info(token_y.clone()),
+            ask_asset_info: token_asset_info(token_z.clone()),
+        },
+    ];
+    let ask_amount = Uint128::new(1_000_000_000);
+    let reverse: SimulateReverseSwapOperationsResponse = app
+        .wrap()
+        .query_wasm_smart(
+            router.clone(),
+            &QueryMsg::SimulateReverseSwapOperations {
+                ask_amount,
+                operations: operations.clone(),
+            },
+        )
+        .unwrap();
+
+    let user = Addr::unchecked("user");
+    let sent = reverse.offer_amount + Uint128::new(500_000_000);
+    mint(&mut app, &owner, &token_x, sent.u128(), &user).unwrap();
+
+    let mut swap = |max_offer: Uint128| {
+        app.execute_contract(
+            user.clone(),
+            token_x.clone(),
+            &Cw20ExecuteMsg::Send {
+                contract: router.to_string(),
+                amount: sent,
+                msg: to_json_binary(&Cw20HookMsg::ExecuteSwapOperationsExactOut {
+                    operations: operations.clone(),
+                    ask_amount,
+                    max_offer,
+                    to: None,
+                })
+                .unwrap(),
+            },
+            &[],
+        )
+    };
+
+    let err = swap(reverse.offer_amount - Uint128::one()).unwrap_err();
+    assert_eq!(
+        err.downcast::<ura_router::error::ContractError>().unwrap(),
+        ura_router::error::ContractError::MaxOfferExceeded {
+            max_offer: reverse.offer_amount - Uint128::one(),
+            offer: reverse.offer_amount,
+        }
+    );
+
+    swap(sent).unwrap();
+
+    let balance = |app: &App, token: &Addr, address: &Addr| -> Uint128 {
+        app.wrap()
+            .query_wasm_smart::<BalanceResponse>(
+                token,
+                &Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap()
+            .balance
+    };
+    // Only the simulated offer is spent, the overpaid part is back with the user
+    assert_eq!(balance(&app, &token_x, &user), sent - reverse.offer_amount);
+    assert_eq!(balance(&app, &token_x, &router), Uint128::zero());
+    assert!(balance(&app, &token_z, &user) >= ask_amount);
+}
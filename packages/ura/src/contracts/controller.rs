@@ -27,6 +27,7 @@ pub enum ExecuteMsg {
     ClaimEmissionRewards(Pool),
     UpdateEmissions(UpdateEmissionsRequest),
     AccumUserEmissions(AccumEmissionsRequest),
+    AccumUserEmissionsBatch(Vec<AccumEmissionsRequest>),
 }
 
 #[cw_serde]
@@ -91,6 +92,31 @@ pub struct AccumEmissionsRequest {
     pub previous_amount: Uint128,
 }
 
+/// Collects [`AccumEmissionsRequest`]s so that a contract touching several LP positions in one
+/// execution notifies the controller with a single message.
+#[derive(Debug, Default)]
+pub struct AccumEmissionsBatch {
+    requests: Vec<AccumEmissionsRequest>,
+}
+
+impl AccumEmissionsBatch {
+    /// Queues the accumulation for `address`. Addresses that held no LP tokens before have
+    /// nothing to accumulate and are skipped.
+    pub fn push(&mut self, address: impl Into<String>, previous_amount: Uint128) {
+        if !previous_amount.is_zero() {
+            self.requests.push(AccumEmissionsRequest {
+                address: address.into(),
+                previous_amount,
+            });
+        }
+    }
+
+    /// Returns the batched controller message, or `None` if nothing was queued.
+    pub fn into_msg(self) -> Option<ExecuteMsg> {
+        (!self.requests.is_empty()).then_some(ExecuteMsg::AccumUserEmissionsBatch(self.requests))
+    }
+}
+
 #[cw_serde]
 pub struct EndEpochRequest {
     pub limit: Option<u64>,
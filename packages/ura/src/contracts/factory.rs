@@ -1,10 +1,12 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Binary};
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, Uint128};
 use std::fmt::{Display, Formatter, Result};
 
-use crate::structs::{asset_info::AssetInfo, pair_info::PairInfo};
+use crate::structs::{asset::Asset, asset_info::AssetInfo, pair_info::PairInfo};
 
 const MAX_TOTAL_FEE_BPS: u16 = 10_000;
+/// The maximum number of pairs created by a single [`ExecuteMsg::CreatePairs`] message
+pub const MAX_CREATE_PAIRS: usize = 10;
 
 /// This structure holds the main contract parameters.
 #[cw_serde]
@@ -19,6 +21,21 @@ pub struct Config {
     pub coin_registry_address: Addr,
     /// Fee accumulation address
     pub fee_address: Addr,
+    /// Whether creating pairs with assets that look like LP tokens is rejected
+    #[serde(default)]
+    pub disallow_lp_assets: bool,
+    /// The gas limit of the emission accounting calls pairs make to the controller
+    #[serde(default)]
+    pub controller_gas_limit: Option<u64>,
+    /// The admins tokenfactory denoms must have to be used in a pair, any admin if not set
+    #[serde(default)]
+    pub trusted_denom_admins: Option<Vec<Addr>>,
+    /// Fee accumulation addresses used instead of `fee_address` for commission in a given asset
+    #[serde(default)]
+    pub asset_fee_addresses: Vec<(AssetInfo, Addr)>,
+    /// The fee the chain charges for creating a native LP denom, declared to every pair creating one
+    #[serde(default)]
+    pub denom_creation_fee: Vec<Coin>,
 }
 
 #[cw_serde]
@@ -52,6 +69,17 @@ pub struct PairConfig {
     /// Setting this to true means that pairs of this type will not be able
     /// to get a gauge
     pub is_controller_disabled: bool,
+    /// Whether pairs of this type charge at least one unit of commission on every swap
+    /// with a non-zero fee, so that fees cannot be dodged by splitting a swap into dust
+    #[serde(default)]
+    pub is_min_commission_enabled: bool,
+    /// The minimum offer amount of a swap in pairs of this type, no minimum if not set
+    #[serde(default)]
+    pub min_swap_amount: Option<Uint128>,
+    /// The maximum share by which a single swap may move the pool price in pairs of this type,
+    /// unlimited if not set
+    #[serde(default)]
+    pub max_price_impact: Option<Decimal>,
 }
 
 impl PairConfig {
@@ -76,6 +104,14 @@ pub struct InstantiateMsg {
     pub coin_registry_address: String,
     /// Address of contract that is used to send fees in phrase 1
     pub fee_address: Option<String>,
+    /// Whether creating pairs with assets that look like LP tokens is rejected, false by default
+    pub disallow_lp_assets: Option<bool>,
+    /// The gas limit of the emission accounting calls pairs make to the controller, unlimited by default
+    pub controller_gas_limit: Option<u64>,
+    /// The admins tokenfactory denoms must have to be used in a pair, any admin by default
+    pub trusted_denom_admins: Option<Vec<String>>,
+    /// The fee the chain charges for creating a native LP denom, none by default
+    pub denom_creation_fee: Option<Vec<Coin>>,
 }
 
 /// This structure describes the execute messages of the contract.
@@ -87,13 +123,22 @@ pub enum ExecuteMsg {
         controller_address: Option<String>,
         /// The address of the contract that contains the coins and their accuracy
         coin_registry_address: Option<String>,
+        /// Whether creating pairs with assets that look like LP tokens is rejected
+        disallow_lp_assets: Option<bool>,
+        /// The gas limit of the emission accounting calls pairs make to the controller
+        controller_gas_limit: Option<u64>,
+        /// The admins tokenfactory denoms must have to be used in a pair, an empty list lifts the restriction
+        trusted_denom_admins: Option<Vec<String>>,
+        /// The fee the chain charges for creating a native LP denom
+        denom_creation_fee: Option<Vec<Coin>>,
     },
     /// UpdatePairConfig updates the config for a pair type.
     UpdatePairConfig {
         /// New [`PairConfig`] settings for a pair type
         config: PairConfig,
     },
-    /// CreatePair instantiates a new pair contract.
+    /// CreatePair instantiates a new pair contract. The attached funds must be exactly the
+    /// denom creation fee, or none for a pair with a cw20 LP token.
     CreatePair {
         /// The pair type (exposed in [`PairType`])
         pair_type: PairType,
@@ -103,18 +148,37 @@ pub enum ExecuteMsg {
         init_params: Option<Binary>,
         /// Optional param to use cw20 LP token
         toggle_cw20_token: Option<bool>,
+        /// Instantiates the cw20 LP token without an admin, so the factory owner can't migrate it
+        immutable_lp: Option<bool>,
+    },
+    /// CreatePairs instantiates up to [`MAX_CREATE_PAIRS`] pair contracts. The attached funds must
+    /// be exactly the denom creation fee for each pair that uses a native LP token.
+    CreatePairs { pairs: Vec<CreatePairRequest> },
+    /// SetAssetFeeAddress routes the commission denominated in `asset_info` to `fee_address`,
+    /// or back to the global fee address if `fee_address` is not set.
+    SetAssetFeeAddress {
+        /// The commission asset to set the fee address for
+        asset_info: AssetInfo,
+        /// The address that accumulates the commission in this asset
+        fee_address: Option<String>,
     },
     /// Deregister removes a previously created pair.
     Deregister {
         /// The assets for which we deregister a pool
         asset_infos: Vec<AssetInfo>,
     },
+    /// UpdatePairLpToken re-indexes the calling pair under its current LP token once it
+    /// replaced `previous_lp_token`. Only registered pairs can call it.
+    UpdatePairLpToken {
+        /// The LP token the pair was indexed under so far
+        previous_lp_token: AssetInfo,
+    },
     /// ProposeNewOwner creates a proposal to change contract ownership.
     /// The validity period for the proposal is set in the `expires_in` variable.
     ProposeNewOwner {
         /// Newly proposed contract owner
         owner: String,
-        /// The date after which this proposal expires
+        /// The date after which this proposal expires. Capped at [`crate::utils::ownership::MAX_OWNERSHIP_PROPOSAL_TTL`] seconds
         expires_in: u64,
     },
     /// DropOwnershipProposal removes the existing offer to change contract ownership.
@@ -123,6 +187,21 @@ pub enum ExecuteMsg {
     ClaimOwnership {},
 }
 
+/// This structure describes a single pair of a [`ExecuteMsg::CreatePairs`] message.
+#[cw_serde]
+pub struct CreatePairRequest {
+    /// The pair type (exposed in [`PairType`])
+    pub pair_type: PairType,
+    /// The assets to create the pool for
+    pub asset_infos: Vec<AssetInfo>,
+    /// Optional binary serialised parameters for custom pool types
+    pub init_params: Option<Binary>,
+    /// Optional param to use cw20 LP token
+    pub toggle_cw20_token: Option<bool>,
+    /// Instantiates the cw20 LP token without an admin, so the factory owner can't migrate it
+    pub immutable_lp: Option<bool>,
+}
+
 /// This structure describes the available query messages for the factory contract.
 #[cw_serde]
 #[derive(QueryResponses)]
@@ -153,6 +232,48 @@ pub enum QueryMsg {
     /// Returns a vector that contains blacklisted pair types
     #[returns(Vec<PairType>)]
     BlacklistedPairTypes {},
+    /// Tvl returns the reserves of registered pairs, paginated like `Pairs`. Every pair is
+    /// queried for its pool, so keep `limit` at the default of 10 or below to stay within gas limits.
+    #[returns(TvlResponse)]
+    Tvl {
+        /// The pair item to start reading from. It is an [`Option`] type that accepts [`AssetInfo`] elements.
+        start_after: Option<Vec<AssetInfo>>,
+        /// The number of pairs to read and return. It is an [`Option`] type.
+        limit: Option<u32>,
+    },
+    /// PairsByAssetPairs returns information about the pairs for each of the given asset pairs in the same order.
+    /// Missing pairs are returned as `None`. At most 30 asset pairs can be queried at once.
+    #[returns(Vec<Option<PairInfo>>)]
+    PairsByAssetPairs {
+        /// The assets of each pair to look up
+        asset_info_pairs: Vec<Vec<AssetInfo>>,
+    },
+    /// PairTypeCodeId returns the code ID currently used to instantiate pairs of the given type.
+    #[returns(PairTypeCodeIdResponse)]
+    PairTypeCodeId {
+        /// The pair type for which we return the code ID
+        pair_type: PairType,
+    },
+    /// PairByAddress returns information about the registered pair at the given contract address.
+    #[returns(PairInfo)]
+    PairByAddress {
+        /// The pair contract address
+        contract_addr: String,
+    },
+    /// PairByLpToken returns information about the registered pair that issued the given LP token.
+    #[returns(PairInfo)]
+    PairByLpToken {
+        /// The LP token, either a native denom or a CW20 token
+        lp_token: AssetInfo,
+    },
+    /// CanonicalAssetOrder returns the assets of the registered pair in the order its pool
+    /// reports them, which is the order the pair was created with and does not depend on the
+    /// order of `asset_infos`. Use it to map the `PoolResponse.assets` of the pair to assets.
+    #[returns(Vec<AssetInfo>)]
+    CanonicalAssetOrder {
+        /// The assets of the pair, in any order
+        asset_infos: Vec<AssetInfo>,
+    },
 }
 
 /// A custom struct for each query response that returns general contract settings/configs.
@@ -168,6 +289,30 @@ pub struct ConfigResponse {
     pub coin_registry_address: Addr,
     /// Address that accumulates the fees in phrase 1
     pub fee_address: Addr,
+    /// Whether creating pairs with assets that look like LP tokens is rejected
+    #[serde(default)]
+    pub disallow_lp_assets: bool,
+    /// The gas limit of the emission accounting calls pairs make to the controller
+    #[serde(default)]
+    pub controller_gas_limit: Option<u64>,
+    /// The admins tokenfactory denoms must have to be used in a pair, any admin if not set
+    #[serde(default)]
+    pub trusted_denom_admins: Option<Vec<Addr>>,
+    /// Fee accumulation addresses used instead of `fee_address` for commission in a given asset
+    #[serde(default)]
+    pub asset_fee_addresses: Vec<(AssetInfo, Addr)>,
+    /// The fee the chain charges for creating a native LP denom, declared to every pair creating one
+    #[serde(default)]
+    pub denom_creation_fee: Vec<Coin>,
+}
+
+/// A custom struct for each query response that returns the code ID of a pair type.
+#[cw_serde]
+pub struct PairTypeCodeIdResponse {
+    /// ID of contract which is allowed to create pairs of this type
+    pub code_id: u64,
+    /// Whether the pair type is disabled
+    pub is_disabled: bool,
 }
 
 /// This structure stores the parameters used in a migration message.
@@ -183,11 +328,36 @@ pub struct PairsResponse {
     pub pairs: Vec<PairInfo>,
 }
 
+/// This structure holds the reserves of a single pair.
+#[cw_serde]
+pub struct PairTvl {
+    /// Information about the pair
+    pub pair: PairInfo,
+    /// The assets in the pool together with asset amounts
+    pub assets: Vec<Asset>,
+}
+
+/// A custom struct for each query response that returns an array of objects of type [`PairTvl`].
+#[cw_serde]
+pub struct TvlResponse {
+    /// Reserves of multiple pairs
+    pub pools: Vec<PairTvl>,
+}
+
 /// A custom struct for each query response that returns an object of type [`FeeInfoResponse`].
 #[cw_serde]
 pub struct FeeInfoResponse {
     /// Total amount of fees (in bps) charged on a swap
     pub total_fee_bps: u16,
+    /// Whether at least one unit of commission is charged on a swap
+    #[serde(default)]
+    pub is_min_commission_enabled: bool,
+    /// The minimum offer amount of a swap
+    #[serde(default)]
+    pub min_swap_amount: Option<Uint128>,
+    /// The maximum share by which a single swap may move the pool price
+    #[serde(default)]
+    pub max_price_impact: Option<Decimal>,
 }
 
 /// This is an enum used for setting and removing a contract address.
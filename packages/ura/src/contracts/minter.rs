@@ -1,6 +1,6 @@
 use crate::contracts::token::InstantiateMarketingInfo;
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Binary, Decimal, Uint128};
+use cosmwasm_std::{Addr, Binary, Decimal, Uint128};
 use cw20::Cw20Coin;
 
 #[cw_serde]
@@ -22,6 +22,12 @@ pub enum ExecuteMsg {
     SetVeStaking {},
     SetGaugeController {},
     UpdateConfig(UpdateConfigRequest),
+    /// Linearly ramps the inflation down to zero over `epochs` epochs, then stops emitting
+    StartSunset {
+        epochs: u64,
+    },
+    /// Sends the escrowed team emissions to the current team wallet
+    ClaimTeamEmissions {},
 }
 
 #[cw_serde]
@@ -42,6 +48,19 @@ pub enum QueryMsg {
     /// Return type: DownloadLogoResponse.
     #[returns(DownloadLogoResponse)]
     DownloadLogo {},
+    /// Returns the emissions minted by each `EndEpoch`, ordered by epoch.
+    #[returns(Vec<EpochEmissions>)]
+    EmissionsHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Returns when the next epoch starts and whether `EndEpoch` can be called now.
+    #[returns(NextEpochInfoResponse)]
+    NextEpochInfo {},
+    /// Returns how the LP emissions of `epoch` are split across gauges by their votes.
+    /// Epochs that were not started yet are projected as if `EndEpoch` started them now.
+    #[returns(EmissionSplitPreviewResponse)]
+    EmissionSplitPreview { epoch: u64 },
 }
 
 #[cw_serde]
@@ -78,3 +97,39 @@ pub struct DownloadLogoResponse {
     pub mime_type: String,
     pub data: Binary,
 }
+
+/// Emissions minted when an epoch was started by `EndEpoch`
+#[cw_serde]
+pub struct EpochEmissions {
+    pub epoch: u64,
+    pub total: Uint128,
+    pub team: Uint128,
+    pub rebase: Uint128,
+    pub lp: Uint128,
+    pub inflation: Decimal,
+}
+
+/// LP emissions of an epoch and their split across gauges
+#[cw_serde]
+pub struct EmissionSplitPreviewResponse {
+    pub epoch: u64,
+    pub lp_emissions: Uint128,
+    pub gauges: Vec<GaugeEmissions>,
+}
+
+/// LP emissions a gauge receives for its share of the votes
+#[cw_serde]
+pub struct GaugeEmissions {
+    pub pool: Addr,
+    pub gauge: Addr,
+    pub votes: Uint128,
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub struct NextEpochInfoResponse {
+    pub current_epoch: u64,
+    pub next_epoch_start_time: u64,
+    pub seconds_remaining: u64,
+    pub can_end_now: bool,
+}
@@ -4,6 +4,7 @@ pub mod factory;
 pub mod gauge;
 pub mod minter;
 pub mod native_coin_registry;
+pub mod oracle;
 pub mod pair;
 pub mod router;
 pub mod token;
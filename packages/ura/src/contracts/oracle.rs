@@ -0,0 +1,21 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::Decimal;
+
+use crate::structs::asset_info::AssetInfo;
+
+/// This structure describes the query messages a price oracle must answer so pairs can check
+/// swaps against it.
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    /// Price returns the price of `base` expressed in units of `quote`
+    #[returns(PriceResponse)]
+    Price { base: AssetInfo, quote: AssetInfo },
+}
+
+/// This structure holds the price returned by an oracle.
+#[cw_serde]
+pub struct PriceResponse {
+    /// The amount of quote assets one base asset is worth
+    pub price: Decimal,
+}
@@ -1,6 +1,6 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
 
-use cosmwasm_std::{Addr, Binary, Decimal, Uint128};
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, Uint128};
 use cw20::Cw20ReceiveMsg;
 
 use crate::structs::{asset::Asset, asset_info::AssetInfo, pair_info::PairInfo};
@@ -25,6 +25,13 @@ pub struct InstantiateMsg {
     pub init_params: Option<Binary>,
     /// Use cw20 LP token
     pub token_code_id: Option<u64>,
+    /// The fee charged by the chain for creating the native LP denom. It must be covered by
+    /// the funds sent along with the instantiation
+    #[serde(default)]
+    pub denom_creation_fee: Vec<Coin>,
+    /// Instantiates the CW20 LP token without an admin. Otherwise the factory owner can migrate it
+    #[serde(default)]
+    pub immutable_lp: bool,
 }
 
 /// This structure describes the execute messages available in the contract.
@@ -40,7 +47,35 @@ pub enum ExecuteMsg {
         slippage_tolerance: Option<Decimal>,
         /// The receiver of LP tokens
         receiver: Option<String>,
+        /// The owner of the CW20 deposits that granted the allowance to the pair, defaults to
+        /// the sender. Another owner must have approved the sender with `SetProvideOperator`,
+        /// the LP tokens then go to the owner and a slippage tolerance is required
+        owner: Option<String>,
+        /// The address the controller accrues the emissions of the receiver's LP tokens to,
+        /// defaults to the receiver
+        beneficiary: Option<String>,
     },
+    /// ProvideLiquidityBatch provides liquidity for several receivers at once. The pool
+    /// controller is notified with a single batched message
+    ProvideLiquidityBatch {
+        /// The deposits to make, applied in order
+        provisions: Vec<LiquidityProvision>,
+        /// The slippage tolerance applied to every deposit
+        slippage_tolerance: Option<Decimal>,
+    },
+    /// ProvideLiquidityAutoBalance swaps part of an imbalanced deposit inside the pool so that
+    /// it matches the pool ratio, then provides the balanced amounts as liquidity
+    ProvideLiquidityAutoBalance {
+        /// The assets to deposit. One side may be omitted or zero
+        assets: Vec<Asset>,
+        /// The maximum spread allowed for the balancing swap
+        max_spread: Option<Decimal>,
+        /// The receiver of LP tokens
+        receiver: Option<String>,
+    },
+    /// Approve or revoke `operator` to provide liquidity from the sender's CW20 allowance with
+    /// `ProvideLiquidity { owner }`
+    SetProvideOperator { operator: String, approved: bool },
     /// Swap performs a swap in the pool
     Swap {
         offer_asset: Asset,
@@ -48,11 +83,63 @@ pub enum ExecuteMsg {
         belief_price: Option<Decimal>,
         max_spread: Option<Decimal>,
         to: Option<String>,
+        /// Message the swap output is delivered with to the `to` contract, instead of a plain
+        /// transfer. A CW20 output is sent with `Cw20ExecuteMsg::Send`, a native one is attached
+        /// to an execution of `to` with a [`SwapHookExecuteMsg`] wrapping this message
+        to_hook: Option<Binary>,
     },
     /// Update the pair configuration
     UpdateConfig { params: Binary },
     /// Withdraw liquidity from the pool
-    WithdrawLiquidity {},
+    WithdrawLiquidity {
+        /// The address the controller accrues the emissions of the sender's LP tokens to,
+        /// defaults to the sender
+        beneficiary: Option<String>,
+    },
+    /// Withdraw liquidity from the pool, swapping the other asset of the refund into `ask_asset_info`
+    WithdrawLiquidityOneSided {
+        /// The amount of LP tokens (sent as funds) to burn
+        amount: Uint128,
+        /// The asset to receive
+        ask_asset_info: AssetInfo,
+        /// The maximum spread allowed for the internal swap
+        max_spread: Option<Decimal>,
+    },
+    /// Withdraw liquidity from the pool without accumulating emissions in the controller
+    EmergencyWithdraw {},
+    /// Send an asset that was transferred to the pair by mistake to `recipient`.
+    /// Only the factory owner can rescue tokens and the pool assets and LP token can't be moved
+    RescueTokens {
+        asset_info: AssetInfo,
+        amount: Uint128,
+        recipient: String,
+    },
+    /// Replace the LP token with a new one of the other kind. Holders claim the new LP token
+    /// 1:1 for the previous one with `ClaimMigratedLp`.
+    /// Only the factory owner can migrate the LP token
+    MigrateLpToken {
+        /// Whether the new LP token is a CW20 token or a native token
+        to_cw20: bool,
+        /// The CW20 token code id, required when migrating to a CW20 LP token
+        token_code_id: Option<u64>,
+    },
+    /// Exchange the previous native LP token 1:1 for the current LP token
+    ClaimMigratedLp {},
+    /// Turn the swap commission kept in the pool since the last call into protocol-owned
+    /// liquidity minted to the fee address. Only the factory owner can compound fees
+    CompoundFees {
+        /// The max spread allowed for the internal swap balancing the commission
+        max_spread: Option<Decimal>,
+    },
+}
+
+/// A single deposit of a [`ExecuteMsg::ProvideLiquidityBatch`].
+#[cw_serde]
+pub struct LiquidityProvision {
+    /// The assets available in the pool
+    pub assets: Vec<Asset>,
+    /// The receiver of LP tokens, defaults to the sender
+    pub receiver: Option<String>,
 }
 
 /// This structure describes a CW20 hook message.
@@ -66,7 +153,41 @@ pub enum Cw20HookMsg {
         to: Option<String>,
     },
     /// Withdraw liquidity from the cw20 LP pool
-    WithdrawLiquidity {},
+    WithdrawLiquidity {
+        /// The address the controller accrues the emissions of the sender's LP tokens to,
+        /// defaults to the sender
+        beneficiary: Option<String>,
+    },
+    /// Withdraw liquidity from the cw20 LP pool into a single asset
+    WithdrawLiquidityOneSided {
+        /// The asset to receive
+        ask_asset_info: AssetInfo,
+        /// The maximum spread allowed for the internal swap
+        max_spread: Option<Decimal>,
+    },
+    /// Withdraw liquidity from the cw20 LP pool without accumulating emissions in the controller
+    EmergencyWithdraw {},
+    /// Exchange the previous cw20 LP token 1:1 for the current LP token
+    ClaimMigratedLp {},
+}
+
+/// The message a pair executes on the recipient contract of a swap with a `to_hook` and a
+/// native output, with the swapped coins attached. Like the [`Cw20ReceiveMsg`] of a CW20
+/// output, it wraps the hook so that the recipient is never executed with a raw payload.
+#[cw_serde]
+pub enum SwapHookExecuteMsg {
+    SwapHook(SwapHookMsg),
+}
+
+/// The swap hook delivered in a [`SwapHookExecuteMsg`].
+#[cw_serde]
+pub struct SwapHookMsg {
+    /// The address that made the swap
+    pub sender: String,
+    /// The amount of swapped coins attached
+    pub amount: Uint128,
+    /// The `to_hook` payload of the swap
+    pub msg: Binary,
 }
 
 /// This structure describes the query messages available in the contract.
@@ -103,6 +224,98 @@ pub enum QueryMsg {
     /// Returns the balance of lp tokens received by the address after providing lp
     #[returns(LpReceivedResponse)]
     LpReceived { address: String },
+    /// Returns the tracked LP balances of all providers, ordered by address
+    #[returns(Vec<(Addr, Uint128)>)]
+    AllLpReceived {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns the amount of offer assets needed to move the pool's marginal price
+    /// (ask assets per offer asset, net of commission) down to `target_price`
+    #[returns(SwapToPriceResponse)]
+    SwapToPrice {
+        target_price: Decimal,
+        offer_asset_info: AssetInfo,
+    },
+    /// Returns the marginal price of the pool and the average price of swapping `offer_asset`
+    /// net of fees, both as ask assets per offer asset
+    #[returns(SwapRateResponse)]
+    SwapRate {
+        offer_asset: Asset,
+        ask_asset_info: Option<AssetInfo>,
+    },
+    /// Returns the price impact of swapping `offer_asset` before fees,
+    /// i.e. `(spot_price - execution_price) / spot_price`
+    #[returns(Decimal)]
+    PriceImpact {
+        offer_asset: Asset,
+        ask_asset_info: Option<AssetInfo>,
+    },
+    /// Returns the precisions stored for the pool assets (stableswap pools only)
+    #[returns(Vec<(AssetInfo, u8)>)]
+    Precisions {},
+    /// Returns the assets withdrawn for `amount` LP tokens and the current pool value per LP token
+    #[returns(WithdrawPreviewResponse)]
+    WithdrawPreview { amount: Uint128 },
+    /// Returns the amount of LP tokens that providing `assets` would mint
+    #[returns(SimulateProvideResponse)]
+    SimulateProvide { assets: Vec<Asset> },
+    /// Returns the amplification coefficient in effect at `timestamp` (stableswap pools only)
+    #[returns(Decimal)]
+    AmpAt { timestamp: u64 },
+    /// Returns a swap simulation of `offer_asset` together with the reverse simulation of its
+    /// return amount, both computed against the same pool state
+    #[returns(QuoteBothResponse)]
+    QuoteBoth {
+        offer_asset: Asset,
+        ask_asset_info: Option<AssetInfo>,
+    },
+    /// Returns the largest amount of `offer_asset_info` that can be swapped with a spread
+    /// within `max_spread` (xyk pools only)
+    #[returns(Uint128)]
+    MaxSwapWithinSlippage {
+        offer_asset_info: AssetInfo,
+        max_spread: Decimal,
+        ask_asset_info: Option<AssetInfo>,
+    },
+    /// Returns the fee rate a swap of `offer_asset` would pay, including any dynamic or minimum fee
+    #[returns(Decimal)]
+    EffectiveFeeRate { offer_asset: Asset },
+    /// Returns the most recent snapshots of the D invariant, oldest first (stableswap pools only)
+    #[returns(Vec<DSnapshot>)]
+    DHistory { limit: Option<u32> },
+    /// Returns the lifetime swap commission collected by the pool in each of its assets
+    #[returns(Vec<Asset>)]
+    CollectedFees {},
+    /// Returns whether `address` is currently allowed to perform `action` on the pair,
+    /// together with the reason when it is not
+    #[returns(CanExecuteResponse)]
+    CanExecute { address: String, action: PairAction },
+    /// Returns a swap simulation of `offer_asset` as if the pool amplification was already
+    /// `target_amp` (stableswap pools only)
+    #[returns(SimulationResponse)]
+    SimulateAmpChange {
+        target_amp: u64,
+        offer_asset: Asset,
+        ask_asset_info: Option<AssetInfo>,
+    },
+}
+
+/// This enum describes the pair actions that can be checked with [`QueryMsg::CanExecute`].
+#[cw_serde]
+pub enum PairAction {
+    Swap,
+    Provide,
+    Withdraw,
+}
+
+/// This structure holds the parameters that are returned from a can execute query
+#[cw_serde]
+pub struct CanExecuteResponse {
+    /// Whether the action is currently permitted
+    pub allowed: bool,
+    /// Why the action is not permitted, if it isn't
+    pub reason: Option<String>,
 }
 
 /// This struct is used to return a query result with the total amount of LP tokens and assets in a specific pool.
@@ -114,6 +327,52 @@ pub struct PoolResponse {
     pub total_share: Uint128,
 }
 
+/// This structure describes a snapshot of a stableswap pool's D invariant taken on a swap.
+#[cw_serde]
+pub struct DSnapshot {
+    /// The block time (in seconds) of the snapshot
+    pub time: u64,
+    /// The D invariant right after the swap
+    pub d: Uint128,
+}
+
+/// This structure holds the parameters that are returned from a swap to price query
+#[cw_serde]
+pub struct SwapToPriceResponse {
+    /// The amount of offer assets to swap
+    pub offer_amount: Uint128,
+    /// The marginal price of the pool once `offer_amount` is swapped
+    pub resulting_price: Decimal,
+}
+
+/// This structure holds the parameters that are returned from a swap rate query
+#[cw_serde]
+pub struct SwapRateResponse {
+    /// The marginal price of the pool before the swap, without fees
+    pub rate: Decimal,
+    /// The amount of ask assets returned per offer asset, net of fees
+    pub rate_after_fees: Decimal,
+}
+
+/// This structure holds the parameters that are returned from a withdraw preview query
+#[cw_serde]
+pub struct WithdrawPreviewResponse {
+    /// The assets returned when withdrawing the queried amount of LP tokens
+    pub assets: Vec<Asset>,
+    /// The pool invariant per LP token. It only grows as swap fees accrue in the pool
+    pub lp_price: Decimal,
+}
+
+/// This structure holds the parameters that are returned from a provide simulation
+#[cw_serde]
+pub struct SimulateProvideResponse {
+    /// The amount of LP tokens minted for the deposit
+    pub share: Uint128,
+    /// The part of the deposit that is not backed by the minted share. A constant product pool
+    /// keeps it as a donation, so it is worth rebalancing the deposit before providing
+    pub refund: Vec<Asset>,
+}
+
 /// This struct is used to return a query result with the general contract configuration.
 #[cw_serde]
 pub struct ConfigResponse {
@@ -147,6 +406,15 @@ pub struct ReverseSimulationResponse {
     pub commission_amount: Uint128,
 }
 
+/// This structure holds the parameters that are returned from a two-way quote query
+#[cw_serde]
+pub struct QuoteBothResponse {
+    /// The simulation of swapping the offer asset
+    pub forward: SimulationResponse,
+    /// The reverse simulation targeting the forward return amount
+    pub reverse: ReverseSimulationResponse,
+}
+
 /// This structure is used to return a cumulative prices query response.
 #[cw_serde]
 pub struct CumulativePricesResponse {
@@ -170,13 +438,55 @@ pub struct LpReceivedResponse {
 #[cw_serde]
 pub struct MigrateMsg {}
 
+/// This structure holds constant product pool parameters.
+#[cw_serde]
+#[derive(Default)]
+pub struct XykPoolParams {
+    /// The launch window end (in seconds) during which `max_lp_per_address` applies
+    pub launch_end_time: Option<u64>,
+    /// The maximum amount of LP tokens a single address can receive from providing during the launch window
+    pub max_lp_per_address: Option<Uint128>,
+    /// The maximum factor by which a swap's `belief_price` may differ from the pool price
+    #[serde(default)]
+    pub max_belief_price_deviation: Option<Decimal>,
+    /// The price oracle swaps are checked against
+    #[serde(default)]
+    pub oracle_addr: Option<String>,
+    /// The maximum factor by which a swap's execution price may differ from the oracle price
+    #[serde(default)]
+    pub max_oracle_deviation: Option<Decimal>,
+    /// The maximum pool reserves after a provide, ordered like the pool assets
+    #[serde(default)]
+    pub reserve_cap: Option<[Uint128; 2]>,
+}
+
+/// This enum stores the options available to update a constant product pool's configuration.
+#[cw_serde]
+pub enum XykPoolUpdateParams {
+    /// Sets the maximum pool reserves after a provide, or removes the cap if `None`
+    SetReserveCap { reserve_cap: Option<[Uint128; 2]> },
+}
+
 /// This structure holds stableswap pool parameters.
 #[cw_serde]
 pub struct StablePoolParams {
     /// The current stableswap pool amplification
     pub amp: u64,
-    /// The contract owner
+    /// The pool owner, allowed to update the pool configuration next to the factory owner
     pub owner: Option<String>,
+    /// Optional imbalance based fee replacing the factory fee
+    #[serde(default)]
+    pub dynamic_fee: Option<DynamicFeeParams>,
+}
+
+/// This structure holds the parameters of a stableswap pool's dynamic fee. The fee is `mid_fee`
+/// when the post-swap balances are equal and moves towards `out_fee` as they deviate from each other.
+#[cw_serde]
+pub struct DynamicFeeParams {
+    /// The fee charged when the pool is balanced
+    pub mid_fee: Decimal,
+    /// The fee charged when the pool is fully imbalanced
+    pub out_fee: Decimal,
 }
 
 /// This structure stores a stableswap pool's configuration.
@@ -184,6 +494,9 @@ pub struct StablePoolParams {
 pub struct StablePoolConfig {
     /// The stableswap pool amplification
     pub amp: Decimal,
+    /// The dynamic fee parameters (if any)
+    #[serde(default)]
+    pub dynamic_fee: Option<DynamicFeeParams>,
 }
 
 /// This enum stores the options available to start and stop changing a stableswap pool's amplification.
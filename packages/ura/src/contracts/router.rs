@@ -1,11 +1,13 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
 
-use cosmwasm_std::Uint128;
+use cosmwasm_std::{Decimal, Uint128};
 use cw20::Cw20ReceiveMsg;
 
 use crate::structs::asset_info::AssetInfo;
 
 pub const MAX_SWAP_OPERATIONS: usize = 50;
+/// The maximum number of times a single asset can appear in a swap route
+pub const MAX_ASSET_VISITS: usize = 2;
 
 /// This structure holds the parameters used for creating a contract.
 #[cw_serde]
@@ -23,6 +25,19 @@ pub struct SwapOperation {
     pub ask_asset_info: AssetInfo,
 }
 
+/// This enum describes an action performed by the router with the output of the last swap operation.
+#[cw_serde]
+pub enum PostSwapAction {
+    /// Sends the whole swap output to the recipient
+    SendTo { recipient: String },
+    /// Provides the whole swap output as single sided liquidity to the pair. LP tokens are minted to the swap recipient.
+    ProvideLiquidity {
+        pair: String,
+        /// The maximum spread of the swap the pair makes to balance the provided liquidity
+        max_spread: Option<Decimal>,
+    },
+}
+
 /// This structure describes the execute messages available in the contract.
 #[cw_serde]
 pub enum ExecuteMsg {
@@ -36,6 +51,23 @@ pub enum ExecuteMsg {
         to: Option<String>,
         /// The minimum amount of tokens to get from a swap
         minimum_receive: Option<Uint128>,
+        /// The minimum amount of tokens to get from a swap as a human amount with the given number of decimal places, converted with the ask asset decimals
+        minimum_receive_human: Option<(Uint128, u8)>,
+        /// The action to perform with the output of the last swap operation
+        post_swap_action: Option<PostSwapAction>,
+        /// If set, the ask asset of the last swap operation must be one of these assets
+        allowed_output_assets: Option<Vec<AssetInfo>>,
+    },
+    /// ExecuteSwapOperationsExactOut processes multiple swaps so that the last swap operation returns at least `ask_amount`
+    ExecuteSwapOperationsExactOut {
+        /// A vector of swap operations
+        operations: Vec<SwapOperation>,
+        /// The exact amount of tokens to get from the last swap operation
+        ask_amount: Uint128,
+        /// The maximum amount of offer tokens to spend, the unused part of the sent offer is refunded
+        max_offer: Uint128,
+        /// The recipient
+        to: Option<String>,
     },
 
     /// Internal use
@@ -52,6 +84,14 @@ pub enum ExecuteMsg {
         minimum_receive: Uint128,
         receiver: String,
     },
+    /// Internal use
+    /// ExecutePostSwapAction forwards the amount of tokens received by the router since `prev_balance`
+    ExecutePostSwapAction {
+        asset_info: AssetInfo,
+        prev_balance: Uint128,
+        action: PostSwapAction,
+        receiver: String,
+    },
 }
 
 #[cw_serde]
@@ -63,6 +103,22 @@ pub enum Cw20HookMsg {
         to: Option<String>,
         /// The minimum amount of tokens to get from a swap
         minimum_receive: Option<Uint128>,
+        /// The minimum amount of tokens to get from a swap as a human amount with the given number of decimal places, converted with the ask asset decimals
+        minimum_receive_human: Option<(Uint128, u8)>,
+        /// The action to perform with the output of the last swap operation
+        post_swap_action: Option<PostSwapAction>,
+        /// If set, the ask asset of the last swap operation must be one of these assets
+        allowed_output_assets: Option<Vec<AssetInfo>>,
+    },
+    ExecuteSwapOperationsExactOut {
+        /// A vector of swap operations
+        operations: Vec<SwapOperation>,
+        /// The exact amount of tokens to get from the last swap operation
+        ask_amount: Uint128,
+        /// The maximum amount of offer tokens to spend, the unused part of the sent offer is refunded
+        max_offer: Uint128,
+        /// The recipient
+        to: Option<String>,
     },
 }
 
@@ -81,6 +137,29 @@ pub enum QueryMsg {
         /// The swap operations to perform, each swap involving a specific pool
         operations: Vec<SwapOperation>,
     },
+    /// SimulateReverseSwapOperations returns the offer amount needed to receive `ask_amount`
+    /// of the final ask asset from multi-hop swap operations
+    #[returns(SimulateReverseSwapOperationsResponse)]
+    SimulateReverseSwapOperations {
+        /// The amount of tokens to receive at the end of the route
+        ask_amount: Uint128,
+        /// The swap operations to perform, each swap involving a specific pool
+        operations: Vec<SwapOperation>,
+    },
+    /// FindPath searches the factory's registered pairs for a route from `offer_asset_info`
+    /// to `ask_asset_info`, returning the first one found with the fewest hops.
+    ///
+    /// Every pair is loaded from the factory and walked breadth-first, so this is meant for
+    /// query nodes only; contracts should not call it on chain.
+    #[returns(Vec<SwapOperation>)]
+    FindPath {
+        /// The asset to start the route from
+        offer_asset_info: AssetInfo,
+        /// The asset to end the route with
+        ask_asset_info: AssetInfo,
+        /// The maximum number of swap operations in the route, capped at [`MAX_SWAP_OPERATIONS`]
+        max_hops: Option<u32>,
+    },
 }
 
 /// This structure describes a custom struct to return a query response containing the base contract configuration.
@@ -97,6 +176,14 @@ pub struct SimulateSwapOperationsResponse {
     pub amount: Uint128,
 }
 
+/// This structure describes a custom struct to return a query response containing the offer amount
+/// of a reverse swap simulation
+#[cw_serde]
+pub struct SimulateReverseSwapOperationsResponse {
+    /// The amount of tokens to offer at the start of the route
+    pub offer_amount: Uint128,
+}
+
 /// This structure describes a migration message.
 /// We currently take no arguments for migrations.
 #[cw_serde]
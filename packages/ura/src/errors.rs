@@ -0,0 +1,123 @@
+use thiserror::Error;
+
+use crate::contracts::pair::MINIMUM_LIQUIDITY_AMOUNT;
+
+/// This enum describes errors shared by the DEX contracts. Each contract's `ContractError`
+/// wraps it transparently, so the error strings are the same no matter which contract fails.
+#[derive(Error, Debug, PartialEq)]
+pub enum DexError {
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Operation non supported")]
+    NonSupported {},
+
+    #[error("Invalid state")]
+    InvalidState {},
+
+    #[error("CW20 tokens can be swapped via Cw20::Send message only")]
+    Cw20DirectSwap {},
+
+    #[error("Doubling assets in asset infos")]
+    DoublingAssets {},
+
+    #[error("Source and target assets are the same")]
+    SameAssets {},
+
+    #[error("Event of zero transfer")]
+    InvalidZeroAmount {},
+
+    #[error("Only the offer asset denom may be attached to a native swap")]
+    InvalidFundsAttached {},
+
+    #[error("Transferred coin {denom} is not in the pool")]
+    ExtraFundsSent { denom: String },
+
+    #[error("Wrong token funds sent to withdraw liquidity")]
+    InvalidLiquidityToken {},
+
+    #[error("Insufficient funds sent to pay the denom creation fee")]
+    InsufficientDenomCreationFee {},
+
+    #[error("Operation exceeds max spread limit")]
+    MaxSpreadAssertion {},
+
+    #[error("Provided spread amount exceeds allowed limit")]
+    AllowedSpreadAssertion {},
+
+    #[error("Swap amount is below the minimum swap amount of the pair")]
+    SwapTooSmall {},
+
+    #[error("Swap moves the pool price more than the maximum price impact of the pair")]
+    PriceImpactTooHigh {},
+
+    #[error("Initial liquidity must be more than {}", MINIMUM_LIQUIDITY_AMOUNT)]
+    MinimumLiquidityAmountError {},
+
+    #[error("Failed to parse or process reply message")]
+    FailedToParseReply {},
+
+    #[error("Failed to create the liquidity token: {reason}")]
+    LiquidityTokenInstantiationFailed { reason: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(DexError::Unauthorized {}, "Unauthorized")]
+    #[test_case(DexError::NonSupported {}, "Operation non supported")]
+    #[test_case(DexError::InvalidState {}, "Invalid state")]
+    #[test_case(
+        DexError::Cw20DirectSwap {},
+        "CW20 tokens can be swapped via Cw20::Send message only"
+    )]
+    #[test_case(DexError::DoublingAssets {}, "Doubling assets in asset infos")]
+    #[test_case(DexError::SameAssets {}, "Source and target assets are the same")]
+    #[test_case(DexError::InvalidZeroAmount {}, "Event of zero transfer")]
+    #[test_case(
+        DexError::InvalidFundsAttached {},
+        "Only the offer asset denom may be attached to a native swap"
+    )]
+    #[test_case(
+        DexError::ExtraFundsSent { denom: "uusd".to_string() },
+        "Transferred coin uusd is not in the pool"
+    )]
+    #[test_case(
+        DexError::InvalidLiquidityToken {},
+        "Wrong token funds sent to withdraw liquidity"
+    )]
+    #[test_case(
+        DexError::InsufficientDenomCreationFee {},
+        "Insufficient funds sent to pay the denom creation fee"
+    )]
+    #[test_case(DexError::MaxSpreadAssertion {}, "Operation exceeds max spread limit")]
+    #[test_case(
+        DexError::AllowedSpreadAssertion {},
+        "Provided spread amount exceeds allowed limit"
+    )]
+    #[test_case(
+        DexError::SwapTooSmall {},
+        "Swap amount is below the minimum swap amount of the pair"
+    )]
+    #[test_case(
+        DexError::PriceImpactTooHigh {},
+        "Swap moves the pool price more than the maximum price impact of the pair"
+    )]
+    #[test_case(
+        DexError::MinimumLiquidityAmountError {},
+        "Initial liquidity must be more than 1000"
+    )]
+    #[test_case(
+        DexError::FailedToParseReply {},
+        "Failed to parse or process reply message"
+    )]
+    #[test_case(
+        DexError::LiquidityTokenInstantiationFailed { reason: "no code".to_string() },
+        "Failed to create the liquidity token: no code"
+    )]
+    fn error_strings(err: DexError, expected: &str) {
+        assert_eq!(err.to_string(), expected);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod contracts;
+pub mod errors;
 pub mod structs;
 pub mod utils;
 
@@ -43,7 +44,7 @@ mod decimal_checked_ops {
     }
 }
 
-use cosmwasm_std::{Decimal, Decimal256, StdError, StdResult, Uint128};
+use cosmwasm_std::{ConversionOverflowError, Decimal, Decimal256, StdError, StdResult, Uint128};
 
 /// Converts [`Decimal256`] to [`Decimal`].
 pub fn to_decimal(value: Decimal256) -> StdResult<Decimal> {
@@ -53,5 +54,13 @@ pub fn to_decimal(value: Decimal256) -> StdResult<Decimal> {
     })
 }
 
+/// Converts [`U256`] to [`Uint128`], failing instead of truncating if the value doesn't fit.
+pub fn to_uint128(value: U256) -> StdResult<Uint128> {
+    if value > U256::from(u128::MAX) {
+        return Err(ConversionOverflowError::new("U256", "Uint128", value.to_string()).into());
+    }
+    Ok(Uint128::new(value.as_u128()))
+}
+
 pub use decimal_checked_ops::DecimalCheckedOps;
 pub use uints::U256;
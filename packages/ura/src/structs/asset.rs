@@ -2,13 +2,16 @@ use std::fmt;
 
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    coin, to_json_binary, Addr, BankMsg, Coin, CosmosMsg, CustomMsg, Decimal256, MessageInfo,
-    StdError, StdResult, Uint128, WasmMsg,
+    coin, to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, CustomMsg, Decimal256,
+    MessageInfo, StdError, StdResult, Uint128, WasmMsg,
 };
 use cw20::{Cw20Coin, Cw20CoinVerified, Cw20ExecuteMsg};
 use cw_utils::must_pay;
 
+use crate::contracts::pair::{SwapHookExecuteMsg, SwapHookMsg};
+
 use super::asset_info::AssetInfo;
+use super::coin::aggregate_coins;
 use super::decimal256::Decimal256Ext;
 use super::decimal256_asset::Decimal256Asset;
 
@@ -151,6 +154,59 @@ impl Asset {
         }
     }
 
+    /// Returns a message delivering the asset to the `contract` along with `msg`: a CW20 token
+    /// is sent with [`Cw20ExecuteMsg::Send`], native tokens are attached to an execution of
+    /// `contract` with a [`SwapHookExecuteMsg`] from `sender` carrying `msg`.
+    pub fn into_hook_msg<T>(
+        self,
+        contract: impl Into<String>,
+        sender: impl Into<String>,
+        msg: Binary,
+    ) -> StdResult<CosmosMsg<T>>
+    where
+        T: CustomMsg,
+    {
+        let contract = contract.into();
+        match &self.info {
+            AssetInfo::Token { contract_addr } => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: contract_addr.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                    contract,
+                    amount: self.amount,
+                    msg,
+                })?,
+                funds: vec![],
+            })),
+            AssetInfo::NativeToken { .. } => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: contract,
+                msg: to_json_binary(&SwapHookExecuteMsg::SwapHook(SwapHookMsg {
+                    sender: sender.into(),
+                    amount: self.amount,
+                    msg,
+                }))?,
+                funds: vec![self.as_coin()?],
+            })),
+        }
+    }
+
+    /// Same as [`Asset::into_msg`], but returns `None` for a zero amount or when `recipient` is
+    /// `self_addr`. Such transfers only waste gas and some CW20 tokens reject zero amounts.
+    pub fn into_msg_checked<T>(
+        self,
+        recipient: impl Into<String>,
+        self_addr: &Addr,
+    ) -> StdResult<Option<CosmosMsg<T>>>
+    where
+        T: CustomMsg,
+    {
+        let recipient = recipient.into();
+        if self.amount.is_zero() || recipient == self_addr.as_str() {
+            return Ok(None);
+        }
+
+        self.into_msg(recipient).map(Some)
+    }
+
     /// Validates an amount of native tokens being sent.
     pub fn assert_sent_native_token_balance(&self, message_info: &MessageInfo) -> StdResult<()> {
         if let AssetInfo::NativeToken { denom } = &self.info {
@@ -183,6 +239,27 @@ impl Asset {
             AssetInfo::NativeToken { denom } => Ok(coin(self.amount.u128(), denom)),
         }
     }
+
+    /// Formats the asset with the decimal point inserted according to `decimals`,
+    /// e.g. `0.123456 uusd` for 123456uusd with 6 decimals. Trailing zeros are trimmed.
+    pub fn to_human_string(&self, decimals: u8) -> String {
+        let digits = self.amount.to_string();
+        let decimals = decimals as usize;
+        let amount = if decimals == 0 {
+            digits
+        } else {
+            let padded = format!("{digits:0>width$}", width = decimals + 1);
+            let (whole, fractional) = padded.split_at(padded.len() - decimals);
+            let fractional = fractional.trim_end_matches('0');
+            if fractional.is_empty() {
+                whole.to_string()
+            } else {
+                format!("{whole}.{fractional}")
+            }
+        };
+
+        format!("{} {}", amount, self.info)
+    }
 }
 
 /// Returns an [`Asset`] object representing a native token and an amount of tokens.
@@ -209,6 +286,42 @@ pub fn token_asset(contract_addr: Addr, amount: Uint128) -> Asset {
     }
 }
 
+/// Returns the messages sending `assets` to `recipient`. Native assets are combined with
+/// [`aggregate_coins`] into a single [`BankMsg::Send`] placed where the first native asset is,
+/// while CW20 tokens get a [`Cw20ExecuteMsg::Transfer`] each. Zero amounts are skipped.
+pub fn assets_into_msgs<T>(
+    assets: impl IntoIterator<Item = Asset>,
+    recipient: impl Into<String>,
+) -> StdResult<Vec<CosmosMsg<T>>>
+where
+    T: CustomMsg,
+{
+    let recipient = recipient.into();
+    let mut messages = vec![];
+    let mut bank_msg_index = None;
+    let mut coins = vec![];
+    for asset in assets.into_iter().filter(|asset| !asset.amount.is_zero()) {
+        if asset.is_native_token() {
+            bank_msg_index.get_or_insert(messages.len());
+            coins.push(asset.as_coin()?);
+        } else {
+            messages.push(asset.into_msg(&recipient)?);
+        }
+    }
+
+    if let Some(index) = bank_msg_index {
+        messages.insert(
+            index,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: recipient,
+                amount: aggregate_coins(coins),
+            }),
+        );
+    }
+
+    Ok(messages)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -273,4 +386,111 @@ mod tests {
         let coin2: Coin = asset.try_into().unwrap();
         assert_eq!(coin, coin2);
     }
+
+    #[test_case(0, 6 => "0 uusd" ; "zero")]
+    #[test_case(0, 0 => "0 uusd" ; "zero without decimals")]
+    #[test_case(123456, 6 => "0.123456 uusd" ; "sub one")]
+    #[test_case(1, 18 => "0.000000000000000001 uusd" ; "smallest unit")]
+    #[test_case(123456, 0 => "123456 uusd" ; "no decimals")]
+    #[test_case(1_000_000, 6 => "1 uusd" ; "whole amount")]
+    #[test_case(1_500_000, 6 => "1.5 uusd" ; "trailing zeros")]
+    #[test_case(123_456_789_000_000_000_000_000, 18 => "123456.789 uusd" ; "large amount")]
+    #[test_case(u128::MAX, 6 => "340282366920938463463374607431768.211455 uusd" ; "max amount")]
+    fn to_human_string(amount: u128, decimals: u8) -> String {
+        Asset::native("uusd", amount).to_human_string(decimals)
+    }
+
+    #[test]
+    fn to_human_string_cw20() {
+        assert_eq!(mock_cw20().to_human_string(3), "123.456 mock_token");
+        // Display keeps the raw base units
+        assert_eq!(mock_cw20().to_string(), "123456mock_token");
+    }
+
+    #[test]
+    fn into_msg_checked_skips_no_op_transfers() {
+        let contract = Addr::unchecked("contract");
+
+        let msg: Option<CosmosMsg> = Asset::native("uusd", 0u128)
+            .into_msg_checked("recipient", &contract)
+            .unwrap();
+        assert_eq!(msg, None);
+        let msg: Option<CosmosMsg> = mock_cw20().into_msg_checked("contract", &contract).unwrap();
+        assert_eq!(msg, None);
+
+        for asset in [mock_native(), mock_cw20()] {
+            let msg: Option<CosmosMsg> = asset
+                .clone()
+                .into_msg_checked("recipient", &contract)
+                .unwrap();
+            assert_eq!(msg, Some(asset.into_msg("recipient").unwrap()));
+        }
+    }
+
+    #[test]
+    fn into_hook_msg_attaches_the_hook() {
+        let hook = Binary::from(b"deposit".as_slice());
+
+        let msg: CosmosMsg = mock_native()
+            .into_hook_msg("vault", "trader", hook.clone())
+            .unwrap();
+        assert_eq!(
+            msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "vault".to_string(),
+                msg: Binary::from(
+                    format!(
+                        r#"{{"swap_hook":{{"sender":"trader","amount":"{}","msg":"{}"}}}}"#,
+                        mock_native().amount,
+                        hook.to_base64()
+                    )
+                    .as_bytes()
+                ),
+                funds: vec![mock_native().as_coin().unwrap()],
+            })
+        );
+
+        let msg: CosmosMsg = mock_cw20()
+            .into_hook_msg("vault", "trader", hook.clone())
+            .unwrap();
+        assert_eq!(
+            msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "mock_token".to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                    contract: "vault".to_string(),
+                    amount: mock_cw20().amount,
+                    msg: hook,
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn assets_into_msgs_combines_native_sends() {
+        let msgs: Vec<CosmosMsg> = assets_into_msgs(
+            vec![
+                mock_cw20(),
+                Asset::native("uusd", 100u128),
+                Asset::native("uluna", 10u128),
+                Asset::native("uusd", 50u128),
+                Asset::native("uatom", 0u128),
+                Asset::cw20_unchecked("zero_token", 0u128),
+            ],
+            "recipient",
+        )
+        .unwrap();
+        assert_eq!(
+            msgs,
+            vec![
+                mock_cw20().into_msg("recipient").unwrap(),
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address: "recipient".to_string(),
+                    amount: vec![coin(10, "uluna"), coin(150, "uusd")],
+                }),
+            ]
+        );
+    }
 }
@@ -7,8 +7,13 @@ use cosmwasm_std::{
 use cw20::Denom;
 use cw_storage_plus::{Key, KeyDeserialize, Prefixer, PrimaryKey};
 
-use crate::utils::querier::{query_balance, query_token_balance, query_token_precision};
-use crate::utils::validation::validate_native_denom;
+use crate::utils::format::is_lp_token_name;
+use crate::utils::querier::{
+    query_balance, query_token_balance, query_token_name, query_token_precision,
+};
+use crate::utils::validation::{
+    canonical_native_denom, validate_canonical_native_denom, validate_native_denom,
+};
 
 use super::asset::Asset;
 use super::decimal256_asset::Decimal256Asset;
@@ -131,6 +136,30 @@ impl AssetInfo {
         }
     }
 
+    /// Checks whether the native coin is a tokenfactory denom, which can be minted by its admin.
+    pub fn is_tokenfactory(&self) -> bool {
+        match self {
+            AssetInfo::NativeToken { denom } => denom.starts_with("factory/"),
+            AssetInfo::Token { .. } => false,
+        }
+    }
+
+    /// Heuristically checks whether the asset is an LP token of a pair: a tokenfactory denom
+    /// or a CW20 token whose name follows the pair LP token naming.
+    pub fn looks_like_lp<C>(&self, querier: &QuerierWrapper<C>) -> StdResult<bool>
+    where
+        C: CustomQuery,
+    {
+        match self {
+            AssetInfo::NativeToken { denom } => Ok(
+                self.is_tokenfactory() && denom.rsplit('/').next().is_some_and(is_lp_token_name)
+            ),
+            AssetInfo::Token { contract_addr } => {
+                Ok(is_lp_token_name(&query_token_name(querier, contract_addr)?))
+            }
+        }
+    }
+
     /// Returns the balance of token in a pool.
     ///
     /// * **pool_addr** is the address of the contract whose token balance we check.
@@ -160,10 +189,12 @@ impl AssetInfo {
 
     /// Returns **true** if the calling token is the same as the token specified in the input parameters.
     /// Otherwise returns **false**.
+    ///
+    /// Native denoms are compared in their canonical form, so casings of the same IBC denom are equal.
     pub fn equal(&self, asset: &AssetInfo) -> bool {
         match (self, asset) {
             (AssetInfo::NativeToken { denom }, AssetInfo::NativeToken { denom: other_denom }) => {
-                denom == other_denom
+                canonical_native_denom(denom) == canonical_native_denom(other_denom)
             }
             (
                 AssetInfo::Token { contract_addr },
@@ -193,6 +224,7 @@ impl AssetInfo {
             }
             AssetInfo::NativeToken { denom } => {
                 validate_native_denom(denom)?;
+                validate_canonical_native_denom(denom)?;
             }
         }
 
@@ -235,6 +267,11 @@ pub fn token_asset_info(contract_addr: Addr) -> AssetInfo {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use cosmwasm_std::testing::{MockApi, MockQuerier};
+    use cosmwasm_std::{
+        to_json_binary, ContractResult, Empty, SystemError, SystemResult, WasmQuery,
+    };
+    use cw20::TokenInfoResponse;
 
     #[test]
     fn test_native_asset_info() {
@@ -247,6 +284,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_equal_compares_canonical_denoms() {
+        let upper = AssetInfo::native(
+            "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2",
+        );
+        let lower = AssetInfo::native(
+            "IBC/27394fb092d2eccd56123c74f36e4c1f926001ceada9ca97ea622b25f41e5eb2",
+        );
+        assert_ne!(upper, lower);
+        assert!(upper.equal(&lower));
+
+        // Only IBC hashes are case insensitive
+        assert!(!AssetInfo::native("uusd").equal(&AssetInfo::native("UUSD")));
+    }
+
     #[test]
     fn cw20_unchecked_asset_info() {
         let info = AssetInfo::cw20_unchecked(Addr::unchecked("mock_token"));
@@ -298,4 +350,71 @@ mod tests {
         let denom2: Denom = info.try_into().unwrap();
         assert_eq!(denom, denom2);
     }
+
+    #[test]
+    fn check_rejects_non_canonical_ibc_denoms() {
+        let api = MockApi::default();
+        let denom = "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2";
+
+        AssetInfo::native(denom).check(&api).unwrap();
+        AssetInfo::native(denom.replace("ibc/", "IBC/"))
+            .check(&api)
+            .unwrap_err();
+        AssetInfo::native(denom.to_lowercase())
+            .check(&api)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn is_tokenfactory() {
+        assert!(AssetInfo::native("factory/minter/uura").is_tokenfactory());
+        assert!(!AssetInfo::native("uusd").is_tokenfactory());
+        assert!(!AssetInfo::native(
+            "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2"
+        )
+        .is_tokenfactory());
+        assert!(!AssetInfo::native("Factory/minter/uura").is_tokenfactory());
+        assert!(!AssetInfo::cw20_unchecked("factory").is_tokenfactory());
+    }
+
+    #[test]
+    fn looks_like_lp() {
+        let mut querier: MockQuerier<Empty> = MockQuerier::new(&[]);
+        querier.update_wasm(|request| match request {
+            WasmQuery::Smart { contract_addr, .. } => {
+                let name = match contract_addr.as_str() {
+                    "lp_token" => "UUSD-ULUN-LP",
+                    _ => "Ura Token",
+                };
+                SystemResult::Ok(ContractResult::Ok(
+                    to_json_binary(&TokenInfoResponse {
+                        name: name.to_string(),
+                        symbol: "TKN".to_string(),
+                        decimals: 6,
+                        total_supply: Uint128::zero(),
+                    })
+                    .unwrap(),
+                ))
+            }
+            _ => SystemResult::Err(SystemError::Unknown {}),
+        });
+        let querier = QuerierWrapper::<Empty>::new(&querier);
+
+        assert!(AssetInfo::native("factory/pair0000/UUSD-ULUN-LP")
+            .looks_like_lp(&querier)
+            .unwrap());
+        assert!(!AssetInfo::native("factory/minter/uura")
+            .looks_like_lp(&querier)
+            .unwrap());
+        assert!(!AssetInfo::native("UUSD-ULUN-LP")
+            .looks_like_lp(&querier)
+            .unwrap());
+        assert!(!AssetInfo::native("uusd").looks_like_lp(&querier).unwrap());
+        assert!(AssetInfo::cw20_unchecked("lp_token")
+            .looks_like_lp(&querier)
+            .unwrap());
+        assert!(!AssetInfo::cw20_unchecked("ura_token")
+            .looks_like_lp(&querier)
+            .unwrap());
+    }
 }
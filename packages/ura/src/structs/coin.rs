@@ -1,14 +1,34 @@
 use super::asset::Asset;
 use super::asset_info::AssetInfo;
-use cosmwasm_std::{coin, ensure, Coin, StdError, StdResult};
+use crate::errors::DexError;
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{coin, ensure, Coin, StdError, StdResult, Uint128};
 use itertools::Itertools;
 
+/// Sums the amounts of `coins` per denom. The result is sorted by denom and holds every denom once.
+pub fn aggregate_coins(coins: Vec<Coin>) -> Vec<Coin> {
+    coins
+        .into_iter()
+        .fold(BTreeMap::<String, Uint128>::new(), |mut acc, coin| {
+            *acc.entry(coin.denom).or_default() += coin.amount;
+            acc
+        })
+        .into_iter()
+        .map(|(denom, amount)| Coin { denom, amount })
+        .collect()
+}
+
 pub trait CoinsExt {
     fn assert_coins_properly_sent(
         &self,
         assets: &[Asset],
         pool_asset_infos: &[AssetInfo],
     ) -> StdResult<()>;
+
+    /// Rejects any transferred coin whose denom is not a native asset of the pool, e.g. every
+    /// coin sent to a CW20/CW20 pool, as it would be stuck in the pair.
+    fn assert_no_extra_funds(&self, pool_asset_infos: &[AssetInfo]) -> Result<(), DexError>;
 }
 
 impl CoinsExt for Vec<Coin> {
@@ -27,6 +47,12 @@ impl CoinsExt for Vec<Coin> {
             StdError::generic_err("Duplicated assets in the input")
         );
 
+        // Funds are not guaranteed to be deduplicated, a repeated denom would only be matched once
+        ensure!(
+            self.iter().map(|coin| &coin.denom).all_unique(),
+            StdError::generic_err("Duplicated denoms in the transferred coins")
+        );
+
         input_assets.iter().try_for_each(|input| {
             if pool_asset_infos.contains(&input.info) {
                 match &input.info {
@@ -54,16 +80,20 @@ impl CoinsExt for Vec<Coin> {
             }
         })?;
 
+        self.assert_no_extra_funds(pool_asset_infos)
+            .map_err(|err| StdError::generic_err(err.to_string()))
+    }
+
+    fn assert_no_extra_funds(&self, pool_asset_infos: &[AssetInfo]) -> Result<(), DexError> {
         self.iter().try_for_each(|coin| {
             if pool_asset_infos.contains(&AssetInfo::NativeToken {
                 denom: coin.denom.clone(),
             }) {
                 Ok(())
             } else {
-                Err(StdError::generic_err(format!(
-                    "Transferred coin {} is not in the pool",
-                    coin.denom
-                )))
+                Err(DexError::ExtraFundsSent {
+                    denom: coin.denom.clone(),
+                })
             }
         })
     }
@@ -161,5 +191,72 @@ mod tests {
             err,
             StdError::generic_err("Transferred coin uusd is not in the pool")
         );
+        assert_eq!(
+            vec![coin(1000, "uusd")]
+                .assert_no_extra_funds(&pool_asset_infos)
+                .unwrap_err(),
+            DexError::ExtraFundsSent {
+                denom: "uusd".to_string()
+            }
+        );
+        vec![].assert_no_extra_funds(&pool_asset_infos).unwrap();
+    }
+
+    #[test]
+    fn aggregate_coins_sums_per_denom() {
+        assert_eq!(aggregate_coins(vec![]), vec![]);
+
+        // Distinct denoms are only sorted
+        assert_eq!(
+            aggregate_coins(vec![coin(100, "uusd"), coin(50, "uluna")]),
+            vec![coin(50, "uluna"), coin(100, "uusd")]
+        );
+
+        // Duplicated denoms are summed into a single coin
+        assert_eq!(
+            aggregate_coins(vec![
+                coin(100, "uusd"),
+                coin(50, "uluna"),
+                coin(25, "uusd"),
+                coin(5, "uluna"),
+                coin(1, "uatom"),
+            ]),
+            vec![coin(1, "uatom"), coin(55, "uluna"), coin(125, "uusd")]
+        );
+    }
+
+    #[test]
+    fn test_funds_order_and_duplicates() {
+        let pool_asset_infos = [
+            native_asset_info("uusd".to_string()),
+            native_asset_info("uluna".to_string()),
+        ];
+        let assets = [
+            pool_asset_infos[0].with_balance(1000u16),
+            pool_asset_infos[1].with_balance(500u16),
+        ];
+
+        // Funds can come in any order
+        vec![coin(500, "uluna"), coin(1000, "uusd")]
+            .assert_coins_properly_sent(&assets, &pool_asset_infos)
+            .unwrap();
+
+        // A repeated denom is rejected even if its first entry matches
+        let err = vec![coin(1000, "uusd"), coin(500, "uluna"), coin(500, "uluna")]
+            .assert_coins_properly_sent(&assets, &pool_asset_infos)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("Duplicated denoms in the transferred coins")
+        );
+
+        // An extra denom outside of the pool is rejected
+        let err = vec![coin(500, "uluna"), coin(1, "random"), coin(1000, "uusd")]
+            .assert_coins_properly_sent(&assets, &pool_asset_infos)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("Transferred coin random is not in the pool")
+        );
     }
 }
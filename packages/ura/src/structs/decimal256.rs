@@ -8,6 +8,10 @@ pub trait Decimal256Ext {
 
     fn to_uint128_with_precision(&self, precision: impl Into<u32>) -> StdResult<Uint128>;
 
+    /// Same as [`Decimal256Ext::to_uint128_with_precision`] but rounds up if any
+    /// fractional part is dropped.
+    fn to_uint128_with_precision_ceil(&self, precision: impl Into<u32>) -> StdResult<Uint128>;
+
     fn to_uint256_with_precision(&self, precision: impl Into<u32>) -> StdResult<Uint256>;
 
     fn from_integer(i: impl Into<Uint256>) -> Self;
@@ -41,6 +45,20 @@ impl Decimal256Ext for Decimal256 {
             })
     }
 
+    fn to_uint128_with_precision_ceil(&self, precision: impl Into<u32>) -> StdResult<Uint128> {
+        let value = self.atomics();
+        let divisor = Uint256::from(10u128.pow(self.decimal_places() - precision.into()));
+
+        let mut result = value.checked_div(divisor)?;
+        if !value.checked_rem(divisor)?.is_zero() {
+            result = result.checked_add(Uint256::one())?;
+        }
+
+        result.try_into().map_err(|o: ConversionOverflowError| {
+            StdError::generic_err(format!("Error converting {}", o.value))
+        })
+    }
+
     fn to_uint256_with_precision(&self, precision: impl Into<u32>) -> StdResult<Uint256> {
         let value = self.atomics();
         let precision = precision.into();
@@ -74,3 +92,62 @@ impl Decimal256Ext for Decimal256 {
             .map_err(|_| StdError::generic_err("Decimal256 range exceeded"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn floor_and_ceil_with_remainder() {
+        let value = Decimal256::with_precision(1_234_567u64, 6u32).unwrap();
+
+        assert_eq!(
+            value.to_uint128_with_precision(3u32).unwrap(),
+            Uint128::new(1234)
+        );
+        assert_eq!(
+            value.to_uint128_with_precision_ceil(3u32).unwrap(),
+            Uint128::new(1235)
+        );
+    }
+
+    #[test]
+    fn floor_and_ceil_without_remainder() {
+        let value = Decimal256::with_precision(1_234_000u64, 6u32).unwrap();
+
+        assert_eq!(
+            value.to_uint128_with_precision(3u32).unwrap(),
+            Uint128::new(1234)
+        );
+        assert_eq!(
+            value.to_uint128_with_precision_ceil(3u32).unwrap(),
+            Uint128::new(1234)
+        );
+
+        // Full precision never has a remainder
+        assert_eq!(
+            value.to_uint128_with_precision_ceil(18u32).unwrap(),
+            value.to_uint128_with_precision(18u32).unwrap()
+        );
+    }
+
+    #[test]
+    fn ceil_of_dust_is_one_unit() {
+        let dust = Decimal256::new(Uint256::one());
+
+        assert_eq!(
+            dust.to_uint128_with_precision(6u32).unwrap(),
+            Uint128::zero()
+        );
+        assert_eq!(
+            dust.to_uint128_with_precision_ceil(6u32).unwrap(),
+            Uint128::one()
+        );
+        assert_eq!(
+            Decimal256::zero()
+                .to_uint128_with_precision_ceil(6u32)
+                .unwrap(),
+            Uint128::zero()
+        );
+    }
+}
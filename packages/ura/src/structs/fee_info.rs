@@ -1,13 +1,35 @@
-use cosmwasm_std::{Addr, Decimal};
+use cosmwasm_std::{Addr, Decimal, Uint128};
+
+use crate::structs::asset_info::AssetInfo;
 
 /// This structure holds parameters that describe the fee structure for a pool.
 pub struct FeeInfo {
     /// The controller address
     pub controller_address: Option<Addr>,
+    /// The gas limit of the emission accounting calls to the controller
+    pub controller_gas_limit: Option<u64>,
     /// The gauge address
     pub gauge_address: Option<Addr>,
     /// The fee address that accumulates the fees in phrase 1
     pub fee_address: Addr,
+    /// The fee addresses that accumulate the fees in specific assets instead of `fee_address`
+    pub asset_fee_addresses: Vec<(AssetInfo, Addr)>,
     /// The total amount of fees charged per swap
     pub total_fee_rate: Decimal,
+    /// Whether at least one unit of commission is charged per swap
+    pub is_min_commission_enabled: bool,
+    /// The minimum offer amount of a swap
+    pub min_swap_amount: Option<Uint128>,
+    /// The maximum share by which a single swap may move the pool price
+    pub max_price_impact: Option<Decimal>,
+}
+
+impl FeeInfo {
+    /// Returns the address that accumulates the fees denominated in `asset_info`.
+    pub fn fee_address_for(&self, asset_info: &AssetInfo) -> &Addr {
+        self.asset_fee_addresses
+            .iter()
+            .find(|(info, _)| info.equal(asset_info))
+            .map_or(&self.fee_address, |(_, address)| address)
+    }
 }
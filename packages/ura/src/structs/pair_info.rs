@@ -21,6 +21,32 @@ pub struct PairInfo {
 }
 
 impl PairInfo {
+    /// Returns the position of `asset_info` in [`PairInfo::asset_infos`].
+    ///
+    /// * **asset_info** asset to look up.
+    pub fn asset_index(&self, asset_info: &AssetInfo) -> StdResult<usize> {
+        self.asset_infos
+            .iter()
+            .position(|info| info.equal(asset_info))
+            .ok_or_else(|| {
+                StdError::generic_err(format!("Asset {asset_info} does not belong to the pair"))
+            })
+    }
+
+    /// Returns the counterpart of `one` in a two-asset pair.
+    ///
+    /// * **one** asset whose counterpart is returned.
+    pub fn other_asset(&self, one: &AssetInfo) -> StdResult<AssetInfo> {
+        if self.asset_infos.len() != 2 {
+            return Err(StdError::generic_err(
+                "The counterpart asset is only defined for two-asset pairs",
+            ));
+        }
+
+        let index = self.asset_index(one)?;
+        Ok(self.asset_infos[1 - index].clone())
+    }
+
     /// Returns the balance for each asset in the pool.
     ///
     /// * **contract_addr** is pair's pool address.
@@ -81,3 +107,65 @@ pub fn pair_info_by_pool(querier: &QuerierWrapper, pool: impl Into<String>) -> S
 
     Ok(pair_info)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::asset_info::{native_asset_info, token_asset_info};
+
+    fn pair_info(asset_infos: Vec<AssetInfo>) -> PairInfo {
+        PairInfo {
+            asset_infos,
+            contract_addr: Addr::unchecked("pair"),
+            liquidity_token: native_asset_info("factory/pair/LP".to_string()),
+            pair_type: PairType::Xyk,
+        }
+    }
+
+    #[test]
+    fn asset_index_and_other_asset() {
+        let uusd = native_asset_info("uusd".to_string());
+        let token = token_asset_info(Addr::unchecked("asset0000"));
+        let pair = pair_info(vec![uusd.clone(), token.clone()]);
+
+        assert_eq!(pair.asset_index(&uusd).unwrap(), 0);
+        assert_eq!(pair.asset_index(&token).unwrap(), 1);
+        assert_eq!(pair.other_asset(&uusd).unwrap(), token);
+        assert_eq!(pair.other_asset(&token).unwrap(), uusd);
+    }
+
+    #[test]
+    fn asset_not_in_pair() {
+        let pair = pair_info(vec![
+            native_asset_info("uusd".to_string()),
+            token_asset_info(Addr::unchecked("asset0000")),
+        ]);
+        let uluna = native_asset_info("uluna".to_string());
+
+        assert_eq!(
+            pair.asset_index(&uluna).unwrap_err(),
+            StdError::generic_err("Asset uluna does not belong to the pair")
+        );
+        assert_eq!(
+            pair.other_asset(&uluna).unwrap_err(),
+            StdError::generic_err("Asset uluna does not belong to the pair")
+        );
+        // A native denom and a cw20 address with the same string are different assets
+        assert!(pair
+            .asset_index(&token_asset_info(Addr::unchecked("uusd")))
+            .is_err());
+    }
+
+    #[test]
+    fn other_asset_requires_two_assets() {
+        let uusd = native_asset_info("uusd".to_string());
+        let pair = pair_info(vec![
+            uusd.clone(),
+            native_asset_info("uluna".to_string()),
+            native_asset_info("ukrw".to_string()),
+        ]);
+
+        assert_eq!(pair.asset_index(&uusd).unwrap(), 0);
+        assert!(pair.other_asset(&uusd).is_err());
+    }
+}
@@ -0,0 +1,40 @@
+use cosmwasm_std::{StdResult, Storage, Uint128};
+use cw_storage_plus::Map;
+
+use crate::structs::asset::Asset;
+use crate::structs::asset_info::{AssetInfo, AssetInfoExt};
+
+/// Lifetime swap commission collected by a pool, keyed by the asset it was charged in
+pub const CUMULATIVE_FEES: Map<String, Uint128> = Map::new("cumulative_fees");
+
+/// Adds `amount` to the lifetime commission collected in `asset_info`.
+pub fn accumulate_fees(
+    storage: &mut dyn Storage,
+    asset_info: &AssetInfo,
+    amount: Uint128,
+) -> StdResult<()> {
+    if amount.is_zero() {
+        return Ok(());
+    }
+    CUMULATIVE_FEES.update(storage, asset_info.to_string(), |fees| -> StdResult<_> {
+        Ok(fees.unwrap_or_default().checked_add(amount)?)
+    })?;
+
+    Ok(())
+}
+
+/// Returns the lifetime commission collected in each of `asset_infos`.
+pub fn read_cumulative_fees(
+    storage: &dyn Storage,
+    asset_infos: &[AssetInfo],
+) -> StdResult<Vec<Asset>> {
+    asset_infos
+        .iter()
+        .map(|asset_info| {
+            let amount = CUMULATIVE_FEES
+                .may_load(storage, asset_info.to_string())?
+                .unwrap_or_default();
+            Ok(asset_info.with_balance(amount))
+        })
+        .collect()
+}
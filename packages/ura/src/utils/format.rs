@@ -1,4 +1,5 @@
-use cosmwasm_std::{CustomQuery, QuerierWrapper, StdResult, Uint128};
+use cosmwasm_std::{Addr, CustomQuery, QuerierWrapper, StdResult, Uint128};
+
 use itertools::Itertools;
 
 use super::querier::query_token_symbol;
@@ -14,8 +15,13 @@ pub const MINIMUM_LIQUIDITY_AMOUNT: Uint128 = Uint128::new(1_000);
 pub const DENOM_MAX_LENGTH: usize = 128;
 
 const TOKEN_SYMBOL_MAX_LENGTH: usize = 4;
+/// Suffix of the LP token names built by [`format_lp_token_name`]
+pub const LP_TOKEN_NAME_SUFFIX: &str = "-LP";
+/// Maximum length in bytes of the LP token names built by [`format_lp_token_name`], which is the
+/// longest subdenom the token factory accepts
+pub const LP_TOKEN_NAME_MAX_LENGTH: usize = 44;
 
-/// Returns a formatted LP token name
+/// Returns a formatted LP token name of at most [`LP_TOKEN_NAME_MAX_LENGTH`] bytes
 pub fn format_lp_token_name<C>(
     asset_infos: &[AssetInfo],
     querier: &QuerierWrapper<C>,
@@ -23,18 +29,149 @@ pub fn format_lp_token_name<C>(
 where
     C: CustomQuery,
 {
-    let mut short_symbols: Vec<String> = vec![];
+    let mut symbols: Vec<String> = vec![];
     for asset_info in asset_infos {
-        let short_symbol = match &asset_info {
-            AssetInfo::NativeToken { denom } => {
-                denom.chars().take(TOKEN_SYMBOL_MAX_LENGTH).collect()
-            }
-            AssetInfo::Token { contract_addr } => {
-                let token_symbol = query_token_symbol(querier, contract_addr)?;
-                token_symbol.chars().take(TOKEN_SYMBOL_MAX_LENGTH).collect()
-            }
+        let symbol = match &asset_info {
+            AssetInfo::NativeToken { denom } => denom.clone(),
+            AssetInfo::Token { contract_addr } => query_token_symbol(querier, contract_addr)?,
         };
-        short_symbols.push(short_symbol);
+        symbols.push(symbol);
+    }
+    let short_symbols: Vec<String> = symbols
+        .iter()
+        .map(|symbol| symbol.chars().take(TOKEN_SYMBOL_MAX_LENGTH).collect())
+        .collect();
+    let name = format!("{}{LP_TOKEN_NAME_SUFFIX}", short_symbols.join("-")).to_uppercase();
+    if name.len() <= LP_TOKEN_NAME_MAX_LENGTH {
+        return Ok(name);
+    }
+
+    // Cut overlong names on a char boundary and tell them apart by a hash of the full symbols
+    let hash = format!("{:08X}", fnv1a_hash(symbols.join("-").as_bytes()));
+    let mut end = LP_TOKEN_NAME_MAX_LENGTH - hash.len() - 1 - LP_TOKEN_NAME_SUFFIX.len();
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    Ok(format!("{}-{hash}{LP_TOKEN_NAME_SUFFIX}", &name[..end]))
+}
+
+/// Returns the 32-bit FNV-1a hash of `bytes`, which is stable across platforms and compilers
+fn fnv1a_hash(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(*byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Returns true if the token name follows the naming of [`format_lp_token_name`]
+pub fn is_lp_token_name(name: &str) -> bool {
+    name.len() > LP_TOKEN_NAME_SUFFIX.len() && name.ends_with(LP_TOKEN_NAME_SUFFIX)
+}
+
+/// Returns the key the factory stores the pair of `asset_infos` under, so that its `pairs` map
+/// can be read from raw state. The key is the concatenation of the native denoms and token
+/// contract addresses sorted bytewise, so it doesn't depend on the order of `asset_infos`.
+pub fn pair_key(asset_infos: &[AssetInfo]) -> Vec<u8> {
+    asset_infos
+        .iter()
+        .map(AssetInfo::as_bytes)
+        .sorted()
+        .flatten()
+        .copied()
+        .collect()
+}
+
+/// Returns the tokenfactory denom of a native LP token created by a pair contract
+pub fn native_lp_denom(contract: &Addr, token_name: &str) -> String {
+    format!("factory/{contract}/{token_name}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockQuerier;
+    use cosmwasm_std::Empty;
+
+    #[test]
+    fn native_lp_denom_format() {
+        let pair = Addr::unchecked("pair0000");
+
+        assert_eq!(
+            native_lp_denom(&pair, "UUSD-ULUN-LP"),
+            "factory/pair0000/UUSD-ULUN-LP"
+        );
+        // The denom only depends on its inputs
+        assert_eq!(
+            native_lp_denom(&pair, "UUSD-ULUN-LP"),
+            native_lp_denom(&Addr::unchecked("pair0000"), "UUSD-ULUN-LP")
+        );
+    }
+
+    fn native_lp_token_name(denoms: &[&str]) -> String {
+        let querier = MockQuerier::<Empty>::new(&[]);
+        let asset_infos: Vec<_> = denoms
+            .iter()
+            .map(|denom| AssetInfo::NativeToken {
+                denom: denom.to_string(),
+            })
+            .collect();
+        format_lp_token_name(&asset_infos, &QuerierWrapper::<Empty>::new(&querier)).unwrap()
+    }
+
+    #[test]
+    fn lp_token_name_length_is_bounded() {
+        // Short names are kept as they are
+        assert_eq!(native_lp_token_name(&["uusd", "uluna"]), "UUSD-ULUN-LP");
+
+        let long_denoms: Vec<String> = (0..12).map(|i| format!("ulongdenom{i}")).collect();
+        let long_denoms: Vec<&str> = long_denoms.iter().map(String::as_str).collect();
+        let name = native_lp_token_name(&long_denoms);
+        assert_eq!(name.len(), LP_TOKEN_NAME_MAX_LENGTH);
+        assert!(is_lp_token_name(&name));
+        assert!(name.starts_with("ULON-ULON-"));
+        assert_eq!(name, native_lp_token_name(&long_denoms));
+
+        // Names that only differ past the truncated symbols still differ
+        let mut other_denoms = long_denoms.clone();
+        other_denoms[11] = "ulongdenom99";
+        let other_name = native_lp_token_name(&other_denoms);
+        assert_eq!(other_name.len(), LP_TOKEN_NAME_MAX_LENGTH);
+        assert_ne!(name, other_name);
+
+        // Multi-byte symbols are cut on a char boundary
+        let name = native_lp_token_name(&["éééé"; 6]);
+        assert!(name.len() <= LP_TOKEN_NAME_MAX_LENGTH);
+        assert!(is_lp_token_name(&name));
+        assert!(name.starts_with("ÉÉÉÉ-ÉÉÉÉ-"));
+    }
+
+    #[test]
+    fn pair_key_is_order_independent() {
+        let uusd = AssetInfo::NativeToken {
+            denom: "uusd".to_string(),
+        };
+        let token = AssetInfo::Token {
+            contract_addr: Addr::unchecked("asset0000"),
+        };
+
+        let key = pair_key(&[uusd.clone(), token.clone()]);
+        assert_eq!(key, pair_key(&[token.clone(), uusd.clone()]));
+        assert_eq!(key, b"asset0000uusd".to_vec());
+
+        let uluna = AssetInfo::NativeToken {
+            denom: "uluna".to_string(),
+        };
+        assert_eq!(
+            pair_key(&[uusd.clone(), token.clone(), uluna.clone()]),
+            pair_key(&[uluna.clone(), uusd, token.clone()])
+        );
+        assert_ne!(key, pair_key(&[uluna, token]));
+    }
+
+    #[test]
+    fn lp_token_name_detection() {
+        assert!(is_lp_token_name("UUSD-ULUN-LP"));
+        assert!(!is_lp_token_name("-LP"));
+        assert!(!is_lp_token_name("uLP"));
+        assert!(!is_lp_token_name("Ura Token"));
     }
-    Ok(format!("{}-LP", short_symbols.iter().join("-")).to_uppercase())
 }
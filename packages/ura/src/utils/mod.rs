@@ -1,6 +1,8 @@
+pub mod fees;
 pub mod format;
 pub mod math;
 pub mod ownership;
+pub mod pagination;
 pub mod querier;
 pub mod time;
 pub mod validation;
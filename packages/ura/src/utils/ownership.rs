@@ -4,7 +4,8 @@ use cosmwasm_std::{
 };
 use cw_storage_plus::Item;
 
-const MAX_PROPOSAL_TTL: u64 = 1209600;
+/// The maximum time (in seconds) an ownership proposal stays valid (two weeks)
+pub const MAX_OWNERSHIP_PROPOSAL_TTL: u64 = 1209600;
 
 /// This structure describes the parameters used for creating a request for a change of contract ownership.
 #[cw_serde]
@@ -49,9 +50,9 @@ where
         return Err(StdError::generic_err("New owner cannot be same"));
     }
 
-    if MAX_PROPOSAL_TTL < expires_in {
+    if MAX_OWNERSHIP_PROPOSAL_TTL < expires_in {
         return Err(StdError::generic_err(format!(
-            "Parameter expires_in cannot be higher than {MAX_PROPOSAL_TTL}"
+            "Parameter expires_in cannot be higher than {MAX_OWNERSHIP_PROPOSAL_TTL}"
         )));
     }
 
@@ -0,0 +1,67 @@
+/// ## Pagination settings
+/// The maximum number of items a paginated query returns
+pub const MAX_LIMIT: u32 = 30;
+/// The number of items a paginated query returns when no limit is set
+pub const DEFAULT_LIMIT: u32 = 10;
+
+/// Returns the number of items to read for `limit`, falling back to [`DEFAULT_LIMIT`] and
+/// capped at [`MAX_LIMIT`].
+pub fn clamp_limit(limit: Option<u32>) -> usize {
+    limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize
+}
+
+/// Calculates the raw key from which to start reading data.
+///
+/// `start_after` is the storage key of the last item of the previous page. A `1` byte is
+/// appended so that an exclusive bound on the result also skips that item.
+pub fn calc_range_start(start_after: Option<Vec<u8>>) -> Option<Vec<u8>> {
+    start_after.map(|mut key| {
+        key.push(1);
+        key
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::{Order, StdResult};
+    use cw_storage_plus::{Bound, Map};
+
+    use super::*;
+
+    #[test]
+    fn clamp_limit_defaults_and_caps() {
+        assert_eq!(clamp_limit(None), DEFAULT_LIMIT as usize);
+        assert_eq!(clamp_limit(Some(0)), 0);
+        assert_eq!(clamp_limit(Some(5)), 5);
+        assert_eq!(clamp_limit(Some(MAX_LIMIT)), MAX_LIMIT as usize);
+        assert_eq!(clamp_limit(Some(u32::MAX)), MAX_LIMIT as usize);
+    }
+
+    #[test]
+    fn calc_range_start_skips_the_start_after_key() {
+        assert_eq!(calc_range_start(None), None);
+        assert_eq!(
+            calc_range_start(Some(b"ab".to_vec())),
+            Some(b"ab\x01".to_vec())
+        );
+
+        let map: Map<&[u8], u8> = Map::new("items");
+        let mut storage = MockStorage::new();
+        for key in [b"a".as_slice(), b"ab", b"abc", b"b"] {
+            map.save(&mut storage, key, &0).unwrap();
+        }
+
+        let start = calc_range_start(Some(b"ab".to_vec()));
+        let keys = map
+            .keys(
+                &storage,
+                start.as_deref().map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .collect::<StdResult<Vec<_>>>()
+            .unwrap();
+        assert_eq!(keys, vec![b"abc".to_vec(), b"b".to_vec()]);
+    }
+}
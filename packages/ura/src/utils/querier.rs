@@ -7,6 +7,7 @@ use crate::contracts::factory::{
     Config as FactoryConfig, ConfigResponse, FeeInfoResponse, PairType, PairsResponse,
     QueryMsg as FactoryQueryMsg,
 };
+use crate::contracts::oracle::{PriceResponse, QueryMsg as OracleQueryMsg};
 use crate::structs::asset_info::AssetInfo;
 use crate::structs::fee_info::FeeInfo;
 use crate::structs::pair_info::PairInfo;
@@ -73,6 +74,22 @@ where
     Ok(resp.balance)
 }
 
+/// Returns a token's name.
+///
+/// * **contract_addr** token contract address.
+pub fn query_token_name<C>(
+    querier: &QuerierWrapper<C>,
+    contract_addr: impl Into<String>,
+) -> StdResult<String>
+where
+    C: CustomQuery,
+{
+    let res: TokenInfoResponse =
+        querier.query_wasm_smart(contract_addr, &Cw20QueryMsg::TokenInfo {})?;
+
+    Ok(res.name)
+}
+
 /// Returns a token's symbol.
 ///
 /// * **contract_addr** token contract address.
@@ -105,6 +122,22 @@ where
     Ok(res.total_supply)
 }
 
+/// Returns the total supply of a native denom or a CW20 token.
+///
+/// * **asset_info** native denom (queried from the bank module) or CW20 token.
+pub fn query_total_supply<C>(
+    querier: &QuerierWrapper<C>,
+    asset_info: &AssetInfo,
+) -> StdResult<Uint128>
+where
+    C: CustomQuery,
+{
+    match asset_info {
+        AssetInfo::NativeToken { denom } => Ok(querier.query_supply(denom)?.amount),
+        AssetInfo::Token { contract_addr } => query_supply(querier, contract_addr),
+    }
+}
+
 /// Returns the number of decimals that a token has.
 ///
 /// * **asset_info** is an object of type [`AssetInfo`] and contains the asset details for a specific token.
@@ -175,6 +208,8 @@ where
         querier.query_wasm_smart(factory_contract.clone(), &FactoryQueryMsg::Config {})?;
 
     let fee_address = res.fee_address;
+    let asset_fee_addresses = res.asset_fee_addresses;
+    let controller_gas_limit = res.controller_gas_limit;
     let mut controller_address = None;
     let mut gauge_address: Option<Addr> = None;
     if let Some(address) = res.controller_address {
@@ -197,9 +232,48 @@ where
 
     Ok(FeeInfo {
         fee_address,
+        asset_fee_addresses,
         controller_address,
+        controller_gas_limit,
         gauge_address,
         total_fee_rate: Decimal::from_ratio(res.total_fee_bps, 10000u16),
+        is_min_commission_enabled: res.is_min_commission_enabled,
+        min_swap_amount: res.min_swap_amount,
+        max_price_impact: res.max_price_impact,
+    })
+}
+
+/// Returns the fee information for a specific pair type without querying the controller.
+/// The controller and gauge addresses are left empty, so this should only be used where
+/// fees are not routed, e.g. in swap simulations.
+///
+/// * **pair_type** pair type we query information for.
+pub fn query_fee_info_basic<C>(
+    querier: &QuerierWrapper<C>,
+    factory_contract: &Addr,
+    pair_type: PairType,
+) -> StdResult<FeeInfo>
+where
+    C: CustomQuery,
+{
+    let res: ConfigResponse =
+        querier.query_wasm_smart(factory_contract.clone(), &FactoryQueryMsg::Config {})?;
+
+    let fee_res: FeeInfoResponse = querier.query_wasm_smart(
+        factory_contract.clone(),
+        &FactoryQueryMsg::FeeInfo { pair_type },
+    )?;
+
+    Ok(FeeInfo {
+        fee_address: res.fee_address,
+        asset_fee_addresses: res.asset_fee_addresses,
+        controller_address: None,
+        controller_gas_limit: res.controller_gas_limit,
+        gauge_address: None,
+        total_fee_rate: Decimal::from_ratio(fee_res.total_fee_bps, 10000u16),
+        is_min_commission_enabled: fee_res.is_min_commission_enabled,
+        min_swap_amount: fee_res.min_swap_amount,
+        max_price_impact: fee_res.max_price_impact,
     })
 }
 
@@ -247,6 +321,56 @@ pub fn query_total_votes_in_gauge(
     Ok(res)
 }
 
+/// Returns the share of the total gauge votes received by the gauge of a pool.
+/// Pools without a gauge have a zero weight, any other controller error is returned.
+///
+/// * **pool** pool address for which we return the gauge weight.
+///
+/// * **epoch** epoch at which the votes are counted, the current one if not specified.
+pub fn query_pool_gauge_weight(
+    querier: &QuerierWrapper,
+    controller: impl Into<String>,
+    pool: Addr,
+    epoch: Option<u64>,
+) -> StdResult<Decimal> {
+    let controller = controller.into();
+    let gauge: GaugeFromPoolResponse = match querier.query_wasm_smart(
+        controller.clone(),
+        &ControllerQueryMsg::GaugeFromPool(GaugeFromPoolRequest { pool: pool.into() }),
+    ) {
+        Ok(gauge) => gauge,
+        Err(StdError::GenericErr { msg }) if msg.contains("not found") => {
+            return Ok(Decimal::zero())
+        }
+        Err(err) => return Err(err),
+    };
+
+    let res = query_total_votes_in_gauge(querier, controller, gauge.gauge.into(), epoch)?;
+    if res.total_votes.is_zero() {
+        return Ok(Decimal::zero());
+    }
+
+    Decimal::checked_from_ratio(res.votes, res.total_votes)
+        .map_err(|err| StdError::generic_err(err.to_string()))
+}
+
+/// Returns the price of `base` in units of `quote` reported by a price oracle.
+pub fn query_oracle_price(
+    querier: &QuerierWrapper,
+    oracle: impl Into<String>,
+    base: &AssetInfo,
+    quote: &AssetInfo,
+) -> StdResult<Decimal> {
+    let res: PriceResponse = querier.query_wasm_smart(
+        oracle,
+        &OracleQueryMsg::Price {
+            base: base.clone(),
+            quote: quote.clone(),
+        },
+    )?;
+    Ok(res.price)
+}
+
 pub fn query_from_asset_list(
     querier: &QuerierWrapper,
     asset_list: &Addr,
@@ -257,3 +381,182 @@ pub fn query_from_asset_list(
         msg: to_json_binary(&AssetListQueryMsg::GetAsset(asset_info.clone()))?,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockQuerier;
+    use cosmwasm_std::{ContractResult, Empty, SystemError, SystemResult};
+
+    fn mock_controller(votes: Uint128, total_votes: Uint128) -> MockQuerier {
+        let mut querier: MockQuerier<Empty> = MockQuerier::new(&[]);
+        querier.update_wasm(move |request| match request {
+            WasmQuery::Smart { contract_addr, msg } if contract_addr == "controller" => {
+                match from_json(msg).unwrap() {
+                    ControllerQueryMsg::GaugeFromPool(GaugeFromPoolRequest { pool })
+                        if pool == "pool" =>
+                    {
+                        SystemResult::Ok(ContractResult::Ok(
+                            to_json_binary(&GaugeFromPoolResponse {
+                                pool: Addr::unchecked(pool),
+                                gauge: Addr::unchecked("gauge"),
+                                bribes: vec![],
+                                fees: vec![],
+                            })
+                            .unwrap(),
+                        ))
+                    }
+                    ControllerQueryMsg::GaugeFromPool(_) => {
+                        SystemResult::Ok(ContractResult::Err("Gauge not found".to_string()))
+                    }
+                    ControllerQueryMsg::TotalGaugeVotes(TotalGaugeVotesRequest {
+                        gauge,
+                        epoch,
+                    }) => {
+                        assert_eq!(gauge, "gauge");
+                        assert_eq!(epoch, Some(3));
+                        SystemResult::Ok(ContractResult::Ok(
+                            to_json_binary(&TotalGaugeVotesResponse {
+                                votes,
+                                total_votes,
+                                epoch: 3,
+                            })
+                            .unwrap(),
+                        ))
+                    }
+                    _ => panic!("DO NOT ENTER HERE"),
+                }
+            }
+            _ => SystemResult::Err(SystemError::Unknown {}),
+        });
+        querier
+    }
+
+    #[test]
+    fn fee_info_basic_skips_controller() {
+        let mut querier: MockQuerier<Empty> = MockQuerier::new(&[]);
+        querier.update_wasm(|request| match request {
+            WasmQuery::Smart { contract_addr, msg } if contract_addr == "factory" => {
+                let res = match from_json(msg).unwrap() {
+                    FactoryQueryMsg::Config {} => to_json_binary(&ConfigResponse {
+                        owner: Addr::unchecked("owner"),
+                        pair_configs: vec![],
+                        controller_address: Some(Addr::unchecked("controller")),
+                        coin_registry_address: Addr::unchecked("coin_registry"),
+                        disallow_lp_assets: false,
+                        controller_gas_limit: None,
+                        trusted_denom_admins: None,
+                        asset_fee_addresses: vec![(
+                            AssetInfo::native("uusd"),
+                            Addr::unchecked("uusd_fee"),
+                        )],
+                        denom_creation_fee: vec![],
+                        fee_address: Addr::unchecked("fee"),
+                    }),
+                    FactoryQueryMsg::FeeInfo { .. } => to_json_binary(&FeeInfoResponse {
+                        total_fee_bps: 30,
+                        is_min_commission_enabled: true,
+                        min_swap_amount: None,
+                        max_price_impact: None,
+                    }),
+                    _ => panic!("DO NOT ENTER HERE"),
+                };
+                SystemResult::Ok(ContractResult::Ok(res.unwrap()))
+            }
+            WasmQuery::Smart { contract_addr, .. } => {
+                panic!("Unexpected query to {contract_addr}")
+            }
+            _ => SystemResult::Err(SystemError::Unknown {}),
+        });
+        let wrapper = QuerierWrapper::<Empty>::new(&querier);
+
+        let fee_info =
+            query_fee_info_basic(&wrapper, &Addr::unchecked("factory"), PairType::Xyk).unwrap();
+        assert_eq!(fee_info.fee_address, Addr::unchecked("fee"));
+        assert_eq!(
+            fee_info.fee_address_for(&AssetInfo::native("uusd")),
+            &Addr::unchecked("uusd_fee")
+        );
+        assert_eq!(
+            fee_info.fee_address_for(&AssetInfo::native("uluna")),
+            &Addr::unchecked("fee")
+        );
+        assert_eq!(fee_info.total_fee_rate, Decimal::permille(3));
+        assert!(fee_info.is_min_commission_enabled);
+        assert_eq!(fee_info.controller_address, None);
+        assert_eq!(fee_info.gauge_address, None);
+    }
+
+    #[test]
+    fn pool_gauge_weight() {
+        let querier = mock_controller(Uint128::new(250), Uint128::new(1_000));
+        let wrapper = QuerierWrapper::<Empty>::new(&querier);
+
+        assert_eq!(
+            query_pool_gauge_weight(&wrapper, "controller", Addr::unchecked("pool"), Some(3))
+                .unwrap(),
+            Decimal::percent(25)
+        );
+        // A pool without a gauge has no weight
+        assert_eq!(
+            query_pool_gauge_weight(&wrapper, "controller", Addr::unchecked("other"), Some(3))
+                .unwrap(),
+            Decimal::zero()
+        );
+        // An unreachable controller is not mistaken for a missing gauge
+        query_pool_gauge_weight(&wrapper, "broken", Addr::unchecked("pool"), Some(3)).unwrap_err();
+    }
+
+    #[test]
+    fn pool_gauge_weight_without_votes() {
+        let querier = mock_controller(Uint128::zero(), Uint128::zero());
+        let wrapper = QuerierWrapper::<Empty>::new(&querier);
+
+        assert_eq!(
+            query_pool_gauge_weight(&wrapper, "controller", Addr::unchecked("pool"), Some(3))
+                .unwrap(),
+            Decimal::zero()
+        );
+    }
+
+    #[test]
+    fn total_supply_for_native_and_cw20() {
+        let mut querier: MockQuerier<Empty> = MockQuerier::new(&[
+            ("alice", &[Coin::new(100, "ulp"), Coin::new(5, "uusd")]),
+            ("bob", &[Coin::new(250, "ulp")]),
+        ]);
+        querier.update_wasm(|request| match request {
+            WasmQuery::Smart { contract_addr, msg } if contract_addr == "lp_token" => {
+                match from_json(msg).unwrap() {
+                    Cw20QueryMsg::TokenInfo {} => SystemResult::Ok(ContractResult::Ok(
+                        to_json_binary(&TokenInfoResponse {
+                            name: "LP".to_string(),
+                            symbol: "LP".to_string(),
+                            decimals: 6,
+                            total_supply: Uint128::new(1_000),
+                        })
+                        .unwrap(),
+                    )),
+                    _ => panic!("DO NOT ENTER HERE"),
+                }
+            }
+            _ => SystemResult::Err(SystemError::Unknown {}),
+        });
+        let wrapper = QuerierWrapper::<Empty>::new(&querier);
+
+        let native = AssetInfo::NativeToken {
+            denom: "ulp".to_string(),
+        };
+        assert_eq!(
+            query_total_supply(&wrapper, &native).unwrap(),
+            Uint128::new(350)
+        );
+        let cw20 = AssetInfo::Token {
+            contract_addr: Addr::unchecked("lp_token"),
+        };
+        assert_eq!(
+            query_total_supply(&wrapper, &cw20).unwrap(),
+            Uint128::new(1_000)
+        );
+    }
+}
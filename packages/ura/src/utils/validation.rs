@@ -41,6 +41,31 @@ pub fn validate_native_denom(denom: &str) -> StdResult<()> {
     Ok(())
 }
 
+/// Returns the canonical form of a native denom: surrounding whitespace is trimmed and IBC denoms
+/// get a lowercase `ibc/` prefix with an uppercase hash.
+pub fn canonical_native_denom(denom: &str) -> String {
+    let denom = denom.trim();
+    match denom.split_once('/') {
+        Some((prefix, hash)) if prefix.eq_ignore_ascii_case("ibc") => {
+            format!("ibc/{}", hash.to_ascii_uppercase())
+        }
+        _ => denom.to_string(),
+    }
+}
+
+/// Checks that a native denom is already in its canonical form, so that the same token cannot be
+/// represented by two different denoms.
+pub fn validate_canonical_native_denom(denom: &str) -> StdResult<()> {
+    let canonical = canonical_native_denom(denom);
+    if canonical != denom {
+        return Err(StdError::generic_err(format!(
+            "Denom is not canonical: {denom}, expected: {canonical}"
+        )));
+    }
+
+    Ok(())
+}
+
 /// Returns a lowercased, validated address upon success if present.
 #[inline]
 pub fn addr_opt_validate(api: &dyn Api, addr: &Option<String>) -> StdResult<Option<Addr>> {
@@ -154,4 +179,37 @@ mod tests {
         .unwrap();
         validate_native_denom("factory/wasm1jdppe6fnj2q7hjsepty5crxtrryzhuqsjrj95y/uusd").unwrap();
     }
+
+    #[test]
+    fn canonical_native_denoms() {
+        let canonical = "ibc/EBD5A24C554198EBAF44979C5B4D2C2D312E6EBAB71962C92F735499C7575839";
+        for denom in [
+            "ibc/EBD5A24C554198EBAF44979C5B4D2C2D312E6EBAB71962C92F735499C7575839",
+            "IBC/EBD5A24C554198EBAF44979C5B4D2C2D312E6EBAB71962C92F735499C7575839",
+            "Ibc/ebd5a24c554198ebaf44979c5b4d2c2d312e6ebab71962c92f735499c7575839",
+            " ibc/EBD5a24c554198EBAF44979C5B4D2C2D312E6EBAB71962C92F735499C7575839 ",
+        ] {
+            assert_eq!(canonical_native_denom(denom), canonical);
+        }
+        validate_canonical_native_denom(canonical).unwrap();
+
+        let err = validate_canonical_native_denom(
+            "IBC/EBD5A24C554198EBAF44979C5B4D2C2D312E6EBAB71962C92F735499C7575839",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(format!(
+                "Denom is not canonical: IBC/EBD5A24C554198EBAF44979C5B4D2C2D312E6EBAB71962C92F735499C7575839, expected: {canonical}"
+            ))
+        );
+
+        // Other denoms keep their casing
+        assert_eq!(canonical_native_denom("uusd"), "uusd");
+        assert_eq!(
+            canonical_native_denom("factory/minter/URA"),
+            "factory/minter/URA"
+        );
+        validate_canonical_native_denom("factory/minter/URA").unwrap();
+    }
 }
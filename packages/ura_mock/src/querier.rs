@@ -1,13 +1,17 @@
 use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_json, to_json_binary, Addr, Coin, Empty, OwnedDeps, Querier, QuerierResult, QueryRequest,
-    SystemError, SystemResult, Uint128, WasmQuery,
+    from_json, to_json_binary, Addr, Coin, ContractInfoResponse, Decimal, Empty, OwnedDeps,
+    Querier, QuerierResult, QueryRequest, SystemError, SystemResult, Uint128, WasmQuery,
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use cw20::{BalanceResponse, Cw20QueryMsg, TokenInfoResponse};
+use ura::contracts::controller::{GaugeFromPoolResponse, QueryMsg as ControllerQueryMsg};
 use ura::contracts::factory::QueryMsg::{Config, FeeInfo};
 use ura::contracts::factory::{Config as FactoryConfig, ConfigResponse, FeeInfoResponse};
+use ura::contracts::native_coin_registry::COINS_INFO;
+use ura::contracts::oracle::PriceResponse;
+use ura::structs::asset_info::AssetInfo;
 
 /// mock_dependencies is a drop-in replacement for cosmwasm_std::testing::mock_dependencies.
 /// This uses the Ura CustomQuerier.
@@ -28,6 +32,17 @@ pub fn mock_dependencies(
 pub struct WasmMockQuerier {
     base: MockQuerier<Empty>,
     token_querier: TokenQuerier,
+    coin_decimals: HashMap<String, u8>,
+    controller_address: Option<Addr>,
+    controller_gas_limit: Option<u64>,
+    gauge_address: Option<Addr>,
+    min_swap_amount: Option<Uint128>,
+    is_min_commission_enabled: bool,
+    max_price_impact: Option<Decimal>,
+    oracle: Option<(Addr, Decimal)>,
+    contracts: HashSet<String>,
+    asset_fee_addresses: Vec<(AssetInfo, Addr)>,
+    denom_creation_fee: Vec<Coin>,
 }
 
 #[derive(Clone, Default)]
@@ -82,20 +97,59 @@ impl WasmMockQuerier {
                 if contract_addr == "factory" {
                     match from_json(&msg).unwrap() {
                         FeeInfo { .. } => SystemResult::Ok(
-                            to_json_binary(&FeeInfoResponse { total_fee_bps: 30 }).into(),
+                            to_json_binary(&FeeInfoResponse {
+                                total_fee_bps: 30,
+                                is_min_commission_enabled: self.is_min_commission_enabled,
+                                min_swap_amount: self.min_swap_amount,
+                                max_price_impact: self.max_price_impact,
+                            })
+                            .into(),
                         ),
                         Config {} => SystemResult::Ok(
                             to_json_binary(&ConfigResponse {
                                 owner: Addr::unchecked("owner"),
                                 pair_configs: vec![],
-                                controller_address: None,
+                                controller_address: self.controller_address.clone(),
                                 coin_registry_address: Addr::unchecked("coin_registry"),
+                                disallow_lp_assets: false,
+                                controller_gas_limit: self.controller_gas_limit,
+                                trusted_denom_admins: None,
+                                asset_fee_addresses: self.asset_fee_addresses.clone(),
+                                denom_creation_fee: self.denom_creation_fee.clone(),
                                 fee_address: Addr::unchecked("owner"),
                             })
                             .into(),
                         ),
                         _ => panic!("DO NOT ENTER HERE"),
                     }
+                } else if self
+                    .controller_address
+                    .as_ref()
+                    .is_some_and(|controller| controller == contract_addr)
+                {
+                    match (from_json(msg), &self.gauge_address) {
+                        (Ok(ControllerQueryMsg::GaugeFromPool(request)), Some(gauge)) => {
+                            SystemResult::Ok(
+                                to_json_binary(&GaugeFromPoolResponse {
+                                    pool: Addr::unchecked(request.pool),
+                                    gauge: gauge.clone(),
+                                    bribes: vec![],
+                                    fees: vec![],
+                                })
+                                .into(),
+                            )
+                        }
+                        // The mocked controller is unreachable
+                        _ => SystemResult::Err(SystemError::NoSuchContract {
+                            addr: contract_addr.to_string(),
+                        }),
+                    }
+                } else if let Some((_, price)) = self
+                    .oracle
+                    .as_ref()
+                    .filter(|(oracle, _)| oracle == contract_addr)
+                {
+                    SystemResult::Ok(to_json_binary(&PriceResponse { price: *price }).into())
                 } else {
                     match from_json(&msg).unwrap() {
                         Cw20QueryMsg::TokenInfo {} => {
@@ -155,6 +209,11 @@ impl WasmMockQuerier {
                                 owner: Addr::unchecked("owner"),
                                 controller_address: None,
                                 coin_registry_address: Addr::unchecked("coin_registry"),
+                                disallow_lp_assets: false,
+                                controller_gas_limit: None,
+                                trusted_denom_admins: None,
+                                asset_fee_addresses: self.asset_fee_addresses.clone(),
+                                denom_creation_fee: self.denom_creation_fee.clone(),
                                 token_code_id: 123u64,
                                 fee_address: Addr::unchecked("owner"),
                             })
@@ -166,11 +225,22 @@ impl WasmMockQuerier {
                         panic!("DO NOT ENTER HERE");
                     }
                 } else if contract_addr == "coin_registry" {
-                    SystemResult::Ok(to_json_binary(&6).into())
+                    let decimals = self
+                        .coin_decimals
+                        .iter()
+                        .find(|(denom, _)| *COINS_INFO.key(denom.to_string()) == *key.as_slice())
+                        .map_or(6, |(_, decimals)| *decimals);
+
+                    SystemResult::Ok(to_json_binary(&decimals).into())
                 } else {
                     panic!("DO NOT ENTER HERE");
                 }
             }
+            QueryRequest::Wasm(WasmQuery::ContractInfo { contract_addr })
+                if self.contracts.contains(contract_addr) =>
+            {
+                SystemResult::Ok(to_json_binary(&ContractInfoResponse::default()).into())
+            }
             _ => self.base.handle_query(request),
         }
     }
@@ -181,6 +251,17 @@ impl WasmMockQuerier {
         WasmMockQuerier {
             base,
             token_querier: TokenQuerier::default(),
+            coin_decimals: HashMap::new(),
+            controller_address: None,
+            controller_gas_limit: None,
+            gauge_address: None,
+            min_swap_amount: None,
+            is_min_commission_enabled: false,
+            max_price_impact: None,
+            oracle: None,
+            contracts: HashSet::new(),
+            asset_fee_addresses: vec![],
+            denom_creation_fee: vec![],
         }
     }
 
@@ -194,4 +275,64 @@ impl WasmMockQuerier {
             self.base.update_balance(addr.to_string(), balance.to_vec());
         }
     }
+
+    // Configure the decimals registered in the coin registry. Unknown denoms have 6 decimals
+    pub fn with_coin_decimals(&mut self, decimals: &[(&str, u8)]) {
+        for (denom, decimals) in decimals {
+            self.coin_decimals.insert(denom.to_string(), *decimals);
+        }
+    }
+
+    // Configure the controller returned by the factory config. Queries to it fail
+    pub fn with_controller(&mut self, controller: &str) {
+        self.controller_address = Some(Addr::unchecked(controller));
+    }
+
+    // Configure the gas limit of the controller calls returned by the factory config
+    pub fn with_controller_gas_limit(&mut self, gas_limit: u64) {
+        self.controller_gas_limit = Some(gas_limit);
+    }
+
+    // Configure the gauge the controller returns for every pool, other controller queries still fail
+    pub fn with_gauge(&mut self, gauge: &str) {
+        self.gauge_address = Some(Addr::unchecked(gauge));
+    }
+
+    // Configure the denom creation fee returned by the factory config
+    pub fn with_denom_creation_fee(&mut self, denom_creation_fee: &[Coin]) {
+        self.denom_creation_fee = denom_creation_fee.to_vec();
+    }
+
+    // Configure the minimum swap amount returned by the factory fee info
+    pub fn with_min_swap_amount(&mut self, min_swap_amount: Uint128) {
+        self.min_swap_amount = Some(min_swap_amount);
+    }
+
+    // Enable the minimum commission in the factory fee info
+    pub fn with_min_commission(&mut self) {
+        self.is_min_commission_enabled = true;
+    }
+
+    // Configure the maximum price impact returned by the factory fee info
+    pub fn with_max_price_impact(&mut self, max_price_impact: Decimal) {
+        self.max_price_impact = Some(max_price_impact);
+    }
+
+    // Configure a price oracle that reports `price` for every asset pair
+    pub fn with_oracle_price(&mut self, oracle: &str, price: Decimal) {
+        self.oracle = Some((Addr::unchecked(oracle), price));
+    }
+
+    // Configure the addresses reported as contracts by contract info queries
+    pub fn with_contracts(&mut self, contracts: &[&str]) {
+        self.contracts
+            .extend(contracts.iter().map(|contract| contract.to_string()));
+    }
+
+    pub fn with_asset_fee_addresses(&mut self, fee_addresses: &[(AssetInfo, &str)]) {
+        self.asset_fee_addresses = fee_addresses
+            .iter()
+            .map(|(asset_info, address)| (asset_info.clone(), Addr::unchecked(*address)))
+            .collect();
+    }
 }